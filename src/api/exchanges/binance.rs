@@ -0,0 +1,751 @@
+use chrono::{DateTime, Duration, Utc};
+use hashbrown::{HashMap, HashSet};
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use std::{collections::BTreeMap, env};
+
+use crate::{
+    errors::ApiError,
+    utils::{merge_by_key, time_windows},
+};
+
+const API_BINANCE_ENDPOINT: &str = "https://api.binance.com";
+const RECV_WINDOW: &str = "10000";
+const PAGE_SIZE: usize = 1000; // Trades, deposits and withdrawals
+
+/* Binance Spot API client. The base url can be changed (env BINANCE_API_URL) to use a local stand-in for the tests.
+https://developers.binance.com/docs/binance-spot-api-docs/rest-api
+*/
+#[derive(Debug, Clone)]
+pub struct BinanceClient {
+    base_url: String,
+    api_key: String,
+    api_secret: String,
+}
+
+/* As per documentation the signature is the hex encoded HMAC-SHA256 of the query string, keyed with the api secret.
+https://developers.binance.com/docs/binance-spot-api-docs/rest-api/endpoint-security-type
+*/
+fn get_binance_signature(query: &str, secret: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("Wrong Key size");
+    mac.update(query.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+impl BinanceClient {
+    pub fn new(base_url: String, api_key: String, api_secret: String) -> Self {
+        Self {
+            base_url,
+            api_key,
+            api_secret,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let api_key = env::var("BINANCE_KEY").expect("BINANCE_KEY not set in .env file");
+        let api_secret = env::var("BINANCE_SECRET").expect("BINANCE_SECRET not set in .env file");
+        Self::new(Self::url_from_env(), api_key, api_secret)
+    }
+
+    /* Client for the public endpoints only (prices), no key needed */
+    pub fn public_from_env() -> Self {
+        Self::new(Self::url_from_env(), String::new(), String::new())
+    }
+
+    fn url_from_env() -> String {
+        env::var("BINANCE_API_URL").unwrap_or(API_BINANCE_ENDPOINT.to_string())
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        url_path: &str,
+        params: &[(&str, String)],
+        signed: bool,
+    ) -> Result<T, ApiError> {
+        let mut query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+        let mut headers = HeaderMap::new();
+        if signed {
+            if !query.is_empty() {
+                query.push('&');
+            }
+            query.push_str(&format!(
+                "recvWindow={RECV_WINDOW}&timestamp={}",
+                Utc::now().timestamp_millis()
+            ));
+            let signature = get_binance_signature(&query, &self.api_secret);
+            query.push_str(&format!("&signature={signature}"));
+            headers.insert(
+                "X-MBX-APIKEY",
+                HeaderValue::from_str(&self.api_key)
+                    .map_err(|e| ApiError::ApiCallError(e.to_string()))?,
+            );
+        }
+        let url = format!("{}{url_path}?{query}", self.base_url);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(match serde_json::from_str::<BinanceError>(&text) {
+                Ok(error) => {
+                    ApiError::ApiCallError(format!("Binance error {}: {}", error.code, error.msg))
+                }
+                Err(_) => ApiError::ApiCallError(format!("Binance error {status}: {text}")),
+            });
+        }
+        serde_json::from_str(&text).map_err(|e| ApiError::DeserializationError(e.to_string()))
+    }
+
+    pub async fn fetch_exchange_info(&self) -> Result<Symbols, ApiError> {
+        let info: ExchangeInfo = self.get("/api/v3/exchangeInfo", &[], false).await?;
+        Ok(info
+            .symbols
+            .into_iter()
+            .map(|symbol| (symbol.symbol.clone(), symbol))
+            .collect())
+    }
+
+    /* Assets with a non zero balance on the spot account */
+    pub async fn fetch_account_assets(&self) -> Result<Vec<String>, ApiError> {
+        let account: AccountInfo = self.get("/api/v3/account", &[], true).await?;
+        Ok(account
+            .balances
+            .into_iter()
+            .filter(|balance| !balance.free.is_zero() || !balance.locked.is_zero())
+            .map(|balance| balance.asset)
+            .collect())
+    }
+
+    /* The trades are only available per symbol, paginated with the trade id: the ones from the given id */
+    pub async fn fetch_trades(
+        &self,
+        symbol: &str,
+        mut from_id: u64,
+    ) -> Result<Vec<BinanceTrade>, ApiError> {
+        let mut trades: Vec<BinanceTrade> = Vec::new();
+        loop {
+            let params = [
+                ("symbol", symbol.to_string()),
+                ("fromId", from_id.to_string()),
+                ("limit", PAGE_SIZE.to_string()),
+            ];
+            let page: Vec<BinanceTrade> = self.get("/api/v3/myTrades", &params, true).await?;
+            let count = page.len();
+            if let Some(last) = page.last() {
+                from_id = last.id + 1;
+            }
+            trades.extend(page);
+            if count < PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(trades)
+    }
+
+    /* Pages of the capital history (deposits or withdrawals) of a window, paginated with an offset */
+    async fn fetch_capital_pages<T: DeserializeOwned>(
+        &self,
+        url_path: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<T>, ApiError> {
+        let mut records: Vec<T> = Vec::new();
+        loop {
+            let params = [
+                ("startTime", start.timestamp_millis().to_string()),
+                ("endTime", end.timestamp_millis().to_string()),
+                ("offset", records.len().to_string()),
+                ("limit", PAGE_SIZE.to_string()),
+            ];
+            let page: Vec<T> = self.get(url_path, &params, true).await?;
+            let count = page.len();
+            records.extend(page);
+            if count < PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(records)
+    }
+
+    pub async fn fetch_deposits(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<BinanceDeposit>, ApiError> {
+        let mut deposits: HashMap<String, BinanceDeposit> = HashMap::new();
+        for (window_start, window_end) in time_windows(start, end, Duration::days(90)) {
+            let page: Vec<BinanceDeposit> = self
+                .fetch_capital_pages("/sapi/v1/capital/deposit/hisrec", window_start, window_end)
+                .await?;
+            deposits.extend(
+                page.into_iter()
+                    .map(|deposit| (deposit.id.clone(), deposit)),
+            );
+        }
+        Ok(deposits.into_values().collect())
+    }
+
+    pub async fn fetch_withdrawals(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<BinanceWithdrawal>, ApiError> {
+        let mut withdrawals: HashMap<String, BinanceWithdrawal> = HashMap::new();
+        for (window_start, window_end) in time_windows(start, end, Duration::days(90)) {
+            let page: Vec<BinanceWithdrawal> = self
+                .fetch_capital_pages(
+                    "/sapi/v1/capital/withdraw/history",
+                    window_start,
+                    window_end,
+                )
+                .await?;
+            withdrawals.extend(
+                page.into_iter()
+                    .map(|withdrawal| (withdrawal.id.clone(), withdrawal)),
+            );
+        }
+        Ok(withdrawals.into_values().collect())
+    }
+
+    /* Deposits (transaction type 0) or withdrawals (1) of fiat, which are not in the capital history */
+    pub async fn fetch_fiat_orders(
+        &self,
+        transaction_type: u8,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<BinanceFiatOrder>, ApiError> {
+        let mut orders: HashMap<String, BinanceFiatOrder> = HashMap::new();
+        for (window_start, window_end) in time_windows(start, end, Duration::days(90)) {
+            let mut page_number = 1;
+            loop {
+                let params = [
+                    ("transactionType", transaction_type.to_string()),
+                    ("beginTime", window_start.timestamp_millis().to_string()),
+                    ("endTime", window_end.timestamp_millis().to_string()),
+                    ("page", page_number.to_string()),
+                    ("rows", "500".to_string()),
+                ];
+                let page: FiatOrders = self.get("/sapi/v1/fiat/orders", &params, true).await?;
+                let count = page.data.len();
+                orders.extend(
+                    page.data
+                        .into_iter()
+                        .map(|order| (order.order_no.clone(), order)),
+                );
+                if count < 500 {
+                    break;
+                }
+                page_number += 1;
+            }
+        }
+        Ok(orders.into_values().collect())
+    }
+
+    pub async fn fetch_convert_history(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<BinanceConvert>, ApiError> {
+        let mut converts: HashMap<u64, BinanceConvert> = HashMap::new();
+        for (window_start, window_end) in time_windows(start, end, Duration::days(30)) {
+            let params = [
+                ("startTime", window_start.timestamp_millis().to_string()),
+                ("endTime", window_end.timestamp_millis().to_string()),
+                ("limit", "1000".to_string()),
+            ];
+            let page: ConvertHistory = self
+                .get("/sapi/v1/convert/tradeFlow", &params, true)
+                .await?;
+            converts.extend(
+                page.list
+                    .into_iter()
+                    .filter(|convert| convert.order_status == "SUCCESS")
+                    .map(|convert| (convert.order_id, convert)),
+            );
+        }
+        Ok(converts.into_values().collect())
+    }
+
+    pub async fn fetch_dust_conversions(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DustConversion>, ApiError> {
+        let mut conversions: HashMap<u64, DustConversion> = HashMap::new();
+        for (window_start, window_end) in time_windows(start, end, Duration::days(100)) {
+            let params = [
+                ("startTime", window_start.timestamp_millis().to_string()),
+                ("endTime", window_end.timestamp_millis().to_string()),
+            ];
+            let page: DustLog = self.get("/sapi/v1/asset/dribblet", &params, true).await?;
+            conversions.extend(
+                page.user_asset_dribblets
+                    .into_iter()
+                    .map(|conversion| (conversion.trans_id, conversion)),
+            );
+        }
+        Ok(conversions.into_values().collect())
+    }
+
+    /* Rewards of the Simple Earn flexible (REALTIME, BONUS and REWARDS types) and locked products */
+    pub async fn fetch_earn_rewards(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<EarnReward>, ApiError> {
+        let mut rewards: HashSet<EarnReward> = HashSet::new();
+        for (window_start, window_end) in time_windows(start, end, Duration::days(90)) {
+            for reward_type in ["REALTIME", "BONUS", "REWARDS"] {
+                let records: Vec<FlexibleReward> = self
+                    .fetch_earn_pages(
+                        "/sapi/v1/simple-earn/flexible/history/rewardsRecord",
+                        window_start,
+                        window_end,
+                        Some(reward_type),
+                    )
+                    .await?;
+                rewards.extend(records.into_iter().map(|record| EarnReward {
+                    asset: record.asset,
+                    amount: record.rewards,
+                    time: record.time,
+                    product: EarnProduct::Flexible(record.r#type),
+                }));
+            }
+            let records: Vec<LockedReward> = self
+                .fetch_earn_pages(
+                    "/sapi/v1/simple-earn/locked/history/rewardsRecord",
+                    window_start,
+                    window_end,
+                    None,
+                )
+                .await?;
+            rewards.extend(records.into_iter().map(|record| EarnReward {
+                asset: record.asset,
+                amount: record.amount,
+                time: record.time,
+                product: EarnProduct::Locked(record.position_id),
+            }));
+        }
+        let mut rewards: Vec<EarnReward> = rewards.into_iter().collect();
+        rewards.sort_by_key(|reward| reward.time);
+        Ok(rewards)
+    }
+
+    async fn fetch_earn_pages<T: DeserializeOwned>(
+        &self,
+        url_path: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        reward_type: Option<&str>,
+    ) -> Result<Vec<T>, ApiError> {
+        let mut records: Vec<T> = Vec::new();
+        let mut current = 1;
+        loop {
+            let mut params = vec![
+                ("startTime", start.timestamp_millis().to_string()),
+                ("endTime", end.timestamp_millis().to_string()),
+                ("current", current.to_string()),
+                ("size", "100".to_string()),
+            ];
+            if let Some(reward_type) = reward_type {
+                params.push(("type", reward_type.to_string()));
+            }
+            let page: EarnRows<T> = self.get(url_path, &params, true).await?;
+            let count = page.rows.len();
+            records.extend(page.rows);
+            if count < 100 || current * 100 >= page.total {
+                break;
+            }
+            current += 1;
+        }
+        Ok(records)
+    }
+
+    /* Close price of the minute at the given time */
+    pub async fn fetch_price(
+        &self,
+        symbol: &str,
+        time: DateTime<Utc>,
+    ) -> Result<Decimal, ApiError> {
+        let params = [
+            ("symbol", symbol.to_string()),
+            ("interval", "1m".to_string()),
+            ("startTime", time.timestamp_millis().to_string()),
+            ("limit", "1".to_string()),
+        ];
+        let klines: Vec<Vec<serde_json::Value>> =
+            self.get("/api/v3/klines", &params, false).await?;
+        let close = klines
+            .first()
+            .and_then(|kline| kline.get(4))
+            .and_then(|close| close.as_str())
+            .ok_or(ApiError::DeserializationError(format!(
+                "No kline for {symbol} at {time}"
+            )))?;
+        close
+            .parse()
+            .map_err(|e: rust_decimal::Error| ApiError::DeserializationError(e.to_string()))
+    }
+}
+
+pub type Symbols = BTreeMap<String, SymbolInfo>;
+
+/* Everything fetched from Binance, kept raw so it can be saved and mapped again without calling the API */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BinanceHistory {
+    pub symbols: Symbols,
+    pub trades: Vec<BinanceTrade>,
+    pub deposits: Vec<BinanceDeposit>,
+    pub withdrawals: Vec<BinanceWithdrawal>,
+    #[serde(default)]
+    pub fiat_deposits: Vec<BinanceFiatOrder>,
+    #[serde(default)]
+    pub fiat_withdrawals: Vec<BinanceFiatOrder>,
+    pub converts: Vec<BinanceConvert>,
+    pub dust_conversions: Vec<DustConversion>,
+    pub earn_rewards: Vec<EarnReward>,
+}
+
+impl BinanceHistory {
+    /* Assets seen in the history: moved, converted, rewarded or traded */
+    pub fn assets(&self) -> HashSet<String> {
+        let mut assets: HashSet<String> = HashSet::new();
+        assets.extend(self.deposits.iter().map(|deposit| deposit.coin.clone()));
+        assets.extend(
+            self.withdrawals
+                .iter()
+                .map(|withdrawal| withdrawal.coin.clone()),
+        );
+        assets.extend(
+            self.fiat_deposits
+                .iter()
+                .chain(&self.fiat_withdrawals)
+                .map(|order| order.fiat_currency.clone()),
+        );
+        for convert in &self.converts {
+            assets.insert(convert.from_asset.clone());
+            assets.insert(convert.to_asset.clone());
+        }
+        for conversion in &self.dust_conversions {
+            assets.extend(
+                conversion
+                    .user_asset_dribblet_details
+                    .iter()
+                    .map(|detail| detail.from_asset.clone()),
+            );
+        }
+        assets.extend(self.earn_rewards.iter().map(|reward| reward.asset.clone()));
+        for trade in &self.trades {
+            if let Some(info) = self.symbols.get(&trade.symbol) {
+                assets.insert(info.base_asset.clone());
+                assets.insert(info.quote_asset.clone());
+            }
+        }
+        assets
+    }
+
+    /* Add the history fetched since this one, the operations fetched again replacing the saved ones */
+    pub fn merge(&mut self, other: BinanceHistory) {
+        if !other.symbols.is_empty() {
+            self.symbols = other.symbols;
+        }
+        merge_by_key(&mut self.trades, other.trades, |trade| {
+            (trade.symbol.clone(), trade.id)
+        });
+        merge_by_key(&mut self.deposits, other.deposits, |deposit| {
+            deposit.id.clone()
+        });
+        merge_by_key(&mut self.withdrawals, other.withdrawals, |withdrawal| {
+            withdrawal.id.clone()
+        });
+        merge_by_key(&mut self.fiat_deposits, other.fiat_deposits, |order| {
+            order.order_no.clone()
+        });
+        merge_by_key(
+            &mut self.fiat_withdrawals,
+            other.fiat_withdrawals,
+            |order| order.order_no.clone(),
+        );
+        merge_by_key(&mut self.converts, other.converts, |convert| {
+            convert.order_id
+        });
+        merge_by_key(
+            &mut self.dust_conversions,
+            other.dust_conversions,
+            |conversion| conversion.trans_id,
+        );
+        merge_by_key(&mut self.earn_rewards, other.earn_rewards, |reward| {
+            reward.clone()
+        });
+    }
+}
+
+/* Fetch the history between start and end, the previous history being the one already fetched.
+The trades can only be fetched per symbol and are not filtered by time: the symbols with an asset seen in the
+account (held, in the history or in the previous one) are searched from the trade following the last one already
+fetched. The assets of the trades found are searched in turn, until no new asset is found, so the trades of coins
+never deposited nor held anymore are found too */
+pub async fn fetch_history_binance(
+    client: &BinanceClient,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    previous: &BinanceHistory,
+) -> Result<BinanceHistory, ApiError> {
+    let mut history = BinanceHistory {
+        symbols: client.fetch_exchange_info().await?,
+        trades: Vec::new(),
+        deposits: client.fetch_deposits(start, end).await?,
+        withdrawals: client.fetch_withdrawals(start, end).await?,
+        fiat_deposits: client.fetch_fiat_orders(0, start, end).await?,
+        fiat_withdrawals: client.fetch_fiat_orders(1, start, end).await?,
+        converts: client.fetch_convert_history(start, end).await?,
+        dust_conversions: client.fetch_dust_conversions(start, end).await?,
+        earn_rewards: client.fetch_earn_rewards(start, end).await?,
+    };
+
+    let mut assets: HashSet<String> = client.fetch_account_assets().await?.into_iter().collect();
+    assets.extend(history.assets());
+    assets.extend(previous.assets());
+    let mut from_ids: HashMap<&str, u64> = HashMap::new();
+    for trade in &previous.trades {
+        let from_id = from_ids.entry(trade.symbol.as_str()).or_insert(0);
+        *from_id = (*from_id).max(trade.id + 1);
+    }
+
+    let mut searched: HashSet<String> = HashSet::new();
+    loop {
+        let pending: Vec<&SymbolInfo> = history
+            .symbols
+            .values()
+            .filter(|info| {
+                !searched.contains(&info.symbol)
+                    && (assets.contains(&info.base_asset) || assets.contains(&info.quote_asset))
+            })
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+        for info in pending {
+            searched.insert(info.symbol.clone());
+            let from_id = from_ids.get(info.symbol.as_str()).copied().unwrap_or(0);
+            let trades = client.fetch_trades(&info.symbol, from_id).await?;
+            if !trades.is_empty() {
+                assets.insert(info.base_asset.clone());
+                assets.insert(info.quote_asset.clone());
+            }
+            history.trades.extend(trades);
+        }
+    }
+    Ok(history)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BinanceError {
+    pub code: i64,
+    pub msg: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExchangeInfo {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountInfo {
+    pub balances: Vec<AccountBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountBalance {
+    pub asset: String,
+    pub free: Decimal,
+    pub locked: Decimal,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceTrade {
+    pub symbol: String,
+    pub id: u64,
+    pub order_id: i64,
+    pub price: Decimal,
+    pub qty: Decimal,
+    pub quote_qty: Decimal,
+    pub commission: Decimal,
+    pub commission_asset: String,
+    pub time: i64,
+    pub is_buyer: bool,
+    pub is_maker: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceDeposit {
+    pub id: String,
+    pub amount: Decimal,
+    pub coin: String,
+    pub network: String,
+    pub status: i32, // 0: pending, 6: credited but cannot withdraw, 1: success
+    pub address: String,
+    pub tx_id: String,
+    pub insert_time: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceWithdrawal {
+    pub id: String,
+    pub amount: Decimal,
+    pub transaction_fee: Decimal,
+    pub coin: String,
+    pub status: i32, // 6: completed
+    pub address: String,
+    pub tx_id: Option<String>,
+    pub apply_time: String, // UTC "2019-10-12 11:12:02"
+    pub network: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FiatOrders {
+    pub data: Vec<BinanceFiatOrder>,
+}
+
+/* The fee is taken from the indicated amount: a deposit credits indicated_amount - total_fee */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceFiatOrder {
+    pub order_no: String,
+    pub fiat_currency: String,
+    pub indicated_amount: Decimal,
+    pub total_fee: Decimal,
+    pub status: String, // Processing, Failed, Successful, Finished, Refunding, Refunded, Refund Failed, Order Partial credit Stopped
+    pub create_time: i64,
+}
+
+impl BinanceFiatOrder {
+    pub fn is_completed(&self) -> bool {
+        self.status == "Successful" || self.status == "Finished"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConvertHistory {
+    pub list: Vec<BinanceConvert>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinanceConvert {
+    pub quote_id: String,
+    pub order_id: u64,
+    pub order_status: String,
+    pub from_asset: String,
+    pub from_amount: Decimal,
+    pub to_asset: String,
+    pub to_amount: Decimal,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DustLog {
+    pub user_asset_dribblets: Vec<DustConversion>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DustConversion {
+    pub operate_time: i64,
+    pub trans_id: u64,
+    pub user_asset_dribblet_details: Vec<DustDetail>,
+}
+
+/* One small asset converted to BNB: amount of from_asset is converted to transfered_amount BNB, after a fee of service_charge_amount BNB */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DustDetail {
+    pub trans_id: u64,
+    pub service_charge_amount: Decimal,
+    pub amount: Decimal,
+    pub operate_time: i64,
+    pub transfered_amount: Decimal,
+    pub from_asset: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EarnRows<T> {
+    pub rows: Vec<T>,
+    pub total: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlexibleReward {
+    pub asset: String,
+    pub rewards: Decimal,
+    pub time: i64,
+    pub r#type: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockedReward {
+    pub position_id: String,
+    pub time: i64,
+    pub asset: String,
+    pub amount: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum EarnProduct {
+    Flexible(String), // Type of reward (REALTIME, BONUS, REWARDS)
+    Locked(String),   // Position id
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct EarnReward {
+    pub asset: String,
+    pub amount: Decimal,
+    pub time: i64,
+    pub product: EarnProduct,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        /* https://developers.binance.com/docs/binance-spot-api-docs/rest-api/endpoint-security-type */
+        let secret = "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j";
+        let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559";
+
+        assert_eq!(
+            get_binance_signature(query, secret),
+            "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+}
@@ -1,107 +1,486 @@
-use chrono::Utc;
-use reqwest::Error;
-use serde::{Deserialize, Serialize};
-use std::env;
-
+use chrono::{DateTime, Utc};
+use hashbrown::HashSet;
 use hmac::{Hmac, Mac};
-use serde_json::json;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, value::RawValue, Value};
 use sha2::Sha384;
+use std::{
+    env,
+    str::FromStr,
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+use crate::{errors::ApiError, utils::merge_by_key};
 
 const API_BITFINEX_ENDPOINT: &str = "https://api.bitfinex.com";
+const API_BITFINEX_PUBLIC_ENDPOINT: &str = "https://api-pub.bitfinex.com";
+const PAGE_LIMIT: usize = 1000; // Maximum of the movements, the trades and ledgers allow 2500
+
+/* The rows are arrays of fields of any type, kept as their JSON text so the amounts are parsed without going
+through a float */
+type BitfinexRow = Vec<Box<RawValue>>;
+
+/* Bitfinex v2 API client. The urls can be changed (env BITFINEX_API_URL) to use a local stand-in for the tests.
+https://docs.bitfinex.com/docs/rest-auth
+*/
+#[derive(Debug)]
+pub struct BitfinexClient {
+    base_url: String,
+    public_url: String,
+    api_key: String,
+    api_secret: String,
+    page_limit: usize,
+    last_nonce: AtomicI64, // The nonce must always increase for a given key
+}
+
+/* As per documentation the signature is the hex encoded HMAC-SHA384 of "/api/v2/<path><nonce><body>", keyed with the api secret */
+fn get_bitfinex_signature(path: &str, nonce: &str, body: &str, secret: &str) -> String {
+    let mut mac = Hmac::<Sha384>::new_from_slice(secret.as_bytes()).expect("Wrong Key size");
+    mac.update(format!("/api/v2/{path}{nonce}{body}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+impl BitfinexClient {
+    pub fn new(base_url: String, public_url: String, api_key: String, api_secret: String) -> Self {
+        Self {
+            base_url,
+            public_url,
+            api_key,
+            api_secret,
+            page_limit: PAGE_LIMIT,
+            last_nonce: AtomicI64::new(0),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let api_key = env::var("BITFINEX_KEY_ID").expect("BITFINEX_KEY_ID not set in .env file");
+        let api_secret =
+            env::var("BITFINEX_KEY_SECRET").expect("BITFINEX_KEY_SECRET not set in .env file");
+        let (base_url, public_url) = Self::urls_from_env();
+        Self::new(base_url, public_url, api_key, api_secret)
+    }
+
+    /* Client for the public endpoints only (prices), no key needed */
+    pub fn public_from_env() -> Self {
+        let (base_url, public_url) = Self::urls_from_env();
+        Self::new(base_url, public_url, String::new(), String::new())
+    }
+
+    fn urls_from_env() -> (String, String) {
+        match env::var("BITFINEX_API_URL") {
+            Ok(url) => (url.clone(), url),
+            Err(_) => (
+                API_BITFINEX_ENDPOINT.to_string(),
+                API_BITFINEX_PUBLIC_ENDPOINT.to_string(),
+            ),
+        }
+    }
+
+    pub fn with_page_limit(mut self, page_limit: usize) -> Self {
+        self.page_limit = page_limit;
+        self
+    }
+
+    fn next_nonce(&self) -> String {
+        let now = Utc::now().timestamp_micros();
+        let previous = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap_or_default();
+        now.max(previous + 1).to_string()
+    }
+
+    async fn post_auth<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &Value,
+    ) -> Result<T, ApiError> {
+        let nonce = self.next_nonce();
+        let body = body.to_string();
+        let signature = get_bitfinex_signature(path, &nonce, &body, &self.api_secret);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        for (name, value) in [
+            ("bfx-nonce", nonce),
+            ("bfx-apikey", self.api_key.clone()),
+            ("bfx-signature", signature),
+        ] {
+            headers.insert(
+                name,
+                HeaderValue::from_str(&value)
+                    .map_err(|e| ApiError::ApiCallError(e.to_string()))?,
+            );
+        }
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("{}/v2/{path}", self.base_url))
+            .headers(headers)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        Self::parse_response(response).await
+    }
+
+    async fn get_public<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<T, ApiError> {
+        let response = reqwest::get(format!("{}/v2/{path_and_query}", self.public_url))
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        Self::parse_response(response).await
+    }
+
+    async fn parse_response<T: DeserializeOwned>(
+        response: reqwest::Response,
+    ) -> Result<T, ApiError> {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        // Errors are returned as ["error", code, message]
+        if !status.is_success() || text.starts_with("[\"error\"") {
+            return Err(ApiError::ApiCallError(format!(
+                "Bitfinex error {status}: {text}"
+            )));
+        }
+        serde_json::from_str(&text).map_err(|e| ApiError::DeserializationError(e.to_string()))
+    }
+
+    /* The history endpoints return the most recent rows first: the next page ends at the oldest row received.
+    The rows at this timestamp are received twice, so they are deduplicated by id */
+    async fn fetch_all_rows(
+        &self,
+        path: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        mts_index: usize,
+    ) -> Result<Vec<BitfinexRow>, ApiError> {
+        let start = start.timestamp_millis();
+        let mut page_end = end.timestamp_millis();
+        let mut ids: HashSet<i64> = HashSet::new();
+        let mut rows: Vec<BitfinexRow> = Vec::new();
+        loop {
+            let body = json!({"start": start, "end": page_end, "limit": self.page_limit});
+            let page: Vec<BitfinexRow> = self.post_auth(path, &body).await?;
+            let count = page.len();
+            let oldest = page
+                .iter()
+                .filter_map(|row| i64_field(row, mts_index).ok())
+                .min();
+            for row in page {
+                if let Ok(id) = i64_field(&row, 0) {
+                    if ids.insert(id) {
+                        rows.push(row);
+                    }
+                }
+            }
+            match oldest {
+                Some(oldest) if count >= self.page_limit => {
+                    page_end = if oldest < page_end {
+                        oldest
+                    } else {
+                        page_end - 1
+                    };
+                }
+                _ => break,
+            }
+        }
+        Ok(rows)
+    }
+
+    pub async fn fetch_trades(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<BitfinexTrade>, ApiError> {
+        let rows = self
+            .fetch_all_rows("auth/r/trades/hist", start, end, 2)
+            .await?;
+        rows.iter()
+            .map(|row| BitfinexTrade::from_row(row))
+            .collect()
+    }
+
+    pub async fn fetch_movements(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<BitfinexMovement>, ApiError> {
+        let rows = self
+            .fetch_all_rows("auth/r/movements/hist", start, end, 5)
+            .await?;
+        rows.iter()
+            .map(|row| BitfinexMovement::from_row(row))
+            .collect()
+    }
+
+    pub async fn fetch_ledgers(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<BitfinexLedgerEntry>, ApiError> {
+        let rows = self
+            .fetch_all_rows("auth/r/ledgers/hist", start, end, 3)
+            .await?;
+        rows.iter()
+            .map(|row| BitfinexLedgerEntry::from_row(row))
+            .collect()
+    }
+
+    /* Close price of the minute at the given time, symbol like tBTCEUR */
+    pub async fn fetch_price(
+        &self,
+        symbol: &str,
+        time: DateTime<Utc>,
+    ) -> Result<Decimal, ApiError> {
+        let candles: Vec<BitfinexRow> = self
+            .get_public(&format!(
+                "candles/trade:1m:{symbol}/hist?start={}&limit=1&sort=1",
+                time.timestamp_millis()
+            ))
+            .await?;
+        candles
+            .first()
+            .ok_or(ApiError::DeserializationError(format!(
+                "No candle for {symbol} at {time}"
+            )))
+            .and_then(|candle| decimal_field(candle, 2))
+    }
+}
+
+/* Everything fetched from Bitfinex, kept raw so it can be saved and mapped again without calling the API */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BitfinexHistory {
+    pub trades: Vec<BitfinexTrade>,
+    pub movements: Vec<BitfinexMovement>,
+    pub ledgers: Vec<BitfinexLedgerEntry>,
+}
+
+impl BitfinexHistory {
+    /* Add the operations of another history (a csv report) that are not already in this one, told apart by their
+    Bitfinex id */
+    pub fn merge(&mut self, other: BitfinexHistory) {
+        let trades: HashSet<i64> = self.trades.iter().map(|trade| trade.id).collect();
+        let movements: HashSet<i64> = self.movements.iter().map(|movement| movement.id).collect();
+        let ledgers: HashSet<i64> = self.ledgers.iter().map(|entry| entry.id).collect();
+        self.trades.extend(
+            other
+                .trades
+                .into_iter()
+                .filter(|trade| !trades.contains(&trade.id)),
+        );
+        self.movements.extend(
+            other
+                .movements
+                .into_iter()
+                .filter(|movement| !movements.contains(&movement.id)),
+        );
+        self.ledgers.extend(
+            other
+                .ledgers
+                .into_iter()
+                .filter(|entry| !ledgers.contains(&entry.id)),
+        );
+    }
+
+    /* Add the history fetched since this one, the operations fetched again replacing the saved ones */
+    pub fn update(&mut self, fetched: BitfinexHistory) {
+        merge_by_key(&mut self.trades, fetched.trades, |trade| trade.id);
+        merge_by_key(&mut self.movements, fetched.movements, |movement| movement.id);
+        merge_by_key(&mut self.ledgers, fetched.ledgers, |entry| entry.id);
+    }
+}
 
-#[derive(Deserialize, Serialize, Debug)]
+pub async fn fetch_history_bitfinex(
+    client: &BitfinexClient,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<BitfinexHistory, ApiError> {
+    Ok(BitfinexHistory {
+        trades: client.fetch_trades(start, end).await?,
+        movements: client.fetch_movements(start, end).await?,
+        ledgers: client.fetch_ledgers(start, end).await?,
+    })
+}
+
+/* [ID, SYMBOL, MTS, ORDER_ID, EXEC_AMOUNT, EXEC_PRICE, ORDER_TYPE, ORDER_PRICE, MAKER, FEE, FEE_CURRENCY, CID]
+EXEC_AMOUNT is positive for a buy, the FEE is negative */
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct BitfinexTrade {
-    id: i32,
-    symbol: String,
-    mts: i32,
-    order_id: i32,
-    exec_amount: f64,
-    exec_price: f64,
-    order_type: Option<String>, // Use Option<String> to handle cases where ORDER_TYPE is not set
-    order_price: f64,
-    maker: i32,
-    fee: f64,
-    fee_currency: String,
-    cid: i32,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct Trade {
-    price: Vec<f64>,
-    amount: Vec<f64>,
-    exchange: Vec<String>,
-    #[serde(rename = "type")]
-    trade_type: Vec<String>, // "type" is a reserved keyword, so we rename it
-    fee_currency: Vec<String>,
-    fee_amount: Vec<f64>,
-    tid: Vec<i32>,
-    order_id: Vec<i32>,
-}
-
-#[tokio::main] // If the function is called often, it is preferable to create the runtime using the runtime builder so the runtime can be reused across calls.
-pub async fn fetch_history_bitfinex(start: i64) -> Result<Vec<Trade>, Error> {
-    let api_key = env::var("BITFINEX_KEY_ID").expect("BITFINEX_KEY_ID not set in .env file");
-    let api_secret =
-        env::var("BITFINEX_KEY_SECRET").expect("BITFINEX_KEY_SECRET not set in .env file");
-
-    let nonce = Utc::now().timestamp_millis().to_string();
-    // let endpoint = "auth/r/trades/hist";
-    let endpoint = "mytrades";
-
-    // let payload = json!({"start": start});
-    // let message = format!("/api/v2/{}{}", endpoint, nonce) + &payload.to_string();
-
-    //V1
-    let url = "/v1/mytrades";
-    let complete_url = format!("{}{}", API_BITFINEX_ENDPOINT, url);
-    let body = serde_json::json!({
-        "request": url,
-        "nonce": nonce
-    });
-
-    let payload = base64::encode(&body.to_string());
-    let signature = {
-        let mut hmac =
-            Hmac::<Sha384>::new_from_slice(api_secret.as_bytes()).expect("HMAC creation failed");
-        hmac.update(payload.as_ref());
-        hmac.finalize().into_bytes()
+    pub id: i64,
+    pub symbol: String,
+    pub mts: i64,
+    pub order_id: i64,
+    pub exec_amount: Decimal,
+    pub exec_price: Decimal,
+    pub fee: Decimal,
+    pub fee_currency: String,
+}
+
+impl BitfinexTrade {
+    fn from_row(row: &[Box<RawValue>]) -> Result<Self, ApiError> {
+        Ok(Self {
+            id: i64_field(row, 0)?,
+            symbol: string_field(row, 1)?,
+            mts: i64_field(row, 2)?,
+            order_id: i64_field(row, 3)?,
+            exec_amount: decimal_field(row, 4)?,
+            exec_price: decimal_field(row, 5)?,
+            fee: decimal_field(row, 9)?,
+            fee_currency: string_field(row, 10)?,
+        })
+    }
+}
+
+/* [ID, CURRENCY, CURRENCY_NAME, null, null, MTS_STARTED, MTS_UPDATED, null, null, STATUS, null, null, AMOUNT, FEES,
+null, null, DESTINATION_ADDRESS, null, null, null, TRANSACTION_ID, WITHDRAW_TRANSACTION_NOTE]
+AMOUNT is negative for a withdrawal, the FEES are negative */
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BitfinexMovement {
+    pub id: i64,
+    pub currency: String,
+    pub mts_started: i64,
+    pub mts_updated: i64,
+    pub status: String,
+    pub amount: Decimal,
+    pub fees: Decimal,
+    pub destination_address: Option<String>,
+    pub transaction_id: Option<String>,
+}
+
+impl BitfinexMovement {
+    fn from_row(row: &[Box<RawValue>]) -> Result<Self, ApiError> {
+        Ok(Self {
+            id: i64_field(row, 0)?,
+            currency: string_field(row, 1)?,
+            mts_started: i64_field(row, 5)?,
+            mts_updated: i64_field(row, 6)?,
+            status: string_field(row, 9)?,
+            amount: decimal_field(row, 12)?,
+            fees: decimal_field(row, 13)?,
+            destination_address: optional_string_field(row, 16),
+            transaction_id: optional_string_field(row, 20),
+        })
+    }
+}
+
+/* [ID, CURRENCY, null, MTS, null, AMOUNT, BALANCE, null, DESCRIPTION] */
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct BitfinexLedgerEntry {
+    pub id: i64,
+    pub currency: String,
+    pub mts: i64,
+    pub amount: Decimal,
+    pub balance: Decimal,
+    pub description: String,
+}
+
+impl BitfinexLedgerEntry {
+    fn from_row(row: &[Box<RawValue>]) -> Result<Self, ApiError> {
+        Ok(Self {
+            id: i64_field(row, 0)?,
+            currency: string_field(row, 1)?,
+            mts: i64_field(row, 3)?,
+            amount: decimal_field(row, 5)?,
+            balance: decimal_field(row, 6)?,
+            description: string_field(row, 8)?,
+        })
+    }
+}
+
+fn missing_field(row: &[Box<RawValue>], index: usize) -> ApiError {
+    let fields: Vec<&str> = row.iter().map(|field| field.get()).collect();
+    ApiError::DeserializationError(format!(
+        "Invalid field {index} in the Bitfinex row [{}]",
+        fields.join(",")
+    ))
+}
+
+fn raw_field(row: &[Box<RawValue>], index: usize) -> Result<&str, ApiError> {
+    row.get(index)
+        .map(|field| field.get())
+        .ok_or_else(|| missing_field(row, index))
+}
+
+fn i64_field(row: &[Box<RawValue>], index: usize) -> Result<i64, ApiError> {
+    raw_field(row, index)?
+        .parse()
+        .map_err(|_| missing_field(row, index))
+}
+
+fn string_field(row: &[Box<RawValue>], index: usize) -> Result<String, ApiError> {
+    optional_string_field(row, index).ok_or_else(|| missing_field(row, index))
+}
+
+fn optional_string_field(row: &[Box<RawValue>], index: usize) -> Option<String> {
+    serde_json::from_str(row.get(index)?.get()).ok()
+}
+
+/* The amounts are JSON numbers (sometimes strings): they are parsed from their text to keep the exact decimal value */
+fn decimal_field(row: &[Box<RawValue>], index: usize) -> Result<Decimal, ApiError> {
+    let raw = raw_field(row, index)?;
+    let text = match serde_json::from_str::<String>(raw) {
+        Ok(text) => text,
+        Err(_) => raw.to_string(),
     };
+    Decimal::from_str(&text)
+        .or_else(|_| Decimal::from_scientific(&text))
+        .map_err(|_| missing_field(row, index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        let signature = get_bitfinex_signature(
+            "auth/r/trades/hist",
+            "1700000000000000",
+            r#"{"limit":1000}"#,
+            "secret",
+        );
+        assert_eq!(
+            signature,
+            "63f933edabace17248211715404262c72f015c005caeb3c6366f3d8920f976ec52febea75b4d60d1af0e7d4e4d242eb8"
+        );
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let row: BitfinexRow = serde_json::from_str(
+            r#"[402088407, "tETHUST", 1574963975602, 34938060782, -0.2, 153.57, "MARKET", 0, -1, -0.061668, "USD", null]"#,
+        )
+        .unwrap();
+        let trade = BitfinexTrade::from_row(&row).unwrap();
+        assert_eq!(trade.exec_amount, Decimal::from_str("-0.2").unwrap());
+        assert_eq!(trade.exec_price, Decimal::from_str("153.57").unwrap());
+        assert_eq!(trade.fee, Decimal::from_str("-0.061668").unwrap());
+
+        let row: BitfinexRow = serde_json::from_str(
+            r#"[1, "BTC", null, 1574963975602, null, 1e-8, 1234567.123456789012, null, "test"]"#,
+        )
+        .unwrap();
+        let entry = BitfinexLedgerEntry::from_row(&row).unwrap();
+        assert_eq!(entry.amount, Decimal::from_str("0.00000001").unwrap());
+        // More digits than a float holds
+        assert_eq!(
+            entry.balance,
+            Decimal::from_str("1234567.123456789012").unwrap()
+        );
+    }
 
-    let sig = hex::encode(signature);
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&complete_url)
-        .header("X-BFX-APIKEY", api_key)
-        .header("X-BFX-PAYLOAD", payload)
-        .header("X-BFX-SIGNATURE", sig)
-        .json(&body)
-        .send()
-        .await?;
-
-    return Ok(response.json::<Vec<Trade>>().await?);
-
-    // let mut hmac = Hmac::<Sha384>::new_from_slice(api_secret.as_bytes()).expect("HMAC creation failed");
-    // hmac.update(message.as_bytes());
-    // let code_bytes = hmac.finalize().into_bytes();
-    // let signature = hex::encode(code_bytes);
-
-    // let mut file = File::create("data.txt").unwrap();
-    // file.write_all(signature.as_bytes()).unwrap();
-
-    // let mut headers = reqwest::header::HeaderMap::new();
-    // headers.insert("bfx-apikey", reqwest::header::HeaderValue::from_str(&api_key).unwrap());
-    // headers.insert("bfx-nonce", reqwest::header::HeaderValue::from_str(&nonce).unwrap());
-    // headers.insert("bfx-signature", reqwest::header::HeaderValue::from_str(&hex::encode(signature)).unwrap());
-
-    // let request_url = format!("{API_BITFINEX_ENDPOINT}/{endpoint}");
-    // let client = reqwest::Client::new();
-    // let response = client
-    //                                     .post(request_url)
-    //                                     .headers(headers)
-    //                                     .json(&payload)
-    //                                     .send()
-    //                                     .await?;
-
-    // let trades: Vec<BitfinexTrade> = response.json().await?;
-    // return Ok(trades);
+    #[test]
+    fn test_nonce_increases() {
+        let client = BitfinexClient::public_from_env();
+        let first: i64 = client.next_nonce().parse().unwrap();
+        let second: i64 = client.next_nonce().parse().unwrap();
+        assert!(second > first);
+    }
 }
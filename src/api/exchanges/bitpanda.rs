@@ -0,0 +1,244 @@
+use reqwest::header::{HeaderMap, HeaderValue};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::env;
+
+use crate::errors::ApiError;
+
+const API_BITPANDA_ENDPOINT: &str = "https://api.bitpanda.com";
+const PAGE_SIZE: u32 = 100;
+
+/* Bitpanda client for the public API of the broker (wallets, trades, crypto and fiat transactions).
+The api key is only sent in the X-Api-Key header, there is no signature.
+The base url can be changed (env BITPANDA_API_URL) to use a local stand-in for the tests.
+https://developers.bitpanda.com/platform/
+*/
+#[derive(Debug, Clone)]
+pub struct BitpandaClient {
+    base_url: String,
+    api_key: String,
+}
+
+impl BitpandaClient {
+    pub fn new(base_url: String, api_key: String) -> Self {
+        Self { base_url, api_key }
+    }
+
+    pub fn from_env() -> Self {
+        let api_key = env::var("BITPANDA_KEY").expect("BITPANDA_KEY not set in .env file");
+        let base_url = env::var("BITPANDA_API_URL").unwrap_or(API_BITPANDA_ENDPOINT.to_string());
+        Self::new(base_url, api_key)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<T, ApiError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Api-Key",
+            HeaderValue::from_str(&self.api_key)
+                .map_err(|e| ApiError::ApiCallError(e.to_string()))?,
+        );
+        let url = format!("{}{path_and_query}", self.base_url);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(ApiError::ApiCallError(format!(
+                "Bitpanda error {status}: {text}"
+            )));
+        }
+        serde_json::from_str(&text).map_err(|e| ApiError::DeserializationError(e.to_string()))
+    }
+
+    /* The lists are paginated with a cursor, given in the meta of each page until the last one */
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<Vec<BitpandaItem<T>>, ApiError> {
+        let mut data: Vec<BitpandaItem<T>> = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut path_and_query = format!("{path}?page_size={PAGE_SIZE}");
+            if let Some(cursor) = &cursor {
+                path_and_query.push_str(&format!("&cursor={cursor}"));
+            }
+            let page: BitpandaPage<T> = self.get(&path_and_query).await?;
+            data.extend(page.data);
+            cursor = page.meta.and_then(|meta| meta.next_cursor);
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(data)
+    }
+
+    pub async fn fetch_wallets(&self) -> Result<Vec<BitpandaItem<BitpandaWallet>>, ApiError> {
+        let page: BitpandaPage<BitpandaWallet> = self.get("/v1/wallets").await?;
+        Ok(page.data)
+    }
+
+    pub async fn fetch_fiat_wallets(
+        &self,
+    ) -> Result<Vec<BitpandaItem<BitpandaFiatWallet>>, ApiError> {
+        let page: BitpandaPage<BitpandaFiatWallet> = self.get("/v1/fiatwallets").await?;
+        Ok(page.data)
+    }
+
+    pub async fn fetch_trades(&self) -> Result<Vec<BitpandaItem<BitpandaTrade>>, ApiError> {
+        self.get_all_pages("/v1/trades").await
+    }
+
+    pub async fn fetch_wallet_transactions(
+        &self,
+    ) -> Result<Vec<BitpandaItem<BitpandaWalletTransaction>>, ApiError> {
+        self.get_all_pages("/v1/wallets/transactions").await
+    }
+
+    pub async fn fetch_fiat_transactions(
+        &self,
+    ) -> Result<Vec<BitpandaItem<BitpandaFiatTransaction>>, ApiError> {
+        self.get_all_pages("/v1/fiatwallets/transactions").await
+    }
+}
+
+/* Everything fetched from Bitpanda, kept raw so it can be saved and mapped again without calling the API */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BitpandaHistory {
+    pub wallets: Vec<BitpandaItem<BitpandaWallet>>,
+    pub fiat_wallets: Vec<BitpandaItem<BitpandaFiatWallet>>,
+    pub trades: Vec<BitpandaItem<BitpandaTrade>>,
+    pub wallet_transactions: Vec<BitpandaItem<BitpandaWalletTransaction>>,
+    pub fiat_transactions: Vec<BitpandaItem<BitpandaFiatTransaction>>,
+}
+
+pub async fn fetch_history_bitpanda(client: &BitpandaClient) -> Result<BitpandaHistory, ApiError> {
+    Ok(BitpandaHistory {
+        wallets: client.fetch_wallets().await?,
+        fiat_wallets: client.fetch_fiat_wallets().await?,
+        trades: client.fetch_trades().await?,
+        wallet_transactions: client.fetch_wallet_transactions().await?,
+        fiat_transactions: client.fetch_fiat_transactions().await?,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BitpandaPage<T> {
+    pub data: Vec<BitpandaItem<T>>,
+    pub meta: Option<BitpandaMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BitpandaMeta {
+    pub next_cursor: Option<String>,
+}
+
+/* Every object of the API is given as {"type": ..., "attributes": {...}, "id": ...} */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaItem<T> {
+    pub id: String,
+    pub attributes: T,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaTime {
+    pub date_iso8601: String,
+    pub unix: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaWallet {
+    pub cryptocoin_id: String,
+    pub cryptocoin_symbol: String,
+    pub balance: Decimal,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaFiatWallet {
+    pub fiat_id: String,
+    pub fiat_symbol: String,
+    pub balance: Decimal,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaTrade {
+    pub status: String,
+    pub r#type: String, // buy or sell
+    pub cryptocoin_id: String,
+    pub fiat_id: String,
+    pub amount_fiat: Decimal,
+    pub amount_cryptocoin: Decimal,
+    pub fiat_to_eur_rate: Decimal,
+    pub wallet_id: String,
+    pub fiat_wallet_id: String,
+    pub time: BitpandaTime,
+    pub price: Decimal, // Price of the cryptocoin in the fiat of the trade
+    #[serde(default)]
+    pub is_savings: bool, // Buy of a savings plan
+    #[serde(default)]
+    pub is_swap: bool,
+    pub best_fee_collection: Option<BitpandaBestFeeCollection>, // Fee of the trade paid in BEST
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaBestFeeCollection {
+    pub attributes: BitpandaBestFeeCollectionAttributes,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaBestFeeCollectionAttributes {
+    pub best_current_price_eur: Decimal,
+    pub wallet_transaction: BitpandaItem<BitpandaWalletTransaction>, // Outgoing transfer of the BEST wallet
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaTagAttributes {
+    pub short_name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaTag {
+    pub attributes: BitpandaTagAttributes,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaWalletTransaction {
+    pub amount: Decimal,
+    pub recipient: Option<String>,
+    pub time: BitpandaTime,
+    pub in_or_out: String, // incoming or outgoing
+    pub r#type: String,    // deposit, withdrawal, transfer, buy or sell
+    pub status: String,
+    pub amount_eur: Option<Decimal>, // Value in euro at the time of the transaction
+    pub wallet_id: String,
+    pub fee: Decimal,
+    pub cryptocoin_id: String,
+    #[serde(default)]
+    pub tags: Vec<BitpandaTag>,
+    #[serde(default)]
+    pub is_bfc: bool, // BEST fee collection: fee of a trade paid in BEST
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BitpandaFiatTransaction {
+    pub fiat_wallet_id: String,
+    pub fiat_id: String,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub to_eur_rate: Decimal,
+    pub time: BitpandaTime,
+    pub in_or_out: String, // incoming or outgoing
+    pub r#type: String,    // deposit, withdrawal, buy or sell
+    pub status: String,
+}
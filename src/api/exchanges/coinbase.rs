@@ -0,0 +1,307 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use std::env;
+
+use crate::errors::ApiError;
+
+const API_COINBASE_ENDPOINT: &str = "https://api.coinbase.com";
+const CB_VERSION: &str = "2024-01-01";
+
+/* Coinbase client for the v2 API (accounts and their transactions) and the Advanced Trade API (fills).
+The Coinbase Pro history has been migrated to Advanced Trade: its fills are returned by the same endpoint.
+The base url can be changed (env COINBASE_API_URL) to use a local stand-in for the tests.
+https://docs.cdp.coinbase.com/coinbase-app/docs/api-key-authentication
+*/
+#[derive(Debug, Clone)]
+pub struct CoinbaseClient {
+    base_url: String,
+    api_key: String,
+    api_secret: String,
+}
+
+/* The signature is the hex encoded HMAC-SHA256 of timestamp + method + request path + body, keyed with the api secret */
+fn get_coinbase_signature(
+    timestamp: &str,
+    method: &str,
+    request_path: &str,
+    body: &str,
+    secret: &str,
+) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("Wrong Key size");
+    mac.update(format!("{timestamp}{method}{request_path}{body}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+impl CoinbaseClient {
+    pub fn new(base_url: String, api_key: String, api_secret: String) -> Self {
+        Self {
+            base_url,
+            api_key,
+            api_secret,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let api_key = env::var("COINBASE_KEY").expect("COINBASE_KEY not set in .env file");
+        let api_secret =
+            env::var("COINBASE_SECRET").expect("COINBASE_SECRET not set in .env file");
+        Self::new(Self::url_from_env(), api_key, api_secret)
+    }
+
+    /* Client for the public endpoints only (prices), no key needed */
+    pub fn public_from_env() -> Self {
+        Self::new(Self::url_from_env(), String::new(), String::new())
+    }
+
+    fn url_from_env() -> String {
+        env::var("COINBASE_API_URL").unwrap_or(API_COINBASE_ENDPOINT.to_string())
+    }
+
+    /* path_and_query is the full request path, as given by the v2 pagination (next_uri).
+    The v2 API signs the path with its query, the Advanced Trade API only the path */
+    async fn get<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<T, ApiError> {
+        let mut headers = HeaderMap::new();
+        headers.insert("CB-VERSION", HeaderValue::from_static(CB_VERSION));
+        if !self.api_key.is_empty() {
+            let timestamp = Utc::now().timestamp().to_string();
+            let request_path = if path_and_query.starts_with("/api/v3/") {
+                path_and_query.split('?').next().unwrap_or_default()
+            } else {
+                path_and_query
+            };
+            let signature =
+                get_coinbase_signature(&timestamp, "GET", request_path, "", &self.api_secret);
+            for (name, value) in [
+                ("CB-ACCESS-KEY", self.api_key.clone()),
+                ("CB-ACCESS-SIGN", signature),
+                ("CB-ACCESS-TIMESTAMP", timestamp),
+            ] {
+                headers.insert(
+                    name,
+                    HeaderValue::from_str(&value)
+                        .map_err(|e| ApiError::ApiCallError(e.to_string()))?,
+                );
+            }
+        }
+        let url = format!("{}{path_and_query}", self.base_url);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(ApiError::ApiCallError(format!(
+                "Coinbase error {status}: {text}"
+            )));
+        }
+        serde_json::from_str(&text).map_err(|e| ApiError::DeserializationError(e.to_string()))
+    }
+
+    /* Follow the next_uri of the v2 pagination */
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        first_path: &str,
+    ) -> Result<Vec<T>, ApiError> {
+        let mut data: Vec<T> = Vec::new();
+        let mut next_path = Some(first_path.to_string());
+        while let Some(path) = next_path {
+            let page: CoinbasePage<T> = self.get(&path).await?;
+            data.extend(page.data);
+            next_path = page.pagination.next_uri;
+        }
+        Ok(data)
+    }
+
+    pub async fn fetch_accounts(&self) -> Result<Vec<CoinbaseAccount>, ApiError> {
+        self.get_all_pages("/v2/accounts?limit=100").await
+    }
+
+    pub async fn fetch_account_transactions(
+        &self,
+        account_id: &str,
+    ) -> Result<Vec<CoinbaseTransaction>, ApiError> {
+        self.get_all_pages(&format!("/v2/accounts/{account_id}/transactions?limit=100"))
+            .await
+    }
+
+    /* Fills of the Advanced Trade (and former Coinbase Pro) orders, paginated with a cursor */
+    pub async fn fetch_fills(&self) -> Result<Vec<CoinbaseFill>, ApiError> {
+        let mut fills: Vec<CoinbaseFill> = Vec::new();
+        let mut cursor = String::new();
+        loop {
+            let mut path = "/api/v3/brokerage/orders/historical/fills?limit=100".to_string();
+            if !cursor.is_empty() {
+                path.push_str(&format!("&cursor={cursor}"));
+            }
+            let page: FillsPage = self.get(&path).await?;
+            let count = page.fills.len();
+            fills.extend(page.fills);
+            if count == 0 || page.cursor.is_empty() {
+                break;
+            }
+            cursor = page.cursor;
+        }
+        Ok(fills)
+    }
+
+    /* Spot price in euro for the day of the given time */
+    pub async fn fetch_price(
+        &self,
+        currency: &str,
+        time: DateTime<Utc>,
+    ) -> Result<Decimal, ApiError> {
+        let price: CoinbaseData<CoinbaseAmount> = self
+            .get(&format!(
+                "/v2/prices/{currency}-EUR/spot?date={}",
+                time.format("%Y-%m-%d")
+            ))
+            .await?;
+        Ok(price.data.amount)
+    }
+}
+
+/* Everything fetched from Coinbase, kept raw so it can be saved and mapped again without calling the API */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CoinbaseHistory {
+    pub accounts: Vec<CoinbaseAccount>,
+    pub transactions: Vec<CoinbaseTransaction>,
+    pub fills: Vec<CoinbaseFill>,
+}
+
+pub async fn fetch_history_coinbase(client: &CoinbaseClient) -> Result<CoinbaseHistory, ApiError> {
+    let accounts = client.fetch_accounts().await?;
+    let mut transactions = Vec::new();
+    for account in &accounts {
+        transactions.extend(client.fetch_account_transactions(&account.id).await?);
+    }
+    let fills = client.fetch_fills().await?;
+    Ok(CoinbaseHistory {
+        accounts,
+        transactions,
+        fills,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinbasePage<T> {
+    pub pagination: CoinbasePagination,
+    pub data: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinbasePagination {
+    pub next_uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinbaseData<T> {
+    pub data: T,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoinbaseAmount {
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoinbaseCurrency {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoinbaseAccount {
+    pub id: String,
+    pub name: String,
+    pub currency: CoinbaseCurrency,
+    pub balance: CoinbaseAmount,
+    pub r#type: String,
+}
+
+/* The amounts are signed: negative when leaving the account */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoinbaseTransaction {
+    pub id: String,
+    pub r#type: String,
+    pub status: String,
+    pub amount: CoinbaseAmount,
+    pub native_amount: CoinbaseAmount,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub network: Option<CoinbaseNetwork>,
+    #[serde(default)]
+    pub to: Option<CoinbaseParty>,
+    #[serde(default)]
+    pub from: Option<CoinbaseParty>,
+    #[serde(default)]
+    pub trade: Option<CoinbaseResource>, // Conversion between two crypto, both sides share the same trade id
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoinbaseNetwork {
+    pub status: Option<String>,
+    pub hash: Option<String>,
+    pub transaction_fee: Option<CoinbaseAmount>,
+    pub transaction_amount: Option<CoinbaseAmount>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoinbaseParty {
+    pub resource: Option<String>,
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoinbaseResource {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FillsPage {
+    pub fills: Vec<CoinbaseFill>,
+    #[serde(default)]
+    pub cursor: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CoinbaseFill {
+    pub entry_id: String,
+    pub trade_id: String,
+    pub order_id: String,
+    pub trade_time: DateTime<Utc>,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub commission: Decimal, // In the quote currency
+    pub product_id: String,  // BASE-QUOTE
+    pub side: String,        // BUY or SELL
+    #[serde(default)]
+    pub size_in_quote: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        let signature =
+            get_coinbase_signature("1700000000", "GET", "/v2/accounts?limit=100", "", "secret");
+        assert_eq!(
+            signature,
+            "10d3a7608f525eb44484257305662b6485f17e3924eb2dec9c53d142686e5750"
+        );
+    }
+}
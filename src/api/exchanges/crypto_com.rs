@@ -0,0 +1,424 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use hashbrown::HashMap;
+use hmac::{Hmac, Mac};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    env,
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+use crate::{
+    errors::ApiError,
+    utils::{merge_by_key, time_windows},
+};
+
+const API_CRYPTO_COM_ENDPOINT: &str = "https://api.crypto.com";
+const TRADES_LIMIT: usize = 100;
+const MOVEMENTS_PAGE_SIZE: usize = 200;
+
+/* Crypto.com Exchange API client (v1). Every private method is a POST of a signed json request.
+The Crypto.com App has no API: its history comes from the csv export of the App (see read_crypto_com_app_csv).
+The base url can be changed (env CRYPTO_COM_API_URL) to use a local stand-in for the tests.
+https://exchange-docs.crypto.com/exchange/v1/rest-ws/index.html
+*/
+#[derive(Debug)]
+pub struct CryptoComClient {
+    base_url: String,
+    api_key: String,
+    api_secret: String,
+    last_nonce: AtomicI64,
+}
+
+/* The signature is the hex encoded HMAC-SHA256 of method + id + api_key + parameters + nonce, the parameters being
+the concatenation of their keys and values sorted by key */
+fn get_crypto_com_signature(
+    method: &str,
+    id: i64,
+    api_key: &str,
+    params: &[(&str, String)],
+    nonce: i64,
+    secret: &str,
+) -> String {
+    let mut sorted_params = params.to_vec();
+    sorted_params.sort_by_key(|(key, _)| *key);
+    let params_string: String = sorted_params
+        .iter()
+        .map(|(key, value)| format!("{key}{value}"))
+        .collect();
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("Wrong Key size");
+    mac.update(format!("{method}{id}{api_key}{params_string}{nonce}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+impl CryptoComClient {
+    pub fn new(base_url: String, api_key: String, api_secret: String) -> Self {
+        Self {
+            base_url,
+            api_key,
+            api_secret,
+            last_nonce: AtomicI64::new(0),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let api_key = env::var("CRYPTO_COM_KEY").expect("CRYPTO_COM_KEY not set in .env file");
+        let api_secret =
+            env::var("CRYPTO_COM_SECRET").expect("CRYPTO_COM_SECRET not set in .env file");
+        let base_url =
+            env::var("CRYPTO_COM_API_URL").unwrap_or(API_CRYPTO_COM_ENDPOINT.to_string());
+        Self::new(base_url, api_key, api_secret)
+    }
+
+    /* The nonce (milliseconds) must increase between two requests, see the Bitfinex client */
+    fn next_nonce(&self) -> i64 {
+        let now = Utc::now().timestamp_millis();
+        let previous = self
+            .last_nonce
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap_or(now);
+        now.max(previous + 1)
+    }
+
+    async fn post_private<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, String)],
+    ) -> Result<T, ApiError> {
+        let nonce = self.next_nonce();
+        let signature = get_crypto_com_signature(
+            method,
+            nonce,
+            &self.api_key,
+            params,
+            nonce,
+            &self.api_secret,
+        );
+        let body = serde_json::json!({
+            "id": nonce,
+            "method": method,
+            "api_key": self.api_key,
+            "params": params
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone().into()))
+                .collect::<serde_json::Map<String, serde_json::Value>>(),
+            "nonce": nonce,
+            "sig": signature,
+        });
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(format!("{}/exchange/v1/{method}", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        // The errors are given in the body with a code other than 0
+        let response: CryptoComResponse<T> = serde_json::from_str(&text).map_err(|e| {
+            if status.is_success() {
+                ApiError::DeserializationError(e.to_string())
+            } else {
+                ApiError::ApiCallError(format!("Crypto.com error {status}: {text}"))
+            }
+        })?;
+        match response.result {
+            Some(result) if response.code == 0 => Ok(result),
+            _ => Err(ApiError::ApiCallError(format!(
+                "Crypto.com error {}: {}",
+                response.code,
+                response.message.unwrap_or_default()
+            ))),
+        }
+    }
+
+    /* The trades are returned from the newest, the next page ends at the oldest trade of the previous one */
+    pub async fn fetch_trades(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CryptoComTrade>, ApiError> {
+        let mut trades: HashMap<String, CryptoComTrade> = HashMap::new();
+        let mut page_end = end.timestamp_millis();
+        loop {
+            let params = [
+                ("start_time", start.timestamp_millis().to_string()),
+                ("end_time", page_end.to_string()),
+                ("limit", TRADES_LIMIT.to_string()),
+            ];
+            let page: CryptoComData<CryptoComTrade> =
+                self.post_private("private/get-trades", &params).await?;
+            let count = page.data.len();
+            let oldest = page.data.iter().map(|trade| trade.create_time).min();
+            trades.extend(
+                page.data
+                    .into_iter()
+                    .map(|trade| (trade.trade_id.clone(), trade)),
+            );
+            match oldest {
+                Some(oldest) if count >= TRADES_LIMIT && oldest < page_end => page_end = oldest,
+                _ => break,
+            }
+        }
+        Ok(trades.into_values().collect())
+    }
+
+    pub async fn fetch_deposits(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CryptoComMovement>, ApiError> {
+        let mut deposits: HashMap<String, CryptoComMovement> = HashMap::new();
+        for (window_start, window_end) in time_windows(start, end, Duration::days(90)) {
+            let mut page = 0;
+            loop {
+                let params = movement_params(window_start, window_end, page);
+                let result: DepositHistory = self
+                    .post_private("private/get-deposit-history", &params)
+                    .await?;
+                let count = result.deposit_list.len();
+                deposits.extend(
+                    result
+                        .deposit_list
+                        .into_iter()
+                        .map(|deposit| (deposit.id.clone(), deposit)),
+                );
+                if count < MOVEMENTS_PAGE_SIZE {
+                    break;
+                }
+                page += 1;
+            }
+        }
+        Ok(deposits.into_values().collect())
+    }
+
+    pub async fn fetch_withdrawals(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CryptoComMovement>, ApiError> {
+        let mut withdrawals: HashMap<String, CryptoComMovement> = HashMap::new();
+        for (window_start, window_end) in time_windows(start, end, Duration::days(90)) {
+            let mut page = 0;
+            loop {
+                let params = movement_params(window_start, window_end, page);
+                let result: WithdrawalHistory = self
+                    .post_private("private/get-withdrawal-history", &params)
+                    .await?;
+                let count = result.withdrawal_list.len();
+                withdrawals.extend(
+                    result
+                        .withdrawal_list
+                        .into_iter()
+                        .map(|withdrawal| (withdrawal.id.clone(), withdrawal)),
+                );
+                if count < MOVEMENTS_PAGE_SIZE {
+                    break;
+                }
+                page += 1;
+            }
+        }
+        Ok(withdrawals.into_values().collect())
+    }
+}
+
+fn movement_params(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    page: usize,
+) -> [(&'static str, String); 4] {
+    [
+        ("start_ts", start.timestamp_millis().to_string()),
+        ("end_ts", end.timestamp_millis().to_string()),
+        ("page_size", MOVEMENTS_PAGE_SIZE.to_string()),
+        ("page", page.to_string()),
+    ]
+}
+
+/* Rows of the "Transactions" csv exported from the Crypto.com App:
+Timestamp (UTC),Transaction Description,Currency,Amount,To Currency,To Amount,Native Currency,Native Amount,Native Amount (in USD),Transaction Kind,Transaction Hash
+*/
+pub fn read_crypto_com_app_csv(file_path: &str) -> Result<Vec<CryptoComAppRow>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_path)
+        .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+    let mut rows = Vec::new();
+    for record in reader.deserialize::<CryptoComAppRecord>() {
+        let record = record.map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+        let timestamp = NaiveDateTime::parse_from_str(&record.timestamp, "%Y-%m-%d %H:%M:%S")
+            .map_err(|e| ApiError::DeserializationError(e.to_string()))?
+            .and_utc()
+            .timestamp();
+        rows.push(CryptoComAppRow {
+            timestamp,
+            description: record.description,
+            currency: record.currency,
+            amount: record.amount,
+            to_currency: record.to_currency.filter(|currency| !currency.is_empty()),
+            to_amount: record.to_amount,
+            native_currency: record.native_currency,
+            native_amount: record.native_amount,
+            kind: record.kind,
+            hash: record.hash.filter(|hash| !hash.is_empty()),
+        });
+    }
+    Ok(rows)
+}
+
+/* Everything fetched from the Crypto.com Exchange and read from the App export, kept raw so it can be saved and
+mapped again without calling the API */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CryptoComHistory {
+    pub trades: Vec<CryptoComTrade>,
+    pub deposits: Vec<CryptoComMovement>,
+    pub withdrawals: Vec<CryptoComMovement>,
+    pub app_rows: Vec<CryptoComAppRow>,
+}
+
+impl CryptoComHistory {
+    /* Add the Exchange history fetched since this one, the operations fetched again replacing the saved ones */
+    pub fn merge(&mut self, other: CryptoComHistory) {
+        merge_by_key(&mut self.trades, other.trades, |trade| {
+            trade.trade_id.clone()
+        });
+        merge_by_key(&mut self.deposits, other.deposits, |deposit| {
+            deposit.id.clone()
+        });
+        merge_by_key(&mut self.withdrawals, other.withdrawals, |withdrawal| {
+            withdrawal.id.clone()
+        });
+    }
+}
+
+pub async fn fetch_history_crypto_com(
+    client: &CryptoComClient,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<CryptoComHistory, ApiError> {
+    Ok(CryptoComHistory {
+        trades: client.fetch_trades(start, end).await?,
+        deposits: client.fetch_deposits(start, end).await?,
+        withdrawals: client.fetch_withdrawals(start, end).await?,
+        app_rows: Vec::new(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CryptoComResponse<T> {
+    pub code: i64,
+    pub message: Option<String>,
+    pub result: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CryptoComData<T> {
+    pub data: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DepositHistory {
+    pub deposit_list: Vec<CryptoComMovement>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WithdrawalHistory {
+    pub withdrawal_list: Vec<CryptoComMovement>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CryptoComTrade {
+    pub trade_id: String,
+    pub order_id: String,
+    pub instrument_name: String, // BASE_QUOTE
+    pub side: String,            // BUY or SELL
+    pub traded_price: Decimal,
+    pub traded_quantity: Decimal,
+    pub fees: Decimal, // Negative
+    pub fee_instrument_name: String,
+    pub create_time: i64,
+}
+
+/* Deposit (status 1: arrived) or withdrawal (status 5: completed) */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CryptoComMovement {
+    pub id: String,
+    pub currency: String,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub address: String,
+    pub status: String,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CryptoComAppRecord {
+    #[serde(rename = "Timestamp (UTC)")]
+    timestamp: String,
+    #[serde(rename = "Transaction Description")]
+    description: String,
+    #[serde(rename = "Currency")]
+    currency: String,
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+    #[serde(rename = "To Currency")]
+    to_currency: Option<String>,
+    #[serde(rename = "To Amount")]
+    to_amount: Option<Decimal>,
+    #[serde(rename = "Native Currency")]
+    native_currency: String,
+    #[serde(rename = "Native Amount")]
+    native_amount: Decimal,
+    #[serde(rename = "Transaction Kind")]
+    kind: String,
+    #[serde(rename = "Transaction Hash")]
+    hash: Option<String>,
+}
+
+/* Row of the App export. The amounts are signed, negative when leaving the App wallet */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CryptoComAppRow {
+    pub timestamp: i64,
+    pub description: String,
+    pub currency: String,
+    pub amount: Decimal,
+    pub to_currency: Option<String>,
+    pub to_amount: Option<Decimal>,
+    pub native_currency: String,
+    pub native_amount: Decimal,
+    pub kind: String, // crypto_viban_exchange, referral_card_cashback, crypto_earn_interest_paid...
+    pub hash: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        let params = [
+            ("start_time", "1672531200000".to_string()),
+            ("end_time", "1675209600000".to_string()),
+            ("limit", "100".to_string()),
+        ];
+        assert_eq!(
+            get_crypto_com_signature(
+                "private/get-trades",
+                1675209600123,
+                "test-key",
+                &params,
+                1675209600123,
+                "test-secret"
+            ),
+            "e8db9911e3887f40f7e0336a81573b5783704e3f16c177fa03ae604536a07575"
+        );
+    }
+}
@@ -87,11 +87,15 @@ async fn fetch_ledger_data(
     api_secret: &String,
     counter: &mut u8,
     tier: &Tier,
+    start: Option<f64>,
 ) -> Result<Vec<LedgerHistory>, ApiError> {
     let mut ledger_history: Vec<LedgerHistory> = Vec::new();
     let mut unique_entries: HashSet<String> = HashSet::new();
     let url_path = String::from("/0/private/Ledgers");
     let mut params = HashMap::new();
+    if let Some(start) = start {
+        params.insert("start", start.to_string()); // Exclusive
+    }
 
     let ledger_response: Response<LedgersInfo> =
         fetch_data(&url_path, &mut params, &api_key, &api_secret)
@@ -143,9 +147,13 @@ async fn fetch_trade_history(
     api_secret: &String,
     counter: &mut u8,
     tier: &Tier,
+    start: Option<f64>,
 ) -> Result<HashMap<String, TradeInfo>, ApiError> {
     let url_path = String::from("/0/private/TradesHistory");
     let mut params = HashMap::new();
+    if let Some(start) = start {
+        params.insert("start", start.to_string());
+    }
     let mut trade_history = HashMap::new();
     let trade_response: Response<TradeHistory> =
         fetch_data(&url_path, &mut params, &api_key, &api_secret)
@@ -298,7 +306,6 @@ pub async fn fetch_specific_trade_data(
     return Ok(trade_response);
 }
 
-#[tokio::main]
 pub async fn fetch_assets_pair() -> Result<Response<AssetPairs>, ApiError> {
     let url = "/0/public/AssetPairs";
     let full_url = [API_KRAKEN_ENDPOINT, url].concat();
@@ -316,7 +323,7 @@ pub async fn fetch_assets_pair() -> Result<Response<AssetPairs>, ApiError> {
         ));
     }
 
-    return Ok(trade_response);
+    Ok(trade_response)
 }
 
 pub type HistoryResponse = (
@@ -326,23 +333,27 @@ pub type HistoryResponse = (
     HashMap<String, Withdrawal>,
 );
 
-#[tokio::main]
-pub async fn fetch_history_kraken(tier: Tier) -> Result<HistoryResponse, ApiError> {
+/* Fetch the history after start (a unix timestamp), or the whole history. The deposits and the withdrawals are always
+fully fetched, being needed to map the ledger entries */
+pub async fn fetch_history_kraken(
+    tier: Tier,
+    start: Option<f64>,
+) -> Result<HistoryResponse, ApiError> {
     let api_key = env::var("KRAKEN_KEY").expect("KRAKEN_KEY not set in .env file");
     let api_secret: String =
         env::var("KRAKEN_SECRET").expect("KRAKEN_SECRET not set in .env file");
     let mut api_counter: u8 = 0; // Counter limit depending on Tier:  see https://docs.kraken.com/api/docs/guides/spot-rest-ratelimits
 
     let ledger_history: Vec<LedgerHistory> =
-        fetch_ledger_data(&api_key, &api_secret, &mut api_counter, &tier).await?;
+        fetch_ledger_data(&api_key, &api_secret, &mut api_counter, &tier, start).await?;
     let trade_history: HashMap<String, TradeInfo> =
-        fetch_trade_history(&api_key, &api_secret, &mut api_counter, &tier).await?;
+        fetch_trade_history(&api_key, &api_secret, &mut api_counter, &tier, start).await?;
     let deposits: HashMap<String, Deposit> =
         fetch_deposit_data(&api_key, &api_secret, &mut api_counter, &tier).await?;
     let withdrawals: HashMap<String, Withdrawal> =
         fetch_withdraw_data(&api_key, &api_secret, &mut api_counter, &tier).await?;
 
-    return Ok((ledger_history, trade_history, deposits, withdrawals));
+    Ok((ledger_history, trade_history, deposits, withdrawals))
 }
 
 pub enum Tier {
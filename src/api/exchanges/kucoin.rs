@@ -0,0 +1,450 @@
+use base64::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use hashbrown::HashMap;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use std::env;
+
+use crate::{
+    errors::ApiError,
+    utils::{merge_by_key, time_windows},
+};
+
+const API_KUCOIN_ENDPOINT: &str = "https://api.kucoin.com";
+const PAGE_SIZE: u32 = 500;
+const SUCCESS_CODE: &str = "200000";
+
+/* KuCoin Spot API client. The base url can be changed (env KUCOIN_API_URL) to use a local stand-in for the tests.
+https://www.kucoin.com/docs/basic-info/connection-method/authentication/creating-a-request
+*/
+#[derive(Debug, Clone)]
+pub struct KuCoinClient {
+    base_url: String,
+    api_key: String,
+    api_secret: String,
+    api_passphrase: String,
+}
+
+/* The signature is the base64 encoded HMAC-SHA256 of timestamp + method + endpoint (with the query) + body.
+With the version 2 of the api keys, the passphrase is also signed with the same secret.
+*/
+fn get_kucoin_signature(message: &str, secret: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("Wrong Key size");
+    mac.update(message.as_bytes());
+    BASE64_STANDARD.encode(mac.finalize().into_bytes())
+}
+
+impl KuCoinClient {
+    pub fn new(
+        base_url: String,
+        api_key: String,
+        api_secret: String,
+        api_passphrase: String,
+    ) -> Self {
+        Self {
+            base_url,
+            api_key,
+            api_secret,
+            api_passphrase,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let api_key = env::var("KUCOIN_KEY").expect("KUCOIN_KEY not set in .env file");
+        let api_secret = env::var("KUCOIN_SECRET").expect("KUCOIN_SECRET not set in .env file");
+        let api_passphrase =
+            env::var("KUCOIN_PASSPHRASE").expect("KUCOIN_PASSPHRASE not set in .env file");
+        Self::new(Self::url_from_env(), api_key, api_secret, api_passphrase)
+    }
+
+    /* Client for the public endpoints only (prices), no key needed */
+    pub fn public_from_env() -> Self {
+        Self::new(
+            Self::url_from_env(),
+            String::new(),
+            String::new(),
+            String::new(),
+        )
+    }
+
+    fn url_from_env() -> String {
+        env::var("KUCOIN_API_URL").unwrap_or(API_KUCOIN_ENDPOINT.to_string())
+    }
+
+    fn auth_headers(&self, endpoint: &str) -> Result<HeaderMap, ApiError> {
+        let timestamp = Utc::now().timestamp_millis().to_string();
+        let signature =
+            get_kucoin_signature(&format!("{timestamp}GET{endpoint}"), &self.api_secret);
+        let passphrase = get_kucoin_signature(&self.api_passphrase, &self.api_secret);
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("KC-API-KEY", self.api_key.as_str()),
+            ("KC-API-SIGN", signature.as_str()),
+            ("KC-API-TIMESTAMP", timestamp.as_str()),
+            ("KC-API-PASSPHRASE", passphrase.as_str()),
+            ("KC-API-KEY-VERSION", "2"),
+        ] {
+            headers.insert(
+                name,
+                HeaderValue::from_str(value).map_err(|e| ApiError::ApiCallError(e.to_string()))?,
+            );
+        }
+        Ok(headers)
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        url_path: &str,
+        params: &[(&str, String)],
+        signed: bool,
+    ) -> Result<T, ApiError> {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+        let endpoint = if query.is_empty() {
+            url_path.to_string()
+        } else {
+            format!("{url_path}?{query}")
+        };
+        let headers = if signed {
+            self.auth_headers(&endpoint)?
+        } else {
+            HeaderMap::new()
+        };
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("{}{endpoint}", self.base_url))
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        // The errors are also given in the body, with a code other than 200000
+        let response: KuCoinResponse<T> = serde_json::from_str(&text).map_err(|e| {
+            if status.is_success() {
+                ApiError::DeserializationError(e.to_string())
+            } else {
+                ApiError::ApiCallError(format!("KuCoin error {status}: {text}"))
+            }
+        })?;
+        match response.data {
+            Some(data) if response.code == SUCCESS_CODE => Ok(data),
+            _ => Err(ApiError::ApiCallError(format!(
+                "KuCoin error {}: {}",
+                response.code,
+                response.msg.unwrap_or_default()
+            ))),
+        }
+    }
+
+    /* Query every page of a paginated endpoint, over consecutive time windows as KuCoin limits the time range of the
+    queries (24 hours for the ledgers) */
+    async fn fetch_windows<T: DeserializeOwned>(
+        &self,
+        url_path: &str,
+        extra_params: &[(&str, String)],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        max_duration: Duration,
+    ) -> Result<Vec<T>, ApiError> {
+        let mut items: Vec<T> = Vec::new();
+        for (window_start, window_end) in time_windows(start, end, max_duration) {
+            let mut current_page = 1;
+            loop {
+                let mut params = extra_params.to_vec();
+                params.extend([
+                    ("startAt", window_start.timestamp_millis().to_string()),
+                    ("endAt", window_end.timestamp_millis().to_string()),
+                    ("currentPage", current_page.to_string()),
+                    ("pageSize", PAGE_SIZE.to_string()),
+                ]);
+                let page: KuCoinPage<T> = self.get(url_path, &params, true).await?;
+                items.extend(page.items);
+                if current_page >= page.total_page {
+                    break;
+                }
+                current_page += 1;
+            }
+        }
+        Ok(items)
+    }
+
+    /* Fills of the trade account (TRADE) or of the cross margin account (MARGIN_TRADE), by windows of 7 days */
+    pub async fn fetch_fills(
+        &self,
+        trade_type: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<KuCoinFill>, ApiError> {
+        let fills: Vec<KuCoinFill> = self
+            .fetch_windows(
+                "/api/v1/fills",
+                &[("tradeType", trade_type.to_string())],
+                start,
+                end,
+                Duration::days(7),
+            )
+            .await?;
+        let fills: HashMap<String, KuCoinFill> = fills
+            .into_iter()
+            .map(|fill| (fill.trade_id.clone(), fill))
+            .collect();
+        Ok(fills.into_values().collect())
+    }
+
+    pub async fn fetch_deposits(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<KuCoinDeposit>, ApiError> {
+        let deposits: Vec<KuCoinDeposit> = self
+            .fetch_windows(
+                "/api/v1/deposits",
+                &[("status", "SUCCESS".to_string())],
+                start,
+                end,
+                Duration::days(1),
+            )
+            .await?;
+        let deposits: HashMap<(String, i64, Option<String>), KuCoinDeposit> = deposits
+            .into_iter()
+            .map(|deposit| {
+                (
+                    (
+                        deposit.currency.clone(),
+                        deposit.created_at,
+                        deposit.wallet_tx_id.clone(),
+                    ),
+                    deposit,
+                )
+            })
+            .collect();
+        Ok(deposits.into_values().collect())
+    }
+
+    pub async fn fetch_withdrawals(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<KuCoinWithdrawal>, ApiError> {
+        let withdrawals: Vec<KuCoinWithdrawal> = self
+            .fetch_windows(
+                "/api/v1/withdrawals",
+                &[("status", "SUCCESS".to_string())],
+                start,
+                end,
+                Duration::days(1),
+            )
+            .await?;
+        let withdrawals: HashMap<String, KuCoinWithdrawal> = withdrawals
+            .into_iter()
+            .map(|withdrawal| (withdrawal.id.clone(), withdrawal))
+            .collect();
+        Ok(withdrawals.into_values().collect())
+    }
+
+    /* Ledgers of the main, trade and margin accounts */
+    pub async fn fetch_ledgers(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<KuCoinLedgerEntry>, ApiError> {
+        let entries: Vec<KuCoinLedgerEntry> = self
+            .fetch_windows(
+                "/api/v1/accounts/ledgers",
+                &[],
+                start,
+                end,
+                Duration::days(1),
+            )
+            .await?;
+        let entries: HashMap<String, KuCoinLedgerEntry> = entries
+            .into_iter()
+            .map(|entry| (entry.id.clone(), entry))
+            .collect();
+        Ok(entries.into_values().collect())
+    }
+
+    /* Close price of the minute at the given time */
+    pub async fn fetch_price(
+        &self,
+        symbol: &str,
+        time: DateTime<Utc>,
+    ) -> Result<Decimal, ApiError> {
+        let params = [
+            ("type", "1min".to_string()),
+            ("symbol", symbol.to_string()),
+            ("startAt", time.timestamp().to_string()),
+            ("endAt", (time.timestamp() + 60).to_string()),
+        ];
+        // [time, open, close, high, low, volume, turnover]
+        let candles: Vec<Vec<String>> = self.get("/api/v1/market/candles", &params, false).await?;
+        let close = candles.last().and_then(|candle| candle.get(2)).ok_or(
+            ApiError::DeserializationError(format!("No candle for {symbol} at {time}")),
+        )?;
+        close
+            .parse()
+            .map_err(|e: rust_decimal::Error| ApiError::DeserializationError(e.to_string()))
+    }
+}
+
+/* Everything fetched from KuCoin, kept raw so it can be saved and mapped again without calling the API */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct KuCoinHistory {
+    pub fills: Vec<KuCoinFill>,
+    pub deposits: Vec<KuCoinDeposit>,
+    pub withdrawals: Vec<KuCoinWithdrawal>,
+    pub ledgers: Vec<KuCoinLedgerEntry>,
+}
+
+impl KuCoinHistory {
+    /* Add the history fetched since this one, the operations fetched again replacing the saved ones */
+    pub fn merge(&mut self, other: KuCoinHistory) {
+        merge_by_key(&mut self.fills, other.fills, |fill| fill.trade_id.clone());
+        merge_by_key(&mut self.deposits, other.deposits, |deposit| {
+            (
+                deposit.currency.clone(),
+                deposit.created_at,
+                deposit.wallet_tx_id.clone(),
+            )
+        });
+        merge_by_key(&mut self.withdrawals, other.withdrawals, |withdrawal| {
+            withdrawal.id.clone()
+        });
+        merge_by_key(&mut self.ledgers, other.ledgers, |entry| entry.id.clone());
+    }
+}
+
+pub async fn fetch_history_kucoin(
+    client: &KuCoinClient,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<KuCoinHistory, ApiError> {
+    let mut fills = client.fetch_fills("TRADE", start, end).await?;
+    fills.extend(client.fetch_fills("MARGIN_TRADE", start, end).await?);
+    Ok(KuCoinHistory {
+        fills,
+        deposits: client.fetch_deposits(start, end).await?,
+        withdrawals: client.fetch_withdrawals(start, end).await?,
+        ledgers: client.fetch_ledgers(start, end).await?,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KuCoinResponse<T> {
+    pub code: String,
+    pub data: Option<T>,
+    pub msg: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KuCoinPage<T> {
+    pub current_page: u32,
+    pub total_page: u32,
+    pub items: Vec<T>,
+}
+
+/* Sub-accounts of a KuCoin user, each one is a distinct wallet */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum KuCoinAccount {
+    Main,
+    Trade,
+    Margin,
+}
+
+impl KuCoinAccount {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KuCoinAccount::Main => "main",
+            KuCoinAccount::Trade => "trade",
+            KuCoinAccount::Margin => "margin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KuCoinFill {
+    pub symbol: String, // BASE-QUOTE
+    pub trade_id: String,
+    pub order_id: String,
+    pub side: String, // buy or sell
+    pub price: Decimal,
+    pub size: Decimal,  // Amount of base currency
+    pub funds: Decimal, // Amount of quote currency
+    pub fee: Decimal,
+    pub fee_currency: String,
+    pub trade_type: String, // TRADE or MARGIN_TRADE
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KuCoinDeposit {
+    pub currency: String,
+    pub chain: Option<String>,
+    pub status: String,
+    pub address: Option<String>,
+    pub is_inner: bool,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub wallet_tx_id: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KuCoinWithdrawal {
+    pub id: String,
+    pub currency: String,
+    pub chain: Option<String>,
+    pub status: String,
+    pub address: Option<String>,
+    pub is_inner: bool,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub wallet_tx_id: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KuCoinLedgerEntry {
+    pub id: String,
+    pub currency: String,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub balance: Decimal, // Balance of the account after the operation
+    pub account_type: KuCoinAccount,
+    pub biz_type: String,  // Transfer, Exchange, Deposit, Withdrawal...
+    pub direction: String, // in or out
+    pub created_at: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        let secret = "f03a5284-5c39-4aaa-9b20-dea10bdcf8e3";
+        let message = "1547015186532GET/api/v1/accounts/ledgers?currency=BTC";
+
+        assert_eq!(
+            get_kucoin_signature(message, secret),
+            "gPkFjNkGNnZ5OF+DjKo+b61zCrDXS3xJZR5Nr3PrSLY="
+        );
+    }
+}
@@ -1,5 +1,20 @@
-// pub mod bitfinex;
-// pub use bitfinex::*;
+pub mod bitfinex;
+pub use bitfinex::*;
 
 pub mod kraken_api;
 pub use kraken_api::*;
+
+pub mod binance;
+pub use binance::*;
+
+pub mod coinbase;
+pub use coinbase::*;
+
+pub mod kucoin;
+pub use kucoin::*;
+
+pub mod bitpanda;
+pub use bitpanda::*;
+
+pub mod crypto_com;
+pub use crypto_com::*;
@@ -0,0 +1,211 @@
+use hashbrown::HashSet;
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::env;
+
+use crate::errors::ApiError;
+
+const ALGORAND_INDEXER_ENDPOINT: &str = "https://mainnet-idx.algonode.cloud";
+const PAGE_LIMIT: usize = 1000;
+
+/* Client of an Algorand Indexer (v2), for reading the transactions of an account and the assets (ASA) it held.
+The public indexers need no token, a private one gets it in the X-Indexer-API-Token header (env ALGORAND_INDEXER_TOKEN).
+The base url can be changed (env ALGORAND_INDEXER_URL) to use another indexer or a local stand-in for the tests.
+https://developer.algorand.org/docs/rest-apis/indexer/
+*/
+#[derive(Debug, Clone)]
+pub struct AlgorandIndexerClient {
+    base_url: String,
+    api_token: Option<String>,
+}
+
+impl AlgorandIndexerClient {
+    pub fn new(base_url: String, api_token: Option<String>) -> Self {
+        Self {
+            base_url,
+            api_token,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let base_url =
+            env::var("ALGORAND_INDEXER_URL").unwrap_or(ALGORAND_INDEXER_ENDPOINT.to_string());
+        Self::new(base_url, env::var("ALGORAND_INDEXER_TOKEN").ok())
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<T, ApiError> {
+        let mut headers = HeaderMap::new();
+        if let Some(api_token) = &self.api_token {
+            headers.insert(
+                "X-Indexer-API-Token",
+                HeaderValue::from_str(api_token)
+                    .map_err(|e| ApiError::ApiCallError(e.to_string()))?,
+            );
+        }
+        let url = format!("{}{path_and_query}", self.base_url);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(ApiError::ApiCallError(format!(
+                "Algorand indexer error {status}: {text}"
+            )));
+        }
+        serde_json::from_str(&text).map_err(|e| ApiError::DeserializationError(e.to_string()))
+    }
+
+    /* The transactions are paginated with the next-token of the previous page, until a page is empty */
+    pub async fn fetch_transactions(
+        &self,
+        address: &str,
+    ) -> Result<Vec<AlgorandTransaction>, ApiError> {
+        let mut transactions: Vec<AlgorandTransaction> = Vec::new();
+        let mut next: Option<String> = None;
+        loop {
+            let mut path_and_query =
+                format!("/v2/accounts/{address}/transactions?limit={PAGE_LIMIT}");
+            if let Some(next) = &next {
+                path_and_query.push_str(&format!("&next={next}"));
+            }
+            let page: AlgorandTransactionsPage = self.get(&path_and_query).await?;
+            let count = page.transactions.len();
+            transactions.extend(page.transactions);
+            next = page.next_token;
+            if count == 0 || next.is_none() {
+                break;
+            }
+        }
+        Ok(transactions)
+    }
+
+    pub async fn fetch_asset(&self, asset_id: u64) -> Result<AlgorandAsset, ApiError> {
+        let response: AlgorandAssetResponse = self.get(&format!("/v2/assets/{asset_id}")).await?;
+        Ok(response.asset)
+    }
+}
+
+/* Everything fetched for an Algorand account, kept raw so it can be saved and mapped again without calling the
+indexer */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AlgorandHistory {
+    pub address: String,
+    pub transactions: Vec<AlgorandTransaction>,
+    pub assets: Vec<AlgorandAsset>,
+}
+
+pub async fn fetch_history_algorand(
+    client: &AlgorandIndexerClient,
+    address: &str,
+) -> Result<AlgorandHistory, ApiError> {
+    let transactions = client.fetch_transactions(address).await?;
+
+    let mut asset_ids: Vec<u64> = Vec::new();
+    let mut known: HashSet<u64> = HashSet::new();
+    let mut pending: Vec<&AlgorandTransaction> = transactions.iter().collect();
+    while let Some(transaction) = pending.pop() {
+        if let Some(transfer) = &transaction.asset_transfer_transaction {
+            if known.insert(transfer.asset_id) {
+                asset_ids.push(transfer.asset_id);
+            }
+        }
+        pending.extend(transaction.inner_txns.iter());
+    }
+    let mut assets = Vec::new();
+    for asset_id in asset_ids {
+        assets.push(client.fetch_asset(asset_id).await?);
+    }
+
+    Ok(AlgorandHistory {
+        address: address.to_string(),
+        transactions,
+        assets,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AlgorandTransactionsPage {
+    pub next_token: Option<String>,
+    pub transactions: Vec<AlgorandTransaction>,
+}
+
+/* Transaction of the account, the amounts being in microalgos or in the base unit of the asset.
+The participation rewards (before 2022) were paid with the transactions to their sender, receiver and close-to
+account. The inner transactions are the ones made by an application called by the transaction. */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AlgorandTransaction {
+    pub id: Option<String>, // Inner transactions have no id
+    pub fee: u64,
+    pub sender: String,
+    pub round_time: i64,
+    pub confirmed_round: u64,
+    #[serde(default)]
+    pub intra_round_offset: u64,
+    pub tx_type: String, // pay, axfer, appl, keyreg, acfg...
+    pub payment_transaction: Option<AlgorandPayment>,
+    pub asset_transfer_transaction: Option<AlgorandAssetTransfer>,
+    #[serde(default)]
+    pub sender_rewards: u64,
+    #[serde(default)]
+    pub receiver_rewards: u64,
+    #[serde(default)]
+    pub close_rewards: u64,
+    #[serde(default)]
+    pub inner_txns: Vec<AlgorandTransaction>,
+}
+
+/* Payment in ALGO. When the account is closed, its remaining balance (close-amount) goes to close-remainder-to */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AlgorandPayment {
+    pub amount: u64,
+    pub receiver: String,
+    #[serde(default)]
+    pub close_amount: u64,
+    pub close_remainder_to: Option<String>,
+}
+
+/* Transfer of an asset. A transfer of 0 from the account to itself is an opt-in to the asset, and a close-to an
+opt-out sending the remaining balance. The sender is only given for a clawback, being the account losing the asset */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AlgorandAssetTransfer {
+    pub amount: u64,
+    pub asset_id: u64,
+    pub receiver: String,
+    #[serde(default)]
+    pub close_amount: u64,
+    pub close_to: Option<String>,
+    pub sender: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlgorandAssetResponse {
+    pub asset: AlgorandAsset,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AlgorandAsset {
+    pub index: u64,
+    pub params: AlgorandAssetParams,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AlgorandAssetParams {
+    pub decimals: u32,
+    pub name: Option<String>,
+    pub unit_name: Option<String>,
+}
@@ -0,0 +1,296 @@
+use hashbrown::HashSet;
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    StatusCode,
+};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::env;
+
+use crate::errors::ApiError;
+
+const BLOCKFROST_ENDPOINT: &str = "https://cardano-mainnet.blockfrost.io/api/v0";
+const PAGE_SIZE: usize = 100;
+
+/* Client of the Blockfrost API (or of a compatible one, like a self hosted Dandelion or Koios proxy), for reading the
+history of a stake key: its addresses, their transactions and the staking rewards.
+The project id is sent in the project_id header. The base url can be changed (env BLOCKFROST_API_URL) to use another
+network or a local stand-in for the tests.
+https://docs.blockfrost.io/
+*/
+#[derive(Debug, Clone)]
+pub struct BlockfrostClient {
+    base_url: String,
+    project_id: String,
+}
+
+impl BlockfrostClient {
+    pub fn new(base_url: String, project_id: String) -> Self {
+        Self {
+            base_url,
+            project_id,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let project_id =
+            env::var("BLOCKFROST_PROJECT_ID").expect("BLOCKFROST_PROJECT_ID not set in .env file");
+        let base_url = env::var("BLOCKFROST_API_URL").unwrap_or(BLOCKFROST_ENDPOINT.to_string());
+        Self::new(base_url, project_id)
+    }
+
+    /* The result is None when the resource is unknown (404), which is the case of an account never used */
+    async fn get<T: DeserializeOwned>(&self, path_and_query: &str) -> Result<Option<T>, ApiError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "project_id",
+            HeaderValue::from_str(&self.project_id)
+                .map_err(|e| ApiError::ApiCallError(e.to_string()))?,
+        );
+        let url = format!("{}{path_and_query}", self.base_url);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            return Err(ApiError::ApiCallError(format!(
+                "Blockfrost error {status}: {text}"
+            )));
+        }
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| ApiError::DeserializationError(e.to_string()))
+    }
+
+    /* The lists are paginated by pages of 100 items at most, the last page being the first one not full */
+    async fn get_all_pages<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>, ApiError> {
+        let mut data: Vec<T> = Vec::new();
+        let mut page = 1;
+        loop {
+            let items: Vec<T> = self
+                .get(&format!("{path}?page={page}&count={PAGE_SIZE}&order=asc"))
+                .await?
+                .unwrap_or_default();
+            let count = items.len();
+            data.extend(items);
+            if count < PAGE_SIZE {
+                break;
+            }
+            page += 1;
+        }
+        Ok(data)
+    }
+
+    pub async fn fetch_addresses(&self, stake_address: &str) -> Result<Vec<String>, ApiError> {
+        let addresses: Vec<BlockfrostAddress> = self
+            .get_all_pages(&format!("/accounts/{stake_address}/addresses"))
+            .await?;
+        Ok(addresses
+            .into_iter()
+            .map(|address| address.address)
+            .collect())
+    }
+
+    pub async fn fetch_rewards(
+        &self,
+        stake_address: &str,
+    ) -> Result<Vec<BlockfrostReward>, ApiError> {
+        self.get_all_pages(&format!("/accounts/{stake_address}/rewards"))
+            .await
+    }
+
+    pub async fn fetch_address_transactions(
+        &self,
+        address: &str,
+    ) -> Result<Vec<BlockfrostAddressTransaction>, ApiError> {
+        self.get_all_pages(&format!("/addresses/{address}/transactions"))
+            .await
+    }
+
+    pub async fn fetch_transaction(&self, hash: &str) -> Result<CardanoTransaction, ApiError> {
+        let not_found = || ApiError::ApiCallError(format!("Unknown Cardano transaction {hash}"));
+        let tx: BlockfrostTx = self
+            .get(&format!("/txs/{hash}"))
+            .await?
+            .ok_or_else(not_found)?;
+        let utxos: BlockfrostUtxos = self
+            .get(&format!("/txs/{hash}/utxos"))
+            .await?
+            .ok_or_else(not_found)?;
+        let withdrawals: Vec<BlockfrostWithdrawal> = if tx.withdrawal_count > 0 {
+            self.get(&format!("/txs/{hash}/withdrawals"))
+                .await?
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        Ok(CardanoTransaction {
+            tx,
+            utxos,
+            withdrawals,
+        })
+    }
+
+    pub async fn fetch_epoch(&self, epoch: u32) -> Result<BlockfrostEpoch, ApiError> {
+        self.get(&format!("/epochs/{epoch}"))
+            .await?
+            .ok_or_else(|| ApiError::ApiCallError(format!("Unknown Cardano epoch {epoch}")))
+    }
+}
+
+/* Everything fetched for a stake key, kept raw so it can be saved and mapped again without calling the API */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CardanoHistory {
+    pub stake_address: String,
+    pub addresses: Vec<String>,
+    pub transactions: Vec<CardanoTransaction>,
+    pub rewards: Vec<BlockfrostReward>,
+    pub epochs: Vec<BlockfrostEpoch>, // Epochs in which the rewards were received
+}
+
+/* The rewards earned in an epoch are received at the start of the second next epoch */
+pub fn reward_epoch(reward: &BlockfrostReward) -> u32 {
+    reward.epoch + 2
+}
+
+pub async fn fetch_history_cardano(
+    client: &BlockfrostClient,
+    stake_address: &str,
+) -> Result<CardanoHistory, ApiError> {
+    let addresses = client.fetch_addresses(stake_address).await?;
+
+    // A transaction between two addresses of the stake key is listed for both
+    let mut hashes: Vec<String> = Vec::new();
+    let mut known: HashSet<String> = HashSet::new();
+    for address in &addresses {
+        for transaction in client.fetch_address_transactions(address).await? {
+            if known.insert(transaction.tx_hash.clone()) {
+                hashes.push(transaction.tx_hash);
+            }
+        }
+    }
+    let mut transactions = Vec::new();
+    for hash in hashes {
+        transactions.push(client.fetch_transaction(&hash).await?);
+    }
+
+    let rewards = client.fetch_rewards(stake_address).await?;
+    let mut epoch_numbers: Vec<u32> = rewards.iter().map(reward_epoch).collect();
+    epoch_numbers.sort();
+    epoch_numbers.dedup();
+    let mut epochs = Vec::new();
+    for epoch in epoch_numbers {
+        epochs.push(client.fetch_epoch(epoch).await?);
+    }
+
+    Ok(CardanoHistory {
+        stake_address: stake_address.to_string(),
+        addresses,
+        transactions,
+        rewards,
+        epochs,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlockfrostAddress {
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockfrostAddressTransaction {
+    pub tx_hash: String,
+    pub block_height: u64,
+    pub block_time: i64,
+}
+
+/* Reward of an epoch, in lovelace. Its type is member (delegation), leader (pool operator), refund... */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockfrostReward {
+    pub epoch: u32,
+    pub amount: Decimal,
+    pub pool_id: String,
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockfrostEpoch {
+    pub epoch: u32,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CardanoTransaction {
+    pub tx: BlockfrostTx,
+    pub utxos: BlockfrostUtxos,
+    pub withdrawals: Vec<BlockfrostWithdrawal>,
+}
+
+/* The amounts are in lovelace. The deposit is the one of the stake key registration (negative when it is refunded) */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockfrostTx {
+    pub hash: String,
+    pub block_time: i64,
+    pub fees: Decimal,
+    pub deposit: Decimal,
+    pub withdrawal_count: u32,
+    #[serde(default = "valid_contract")]
+    pub valid_contract: bool, // When false, only the collateral is spent
+}
+
+fn valid_contract() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockfrostUtxos {
+    pub inputs: Vec<BlockfrostUtxo>,
+    pub outputs: Vec<BlockfrostUtxo>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockfrostUtxo {
+    pub address: String,
+    pub amount: Vec<BlockfrostAmount>,
+    #[serde(default)]
+    pub collateral: bool,
+    #[serde(default)]
+    pub reference: bool, // Reference inputs are only read
+}
+
+impl BlockfrostUtxo {
+    pub fn lovelace(&self) -> Decimal {
+        self.amount
+            .iter()
+            .filter(|amount| amount.unit == "lovelace")
+            .map(|amount| amount.quantity)
+            .sum()
+    }
+}
+
+/* Quantity of a unit, lovelace or native token (policy id and hex encoded asset name) */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockfrostAmount {
+    pub unit: String,
+    pub quantity: Decimal,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BlockfrostWithdrawal {
+    pub address: String, // Stake address
+    pub amount: Decimal,
+}
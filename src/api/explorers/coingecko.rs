@@ -0,0 +1,146 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderValue};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::env;
+
+use crate::errors::ApiError;
+
+const COINGECKO_ENDPOINT: &str = "https://api.coingecko.com/api/v3";
+
+/* Client of the CoinGecko API, the price source of the wallets held outside of the exchanges (Platform::Blockchain):
+it lists the native coins and most of the tokens of every chain, and its history is daily.
+The base url can be changed (env COINGECKO_API_URL) to use a local stand-in for the tests. The demo api key
+(env COINGECKO_API_KEY) is optional, it raises the rate limit.
+https://docs.coingecko.com/v3.0.1/reference/coins-id-history
+*/
+#[derive(Debug, Clone)]
+pub struct CoinGeckoClient {
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl CoinGeckoClient {
+    pub fn new(base_url: String, api_key: Option<String>) -> Self {
+        Self { base_url, api_key }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            env::var("COINGECKO_API_URL").unwrap_or(COINGECKO_ENDPOINT.to_string()),
+            env::var("COINGECKO_API_KEY").ok(),
+        )
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        let mut headers = HeaderMap::new();
+        if let Some(api_key) = &self.api_key {
+            headers.insert(
+                "x-cg-demo-api-key",
+                HeaderValue::from_str(api_key)
+                    .map_err(|e| ApiError::ApiCallError(e.to_string()))?,
+            );
+        }
+        let url = format!("{}{path}", self.base_url);
+        let response = reqwest::Client::new()
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(ApiError::ApiCallError(format!(
+                "CoinGecko error {status}: {text}"
+            )));
+        }
+        serde_json::from_str(&text).map_err(|e| ApiError::DeserializationError(e.to_string()))
+    }
+
+    /* CoinGecko identifies the coins by an id: the main coins are known, the others are searched by their symbol,
+    taking the one with the best market cap rank (the search results are ordered by it) */
+    pub async fn fetch_coin_id(&self, symbol: &str) -> Result<String, ApiError> {
+        if let Some(id) = known_coin_id(symbol) {
+            return Ok(id.to_string());
+        }
+        let search: CoinGeckoSearch = self.get(&format!("/search?query={symbol}")).await?;
+        search
+            .coins
+            .into_iter()
+            .find(|coin| coin.symbol.eq_ignore_ascii_case(symbol))
+            .map(|coin| coin.id)
+            .ok_or(ApiError::CouldNotFindPrice {
+                pairs: vec![(symbol.to_string(), "EUR".to_string())],
+            })
+    }
+
+    /* Price in euro of a coin (by its symbol) for the day of the given time */
+    pub async fn fetch_price(
+        &self,
+        symbol: &str,
+        time: DateTime<Utc>,
+    ) -> Result<Decimal, ApiError> {
+        let id = self.fetch_coin_id(symbol).await?;
+        let date = time.format("%d-%m-%Y");
+        let history: CoinGeckoHistory = self
+            .get(&format!(
+                "/coins/{id}/history?date={date}&localization=false"
+            ))
+            .await?;
+        history
+            .market_data
+            .and_then(|market_data| market_data.current_price.eur)
+            .ok_or(ApiError::CouldNotFindPrice {
+                pairs: vec![(symbol.to_string(), "EUR".to_string())],
+            })
+    }
+}
+
+fn known_coin_id(symbol: &str) -> Option<&'static str> {
+    match symbol {
+        "BTC" => Some("bitcoin"),
+        "ETH" => Some("ethereum"),
+        "SOL" => Some("solana"),
+        "ADA" => Some("cardano"),
+        "ALGO" => Some("algorand"),
+        "BNB" => Some("binancecoin"),
+        "MATIC" => Some("matic-network"),
+        "POL" => Some("polygon-ecosystem-token"),
+        "AVAX" => Some("avalanche-2"),
+        "USDT" => Some("tether"),
+        "USDC" => Some("usd-coin"),
+        "DAI" => Some("dai"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinGeckoSearch {
+    pub coins: Vec<CoinGeckoCoin>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinGeckoCoin {
+    pub id: String,
+    pub symbol: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinGeckoHistory {
+    pub market_data: Option<CoinGeckoMarketData>, // Missing before the coin was listed
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinGeckoMarketData {
+    pub current_price: CoinGeckoPrices,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CoinGeckoPrices {
+    pub eur: Option<Decimal>,
+}
@@ -0,0 +1,229 @@
+use bitcoin::{
+    base58,
+    bip32::{ChildNumber, Xpub},
+    secp256k1::Secp256k1,
+    Address, Network,
+};
+use hashbrown::HashSet;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::env;
+
+use crate::errors::ApiError;
+
+const ESPLORA_ENDPOINT: &str = "https://blockstream.info/api";
+const PAGE_SIZE: usize = 25;
+pub const BITCOIN_GAP_LIMIT: u32 = 20;
+
+// Version bytes of the mainnet extended public keys (BIP32, BIP49 and BIP84)
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+const YPUB_VERSION: [u8; 4] = [0x04, 0x9d, 0x7c, 0xb2];
+const ZPUB_VERSION: [u8; 4] = [0x04, 0xb2, 0x47, 0x46];
+
+/* Client of an Esplora API (Blockstream, mempool.space or a self hosted electrs), for reading the confirmed
+transactions of Bitcoin addresses.
+The base url can be changed (env ESPLORA_API_URL) to use another explorer or a local stand-in for the tests.
+https://github.com/Blockstream/esplora/blob/master/API.md
+*/
+#[derive(Debug, Clone)]
+pub struct EsploraClient {
+    base_url: String,
+}
+
+impl EsploraClient {
+    pub fn new(base_url: String) -> Self {
+        Self { base_url }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(env::var("ESPLORA_API_URL").unwrap_or(ESPLORA_ENDPOINT.to_string()))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        let url = format!("{}{path}", self.base_url);
+        let response = reqwest::get(url)
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(ApiError::ApiCallError(format!(
+                "Esplora error {status}: {text}"
+            )));
+        }
+        serde_json::from_str(&text).map_err(|e| ApiError::DeserializationError(e.to_string()))
+    }
+
+    /* The confirmed transactions are listed from the newest, by pages of 25 following the last transaction seen */
+    pub async fn fetch_address_transactions(
+        &self,
+        address: &str,
+    ) -> Result<Vec<EsploraTransaction>, ApiError> {
+        let mut transactions: Vec<EsploraTransaction> = Vec::new();
+        loop {
+            let path = match transactions.last() {
+                Some(last) => format!("/address/{address}/txs/chain/{}", last.txid),
+                None => format!("/address/{address}/txs/chain"),
+            };
+            let page: Vec<EsploraTransaction> = self.get(&path).await?;
+            let count = page.len();
+            transactions.extend(page);
+            if count < PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(transactions)
+    }
+}
+
+/* Address type of the wallet, given by the prefix of its extended public key */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitcoinScript {
+    Legacy,       // xpub, P2PKH addresses (1...)
+    NestedSegwit, // ypub, P2SH-P2WPKH addresses (3...)
+    NativeSegwit, // zpub, P2WPKH addresses (bc1q...)
+}
+
+/* Extended public key of an account (m/purpose'/0'/account'), from which its receive (chain 0) and change (chain 1)
+addresses are derived */
+#[derive(Debug, Clone)]
+pub struct BitcoinAccount {
+    xpub: Xpub,
+    script: BitcoinScript,
+}
+
+impl BitcoinAccount {
+    pub fn parse(extended_key: &str) -> Result<Self, ApiError> {
+        let invalid = |e: String| {
+            ApiError::ApiCallError(format!("Invalid extended public key {extended_key}: {e}"))
+        };
+        let mut data = base58::decode_check(extended_key).map_err(|e| invalid(e.to_string()))?;
+        if data.len() < 4 {
+            return Err(invalid("too short".to_string()));
+        }
+        let version = &data[0..4];
+        let script = if version == XPUB_VERSION {
+            BitcoinScript::Legacy
+        } else if version == YPUB_VERSION {
+            BitcoinScript::NestedSegwit
+        } else if version == ZPUB_VERSION {
+            BitcoinScript::NativeSegwit
+        } else {
+            return Err(invalid(
+                "only xpub, ypub and zpub are supported".to_string(),
+            ));
+        };
+        // The ypub and zpub only differ from the xpub by their version
+        data[0..4].copy_from_slice(&XPUB_VERSION);
+        let xpub = Xpub::decode(&data).map_err(|e| invalid(e.to_string()))?;
+        Ok(Self { xpub, script })
+    }
+
+    pub fn derive_address(&self, chain: u32, index: u32) -> Result<String, ApiError> {
+        let secp = Secp256k1::verification_only();
+        let path = [
+            ChildNumber::from_normal_idx(chain),
+            ChildNumber::from_normal_idx(index),
+        ]
+        .into_iter()
+        .collect::<Result<Vec<ChildNumber>, _>>()
+        .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let public_key = self
+            .xpub
+            .derive_pub(&secp, &path)
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?
+            .to_pub();
+        let address = match self.script {
+            BitcoinScript::Legacy => Address::p2pkh(public_key, Network::Bitcoin),
+            BitcoinScript::NestedSegwit => Address::p2shwpkh(&public_key, Network::Bitcoin),
+            BitcoinScript::NativeSegwit => Address::p2wpkh(&public_key, Network::Bitcoin),
+        };
+        Ok(address.to_string())
+    }
+}
+
+/* Everything fetched for an extended public key, kept raw so it can be saved and mapped again without calling the
+explorer */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BitcoinHistory {
+    pub xpub: String,
+    pub addresses: Vec<String>, // Used addresses, receive and change
+    pub transactions: Vec<EsploraTransaction>,
+}
+
+/* The receive and change addresses are scanned in order until gap_limit addresses in a row were never used.
+A transaction between two addresses of the wallet is only kept once */
+pub async fn fetch_history_bitcoin(
+    client: &EsploraClient,
+    xpub: &str,
+    gap_limit: u32,
+) -> Result<BitcoinHistory, ApiError> {
+    let account = BitcoinAccount::parse(xpub)?;
+    let mut addresses: Vec<String> = Vec::new();
+    let mut transactions: Vec<EsploraTransaction> = Vec::new();
+    let mut known: HashSet<String> = HashSet::new();
+
+    for chain in [0, 1] {
+        let mut index = 0;
+        let mut unused = 0;
+        while unused < gap_limit {
+            let address = account.derive_address(chain, index)?;
+            let address_transactions = client.fetch_address_transactions(&address).await?;
+            if address_transactions.is_empty() {
+                unused += 1;
+            } else {
+                unused = 0;
+                addresses.push(address);
+            }
+            for transaction in address_transactions {
+                if known.insert(transaction.txid.clone()) {
+                    transactions.push(transaction);
+                }
+            }
+            index += 1;
+        }
+    }
+
+    Ok(BitcoinHistory {
+        xpub: xpub.to_string(),
+        addresses,
+        transactions,
+    })
+}
+
+/* Confirmed transaction, the values and the fee being in satoshis */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EsploraTransaction {
+    pub txid: String,
+    pub fee: u64,
+    pub status: EsploraStatus,
+    pub vin: Vec<EsploraInput>,
+    pub vout: Vec<EsploraOutput>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EsploraStatus {
+    pub confirmed: bool,
+    pub block_height: Option<u64>,
+    pub block_time: Option<i64>,
+}
+
+/* The output spent by the input is given as prevout, except for the coinbase input of the miners */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EsploraInput {
+    pub txid: String,
+    pub vout: u32,
+    pub prevout: Option<EsploraOutput>,
+    #[serde(default)]
+    pub is_coinbase: bool,
+}
+
+/* Outputs without address are the OP_RETURN ones and the non standard scripts */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EsploraOutput {
+    pub scriptpubkey_address: Option<String>,
+    pub value: u64,
+}
@@ -0,0 +1,224 @@
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{env, str::FromStr};
+
+use crate::errors::ApiError;
+
+const ETHERSCAN_ENDPOINT: &str = "https://api.etherscan.io/v2/api";
+const PAGE_SIZE: usize = 1000;
+const MAX_DECIMAL_DIGITS: usize = 28;
+
+/* EVM chain on which an address is followed. The chain id selects the chain on the multichain API of Etherscan (v2),
+the native currency is the one paying the gas */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmChain {
+    pub name: String,
+    pub chain_id: u64,
+    pub native_currency: String,
+}
+
+impl EvmChain {
+    pub fn new(name: &str, chain_id: u64, native_currency: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            chain_id,
+            native_currency: native_currency.to_string(),
+        }
+    }
+
+    /* A known chain by its name (ethereum, arbitrum, polygon), or any chain given as name:chain_id:native_currency */
+    pub fn parse(chain: &str) -> Result<Self, ApiError> {
+        match chain.split(':').collect::<Vec<&str>>().as_slice() {
+            ["ethereum"] => Ok(Self::new("ethereum", 1, "ETH")),
+            ["arbitrum"] => Ok(Self::new("arbitrum", 42161, "ETH")),
+            ["polygon"] => Ok(Self::new("polygon", 137, "POL")),
+            [name, chain_id, native_currency] => {
+                let chain_id = chain_id.parse().map_err(|_| {
+                    ApiError::ApiCallError(format!("Invalid chain id of the EVM chain {chain}"))
+                })?;
+                Ok(Self::new(name, chain_id, native_currency))
+            }
+            _ => Err(ApiError::ApiCallError(format!("Unknown EVM chain {chain}"))),
+        }
+    }
+}
+
+/* Client of the Etherscan API (or of a compatible one, like Blockscout or Routescan), for reading the normal,
+internal and ERC-20 token transactions of an address.
+The base url can be changed (env ETHERSCAN_API_URL) to use another explorer or a local stand-in for the tests.
+https://docs.etherscan.io/etherscan-v2
+*/
+#[derive(Debug, Clone)]
+pub struct EtherscanClient {
+    base_url: String,
+    api_key: String,
+}
+
+impl EtherscanClient {
+    pub fn new(base_url: String, api_key: String) -> Self {
+        Self { base_url, api_key }
+    }
+
+    pub fn from_env() -> Self {
+        let api_key =
+            env::var("ETHERSCAN_API_KEY").expect("ETHERSCAN_API_KEY not set in .env file");
+        let base_url = env::var("ETHERSCAN_API_URL").unwrap_or(ETHERSCAN_ENDPOINT.to_string());
+        Self::new(base_url, api_key)
+    }
+
+    /* The lists are paginated by pages of 1000 items at most, from the oldest, the last page being the first one not
+    full. An address without transactions gets the status 0 with the message "No transactions found" */
+    async fn fetch_list<T: DeserializeOwned>(
+        &self,
+        chain: &EvmChain,
+        action: &str,
+        address: &str,
+    ) -> Result<Vec<T>, ApiError> {
+        let mut data: Vec<T> = Vec::new();
+        let mut page = 1;
+        loop {
+            let url = format!(
+                "{}?chainid={}&module=account&action={action}&address={address}&startblock=0&endblock=99999999&page={page}&offset={PAGE_SIZE}&sort=asc&apikey={}",
+                self.base_url, chain.chain_id, self.api_key
+            );
+            let response = reqwest::get(url)
+                .await
+                .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+            let status = response.status();
+            let text = response
+                .text()
+                .await
+                .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+            if !status.is_success() {
+                return Err(ApiError::ApiCallError(format!(
+                    "Etherscan error {status}: {text}"
+                )));
+            }
+
+            let response: EtherscanResponse = serde_json::from_str(&text)
+                .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+            if response.status != "1" {
+                if response.message.starts_with("No transactions found") {
+                    break;
+                }
+                return Err(ApiError::ApiCallError(format!(
+                    "Etherscan error {}: {}",
+                    response.message, response.result
+                )));
+            }
+            let items: Vec<T> = serde_json::from_value(response.result)
+                .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+            let count = items.len();
+            data.extend(items);
+            if count < PAGE_SIZE {
+                break;
+            }
+            page += 1;
+        }
+        Ok(data)
+    }
+}
+
+/* Everything fetched for an address on a chain, kept raw so it can be saved and mapped again without calling the
+API */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvmHistory {
+    pub address: String,
+    pub chain: EvmChain,
+    pub transactions: Vec<EtherscanTransaction>,
+    pub internal_transactions: Vec<EtherscanInternalTransaction>,
+    pub token_transfers: Vec<EtherscanTokenTransfer>,
+}
+
+pub async fn fetch_history_evm(
+    client: &EtherscanClient,
+    chain: &EvmChain,
+    address: &str,
+) -> Result<EvmHistory, ApiError> {
+    Ok(EvmHistory {
+        address: address.to_lowercase(),
+        chain: chain.clone(),
+        transactions: client.fetch_list(chain, "txlist", address).await?,
+        internal_transactions: client.fetch_list(chain, "txlistinternal", address).await?,
+        token_transfers: client.fetch_list(chain, "tokentx", address).await?,
+    })
+}
+
+/* Amount of an integer quantity (wei, or base unit of a token) with the given decimals. The digits which don't fit
+in a Decimal (28) are dropped, they are far below anything meaningful */
+pub fn evm_amount(quantity: &str, decimals: u32) -> Result<Decimal, ApiError> {
+    let invalid = || ApiError::DeserializationError(format!("Invalid EVM quantity {quantity}"));
+    if quantity.is_empty() || !quantity.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let digits = quantity.trim_start_matches('0');
+    let decimals = decimals as usize;
+    let (integer, fraction) = if digits.len() > decimals {
+        digits.split_at(digits.len() - decimals)
+    } else {
+        ("0", digits)
+    };
+    let fraction = format!("{fraction:0>decimals$}");
+    let kept = MAX_DECIMAL_DIGITS
+        .saturating_sub(integer.len())
+        .min(fraction.len());
+    let amount = if kept == 0 {
+        integer.to_string()
+    } else {
+        format!("{integer}.{}", &fraction[..kept])
+    };
+    Decimal::from_str(&amount)
+        .map(|amount| amount.normalize())
+        .map_err(|_| invalid())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EtherscanResponse {
+    pub status: String,
+    pub message: String,
+    pub result: serde_json::Value, // The list, or the error message
+}
+
+/* Transaction sent or received by the address, the value being in wei. A failed transaction (isError 1) only
+costs the gas to its sender */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EtherscanTransaction {
+    pub block_number: String,
+    pub time_stamp: String,
+    pub hash: String,
+    pub from: String,
+    pub to: String, // Empty for the creation of a contract
+    pub value: String,
+    pub gas_price: String,
+    pub gas_used: String,
+    pub is_error: String,
+}
+
+/* Move of the native currency made by a contract, during a transaction which may not involve the address */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EtherscanInternalTransaction {
+    pub block_number: String,
+    pub time_stamp: String,
+    pub hash: String,
+    pub from: String,
+    pub to: String,
+    pub value: String,
+    pub is_error: String,
+}
+
+/* Transfer event of an ERC-20 token, the value being in the base unit of the token */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EtherscanTokenTransfer {
+    pub block_number: String,
+    pub time_stamp: String,
+    pub hash: String,
+    pub from: String,
+    pub to: String,
+    pub value: String,
+    pub contract_address: String,
+    pub token_symbol: String,
+    pub token_decimal: String,
+}
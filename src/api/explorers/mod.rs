@@ -0,0 +1,20 @@
+/* This folder is used for fetching the history of the addresses from the blockchains, through public nodes and
+explorers, when the crypto is held outside of the exchanges */
+
+pub mod solana_rpc;
+pub use solana_rpc::*;
+
+pub mod cardano;
+pub use cardano::*;
+
+pub mod algorand;
+pub use algorand::*;
+
+pub mod esplora;
+pub use esplora::*;
+
+pub mod etherscan;
+pub use etherscan::*;
+
+pub mod coingecko;
+pub use coingecko::*;
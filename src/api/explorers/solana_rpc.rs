@@ -1 +1,231 @@
-// TODO: Integrate staking address 
\ No newline at end of file
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::env;
+
+use crate::errors::ApiError;
+
+const SOLANA_RPC_ENDPOINT: &str = "https://api.mainnet-beta.solana.com";
+const SIGNATURES_LIMIT: usize = 1000;
+
+/* Solana JSON-RPC client, for reading the history of an address (no key needed on the public node).
+The rpc url can be changed (env SOLANA_RPC_URL) to use a private node, or a local stand-in for the tests.
+https://solana.com/docs/rpc/http
+*/
+#[derive(Debug, Clone)]
+pub struct SolanaRpcClient {
+    rpc_url: String,
+}
+
+impl SolanaRpcClient {
+    pub fn new(rpc_url: String) -> Self {
+        Self { rpc_url }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(env::var("SOLANA_RPC_URL").unwrap_or(SOLANA_RPC_ENDPOINT.to_string()))
+    }
+
+    /* The result is None when the node answers null, for instance for a transaction it doesn't have anymore */
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<Option<T>, ApiError> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let client = reqwest::Client::new();
+        let response = client
+            .post(&self.rpc_url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ApiError::ApiCallError(e.to_string()))?;
+
+        if !status.is_success() {
+            return Err(ApiError::ApiCallError(format!(
+                "Solana RPC error {status}: {text}"
+            )));
+        }
+        let response: RpcResponse<T> = serde_json::from_str(&text)
+            .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+        match response.error {
+            Some(error) => Err(ApiError::ApiCallError(format!(
+                "Solana RPC error {}: {}",
+                error.code, error.message
+            ))),
+            None => Ok(response.result),
+        }
+    }
+
+    /* The signatures are returned from the newest, the next page starts before the oldest of the previous one */
+    pub async fn fetch_signatures(&self, address: &str) -> Result<Vec<SolanaSignature>, ApiError> {
+        let mut signatures: Vec<SolanaSignature> = Vec::new();
+        loop {
+            let mut config = serde_json::json!({ "limit": SIGNATURES_LIMIT });
+            if let Some(last) = signatures.last() {
+                config["before"] = serde_json::Value::String(last.signature.clone());
+            }
+            let page: Vec<SolanaSignature> = self
+                .call(
+                    "getSignaturesForAddress",
+                    serde_json::json!([address, config]),
+                )
+                .await?
+                .unwrap_or_default();
+            let count = page.len();
+            signatures.extend(page);
+            if count < SIGNATURES_LIMIT {
+                break;
+            }
+        }
+        Ok(signatures)
+    }
+
+    /* The jsonParsed encoding gives the account keys loaded from the lookup tables of the versioned transactions
+    with the others, in the order of the balances */
+    pub async fn fetch_transaction(
+        &self,
+        signature: &str,
+    ) -> Result<Option<SolanaTransaction>, ApiError> {
+        self.call(
+            "getTransaction",
+            serde_json::json!([
+                signature,
+                {
+                    "encoding": "jsonParsed",
+                    "maxSupportedTransactionVersion": 0,
+                    "commitment": "finalized"
+                }
+            ]),
+        )
+        .await
+    }
+}
+
+/* Everything fetched for a Solana address, kept raw so it can be saved and mapped again without calling the node */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SolanaHistory {
+    pub address: String,
+    pub transactions: Vec<SolanaTransaction>,
+}
+
+pub async fn fetch_history_solana(
+    client: &SolanaRpcClient,
+    address: &str,
+) -> Result<SolanaHistory, ApiError> {
+    let mut transactions = Vec::new();
+    for signature in client.fetch_signatures(address).await? {
+        if let Some(transaction) = client.fetch_transaction(&signature.signature).await? {
+            transactions.push(transaction);
+        }
+    }
+    Ok(SolanaHistory {
+        address: address.to_string(),
+        transactions,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcResponse<T> {
+    pub result: Option<T>,
+    pub error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolanaSignature {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolanaTransaction {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub meta: SolanaMeta,
+    pub transaction: SolanaTransactionData,
+}
+
+impl SolanaTransaction {
+    pub fn signature(&self) -> &str {
+        self.transaction
+            .signatures
+            .first()
+            .map(|signature| signature.as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.meta.err.is_some()
+    }
+}
+
+/* Balances of every account of the transaction, in lamports for SOL, before and after its execution.
+The fee is paid by the first account and is included in its balance change, even when the transaction failed. */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolanaMeta {
+    pub err: Option<serde_json::Value>,
+    pub fee: u64,
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+    #[serde(default)]
+    pub pre_token_balances: Vec<SolanaTokenBalance>,
+    #[serde(default)]
+    pub post_token_balances: Vec<SolanaTokenBalance>,
+}
+
+/* Balance of a token account, the owner being the address holding the tokens */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolanaTokenBalance {
+    pub account_index: usize,
+    pub mint: String,
+    pub owner: Option<String>,
+    pub ui_token_amount: SolanaTokenAmount,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolanaTokenAmount {
+    pub amount: String, // In the smallest unit of the token
+    pub decimals: u32,
+    pub ui_amount_string: Decimal,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SolanaTransactionData {
+    pub signatures: Vec<String>,
+    pub message: SolanaMessage,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolanaMessage {
+    pub account_keys: Vec<SolanaAccountKey>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SolanaAccountKey {
+    pub pubkey: String,
+    pub signer: bool,
+    pub writable: bool,
+}
@@ -0,0 +1,365 @@
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{AlgorandAsset, AlgorandHistory, AlgorandTransaction, CoinGeckoClient},
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Income, IncomeType, Transaction,
+        TransactionBase, WalletSnapshot,
+    },
+};
+
+const ALGO_DECIMALS: u32 = 6;
+
+// Assets with a price, by asset id. The others are named ASA-{id}
+const ALGORAND_ASSETS: [(u64, &str); 2] = [(31566704, "USDC"), (312769, "USDT")];
+
+// The governance rewards of the Algorand Foundation are paid from its governance address
+const GOVERNANCE_ADDRESSES: [&str; 1] =
+    ["GULDQIEZ2CUPBSHKXRWUW7X3LCYL44AI5GGSHHOQDGKJAZ2OANZJ43S72U"];
+
+/* Map the transactions of an Algorand account to transactions on its Platform::Blockchain wallets.
+- each payment or asset transfer from or to the account is a Transfer, the close-to amount being a second one
+- the opt-in to an asset only costs its fee, and creates the wallet of the asset
+- the participation rewards paid with the transactions and the governance rewards are incomes (IncomeType::Staking)
+- the fee is recorded on the first ALGO leaving the account, or in its own transfer when no ALGO is sent
+The inner transactions of the application calls are mapped as the others. As for Binance, the balances are
+recalculated from zero.
+*/
+pub async fn create_algorand_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &AlgorandHistory,
+    price_client: &CoinGeckoClient,
+) -> Result<(), ApiError> {
+    let mut transactions: Vec<&AlgorandTransaction> = history.transactions.iter().collect();
+    transactions
+        .sort_by_key(|transaction| (transaction.confirmed_round, transaction.intra_round_offset));
+
+    let mut mapper = AlgorandMapper {
+        address: &history.address,
+        assets: history
+            .assets
+            .iter()
+            .map(|asset| (asset.index, asset))
+            .collect(),
+        price_client,
+        wallet_manager,
+        balances: HashMap::new(),
+        prices: HashMap::new(),
+    };
+    for transaction in transactions {
+        mapper.map_transaction(txs, transaction).await?;
+    }
+    Ok(())
+}
+
+pub fn algorand_asset_symbol(asset_id: u64) -> Option<&'static str> {
+    ALGORAND_ASSETS
+        .iter()
+        .find(|(id, _)| *id == asset_id)
+        .map(|(_, symbol)| *symbol)
+}
+
+/* Move of a currency in or out of the account (positive amount when received) */
+struct Movement {
+    id: String,
+    currency: String,
+    amount: Decimal,
+    counterparty: Option<String>,
+    income: Option<IncomeType>,
+}
+
+struct AlgorandMapper<'a> {
+    address: &'a str,
+    assets: HashMap<u64, &'a AlgorandAsset>,
+    price_client: &'a CoinGeckoClient,
+    wallet_manager: &'a mut WalletManager,
+    balances: HashMap<String, Decimal>,
+    prices: HashMap<(String, String), Decimal>, // Price of a currency for a given day
+}
+
+impl AlgorandMapper<'_> {
+    async fn map_transaction(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        transaction: &AlgorandTransaction,
+    ) -> Result<(), ApiError> {
+        let id = format!("algorand-{}", transaction.id.as_deref().unwrap_or_default());
+        let time = DateTime::from_timestamp(transaction.round_time, 0).ok_or(
+            ApiError::MappingError(MappingError::Other(format!(
+                "Invalid round time of the Algorand transaction {id}"
+            ))),
+        )?;
+        let mut movements: Vec<Movement> = Vec::new();
+        self.collect_movements(transaction, &id, &mut movements)?;
+
+        // The fee is paid by the sender of the transaction, including the ones of its inner transactions
+        let mut fee = Some(Decimal::new(transaction.fee as i64, ALGO_DECIMALS))
+            .filter(|fee| transaction.sender == self.address && !fee.is_zero());
+        let pays_with_algo = movements.iter().any(|movement| {
+            movement.currency == "ALGO" && movement.amount < dec!(0) && movement.income.is_none()
+        });
+        if fee.is_some() && !pays_with_algo {
+            let snapshot = self.snapshot("ALGO", fee.take(), time).await?;
+            self.update_balance("ALGO", -snapshot.fee.unwrap_or(dec!(0)));
+            txs.push(Transaction::Transfer {
+                tx: TransactionBase {
+                    id: format!("{id}-fee"),
+                    timestamp: time,
+                },
+                from: snapshot.clone(),
+                to: WalletSnapshot {
+                    fee: None,
+                    ..snapshot
+                },
+                amount: dec!(0),
+                income: None,
+            });
+        }
+
+        for movement in movements {
+            let tx = TransactionBase {
+                id: movement.id,
+                timestamp: time,
+            };
+            if let Some(subtype) = movement.income {
+                let snapshot = self.snapshot(&movement.currency, None, time).await?;
+                let income = Income::new(movement.amount * snapshot.price_eur, subtype);
+                self.update_balance(&movement.currency, movement.amount);
+                txs.push(Transaction::Transfer {
+                    tx,
+                    from: snapshot.clone(),
+                    to: snapshot,
+                    amount: movement.amount,
+                    income: Some(income),
+                });
+                continue;
+            }
+
+            let movement_fee = if movement.currency == "ALGO" && movement.amount < dec!(0) {
+                fee.take()
+            } else {
+                None
+            };
+            let own = self
+                .snapshot(&movement.currency, movement_fee, time)
+                .await?;
+            let external_id = self.wallet_manager.create_or_get_wallet_id(
+                &movement.currency,
+                &Platform::Blockchain,
+                &movement.counterparty,
+                false,
+            );
+            let external = WalletSnapshot {
+                id: external_id,
+                // We don't know the balance of the counterparty so it only holds the amount transfered
+                pre_tx_balance: movement.amount.max(dec!(0)),
+                price_eur: own.price_eur,
+                fee: None,
+            };
+            self.update_balance(
+                &movement.currency,
+                movement.amount - movement_fee.unwrap_or(dec!(0)),
+            );
+            let (from, to) = if movement.amount > dec!(0) {
+                (external, own)
+            } else {
+                (own, external)
+            };
+            txs.push(Transaction::Transfer {
+                tx,
+                from,
+                to,
+                amount: movement.amount.abs(),
+                income: None,
+            });
+        }
+        Ok(())
+    }
+
+    /* Moves of the transaction and of its inner transactions, which are identified by their position */
+    fn collect_movements(
+        &mut self,
+        transaction: &AlgorandTransaction,
+        id: &str,
+        movements: &mut Vec<Movement>,
+    ) -> Result<(), ApiError> {
+        let algo = |microalgos: u64| Decimal::new(microalgos as i64, ALGO_DECIMALS);
+        let mut receiver = None;
+        let mut close_to = None;
+
+        if let Some(payment) = &transaction.payment_transaction {
+            receiver = Some(&payment.receiver);
+            close_to = payment.close_remainder_to.as_ref();
+            self.push_movement(
+                movements,
+                id.to_string(),
+                "ALGO",
+                algo(payment.amount),
+                &transaction.sender,
+                &payment.receiver,
+            );
+            if let Some(close_remainder_to) = &payment.close_remainder_to {
+                self.push_movement(
+                    movements,
+                    format!("{id}-close"),
+                    "ALGO",
+                    algo(payment.close_amount),
+                    &transaction.sender,
+                    close_remainder_to,
+                );
+            }
+        }
+
+        if let Some(transfer) = &transaction.asset_transfer_transaction {
+            receiver = Some(&transfer.receiver);
+            close_to = transfer.close_to.as_ref();
+            let asset = self
+                .assets
+                .get(&transfer.asset_id)
+                .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                    "Unknown Algorand asset {}",
+                    transfer.asset_id
+                ))))?;
+            let decimals = asset.params.decimals;
+            let currency = algorand_asset_symbol(transfer.asset_id)
+                .map(|symbol| symbol.to_string())
+                .unwrap_or(format!("ASA-{}", transfer.asset_id));
+            // The clawback takes the asset from another account than the sender of the transaction
+            let source = transfer.sender.as_ref().unwrap_or(&transaction.sender);
+            if source == self.address && transfer.receiver == self.address {
+                // Opt-in
+                self.wallet_manager.create_or_get_wallet_id(
+                    &currency,
+                    &Platform::Blockchain,
+                    &Some(self.address.to_string()),
+                    false,
+                );
+            }
+            self.push_movement(
+                movements,
+                id.to_string(),
+                &currency,
+                Decimal::new(transfer.amount as i64, decimals),
+                source,
+                &transfer.receiver,
+            );
+            if let Some(close_to) = &transfer.close_to {
+                self.push_movement(
+                    movements,
+                    format!("{id}-close"),
+                    &currency,
+                    Decimal::new(transfer.close_amount as i64, decimals),
+                    source,
+                    close_to,
+                );
+            }
+        }
+
+        let mut rewards = 0;
+        if transaction.sender == self.address {
+            rewards += transaction.sender_rewards;
+        }
+        if receiver.is_some_and(|receiver| receiver == self.address) {
+            rewards += transaction.receiver_rewards;
+        }
+        if close_to.is_some_and(|close_to| close_to == self.address) {
+            rewards += transaction.close_rewards;
+        }
+        if rewards > 0 {
+            movements.push(Movement {
+                id: format!("{id}-rewards"),
+                currency: "ALGO".to_string(),
+                amount: algo(rewards),
+                counterparty: None,
+                income: Some(IncomeType::Staking),
+            });
+        }
+
+        for (index, inner) in transaction.inner_txns.iter().enumerate() {
+            self.collect_movements(inner, &format!("{id}-{index}"), movements)?;
+        }
+        Ok(())
+    }
+
+    fn push_movement(
+        &self,
+        movements: &mut Vec<Movement>,
+        id: String,
+        currency: &str,
+        amount: Decimal,
+        sender: &str,
+        receiver: &str,
+    ) {
+        if amount.is_zero() || sender == receiver {
+            return;
+        }
+        if sender == self.address {
+            movements.push(Movement {
+                id,
+                currency: currency.to_string(),
+                amount: -amount,
+                counterparty: Some(receiver.to_string()),
+                income: None,
+            });
+        } else if receiver == self.address {
+            let is_governance_reward =
+                currency == "ALGO" && GOVERNANCE_ADDRESSES.contains(&sender);
+            movements.push(Movement {
+                id,
+                currency: currency.to_string(),
+                amount,
+                counterparty: Some(sender.to_string()),
+                income: Some(IncomeType::Staking).filter(|_| is_governance_reward),
+            });
+        }
+    }
+
+    async fn snapshot(
+        &mut self,
+        currency: &str,
+        fee: Option<Decimal>,
+        time: DateTime<Utc>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Blockchain,
+            &Some(self.address.to_string()),
+            false,
+        );
+        let pre_tx_balance = *self.balances.get(currency).unwrap_or(&dec!(0));
+        let price_eur = self.price(currency, time).await?;
+        Ok(WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur,
+            fee,
+        })
+    }
+
+    fn update_balance(&mut self, currency: &str, change: Decimal) {
+        *self.balances.entry(currency.to_string()).or_insert(dec!(0)) += change;
+    }
+
+    /* The assets without symbol have no price: they are mostly airdrops of worthless tokens */
+    async fn price(&mut self, currency: &str, time: DateTime<Utc>) -> Result<Decimal, ApiError> {
+        if currency != "ALGO"
+            && !ALGORAND_ASSETS
+                .iter()
+                .any(|(_, symbol)| *symbol == currency)
+        {
+            return Ok(dec!(0));
+        }
+        let key = (currency.to_string(), time.format("%Y-%m-%d").to_string());
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+        let price = self.price_client.fetch_price(currency, time).await?;
+        self.prices.insert(key, price);
+        Ok(price)
+    }
+}
@@ -0,0 +1,393 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        BinanceClient, BinanceConvert, BinanceDeposit, BinanceFiatOrder, BinanceHistory,
+        BinanceTrade, BinanceWithdrawal, DustDetail, EarnProduct, EarnReward, ExchangeMapper,
+        ExchangePriceSource, FiatCurrency, PriceFuture,
+    },
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, IncomeType, Transaction, TransactionBase,
+    },
+};
+
+/* Map the Binance history to transactions.
+
+Binance doesn't give the balance of the account after each operation (unlike the Kraken ledger), so all the events
+are sorted by time and the balances before each transaction are recalculated from zero.
+
+A trade commission paid in a third asset (BNB most of the time) can't be held as is by the from or to WalletSnapshot:
+it is put as fee of the sold side, converted with the euro prices, and taken from the balance of the third asset.
+
+The fiat deposits and withdrawals are taken from the fiat orders, the capital history only holds the crypto ones.
+*/
+pub async fn create_binance_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &BinanceHistory,
+    client: &BinanceClient,
+) -> Result<(), ApiError> {
+    let mut events: Vec<BinanceEvent> = Vec::new();
+    events.extend(history.trades.iter().map(BinanceEvent::Trade));
+    events.extend(
+        history
+            .deposits
+            .iter()
+            .filter(|deposit| deposit.status == 1 && !FiatCurrency::is_fiat(&deposit.coin))
+            .map(BinanceEvent::Deposit),
+    );
+    events.extend(
+        history
+            .withdrawals
+            .iter()
+            .filter(|withdrawal| {
+                withdrawal.status == 6 && !FiatCurrency::is_fiat(&withdrawal.coin)
+            })
+            .map(BinanceEvent::Withdrawal),
+    );
+    events.extend(
+        history
+            .fiat_deposits
+            .iter()
+            .filter(|order| order.is_completed())
+            .map(BinanceEvent::FiatDeposit),
+    );
+    events.extend(
+        history
+            .fiat_withdrawals
+            .iter()
+            .filter(|order| order.is_completed())
+            .map(BinanceEvent::FiatWithdrawal),
+    );
+    events.extend(history.converts.iter().map(BinanceEvent::Convert));
+    for conversion in &history.dust_conversions {
+        events.extend(
+            conversion
+                .user_asset_dribblet_details
+                .iter()
+                .map(BinanceEvent::Dust),
+        );
+    }
+    events.extend(history.earn_rewards.iter().map(BinanceEvent::Reward));
+
+    let mut times = Vec::with_capacity(events.len());
+    for event in &events {
+        times.push(event.time()?);
+    }
+    let mut indexes: Vec<usize> = (0..events.len()).collect();
+    indexes.sort_by_key(|index| times[*index]);
+
+    let mut mapper = BinanceMapper::new(client, wallet_manager);
+    for index in indexes {
+        let time = times[index];
+        match events[index] {
+            BinanceEvent::Trade(trade) => mapper.map_trade(txs, history, trade, time).await?,
+            BinanceEvent::Deposit(deposit) => mapper.map_deposit(txs, deposit, time).await?,
+            BinanceEvent::Withdrawal(withdrawal) => {
+                mapper.map_withdrawal(txs, withdrawal, time).await?
+            }
+            BinanceEvent::FiatDeposit(order) => {
+                mapper.map_fiat_order(txs, order, true, time).await?
+            }
+            BinanceEvent::FiatWithdrawal(order) => {
+                mapper.map_fiat_order(txs, order, false, time).await?
+            }
+            BinanceEvent::Convert(convert) => mapper.map_convert(txs, convert, time).await?,
+            BinanceEvent::Dust(detail) => mapper.map_dust(txs, detail, time).await?,
+            BinanceEvent::Reward(reward) => mapper.map_reward(txs, reward, time).await?,
+        }
+    }
+    Ok(())
+}
+
+enum BinanceEvent<'a> {
+    Trade(&'a BinanceTrade),
+    Deposit(&'a BinanceDeposit),
+    Withdrawal(&'a BinanceWithdrawal),
+    FiatDeposit(&'a BinanceFiatOrder),
+    FiatWithdrawal(&'a BinanceFiatOrder),
+    Convert(&'a BinanceConvert),
+    Dust(&'a DustDetail),
+    Reward(&'a EarnReward),
+}
+
+impl BinanceEvent<'_> {
+    fn time(&self) -> Result<DateTime<Utc>, ApiError> {
+        let millis = match self {
+            BinanceEvent::Trade(trade) => trade.time,
+            BinanceEvent::Deposit(deposit) => deposit.insert_time,
+            BinanceEvent::Withdrawal(withdrawal) => {
+                // The withdrawal time is the only one given as a string (UTC)
+                return NaiveDateTime::parse_from_str(&withdrawal.apply_time, "%Y-%m-%d %H:%M:%S")
+                    .map(|time| time.and_utc())
+                    .map_err(|e| ApiError::MappingError(MappingError::Other(e.to_string())));
+            }
+            BinanceEvent::FiatDeposit(order) | BinanceEvent::FiatWithdrawal(order) => {
+                order.create_time
+            }
+            BinanceEvent::Convert(convert) => convert.create_time,
+            BinanceEvent::Dust(detail) => detail.operate_time,
+            BinanceEvent::Reward(reward) => reward.time,
+        };
+        DateTime::from_timestamp_millis(millis).ok_or(ApiError::MappingError(MappingError::Other(
+            format!("Invalid timestamp {millis}"),
+        )))
+    }
+}
+
+type BinanceMapper<'a> = ExchangeMapper<'a, BinanceClient>;
+
+impl ExchangePriceSource for BinanceClient {
+    fn platform(&self) -> Platform {
+        Platform::Binance
+    }
+
+    fn price_period(&self) -> i64 {
+        60 // Close price of the minute
+    }
+
+    fn fetch_price_eur<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_binance_price(self, time, currency))
+    }
+}
+
+impl BinanceMapper<'_> {
+    async fn map_trade(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        history: &BinanceHistory,
+        trade: &BinanceTrade,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let info = history
+            .symbols
+            .get(&trade.symbol)
+            .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                "Unknown Binance symbol {}",
+                trade.symbol
+            ))))?;
+        let (base, quote) = (&info.base_asset, &info.quote_asset);
+        let (sold_asset, sold_amount, bought_asset, bought_amount) = if trade.is_buyer {
+            (quote, trade.quote_qty, base, trade.qty)
+        } else {
+            (base, trade.qty, quote, trade.quote_qty)
+        };
+
+        // When the pair is quoted in euro, the trade gives the price
+        if FiatCurrency::is_eur(quote) {
+            self.set_price(base, time, trade.price);
+        }
+
+        let id = format!("binance-trade-{}-{}", trade.symbol, trade.id);
+        let mut from_fee = None;
+        let mut to_fee = None;
+        let mut third_asset_fee = None;
+        if !trade.commission.is_zero() {
+            if trade.commission_asset == *sold_asset {
+                from_fee = Some(trade.commission);
+            } else if trade.commission_asset == *bought_asset {
+                to_fee = Some(trade.commission);
+            } else {
+                third_asset_fee = Some((&trade.commission_asset, trade.commission));
+            }
+        }
+
+        let mut tx = self
+            .trade(
+                TransactionBase {
+                    id,
+                    timestamp: time,
+                },
+                (sold_asset, sold_amount, from_fee),
+                (bought_asset, bought_amount, to_fee),
+                Some((base.clone(), quote.clone())),
+            )
+            .await?;
+        if let Some((asset, commission)) = third_asset_fee {
+            self.third_currency_fee(&mut tx, asset, commission).await?;
+        }
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_deposit(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        deposit: &BinanceDeposit,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        // The deposit address is the Binance one, the origin of the funds is unknown
+        let tx = self
+            .transfer_in(
+                TransactionBase {
+                    id: format!("binance-deposit-{}", deposit.id),
+                    timestamp: time,
+                },
+                &deposit.coin,
+                &None,
+                deposit.amount,
+                None,
+            )
+            .await?;
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_withdrawal(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        withdrawal: &BinanceWithdrawal,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let fee = Some(withdrawal.transaction_fee).filter(|fee| !fee.is_zero());
+        let tx = self
+            .transfer_out(
+                TransactionBase {
+                    id: format!("binance-withdrawal-{}", withdrawal.id),
+                    timestamp: time,
+                },
+                &withdrawal.coin,
+                &Some(withdrawal.address.clone()),
+                withdrawal.amount,
+                fee,
+            )
+            .await?;
+        txs.push(tx);
+        Ok(())
+    }
+
+    /* The fee is taken from the indicated amount of the order */
+    async fn map_fiat_order(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        order: &BinanceFiatOrder,
+        is_deposit: bool,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let fee = Some(order.total_fee).filter(|fee| !fee.is_zero());
+        let tx = if is_deposit {
+            let tx = TransactionBase {
+                id: format!("binance-fiat-deposit-{}", order.order_no),
+                timestamp: time,
+            };
+            self.deposit(tx, &order.fiat_currency, order.indicated_amount, fee)
+                .await?
+        } else {
+            let tx = TransactionBase {
+                id: format!("binance-fiat-withdrawal-{}", order.order_no),
+                timestamp: time,
+            };
+            let amount = order.indicated_amount - order.total_fee;
+            self.withdrawal(tx, &order.fiat_currency, amount, fee)
+                .await?
+        };
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_convert(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        convert: &BinanceConvert,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let tx = self
+            .trade(
+                TransactionBase {
+                    id: format!("binance-convert-{}", convert.order_id),
+                    timestamp: time,
+                },
+                (&convert.from_asset, convert.from_amount, None),
+                (&convert.to_asset, convert.to_amount, None),
+                Some((convert.from_asset.clone(), convert.to_asset.clone())),
+            )
+            .await?;
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_dust(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        detail: &DustDetail,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let fee = Some(detail.service_charge_amount).filter(|fee| !fee.is_zero());
+        let tx = self
+            .trade(
+                TransactionBase {
+                    id: format!("binance-dust-{}-{}", detail.trans_id, detail.from_asset),
+                    timestamp: time,
+                },
+                (&detail.from_asset, detail.amount, None),
+                (
+                    "BNB",
+                    detail.transfered_amount + detail.service_charge_amount,
+                    fee,
+                ),
+                None,
+            )
+            .await?;
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_reward(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        reward: &EarnReward,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let (subtype, product) = match &reward.product {
+            EarnProduct::Flexible(reward_type) => {
+                (IncomeType::Interest, format!("flexible-{reward_type}"))
+            }
+            EarnProduct::Locked(position_id) => {
+                (IncomeType::Staking, format!("locked-{position_id}"))
+            }
+        };
+        let tx = TransactionBase {
+            id: format!("binance-earn-{}-{}-{product}", reward.asset, reward.time),
+            timestamp: time,
+        };
+        let tx = self
+            .income(tx, &reward.asset, reward.amount, subtype)
+            .await?;
+        txs.push(tx);
+        Ok(())
+    }
+}
+
+/* Price in euro of an asset, using the EUR pair if it exists, otherwise through USDT or BTC */
+pub async fn get_binance_price(
+    client: &BinanceClient,
+    time: DateTime<Utc>,
+    currency: &str,
+) -> Result<Decimal, ApiError> {
+    if FiatCurrency::is_eur(currency) {
+        return Ok(dec!(1));
+    }
+    if let Ok(price) = client.fetch_price(&format!("{currency}EUR"), time).await {
+        return Ok(price);
+    }
+    if let Ok(price) = client.fetch_price(&format!("EUR{currency}"), time).await {
+        return Ok(dec!(1) / price);
+    }
+    if let Ok(price_usdt) = client.fetch_price(&format!("{currency}USDT"), time).await {
+        let eur_usdt = client.fetch_price("EURUSDT", time).await?;
+        return Ok(price_usdt / eur_usdt);
+    }
+    if let Ok(price_btc) = client.fetch_price(&format!("{currency}BTC"), time).await {
+        let btc_eur = client.fetch_price("BTCEUR", time).await?;
+        return Ok(price_btc * btc_eur);
+    }
+    Err(ApiError::CouldNotFindPrice {
+        pairs: ["EUR", "USDT", "BTC"]
+            .iter()
+            .map(|quote| (currency.to_string(), quote.to_string()))
+            .collect(),
+    })
+}
@@ -0,0 +1,209 @@
+use chrono::{DateTime, Utc};
+use hashbrown::{HashMap, HashSet};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{BitcoinHistory, CoinGeckoClient, EsploraTransaction},
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Income, IncomeType, Transaction,
+        TransactionBase, WalletSnapshot,
+    },
+};
+
+const SATOSHI_DECIMALS: u32 = 8;
+
+/* Map the history of an extended public key to transactions on its BTC wallet.
+
+All the addresses derived from the key belong to the same wallet (Platform::Blockchain, address: the xpub), so the
+change sent back to the wallet is not a move:
+- each transaction changing the balance of the addresses is a Transfer, from or to the first other address
+- the fee is only recorded when the addresses paid it, in its own transfer for the moves between the addresses
+- the coinbase outputs received by the addresses are incomes (IncomeType::Mining)
+As for Binance, the balances are recalculated from zero.
+*/
+pub async fn create_bitcoin_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &BitcoinHistory,
+    price_client: &CoinGeckoClient,
+) -> Result<(), ApiError> {
+    let mut transactions: Vec<&EsploraTransaction> = history.transactions.iter().collect();
+    transactions.sort_by_key(|transaction| transaction.status.block_height);
+
+    let wallet_id = wallet_manager.create_or_get_wallet_id(
+        "BTC",
+        &Platform::Blockchain,
+        &Some(history.xpub.clone()),
+        false,
+    );
+    let mut mapper = BitcoinMapper {
+        price_client,
+        wallet_manager,
+        wallet_id,
+        addresses: history
+            .addresses
+            .iter()
+            .map(|address| address.as_str())
+            .collect(),
+        balance: dec!(0),
+        prices: HashMap::new(),
+    };
+    for transaction in transactions {
+        mapper.map_transaction(txs, transaction).await?;
+    }
+    Ok(())
+}
+
+struct BitcoinMapper<'a> {
+    price_client: &'a CoinGeckoClient,
+    wallet_manager: &'a mut WalletManager,
+    wallet_id: String,
+    addresses: HashSet<&'a str>,
+    balance: Decimal,
+    prices: HashMap<String, Decimal>, // Price of BTC for a given day
+}
+
+impl BitcoinMapper<'_> {
+    async fn map_transaction(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        transaction: &EsploraTransaction,
+    ) -> Result<(), ApiError> {
+        let id = format!("bitcoin-{}", transaction.txid);
+        let time = transaction
+            .status
+            .block_time
+            .and_then(|time| DateTime::from_timestamp(time, 0))
+            .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                "Missing block time of the Bitcoin transaction {id}"
+            ))))?;
+        let satoshis = |value: u64| Decimal::new(value as i64, SATOSHI_DECIMALS);
+        let spent: Decimal = transaction
+            .vin
+            .iter()
+            .filter_map(|input| input.prevout.as_ref())
+            .filter(|prevout| self.is_own(&prevout.scriptpubkey_address))
+            .map(|prevout| satoshis(prevout.value))
+            .sum();
+        let received: Decimal = transaction
+            .vout
+            .iter()
+            .filter(|output| self.is_own(&output.scriptpubkey_address))
+            .map(|output| satoshis(output.value))
+            .sum();
+        // The change coming back to the wallet cancels out with the inputs it comes from
+        let fee = Some(satoshis(transaction.fee)).filter(|fee| !spent.is_zero() && !fee.is_zero());
+        let amount = received - spent + fee.unwrap_or(dec!(0));
+
+        let own = self.snapshot(fee, time).await?;
+        self.balance += amount - fee.unwrap_or(dec!(0));
+
+        if amount.is_zero() {
+            // Consolidation of the addresses, or change sent to a new address
+            if fee.is_some() {
+                txs.push(Transaction::Transfer {
+                    tx: TransactionBase {
+                        id: format!("{id}-fee"),
+                        timestamp: time,
+                    },
+                    from: own.clone(),
+                    to: WalletSnapshot { fee: None, ..own },
+                    amount: dec!(0),
+                    income: None,
+                });
+            }
+            return Ok(());
+        }
+
+        let tx = TransactionBase {
+            id,
+            timestamp: time,
+        };
+        if transaction.vin.iter().any(|input| input.is_coinbase) {
+            let income = Income::new(amount * own.price_eur, IncomeType::Mining);
+            txs.push(Transaction::Transfer {
+                tx,
+                from: own.clone(),
+                to: own,
+                amount,
+                income: Some(income),
+            });
+            return Ok(());
+        }
+
+        let counterparty = if amount > dec!(0) {
+            transaction
+                .vin
+                .iter()
+                .filter_map(|input| input.prevout.as_ref())
+                .map(|prevout| &prevout.scriptpubkey_address)
+                .find(|address| address.is_some() && !self.is_own(address))
+                .cloned()
+                .flatten()
+        } else {
+            transaction
+                .vout
+                .iter()
+                .map(|output| &output.scriptpubkey_address)
+                .find(|address| address.is_some() && !self.is_own(address))
+                .cloned()
+                .flatten()
+        };
+        let external_id = self.wallet_manager.create_or_get_wallet_id(
+            "BTC",
+            &Platform::Blockchain,
+            &counterparty,
+            false,
+        );
+        let external = WalletSnapshot {
+            id: external_id,
+            // We don't know the balance of the counterparty so it only holds the amount transfered
+            pre_tx_balance: amount.max(dec!(0)),
+            price_eur: own.price_eur,
+            fee: None,
+        };
+        let (from, to) = if amount > dec!(0) {
+            (external, own)
+        } else {
+            (own, external)
+        };
+        txs.push(Transaction::Transfer {
+            tx,
+            from,
+            to,
+            amount: amount.abs(),
+            income: None,
+        });
+        Ok(())
+    }
+
+    fn is_own(&self, address: &Option<String>) -> bool {
+        address
+            .as_ref()
+            .is_some_and(|address| self.addresses.contains(address.as_str()))
+    }
+
+    async fn snapshot(
+        &mut self,
+        fee: Option<Decimal>,
+        time: DateTime<Utc>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let day = time.format("%Y-%m-%d").to_string();
+        let price_eur = match self.prices.get(&day) {
+            Some(price) => *price,
+            None => {
+                let price = self.price_client.fetch_price("BTC", time).await?;
+                self.prices.insert(day, price);
+                price
+            }
+        };
+        Ok(WalletSnapshot {
+            id: self.wallet_id.clone(),
+            pre_tx_balance: self.balance,
+            price_eur,
+            fee,
+        })
+    }
+}
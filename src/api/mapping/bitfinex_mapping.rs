@@ -0,0 +1,308 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        BitfinexClient, BitfinexHistory, BitfinexLedgerEntry, BitfinexMovement, BitfinexTrade,
+        ExchangeMapper, ExchangePriceSource, FiatCurrency, PriceFuture,
+    },
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, IncomeType, Transaction, TransactionBase,
+    },
+};
+
+/* Map the Bitfinex history to transactions.
+
+The trades and the movements (deposits and withdrawals) are mapped directly. The ledgers also contain these
+operations, as well as the moves between the exchange, margin and funding wallets which are all the same Bitfinex
+wallet here: only the margin funding payments (interest of the lending) and the staking rewards are taken from them.
+As for Binance, the balances before each transaction are recalculated from zero.
+
+The ids are the Bitfinex ids, prefixed by the kind of operation, so the same operation imported from a csv report
+has the same id.
+*/
+pub async fn create_bitfinex_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &BitfinexHistory,
+    client: &BitfinexClient,
+) -> Result<(), ApiError> {
+    let mut events: Vec<BitfinexEvent> = Vec::new();
+    events.extend(history.trades.iter().map(BitfinexEvent::Trade));
+    events.extend(
+        history
+            .movements
+            .iter()
+            .filter(|movement| movement.status == "COMPLETED")
+            .map(BitfinexEvent::Movement),
+    );
+    events.extend(
+        history
+            .ledgers
+            .iter()
+            .filter_map(|entry| Some(BitfinexEvent::Income(entry, ledger_income_type(entry)?))),
+    );
+    events.sort_by_key(|event| event.mts());
+
+    let mut mapper = BitfinexMapper::new(client, wallet_manager);
+    for event in events {
+        match event {
+            BitfinexEvent::Trade(trade) => mapper.map_trade(txs, trade).await?,
+            BitfinexEvent::Movement(movement) => mapper.map_movement(txs, movement).await?,
+            BitfinexEvent::Income(entry, subtype) => {
+                mapper.map_income(txs, entry, subtype).await?
+            }
+        }
+    }
+    Ok(())
+}
+
+/* Ledger entries that are incomes, from their description */
+pub fn ledger_income_type(entry: &BitfinexLedgerEntry) -> Option<IncomeType> {
+    let description = entry.description.to_lowercase();
+    if entry.amount <= dec!(0) {
+        None
+    } else if description.starts_with("margin funding payment") {
+        Some(IncomeType::Interest)
+    } else if description.starts_with("staking reward") {
+        Some(IncomeType::Staking)
+    } else {
+        None
+    }
+}
+
+enum BitfinexEvent<'a> {
+    Trade(&'a BitfinexTrade),
+    Movement(&'a BitfinexMovement),
+    Income(&'a BitfinexLedgerEntry, IncomeType),
+}
+
+impl BitfinexEvent<'_> {
+    fn mts(&self) -> i64 {
+        match self {
+            BitfinexEvent::Trade(trade) => trade.mts,
+            BitfinexEvent::Movement(movement) => movement.mts_updated,
+            BitfinexEvent::Income(entry, _) => entry.mts,
+        }
+    }
+}
+
+fn to_datetime(mts: i64) -> Result<DateTime<Utc>, ApiError> {
+    DateTime::from_timestamp_millis(mts).ok_or(ApiError::MappingError(MappingError::Other(
+        format!("Invalid timestamp {mts}"),
+    )))
+}
+
+type BitfinexMapper<'a> = ExchangeMapper<'a, BitfinexClient>;
+
+/* The balances and the prices are kept by Bitfinex code, the wallets are named with the usual codes */
+impl ExchangePriceSource for BitfinexClient {
+    fn platform(&self) -> Platform {
+        Platform::Bitfinex
+    }
+
+    fn price_period(&self) -> i64 {
+        60 // Close price of the minute
+    }
+
+    fn fetch_price_eur<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_bitfinex_price(self, time, currency))
+    }
+
+    fn wallet_currency(&self, currency: &str) -> String {
+        bitfinex_currency(currency)
+    }
+}
+
+impl BitfinexMapper<'_> {
+    async fn map_trade(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        trade: &BitfinexTrade,
+    ) -> Result<(), ApiError> {
+        let (base, quote) = split_symbol(&trade.symbol)?;
+        let time = to_datetime(trade.mts)?;
+        let base_amount = trade.exec_amount.abs();
+        let quote_amount = base_amount * trade.exec_price;
+        let ((sold, sold_amount), (bought, bought_amount)) = if trade.exec_amount > dec!(0) {
+            ((&quote, quote_amount), (&base, base_amount))
+        } else {
+            ((&base, base_amount), (&quote, quote_amount))
+        };
+        if FiatCurrency::is_eur(&quote) {
+            self.set_price(&base, time, trade.exec_price);
+        }
+
+        let fee = trade.fee.abs();
+        let mut from_fee = None;
+        let mut to_fee = None;
+        let mut third_currency_fee = None;
+        if !fee.is_zero() {
+            if trade.fee_currency == *sold {
+                from_fee = Some(fee);
+            } else if trade.fee_currency == *bought {
+                to_fee = Some(fee);
+            } else {
+                third_currency_fee = Some(fee);
+            }
+        }
+
+        let mut tx = self
+            .trade(
+                TransactionBase {
+                    id: format!("bitfinex-trade-{}", trade.id),
+                    timestamp: time,
+                },
+                (sold, sold_amount, from_fee),
+                (bought, bought_amount, to_fee),
+                Some((bitfinex_currency(&base), bitfinex_currency(&quote))),
+            )
+            .await?;
+        if let Some(fee) = third_currency_fee {
+            self.third_currency_fee(&mut tx, &trade.fee_currency, fee)
+                .await?;
+        }
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_movement(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        movement: &BitfinexMovement,
+    ) -> Result<(), ApiError> {
+        let currency = &movement.currency;
+        let tx = TransactionBase {
+            id: format!("bitfinex-movement-{}", movement.id),
+            timestamp: to_datetime(movement.mts_updated)?,
+        };
+        let amount = movement.amount.abs();
+        let fee = Some(movement.fees.abs()).filter(|fee| !fee.is_zero());
+        let is_deposit = movement.amount > dec!(0);
+
+        let tx = match (FiatCurrency::is_fiat(currency), is_deposit) {
+            (true, true) => self.deposit(tx, currency, amount, fee).await?,
+            (true, false) => self.withdrawal(tx, currency, amount, fee).await?,
+            // The destination address of a deposit is the Bitfinex one, the origin of the funds is unknown
+            (false, true) => self.transfer_in(tx, currency, &None, amount, fee).await?,
+            (false, false) => {
+                self.transfer_out(tx, currency, &movement.destination_address, amount, fee)
+                    .await?
+            }
+        };
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_income(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        entry: &BitfinexLedgerEntry,
+        subtype: IncomeType,
+    ) -> Result<(), ApiError> {
+        let tx = TransactionBase {
+            id: format!("bitfinex-ledger-{}", entry.id),
+            timestamp: to_datetime(entry.mts)?,
+        };
+        let tx = self
+            .income(tx, &entry.currency, entry.amount, subtype)
+            .await?;
+        txs.push(tx);
+        Ok(())
+    }
+}
+
+/* Trading symbols are t + base + quote, with a ":" between them when one of the codes is longer than 3 letters */
+pub fn split_symbol(symbol: &str) -> Result<(String, String), ApiError> {
+    let pair = symbol.strip_prefix('t').unwrap_or(symbol);
+    let (base, quote) = match pair.split_once(':') {
+        Some(split) => split,
+        None if pair.len() == 6 => pair.split_at(3),
+        None => {
+            return Err(ApiError::MappingError(MappingError::Other(format!(
+                "Unknown Bitfinex symbol {symbol}"
+            ))))
+        }
+    };
+    Ok((base.to_string(), quote.to_string()))
+}
+
+fn to_symbol(base: &str, quote: &str) -> String {
+    if base.len() > 3 || quote.len() > 3 {
+        format!("t{base}:{quote}")
+    } else {
+        format!("t{base}{quote}")
+    }
+}
+
+/* Bitfinex uses 3 letters codes for some currencies */
+pub fn bitfinex_currency(code: &str) -> String {
+    match code {
+        "UST" => "USDT",
+        "UDC" => "USDC",
+        "DSH" => "DASH",
+        "IOT" => "IOTA",
+        "QTM" => "QTUM",
+        "MNA" => "MANA",
+        "DAT" => "DATA",
+        "YYW" => "YOYOW",
+        "ALG" => "ALGO",
+        "ATO" => "ATOM",
+        "DOG" => "DOGE",
+        code => code,
+    }
+    .to_string()
+}
+
+/* Price in euro of a currency (Bitfinex code), using the EUR pair if it exists, otherwise the USD pair converted
+with the BTC price in both currencies */
+pub async fn get_bitfinex_price(
+    client: &BitfinexClient,
+    time: DateTime<Utc>,
+    currency: &str,
+) -> Result<Decimal, ApiError> {
+    if FiatCurrency::is_eur(currency) {
+        return Ok(dec!(1));
+    }
+    if let Ok(price) = client.fetch_price(&to_symbol(currency, "EUR"), time).await {
+        return Ok(price);
+    }
+    let usd_price = if currency == "USD" {
+        dec!(1)
+    } else {
+        client
+            .fetch_price(&to_symbol(currency, "USD"), time)
+            .await
+            .map_err(|_| ApiError::CouldNotFindPrice {
+                pairs: vec![
+                    (currency.to_string(), "EUR".to_string()),
+                    (currency.to_string(), "USD".to_string()),
+                ],
+            })?
+    };
+    let btc_eur = client.fetch_price("tBTCEUR", time).await?;
+    let btc_usd = client.fetch_price("tBTCUSD", time).await?;
+    Ok(usd_price * btc_eur / btc_usd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_symbol() {
+        assert_eq!(
+            split_symbol("tBTCEUR").unwrap(),
+            ("BTC".to_string(), "EUR".to_string())
+        );
+        assert_eq!(
+            split_symbol("tTESTBTC:TESTUSD").unwrap(),
+            ("TESTBTC".to_string(), "TESTUSD".to_string())
+        );
+        assert!(split_symbol("tBTCEURO").is_err());
+        assert_eq!(to_symbol("DOGE", "EUR"), "tDOGE:EUR");
+        assert_eq!(bitfinex_currency("UST"), "USDT");
+    }
+}
@@ -0,0 +1,313 @@
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+
+use crate::{
+    api::{
+        get_spot_price, BitpandaClient, BitpandaFiatTransaction, BitpandaHistory, BitpandaItem,
+        BitpandaTime, BitpandaTrade, BitpandaWalletTransaction, ExchangeMapper,
+        ExchangePriceSource, PriceFuture,
+    },
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, IncomeType, Transaction, TransactionBase,
+    },
+};
+
+/* Map the Bitpanda history to transactions.
+
+Bitpanda gives the euro value of every operation (price and fiat_to_eur_rate of the trades, amount_eur of the crypto
+transactions, to_eur_rate of the fiat transactions), so the prices don't need to be fetched.
+The buys of a savings plan are trades like the others: the direct debit is a deposit on the fiat wallet, then the fiat
+is traded, the local cost basis being the euro amount paid.
+The crypto transactions of type buy and sell are the crypto side of the trades and are ignored, as are the fiat ones.
+The fees paid in BEST (BEST fee collection) are given with their trade and put on it, their transfer out of the BEST
+wallet is ignored too. The BEST rewards and the card cashback are incomes.
+As for Binance, the balances before each transaction are recalculated from zero.
+*/
+pub async fn create_bitpanda_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &BitpandaHistory,
+    client: &BitpandaClient,
+) -> Result<(), ApiError> {
+    let mut events: Vec<BitpandaEvent> = Vec::new();
+    events.extend(
+        history
+            .trades
+            .iter()
+            .filter(|trade| trade.attributes.status == "finished")
+            .map(BitpandaEvent::Trade),
+    );
+    events.extend(
+        history
+            .wallet_transactions
+            .iter()
+            .filter(|transaction| transaction.attributes.status == "finished")
+            .filter(|transaction| {
+                !["buy", "sell"].contains(&transaction.attributes.r#type.as_str())
+                    && !transaction.attributes.is_bfc
+            })
+            .map(BitpandaEvent::WalletTransaction),
+    );
+    events.extend(
+        history
+            .fiat_transactions
+            .iter()
+            .filter(|transaction| transaction.attributes.status == "finished")
+            .filter(|transaction| {
+                ["deposit", "withdrawal"].contains(&transaction.attributes.r#type.as_str())
+            })
+            .map(BitpandaEvent::FiatTransaction),
+    );
+    let mut times = Vec::with_capacity(events.len());
+    for event in &events {
+        times.push(to_datetime(event.time())?);
+    }
+    let mut indexes: Vec<usize> = (0..events.len()).collect();
+    indexes.sort_by_key(|index| times[*index]);
+
+    let symbols = BitpandaSymbols {
+        cryptocoins: history
+            .wallets
+            .iter()
+            .map(|wallet| {
+                (
+                    wallet.attributes.cryptocoin_id.clone(),
+                    wallet.attributes.cryptocoin_symbol.clone(),
+                )
+            })
+            .collect(),
+        fiats: history
+            .fiat_wallets
+            .iter()
+            .map(|wallet| {
+                (
+                    wallet.attributes.fiat_id.clone(),
+                    wallet.attributes.fiat_symbol.clone(),
+                )
+            })
+            .collect(),
+    };
+    let mut mapper = BitpandaMapper::new(client, wallet_manager);
+    for index in indexes {
+        let time = times[index];
+        match events[index] {
+            BitpandaEvent::Trade(trade) => mapper.map_trade(txs, &symbols, trade, time).await?,
+            BitpandaEvent::WalletTransaction(transaction) => {
+                mapper
+                    .map_wallet_transaction(txs, &symbols, transaction, time)
+                    .await?
+            }
+            BitpandaEvent::FiatTransaction(transaction) => {
+                mapper
+                    .map_fiat_transaction(txs, &symbols, transaction, time)
+                    .await?
+            }
+        }
+    }
+    Ok(())
+}
+
+/* Incoming transfers that are incomes, from their tags */
+pub fn bitpanda_income_type(transaction: &BitpandaWalletTransaction) -> Option<IncomeType> {
+    if transaction.r#type != "transfer" || transaction.in_or_out != "incoming" {
+        return None;
+    }
+    transaction
+        .tags
+        .iter()
+        .find_map(|tag| match tag.attributes.short_name.as_str() {
+            "staking" => Some(IncomeType::Staking),
+            "reward" | "best_reward" | "cashback" => Some(IncomeType::Income),
+            _ => None,
+        })
+}
+
+enum BitpandaEvent<'a> {
+    Trade(&'a BitpandaItem<BitpandaTrade>),
+    WalletTransaction(&'a BitpandaItem<BitpandaWalletTransaction>),
+    FiatTransaction(&'a BitpandaItem<BitpandaFiatTransaction>),
+}
+
+impl BitpandaEvent<'_> {
+    fn time(&self) -> &BitpandaTime {
+        match self {
+            BitpandaEvent::Trade(trade) => &trade.attributes.time,
+            BitpandaEvent::WalletTransaction(transaction) => &transaction.attributes.time,
+            BitpandaEvent::FiatTransaction(transaction) => &transaction.attributes.time,
+        }
+    }
+}
+
+fn to_datetime(time: &BitpandaTime) -> Result<DateTime<Utc>, ApiError> {
+    time.unix
+        .parse()
+        .ok()
+        .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+        .ok_or(ApiError::MappingError(MappingError::Other(format!(
+            "Invalid timestamp {}",
+            time.unix
+        ))))
+}
+
+/* Symbols of the cryptocoin and fiat ids of Bitpanda */
+struct BitpandaSymbols {
+    cryptocoins: HashMap<String, String>,
+    fiats: HashMap<String, String>,
+}
+
+impl BitpandaSymbols {
+    fn cryptocoin(&self, cryptocoin_id: &str) -> Result<String, ApiError> {
+        self.cryptocoins
+            .get(cryptocoin_id)
+            .cloned()
+            .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                "Unknown Bitpanda cryptocoin {cryptocoin_id}"
+            ))))
+    }
+
+    fn fiat(&self, fiat_id: &str) -> Result<String, ApiError> {
+        self.fiats
+            .get(fiat_id)
+            .cloned()
+            .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                "Unknown Bitpanda fiat {fiat_id}"
+            ))))
+    }
+}
+
+type BitpandaMapper<'a> = ExchangeMapper<'a, BitpandaClient>;
+
+impl ExchangePriceSource for BitpandaClient {
+    fn platform(&self) -> Platform {
+        Platform::Bitpanda
+    }
+
+    fn price_period(&self) -> i64 {
+        86400 // The spot prices are daily
+    }
+
+    // Bitpanda has no historical prices, used only when the operation has no euro value
+    fn fetch_price_eur<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_spot_price(time, currency))
+    }
+}
+
+impl BitpandaMapper<'_> {
+    async fn map_trade(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        symbols: &BitpandaSymbols,
+        trade: &BitpandaItem<BitpandaTrade>,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let attributes = &trade.attributes;
+        let crypto = symbols.cryptocoin(&attributes.cryptocoin_id)?;
+        let fiat = symbols.fiat(&attributes.fiat_id)?;
+        self.set_price(
+            &crypto,
+            time,
+            attributes.price * attributes.fiat_to_eur_rate,
+        );
+        self.set_price(&fiat, time, attributes.fiat_to_eur_rate);
+
+        let crypto_side = (crypto.as_str(), attributes.amount_cryptocoin, None);
+        let fiat_side = (fiat.as_str(), attributes.amount_fiat, None);
+        let (sold, bought) = if attributes.r#type == "buy" {
+            (fiat_side, crypto_side)
+        } else {
+            (crypto_side, fiat_side)
+        };
+        let tx = TransactionBase {
+            id: format!("bitpanda-trade-{}", trade.id),
+            timestamp: time,
+        };
+        let mut trade_tx = self
+            .trade(tx, sold, bought, Some((crypto.clone(), fiat.clone())))
+            .await?;
+
+        if let Some(collection) = &attributes.best_fee_collection {
+            let collection = &collection.attributes;
+            let best =
+                symbols.cryptocoin(&collection.wallet_transaction.attributes.cryptocoin_id)?;
+            self.set_price(&best, time, collection.best_current_price_eur);
+            self.third_currency_fee(
+                &mut trade_tx,
+                &best,
+                collection.wallet_transaction.attributes.amount,
+            )
+            .await?;
+        }
+        txs.push(trade_tx);
+        Ok(())
+    }
+
+    async fn map_wallet_transaction(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        symbols: &BitpandaSymbols,
+        transaction: &BitpandaItem<BitpandaWalletTransaction>,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let attributes = &transaction.attributes;
+        let currency = symbols.cryptocoin(&attributes.cryptocoin_id)?;
+        if let Some(amount_eur) = attributes.amount_eur {
+            if !attributes.amount.is_zero() {
+                self.set_price(&currency, time, amount_eur / attributes.amount);
+            }
+        }
+        let amount = attributes.amount;
+        let fee = Some(attributes.fee).filter(|fee| !fee.is_zero());
+
+        if let Some(subtype) = bitpanda_income_type(attributes) {
+            let tx = TransactionBase {
+                id: format!("bitpanda-income-{}", transaction.id),
+                timestamp: time,
+            };
+            txs.push(self.income(tx, &currency, amount, subtype).await?);
+            return Ok(());
+        }
+
+        let tx = TransactionBase {
+            id: format!("bitpanda-{}-{}", attributes.r#type, transaction.id),
+            timestamp: time,
+        };
+        match (attributes.r#type.as_str(), attributes.in_or_out.as_str()) {
+            // The origin of the funds is unknown
+            ("deposit", "incoming") => {
+                txs.push(self.transfer_in(tx, &currency, &None, amount, fee).await?)
+            }
+            ("withdrawal", "outgoing") => txs.push(
+                self.transfer_out(tx, &currency, &attributes.recipient, amount, fee)
+                    .await?,
+            ),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn map_fiat_transaction(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        symbols: &BitpandaSymbols,
+        transaction: &BitpandaItem<BitpandaFiatTransaction>,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let attributes = &transaction.attributes;
+        let currency = symbols.fiat(&attributes.fiat_id)?;
+        self.set_price(&currency, time, attributes.to_eur_rate);
+        let fee = Some(attributes.fee).filter(|fee| !fee.is_zero());
+        let tx = TransactionBase {
+            id: format!("bitpanda-fiat-{}", transaction.id),
+            timestamp: time,
+        };
+        let transaction = if attributes.r#type == "deposit" {
+            self.deposit(tx, &currency, attributes.amount, fee).await?
+        } else {
+            self.withdrawal(tx, &currency, attributes.amount, fee)
+                .await?
+        };
+        txs.push(transaction);
+        Ok(())
+    }
+}
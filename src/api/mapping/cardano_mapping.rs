@@ -0,0 +1,251 @@
+use chrono::{DateTime, Utc};
+use hashbrown::{HashMap, HashSet};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        reward_epoch, BlockfrostReward, BlockfrostUtxo, CardanoHistory, CardanoTransaction,
+        CoinGeckoClient,
+    },
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Income, IncomeType, Transaction,
+        TransactionBase, WalletSnapshot,
+    },
+};
+
+const LOVELACE_PER_ADA: Decimal = dec!(1000000);
+
+/* Map the history of a Cardano stake key to transactions on its ADA wallet.
+
+All the addresses of the stake key belong to the same wallet (Platform::Blockchain, address: the stake address),
+which also holds the rewards not withdrawn yet and the deposit of the registration of the key:
+- each transaction moving ADA in or out of the addresses is a Transfer, from or to the first other address
+- the rewards are incomes (IncomeType::Staking) when received, so their withdrawal is not a move of the wallet
+- the fee is only recorded when the addresses of the stake key paid it
+Only ADA is mapped, the native tokens are ignored. As for Binance, the balances are recalculated from zero.
+*/
+pub async fn create_cardano_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &CardanoHistory,
+    price_client: &CoinGeckoClient,
+) -> Result<(), ApiError> {
+    let epoch_starts: HashMap<u32, i64> = history
+        .epochs
+        .iter()
+        .map(|epoch| (epoch.epoch, epoch.start_time))
+        .collect();
+    let mut events: Vec<(i64, CardanoEvent)> = Vec::new();
+    for transaction in &history.transactions {
+        events.push((
+            transaction.tx.block_time,
+            CardanoEvent::Transaction(transaction),
+        ));
+    }
+    for reward in &history.rewards {
+        let time = epoch_starts
+            .get(&reward_epoch(reward))
+            .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                "Missing start of the Cardano epoch {}",
+                reward_epoch(reward)
+            ))))?;
+        events.push((*time, CardanoEvent::Reward(reward)));
+    }
+    events.sort_by_key(|(time, _)| *time);
+
+    let wallet_id = wallet_manager.create_or_get_wallet_id(
+        "ADA",
+        &Platform::Blockchain,
+        &Some(history.stake_address.clone()),
+        false,
+    );
+    let mut mapper = CardanoMapper {
+        price_client,
+        wallet_manager,
+        wallet_id,
+        stake_address: &history.stake_address,
+        addresses: history
+            .addresses
+            .iter()
+            .map(|address| address.as_str())
+            .collect(),
+        balance: dec!(0),
+        prices: HashMap::new(),
+    };
+    for (time, event) in events {
+        let time = DateTime::from_timestamp(time, 0).ok_or(ApiError::MappingError(
+            MappingError::Other("Invalid Cardano block time".to_string()),
+        ))?;
+        match event {
+            CardanoEvent::Transaction(transaction) => {
+                mapper.map_transaction(txs, transaction, time).await?
+            }
+            CardanoEvent::Reward(reward) => mapper.map_reward(txs, reward, time).await?,
+        }
+    }
+    Ok(())
+}
+
+enum CardanoEvent<'a> {
+    Transaction(&'a CardanoTransaction),
+    Reward(&'a BlockfrostReward),
+}
+
+struct CardanoMapper<'a> {
+    price_client: &'a CoinGeckoClient,
+    wallet_manager: &'a mut WalletManager,
+    wallet_id: String,
+    stake_address: &'a str,
+    addresses: HashSet<&'a str>,
+    balance: Decimal,
+    prices: HashMap<String, Decimal>, // Price of ADA for a given day
+}
+
+impl CardanoMapper<'_> {
+    async fn map_transaction(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        transaction: &CardanoTransaction,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let hash = &transaction.tx.hash;
+        // A failed script only spends the collateral, the other inputs and outputs are not used
+        let is_spent = |utxo: &&BlockfrostUtxo| {
+            !utxo.reference && utxo.collateral != transaction.tx.valid_contract
+        };
+        let inputs: Vec<&BlockfrostUtxo> =
+            transaction.utxos.inputs.iter().filter(is_spent).collect();
+        let outputs: Vec<&BlockfrostUtxo> =
+            transaction.utxos.outputs.iter().filter(is_spent).collect();
+        let is_own = |utxo: &&&BlockfrostUtxo| self.addresses.contains(utxo.address.as_str());
+
+        let spent: Decimal = inputs
+            .iter()
+            .filter(is_own)
+            .map(|utxo| utxo.lovelace())
+            .sum();
+        let received: Decimal = outputs
+            .iter()
+            .filter(is_own)
+            .map(|utxo| utxo.lovelace())
+            .sum();
+        let pays_fee = !spent.is_zero();
+        let (fee, deposit) = if pays_fee {
+            (transaction.tx.fees, transaction.tx.deposit)
+        } else {
+            (dec!(0), dec!(0))
+        };
+        // The withdrawn rewards and the deposit of the stake key were and stay in the wallet
+        let withdrawn: Decimal = transaction
+            .withdrawals
+            .iter()
+            .filter(|withdrawal| withdrawal.address == self.stake_address)
+            .map(|withdrawal| withdrawal.amount)
+            .sum();
+        let amount = (received - spent + fee - withdrawn + deposit) / LOVELACE_PER_ADA;
+        let fee = Some(fee / LOVELACE_PER_ADA).filter(|fee| !fee.is_zero());
+
+        let own = self.snapshot(fee, time).await?;
+        let tx = TransactionBase {
+            id: format!("cardano-{hash}"),
+            timestamp: time,
+        };
+        self.balance += amount - fee.unwrap_or(dec!(0));
+
+        if amount.is_zero() {
+            // Transaction only moving ADA between the addresses, or registering the key and delegating
+            if fee.is_some() {
+                txs.push(Transaction::Transfer {
+                    tx: TransactionBase {
+                        id: format!("cardano-{hash}-fee"),
+                        timestamp: time,
+                    },
+                    from: own.clone(),
+                    to: WalletSnapshot { fee: None, ..own },
+                    amount: dec!(0),
+                    income: None,
+                });
+            }
+            return Ok(());
+        }
+
+        let counterparty = if amount > dec!(0) { &inputs } else { &outputs }
+            .iter()
+            .map(|utxo| &utxo.address)
+            .find(|address| !self.addresses.contains(address.as_str()))
+            .cloned();
+        let external_id = self.wallet_manager.create_or_get_wallet_id(
+            "ADA",
+            &Platform::Blockchain,
+            &counterparty,
+            false,
+        );
+        let external = WalletSnapshot {
+            id: external_id,
+            // We don't know the balance of the counterparty so it only holds the amount transfered
+            pre_tx_balance: amount.max(dec!(0)),
+            price_eur: own.price_eur,
+            fee: None,
+        };
+        let (from, to) = if amount > dec!(0) {
+            (external, own)
+        } else {
+            (own, external)
+        };
+        txs.push(Transaction::Transfer {
+            tx,
+            from,
+            to,
+            amount: amount.abs(),
+            income: None,
+        });
+        Ok(())
+    }
+
+    async fn map_reward(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        reward: &BlockfrostReward,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let amount = reward.amount / LOVELACE_PER_ADA;
+        let snapshot = self.snapshot(None, time).await?;
+        let income = Income::new(amount * snapshot.price_eur, IncomeType::Staking);
+        self.balance += amount;
+        txs.push(Transaction::Transfer {
+            tx: TransactionBase {
+                id: format!("cardano-reward-{}-{}", self.stake_address, reward.epoch),
+                timestamp: time,
+            },
+            from: snapshot.clone(),
+            to: snapshot,
+            amount,
+            income: Some(income),
+        });
+        Ok(())
+    }
+
+    async fn snapshot(
+        &mut self,
+        fee: Option<Decimal>,
+        time: DateTime<Utc>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let day = time.format("%Y-%m-%d").to_string();
+        let price_eur = match self.prices.get(&day) {
+            Some(price) => *price,
+            None => {
+                let price = self.price_client.fetch_price("ADA", time).await?;
+                self.prices.insert(day, price);
+                price
+            }
+        };
+        Ok(WalletSnapshot {
+            id: self.wallet_id.clone(),
+            pre_tx_balance: self.balance,
+            price_eur,
+            fee,
+        })
+    }
+}
@@ -0,0 +1,314 @@
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        CoinbaseClient, CoinbaseFill, CoinbaseHistory, CoinbaseTransaction, ExchangeMapper,
+        ExchangePriceSource, FiatCurrency, PriceFuture,
+    },
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, IncomeType, Transaction, TransactionBase,
+    },
+};
+
+/* Map the Coinbase history to transactions.
+
+Every v2 transaction belongs to an account (one per currency) and its amount is signed. A buy or a sell appears in
+the crypto account and, when paid from the fiat balance, in the fiat account too: only the crypto side is mapped,
+with the fiat amount given by the native amount. A conversion ("trade") has one transaction on each side, grouped
+by their trade id.
+The Advanced Trade fills also appear as "advanced_trade_fill" transactions in the accounts, they are mapped from
+the fills which hold the price and the commission.
+As for Binance, the balances before each transaction are recalculated from zero.
+*/
+pub async fn create_coinbase_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &CoinbaseHistory,
+    client: &CoinbaseClient,
+) -> Result<(), ApiError> {
+    let mut conversions: HashMap<&str, Vec<&CoinbaseTransaction>> = HashMap::new();
+    let mut events: Vec<CoinbaseEvent> = Vec::new();
+    for transaction in history
+        .transactions
+        .iter()
+        .filter(|tx| tx.status == "completed")
+    {
+        match (transaction.r#type.as_str(), &transaction.trade) {
+            ("trade", Some(trade)) => {
+                let sides = conversions.entry(trade.id.as_str()).or_default();
+                sides.push(transaction);
+                if sides.len() == 2 {
+                    events.push(CoinbaseEvent::Conversion(sides[0], sides[1]));
+                }
+            }
+            _ => events.push(CoinbaseEvent::Transaction(transaction)),
+        }
+    }
+    events.extend(history.fills.iter().map(CoinbaseEvent::Fill));
+    events.sort_by_key(|event| event.time());
+
+    let mut mapper = CoinbaseMapper::new(client, wallet_manager);
+    for event in events {
+        match event {
+            CoinbaseEvent::Transaction(transaction) => {
+                mapper.map_transaction(txs, transaction).await?
+            }
+            CoinbaseEvent::Conversion(first, second) => {
+                mapper.map_conversion(txs, first, second).await?
+            }
+            CoinbaseEvent::Fill(fill) => mapper.map_fill(txs, fill).await?,
+        }
+    }
+    Ok(())
+}
+
+enum CoinbaseEvent<'a> {
+    Transaction(&'a CoinbaseTransaction),
+    Conversion(&'a CoinbaseTransaction, &'a CoinbaseTransaction),
+    Fill(&'a CoinbaseFill),
+}
+
+impl CoinbaseEvent<'_> {
+    fn time(&self) -> DateTime<Utc> {
+        match self {
+            CoinbaseEvent::Transaction(transaction) => transaction.created_at,
+            CoinbaseEvent::Conversion(first, second) => first.created_at.min(second.created_at),
+            CoinbaseEvent::Fill(fill) => fill.trade_time,
+        }
+    }
+}
+
+type CoinbaseMapper<'a> = ExchangeMapper<'a, CoinbaseClient>;
+
+impl ExchangePriceSource for CoinbaseClient {
+    fn platform(&self) -> Platform {
+        Platform::Coinbase
+    }
+
+    fn price_period(&self) -> i64 {
+        86400 // Coinbase prices are daily
+    }
+
+    fn fetch_price_eur<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_coinbase_price(self, time, currency))
+    }
+}
+
+impl CoinbaseMapper<'_> {
+    async fn map_transaction(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        transaction: &CoinbaseTransaction,
+    ) -> Result<(), ApiError> {
+        let currency = &transaction.amount.currency;
+        let amount = transaction.amount.amount.abs();
+        let native_amount = transaction.native_amount.amount.abs();
+        let native_currency = &transaction.native_amount.currency;
+        let tx = TransactionBase {
+            id: format!("coinbase-{}", transaction.id),
+            timestamp: transaction.created_at,
+        };
+        let is_fiat = FiatCurrency::is_fiat(currency);
+        // Price of the transaction given by the native amount, when it is in euro
+        if FiatCurrency::is_eur(native_currency) && !amount.is_zero() {
+            self.set_price(currency, tx.timestamp, native_amount / amount);
+        }
+
+        match transaction.r#type.as_str() {
+            "buy" | "sell" if is_fiat => Ok(()), // Mapped from the crypto account
+            "buy" => {
+                let trade = self
+                    .trade(
+                        tx,
+                        (native_currency, native_amount, None),
+                        (currency, amount, None),
+                        Some((currency.clone(), native_currency.clone())),
+                    )
+                    .await?;
+                txs.push(trade);
+                Ok(())
+            }
+            "sell" => {
+                let trade = self
+                    .trade(
+                        tx,
+                        (currency, amount, None),
+                        (native_currency, native_amount, None),
+                        Some((currency.clone(), native_currency.clone())),
+                    )
+                    .await?;
+                txs.push(trade);
+                Ok(())
+            }
+            "send" | "receive" if transaction.amount.amount.is_sign_negative() => {
+                // The network gives the amount received and the fee, their sum is the amount of the transaction
+                let network = transaction.network.as_ref();
+                let fee = network
+                    .and_then(|network| network.transaction_fee.as_ref())
+                    .map(|fee| fee.amount)
+                    .filter(|fee| !fee.is_zero());
+                let sent = network
+                    .and_then(|network| network.transaction_amount.as_ref())
+                    .map(|sent| sent.amount)
+                    .unwrap_or(amount - fee.unwrap_or(dec!(0)));
+                let address = transaction.to.as_ref().and_then(|to| to.address.clone());
+
+                let transfer = self.transfer_out(tx, currency, &address, sent, fee).await?;
+                txs.push(transfer);
+                Ok(())
+            }
+            "send" | "receive" => {
+                let address = transaction
+                    .from
+                    .as_ref()
+                    .and_then(|from| from.address.clone());
+                let transfer = self
+                    .transfer_in(tx, currency, &address, amount, None)
+                    .await?;
+                txs.push(transfer);
+                Ok(())
+            }
+            "staking_reward" | "inflation_reward" | "interest" => {
+                let subtype = if transaction.r#type == "interest" {
+                    IncomeType::Interest
+                } else {
+                    IncomeType::Staking
+                };
+                let income = self.income(tx, currency, amount, subtype).await?;
+                txs.push(income);
+                Ok(())
+            }
+            "fiat_deposit" => {
+                let deposit = self.deposit(tx, currency, amount, None).await?;
+                txs.push(deposit);
+                Ok(())
+            }
+            "fiat_withdrawal" => {
+                let withdrawal = self.withdrawal(tx, currency, amount, None).await?;
+                txs.push(withdrawal);
+                Ok(())
+            }
+            // Mapped from the fills, or moves between Coinbase and Coinbase Pro which share the same wallets
+            "advanced_trade_fill"
+            | "pro_deposit"
+            | "pro_withdrawal"
+            | "exchange_deposit"
+            | "exchange_withdrawal" => Ok(()),
+            other => Err(ApiError::MappingError(MappingError::Other(format!(
+                "Coinbase transaction {} of type {other} is not handled",
+                transaction.id
+            )))),
+        }
+    }
+
+    async fn map_conversion(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        first: &CoinbaseTransaction,
+        second: &CoinbaseTransaction,
+    ) -> Result<(), ApiError> {
+        let (sold, bought) = if first.amount.amount.is_sign_negative() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        for side in [sold, bought] {
+            if FiatCurrency::is_eur(&side.native_amount.currency) && !side.amount.amount.is_zero()
+            {
+                self.set_price(
+                    &side.amount.currency,
+                    side.created_at,
+                    side.native_amount.amount.abs() / side.amount.amount.abs(),
+                );
+            }
+        }
+        let trade = self
+            .trade(
+                TransactionBase {
+                    id: format!("coinbase-{}", sold.id),
+                    timestamp: sold.created_at.min(bought.created_at),
+                },
+                (&sold.amount.currency, sold.amount.amount.abs(), None),
+                (&bought.amount.currency, bought.amount.amount.abs(), None),
+                Some((sold.amount.currency.clone(), bought.amount.currency.clone())),
+            )
+            .await?;
+        txs.push(trade);
+        Ok(())
+    }
+
+    async fn map_fill(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        fill: &CoinbaseFill,
+    ) -> Result<(), ApiError> {
+        let (base, quote) = fill
+            .product_id
+            .split_once('-')
+            .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                "Unknown Coinbase product {}",
+                fill.product_id
+            ))))?;
+        let (base, quote) = (base.to_string(), quote.to_string());
+        let base_size = if fill.size_in_quote {
+            fill.size / fill.price
+        } else {
+            fill.size
+        };
+        let quote_size = base_size * fill.price;
+        if FiatCurrency::is_eur(&quote) {
+            self.set_price(&base, fill.trade_time, fill.price);
+        }
+
+        let commission = Some(fill.commission).filter(|commission| !commission.is_zero());
+        let tx = TransactionBase {
+            id: format!("coinbase-fill-{}", fill.entry_id),
+            timestamp: fill.trade_time,
+        };
+        let pair = Some((base.clone(), quote.clone()));
+        let trade = match fill.side.as_str() {
+            "BUY" => {
+                self.trade(
+                    tx,
+                    (&quote, quote_size, commission),
+                    (&base, base_size, None),
+                    pair,
+                )
+                .await?
+            }
+            "SELL" => {
+                self.trade(
+                    tx,
+                    (&base, base_size, None),
+                    (&quote, quote_size, commission),
+                    pair,
+                )
+                .await?
+            }
+            side => {
+                return Err(ApiError::MappingError(MappingError::Other(format!(
+                    "Unknown side {side} for the Coinbase fill {}",
+                    fill.entry_id
+                ))))
+            }
+        };
+        txs.push(trade);
+        Ok(())
+    }
+}
+
+/* Price in euro of a currency for the day of the given time */
+pub async fn get_coinbase_price(
+    client: &CoinbaseClient,
+    time: DateTime<Utc>,
+    currency: &str,
+) -> Result<Decimal, ApiError> {
+    if FiatCurrency::is_eur(currency) {
+        return Ok(dec!(1));
+    }
+    client.fetch_price(currency, time).await
+}
@@ -0,0 +1,300 @@
+use chrono::{DateTime, Utc};
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        coinbase_mapping, CoinbaseClient, CryptoComAppRow, CryptoComHistory, CryptoComMovement,
+        CryptoComTrade, ExchangeMapper, ExchangePriceSource, FiatCurrency, PriceFuture,
+    },
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, IncomeType, Transaction, TransactionBase,
+    },
+};
+
+// The App and the Exchange have distinct balances, their wallets are told apart by the account in their info
+const APP: &str = "app";
+const EXCHANGE: &str = "exchange";
+
+/* Map the Crypto.com history (Exchange API and App export) to transactions.
+
+The rows of the App give the value of the operation in the native currency of the user: when it is the euro, it gives
+the price. Otherwise, and for the Exchange, the price is the Coinbase one as for the wallets outside exchanges.
+- crypto_viban_exchange (crypto sold for fiat), viban_purchase (fiat to crypto) and crypto_exchange are trades
+- the card cashback, the Earn interest and the supercharger rewards are incomes (see app_income_type)
+- the moves between the App wallet and Earn or the supercharger keep the funds in the App wallet and are ignored
+As for Binance, the balances before each transaction are recalculated from zero.
+*/
+pub async fn create_crypto_com_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &CryptoComHistory,
+    price_client: &CoinbaseClient,
+) -> Result<(), ApiError> {
+    let mut events: Vec<CryptoComEvent> = Vec::new();
+    events.extend(history.trades.iter().map(CryptoComEvent::Trade));
+    events.extend(
+        history
+            .deposits
+            .iter()
+            .filter(|deposit| deposit.status == "1")
+            .map(CryptoComEvent::Deposit),
+    );
+    events.extend(
+        history
+            .withdrawals
+            .iter()
+            .filter(|withdrawal| withdrawal.status == "5")
+            .map(CryptoComEvent::Withdrawal),
+    );
+    events.extend(history.app_rows.iter().map(CryptoComEvent::App));
+    let mut times = Vec::with_capacity(events.len());
+    for event in &events {
+        times.push(event.time()?);
+    }
+    let mut indexes: Vec<usize> = (0..events.len()).collect();
+    indexes.sort_by_key(|index| times[*index]);
+
+    let prices = CryptoComPrices { price_client };
+    let mut mapper = CryptoComMapper::new(&prices, wallet_manager);
+    for index in indexes {
+        let time = times[index];
+        match events[index] {
+            CryptoComEvent::Trade(trade) => mapper.map_trade(txs, trade, time).await?,
+            CryptoComEvent::Deposit(deposit) => mapper.map_deposit(txs, deposit, time).await?,
+            CryptoComEvent::Withdrawal(withdrawal) => {
+                mapper.map_withdrawal(txs, withdrawal, time).await?
+            }
+            CryptoComEvent::App(row) => mapper.map_app_row(txs, row, time).await?,
+        }
+    }
+    Ok(())
+}
+
+/* Kinds of rows of the App that are incomes */
+pub fn app_income_type(kind: &str) -> Option<IncomeType> {
+    match kind {
+        "referral_card_cashback" | "reimbursement" => Some(IncomeType::Income),
+        "crypto_earn_interest_paid" => Some(IncomeType::Interest),
+        "supercharger_reward_to_app_credited" | "mco_stake_reward" => Some(IncomeType::Staking),
+        "referral_gift" | "referral_bonus" => Some(IncomeType::Gift),
+        _ => None,
+    }
+}
+
+enum CryptoComEvent<'a> {
+    Trade(&'a CryptoComTrade),
+    Deposit(&'a CryptoComMovement),
+    Withdrawal(&'a CryptoComMovement),
+    App(&'a CryptoComAppRow),
+}
+
+impl CryptoComEvent<'_> {
+    fn time(&self) -> Result<DateTime<Utc>, ApiError> {
+        let time = match self {
+            CryptoComEvent::Trade(trade) => DateTime::from_timestamp_millis(trade.create_time),
+            CryptoComEvent::Deposit(movement) | CryptoComEvent::Withdrawal(movement) => {
+                DateTime::from_timestamp_millis(movement.create_time)
+            }
+            CryptoComEvent::App(row) => DateTime::from_timestamp(row.timestamp, 0),
+        };
+        time.ok_or(ApiError::MappingError(MappingError::Other(
+            "Invalid Crypto.com timestamp".to_string(),
+        )))
+    }
+}
+
+/* Crypto.com has no price history, the Coinbase prices are used as for the wallets outside exchanges */
+struct CryptoComPrices<'a> {
+    price_client: &'a CoinbaseClient,
+}
+
+impl ExchangePriceSource for CryptoComPrices<'_> {
+    fn platform(&self) -> Platform {
+        Platform::CryptoCom
+    }
+
+    fn price_period(&self) -> i64 {
+        86400 // The Coinbase prices are daily
+    }
+
+    fn fetch_price_eur<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(coinbase_mapping::get_coinbase_price(
+            self.price_client,
+            time,
+            currency,
+        ))
+    }
+}
+
+type CryptoComMapper<'a> = ExchangeMapper<'a, CryptoComPrices<'a>>;
+
+impl CryptoComMapper<'_> {
+    async fn map_trade(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        trade: &CryptoComTrade,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let (base, quote) =
+            trade
+                .instrument_name
+                .split_once('_')
+                .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                    "Unknown Crypto.com instrument {}",
+                    trade.instrument_name
+                ))))?;
+        let base_amount = trade.traded_quantity;
+        let quote_amount = trade.traded_quantity * trade.traded_price;
+        let ((sold, sold_amount), (bought, bought_amount)) = if trade.side == "BUY" {
+            ((quote, quote_amount), (base, base_amount))
+        } else {
+            ((base, base_amount), (quote, quote_amount))
+        };
+        if FiatCurrency::is_eur(quote) {
+            self.set_price(base, time, trade.traded_price);
+        }
+
+        let fee = trade.fees.abs();
+        let fee_of = |currency: &str| {
+            Some(fee).filter(|fee| !fee.is_zero() && trade.fee_instrument_name == currency)
+        };
+        self.account = Some(EXCHANGE);
+        let mut tx = self
+            .trade(
+                TransactionBase {
+                    id: format!("cryptocom-trade-{}", trade.trade_id),
+                    timestamp: time,
+                },
+                (sold, sold_amount, fee_of(sold)),
+                (bought, bought_amount, fee_of(bought)),
+                Some((base.to_string(), quote.to_string())),
+            )
+            .await?;
+        if !fee.is_zero() && fee_of(sold).is_none() && fee_of(bought).is_none() {
+            // Fee paid in a third currency, CRO with the discount
+            self.third_currency_fee(&mut tx, &trade.fee_instrument_name, fee)
+                .await?;
+        }
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_deposit(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        deposit: &CryptoComMovement,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        self.account = Some(EXCHANGE);
+        let tx = self
+            .transfer_in(
+                TransactionBase {
+                    id: format!("cryptocom-deposit-{}", deposit.id),
+                    timestamp: time,
+                },
+                &deposit.currency,
+                &None,
+                deposit.amount,
+                Some(deposit.fee).filter(|fee| !fee.is_zero()),
+            )
+            .await?;
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_withdrawal(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        withdrawal: &CryptoComMovement,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        self.account = Some(EXCHANGE);
+        let tx = self
+            .transfer_out(
+                TransactionBase {
+                    id: format!("cryptocom-withdrawal-{}", withdrawal.id),
+                    timestamp: time,
+                },
+                &withdrawal.currency,
+                &Some(withdrawal.address.clone()),
+                withdrawal.amount.abs(),
+                Some(withdrawal.fee).filter(|fee| !fee.is_zero()),
+            )
+            .await?;
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_app_row(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &CryptoComAppRow,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let tx = TransactionBase {
+            id: format!(
+                "cryptocom-app-{}-{}-{}",
+                row.kind, row.currency, row.timestamp
+            ),
+            timestamp: time,
+        };
+        let amount = row.amount.abs();
+        // Price given by the native amount when the native currency is the euro
+        if FiatCurrency::is_eur(&row.native_currency) && !amount.is_zero() {
+            self.set_price(&row.currency, time, row.native_amount.abs() / amount);
+        }
+        self.account = Some(APP);
+
+        if let Some(subtype) = app_income_type(&row.kind) {
+            txs.push(self.income(tx, &row.currency, row.amount, subtype).await?);
+            return Ok(());
+        }
+
+        match row.kind.as_str() {
+            "crypto_viban_exchange" | "viban_purchase" | "crypto_exchange" => {
+                let (to_currency, to_amount) = match (&row.to_currency, row.to_amount) {
+                    (Some(to_currency), Some(to_amount)) => (to_currency, to_amount.abs()),
+                    _ => {
+                        return Err(ApiError::MappingError(MappingError::Other(format!(
+                            "Missing To Currency for the Crypto.com {} of {}",
+                            row.kind, row.timestamp
+                        ))))
+                    }
+                };
+                if FiatCurrency::is_eur(&row.native_currency) && !to_amount.is_zero() {
+                    self.set_price(to_currency, time, row.native_amount.abs() / to_amount);
+                }
+                let trade = self
+                    .trade(
+                        tx,
+                        (&row.currency, amount, None),
+                        (to_currency, to_amount, None),
+                        None,
+                    )
+                    .await?;
+                txs.push(trade);
+            }
+            "crypto_deposit" | "crypto_withdrawal" => {
+                let transfer = if row.amount > dec!(0) {
+                    self.transfer_in(tx, &row.currency, &None, amount, None)
+                        .await?
+                } else {
+                    self.transfer_out(tx, &row.currency, &None, amount, None)
+                        .await?
+                };
+                txs.push(transfer);
+            }
+            "viban_deposit" | "viban_withdrawal" if FiatCurrency::is_fiat(&row.currency) => {
+                let transaction = if row.amount > dec!(0) {
+                    self.deposit(tx, &row.currency, amount, None).await?
+                } else {
+                    self.withdrawal(tx, &row.currency, amount, None).await?
+                };
+                txs.push(transaction);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,442 @@
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::str::FromStr;
+
+use crate::{
+    api::{
+        evm_amount, CoinGeckoClient, EtherscanInternalTransaction, EtherscanTokenTransfer,
+        EtherscanTransaction, EvmChain, EvmHistory,
+    },
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Address, TradeType, Transaction,
+        TransactionBase, WalletSnapshot,
+    },
+};
+
+const NATIVE_DECIMALS: u32 = 18;
+
+// Tokens with a price on each chain, by contract address. The others keep their contract address as currency.
+// The wrapped native currencies (WETH, WPOL) are merged with the native currency, so wrapping is not a move
+const ETHEREUM_TOKENS: [(&str, &str); 5] = [
+    ("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "ETH"),
+    ("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "USDC"),
+    ("0xdac17f958d2ee523a2206206994597c13d831ec7", "USDT"),
+    ("0x6b175474e89094c44da98b954eedeac495271d0f", "DAI"),
+    ("0x514910771af9ca656af840dff83e8264ecf986ca", "LINK"),
+];
+const ARBITRUM_TOKENS: [(&str, &str); 4] = [
+    ("0x82af49447d8a07e3bd95bd0d56f35241523fbab1", "ETH"),
+    ("0xaf88d065e77c8cc2239327c5edb3a432268e5831", "USDC"),
+    ("0xfd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9", "USDT"),
+    ("0x912ce59144191c1204e64559fe8253a0e49e6548", "ARB"),
+];
+const POLYGON_TOKENS: [(&str, &str); 5] = [
+    ("0x0d500b1d8e8ef31e21c99d1db9a6444d3adf1270", "POL"),
+    ("0x3c499c542cef5e3811e1192ce70d8cc03d5c3359", "USDC"),
+    ("0xc2132d05d31c914a87c6611c10748aeb04b58e8f", "USDT"),
+    ("0x7ceb23fd6bc0add59e62ac25578270cff1b9f619", "ETH"),
+    ("0x8f3cf7ad23cd3cadbd9735aff958023239c6a063", "DAI"),
+];
+
+/* Map the history of an address on an EVM chain to transactions on its Platform::Blockchain wallets.
+
+The wallets are distinct for each chain, their address being {chain}:{address}, as are the ones of the
+counterparties. The normal, internal and token transactions with the same hash are one transaction:
+- a transaction selling one currency and buying another is a swap (Trade)
+- every other change of balance is a Transfer from or to the counterparty
+- the gas (gasUsed * gasPrice) is paid in the native currency by the sender of the transaction, it is only recorded
+when it is the address, in its own transfer when the native currency is not moved (approval, failed transaction...)
+As for Binance, the balances are recalculated from zero.
+*/
+pub async fn create_evm_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &EvmHistory,
+    price_client: &CoinGeckoClient,
+) -> Result<(), ApiError> {
+    let groups = group_transactions(history)?;
+
+    let mut mapper = EvmMapper {
+        chain: &history.chain,
+        address: &history.address,
+        price_client,
+        wallet_manager,
+        balances: HashMap::new(),
+        prices: HashMap::new(),
+    };
+    for group in groups {
+        mapper.map_transaction(txs, &group).await?;
+    }
+    Ok(())
+}
+
+fn chain_tokens(chain: &str) -> &'static [(&'static str, &'static str)] {
+    match chain {
+        "ethereum" => &ETHEREUM_TOKENS,
+        "arbitrum" => &ARBITRUM_TOKENS,
+        "polygon" => &POLYGON_TOKENS,
+        _ => &[],
+    }
+}
+
+pub fn evm_token_symbol(chain: &str, contract_address: &str) -> Option<&'static str> {
+    chain_tokens(chain)
+        .iter()
+        .find(|(contract, _)| contract.eq_ignore_ascii_case(contract_address))
+        .map(|(_, symbol)| *symbol)
+}
+
+/* The normal, internal and token transactions of a same hash */
+struct EvmTransactionGroup<'a> {
+    hash: &'a str,
+    block_number: u64,
+    time_stamp: i64,
+    transaction: Option<&'a EtherscanTransaction>,
+    internal_transactions: Vec<&'a EtherscanInternalTransaction>,
+    token_transfers: Vec<&'a EtherscanTokenTransfer>,
+}
+
+fn parse_integer<T: FromStr>(value: &str, hash: &str) -> Result<T, ApiError> {
+    value.parse().map_err(|_| {
+        ApiError::DeserializationError(format!(
+            "Invalid number {value} in the EVM transaction {hash}"
+        ))
+    })
+}
+
+/* Index of the group of the hash, created when it is the first transaction seen with it */
+fn group_index<'a>(
+    groups: &mut Vec<EvmTransactionGroup<'a>>,
+    positions: &mut HashMap<&'a str, usize>,
+    hash: &'a str,
+    block_number: &str,
+    time_stamp: &str,
+) -> Result<usize, ApiError> {
+    if let Some(index) = positions.get(hash) {
+        return Ok(*index);
+    }
+    groups.push(EvmTransactionGroup {
+        hash,
+        block_number: parse_integer(block_number, hash)?,
+        time_stamp: parse_integer(time_stamp, hash)?,
+        transaction: None,
+        internal_transactions: Vec::new(),
+        token_transfers: Vec::new(),
+    });
+    positions.insert(hash, groups.len() - 1);
+    Ok(groups.len() - 1)
+}
+
+fn group_transactions(history: &EvmHistory) -> Result<Vec<EvmTransactionGroup<'_>>, ApiError> {
+    let mut groups: Vec<EvmTransactionGroup> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for transaction in &history.transactions {
+        let index = group_index(
+            &mut groups,
+            &mut positions,
+            &transaction.hash,
+            &transaction.block_number,
+            &transaction.time_stamp,
+        )?;
+        groups[index].transaction = Some(transaction);
+    }
+    for transaction in &history.internal_transactions {
+        let index = group_index(
+            &mut groups,
+            &mut positions,
+            &transaction.hash,
+            &transaction.block_number,
+            &transaction.time_stamp,
+        )?;
+        groups[index].internal_transactions.push(transaction);
+    }
+    for transfer in &history.token_transfers {
+        let index = group_index(
+            &mut groups,
+            &mut positions,
+            &transfer.hash,
+            &transfer.block_number,
+            &transfer.time_stamp,
+        )?;
+        groups[index].token_transfers.push(transfer);
+    }
+    groups.sort_by_key(|group| group.block_number);
+    Ok(groups)
+}
+
+/* Change of the balance of a currency of the address in a transaction, without the fee */
+struct BalanceChange {
+    currency: String,
+    amount: Decimal,
+    counterparty: Option<String>,
+}
+
+fn add_change(
+    changes: &mut Vec<BalanceChange>,
+    address: &str,
+    currency: String,
+    amount: Decimal,
+    from: &str,
+    to: &str,
+) {
+    let is_sent = from.eq_ignore_ascii_case(address);
+    let is_received = to.eq_ignore_ascii_case(address);
+    if amount.is_zero() || is_sent == is_received {
+        return;
+    }
+    let (amount, counterparty) = if is_sent {
+        (-amount, to)
+    } else {
+        (amount, from)
+    };
+    match changes
+        .iter_mut()
+        .find(|change| change.currency == currency)
+    {
+        Some(change) => change.amount += amount,
+        None => changes.push(BalanceChange {
+            currency,
+            amount,
+            counterparty: Some(counterparty.to_lowercase()).filter(|address| !address.is_empty()),
+        }),
+    }
+}
+
+struct EvmMapper<'a> {
+    chain: &'a EvmChain,
+    address: &'a str,
+    price_client: &'a CoinGeckoClient,
+    wallet_manager: &'a mut WalletManager,
+    balances: HashMap<String, Decimal>,
+    prices: HashMap<(String, String), Decimal>, // Price of a currency for a given day
+}
+
+impl EvmMapper<'_> {
+    async fn map_transaction(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        group: &EvmTransactionGroup<'_>,
+    ) -> Result<(), ApiError> {
+        let id = format!("{}-{}", self.chain.name, group.hash);
+        let time = DateTime::from_timestamp(group.time_stamp, 0).ok_or(ApiError::MappingError(
+            MappingError::Other(format!("Invalid time of the EVM transaction {id}")),
+        ))?;
+        let native = self.chain.native_currency.clone();
+
+        let fee = match group.transaction {
+            Some(transaction) if transaction.from.eq_ignore_ascii_case(self.address) => {
+                let gas_used: u128 = parse_integer(&transaction.gas_used, group.hash)?;
+                let gas_price: u128 = parse_integer(&transaction.gas_price, group.hash)?;
+                Some(evm_amount(
+                    &(gas_used * gas_price).to_string(),
+                    NATIVE_DECIMALS,
+                )?)
+                .filter(|fee| !fee.is_zero())
+            }
+            _ => None,
+        };
+        let changes = if group
+            .transaction
+            .is_some_and(|transaction| transaction.is_error == "1")
+        {
+            Vec::new()
+        } else {
+            self.balance_changes(group)?
+        };
+
+        if fee.is_some() && !changes.iter().any(|change| change.currency == native) {
+            let snapshot = self.snapshot(&native, fee, time).await?;
+            self.update_balance(&native, -fee.unwrap_or(dec!(0)));
+            txs.push(Transaction::Transfer {
+                tx: TransactionBase {
+                    id: format!("{id}-fee"),
+                    timestamp: time,
+                },
+                from: snapshot.clone(),
+                to: WalletSnapshot {
+                    fee: None,
+                    ..snapshot
+                },
+                amount: dec!(0),
+                income: None,
+            });
+        }
+        let fee_of = |currency: &str| fee.filter(|_| currency == native);
+
+        if let [first, second] = changes.as_slice() {
+            if first.amount.is_sign_negative() != second.amount.is_sign_negative() {
+                let (sold, bought) = if first.amount < dec!(0) {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                let from = self
+                    .snapshot(&sold.currency, fee_of(&sold.currency), time)
+                    .await?;
+                let to = self
+                    .snapshot(&bought.currency, fee_of(&bought.currency), time)
+                    .await?;
+                for change in [sold, bought] {
+                    let fee = fee_of(&change.currency).unwrap_or(dec!(0));
+                    self.update_balance(&change.currency, change.amount - fee);
+                }
+                txs.push(Transaction::Trade {
+                    tx: TransactionBase {
+                        id,
+                        timestamp: time,
+                    },
+                    from,
+                    to,
+                    exchange_pair: None,
+                    sold_amount: sold.amount.abs(),
+                    bought_amount: bought.amount,
+                    trade_type: TradeType::CryptoToCrypto,
+                    soulte: None,
+                });
+                return Ok(());
+            }
+        }
+
+        for change in &changes {
+            let change_fee = fee_of(&change.currency);
+            let own = self.snapshot(&change.currency, change_fee, time).await?;
+            self.update_balance(
+                &change.currency,
+                change.amount - change_fee.unwrap_or(dec!(0)),
+            );
+            let external_address = change
+                .counterparty
+                .as_ref()
+                .and_then(|counterparty| self.wallet_address(counterparty));
+            let external_id = self.wallet_manager.create_or_get_wallet_id(
+                &change.currency,
+                &Platform::Blockchain,
+                &external_address,
+                false,
+            );
+            let external = WalletSnapshot {
+                id: external_id,
+                // We don't know the balance of the counterparty so it only holds the amount transfered
+                pre_tx_balance: change.amount.max(dec!(0)),
+                price_eur: own.price_eur,
+                fee: None,
+            };
+            let (from, to) = if change.amount > dec!(0) {
+                (external, own)
+            } else {
+                (own, external)
+            };
+            txs.push(Transaction::Transfer {
+                tx: TransactionBase {
+                    id: format!("{id}-{}", change.currency),
+                    timestamp: time,
+                },
+                from,
+                to,
+                amount: change.amount.abs(),
+                income: None,
+            });
+        }
+        Ok(())
+    }
+
+    /* Changes of the balances of the address, the failed internal transactions moving nothing */
+    fn balance_changes(
+        &self,
+        group: &EvmTransactionGroup<'_>,
+    ) -> Result<Vec<BalanceChange>, ApiError> {
+        let native = &self.chain.native_currency;
+        let mut changes: Vec<BalanceChange> = Vec::new();
+        if let Some(transaction) = group.transaction {
+            add_change(
+                &mut changes,
+                self.address,
+                native.clone(),
+                evm_amount(&transaction.value, NATIVE_DECIMALS)?,
+                &transaction.from,
+                &transaction.to,
+            );
+        }
+        for transaction in &group.internal_transactions {
+            if transaction.is_error == "1" {
+                continue;
+            }
+            add_change(
+                &mut changes,
+                self.address,
+                native.clone(),
+                evm_amount(&transaction.value, NATIVE_DECIMALS)?,
+                &transaction.from,
+                &transaction.to,
+            );
+        }
+        for transfer in &group.token_transfers {
+            let currency = evm_token_symbol(&self.chain.name, &transfer.contract_address)
+                .map(|symbol| symbol.to_string())
+                .unwrap_or(transfer.contract_address.to_lowercase());
+            let decimals = parse_integer(&transfer.token_decimal, group.hash)?;
+            add_change(
+                &mut changes,
+                self.address,
+                currency,
+                evm_amount(&transfer.value, decimals)?,
+                &transfer.from,
+                &transfer.to,
+            );
+        }
+        changes.retain(|change| !change.amount.is_zero());
+        Ok(changes)
+    }
+
+    fn wallet_address(&self, address: &str) -> Address {
+        Some(format!("{}:{}", self.chain.name, address.to_lowercase()))
+    }
+
+    async fn snapshot(
+        &mut self,
+        currency: &str,
+        fee: Option<Decimal>,
+        time: DateTime<Utc>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let address = self.wallet_address(self.address);
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Blockchain,
+            &address,
+            false,
+        );
+        let pre_tx_balance = *self.balances.get(currency).unwrap_or(&dec!(0));
+        let price_eur = self.price(currency, time).await?;
+        Ok(WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur,
+            fee,
+        })
+    }
+
+    fn update_balance(&mut self, currency: &str, change: Decimal) {
+        *self.balances.entry(currency.to_string()).or_insert(dec!(0)) += change;
+    }
+
+    /* The tokens without symbol have no price: they are mostly airdrops of worthless tokens */
+    async fn price(&mut self, currency: &str, time: DateTime<Utc>) -> Result<Decimal, ApiError> {
+        if currency != self.chain.native_currency
+            && !chain_tokens(&self.chain.name)
+                .iter()
+                .any(|(_, symbol)| *symbol == currency)
+        {
+            return Ok(dec!(0));
+        }
+        let key = (currency.to_string(), time.format("%Y-%m-%d").to_string());
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+        let price = self.price_client.fetch_price(currency, time).await?;
+        self.prices.insert(key, price);
+        Ok(price)
+    }
+}
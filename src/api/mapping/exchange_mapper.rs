@@ -0,0 +1,354 @@
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::PriceFuture,
+    errors::ApiError,
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Address, Income, IncomeType, TradeType,
+        Transaction, TransactionBase, WalletId, WalletSnapshot,
+    },
+};
+
+/* The price source of an exchange, used by the ExchangeMapper to price the wallets of the exchange */
+pub trait ExchangePriceSource {
+    /* The platform of the wallets of the exchange */
+    fn platform(&self) -> Platform;
+
+    /* Duration in seconds during which a price is reused: the precision of the price history of the exchange */
+    fn price_period(&self) -> i64;
+
+    /* Price in euros of the currency (code of the exchange) at the given time */
+    fn fetch_price_eur<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a>;
+
+    /* Currency of the wallets, for the exchanges using their own codes */
+    fn wallet_currency(&self, currency: &str) -> String {
+        currency.to_string()
+    }
+}
+
+/* Common part of the mapping of the exchanges whose API doesn't give the balance of the account after each
+operation (unlike the Kraken ledger): the events are mapped in order of time and the balances before each
+transaction are recalculated from zero.
+The prices are fetched from the exchange, unless already known for the period (e.g. given by a trade in euro).
+*/
+pub struct ExchangeMapper<'a, S: ExchangePriceSource> {
+    pub source: &'a S,
+    pub wallet_manager: &'a mut WalletManager,
+    /* Account on which the operations are made, for the exchanges whose accounts are distinct wallets (see
+    WalletManager::create_or_get_account_wallet_id). None when the exchange has a single wallet per currency */
+    pub account: Option<&'static str>,
+    balances: HashMap<WalletId, Decimal>, // Balance of each wallet of the exchange before the current event
+    prices: HashMap<(String, i64), Decimal>, // Price of a currency for a period
+}
+
+impl<'a, S: ExchangePriceSource> ExchangeMapper<'a, S> {
+    pub fn new(source: &'a S, wallet_manager: &'a mut WalletManager) -> Self {
+        Self {
+            source,
+            wallet_manager,
+            account: None,
+            balances: HashMap::new(),
+            prices: HashMap::new(),
+        }
+    }
+
+    /* Wallet of the currency in the current account of the exchange */
+    fn wallet_id(&mut self, currency: &str) -> WalletId {
+        let wallet_currency = self.source.wallet_currency(currency);
+        let platform = self.source.platform();
+        let is_fiat = FiatCurrency::is_fiat(currency);
+        match self.account {
+            Some(account) => self.wallet_manager.create_or_get_account_wallet_id(
+                &wallet_currency,
+                &platform,
+                account,
+                is_fiat,
+            ),
+            None => self.wallet_manager.create_or_get_wallet_id(
+                &wallet_currency,
+                &platform,
+                &None,
+                is_fiat,
+            ),
+        }
+    }
+
+    pub fn update_balance(&mut self, currency: &str, change: Decimal) {
+        let id = self.wallet_id(currency);
+        *self.balances.entry(id).or_insert(dec!(0)) += change;
+    }
+
+    fn price_key(&self, currency: &str, time: DateTime<Utc>) -> (String, i64) {
+        (
+            currency.to_string(),
+            time.timestamp().div_euclid(self.source.price_period()),
+        )
+    }
+
+    /* Price known from the operation itself, e.g. a trade on a pair quoted in euro */
+    pub fn set_price(&mut self, currency: &str, time: DateTime<Utc>, price: Decimal) {
+        let key = self.price_key(currency, time);
+        self.prices.insert(key, price);
+    }
+
+    pub async fn price(
+        &mut self,
+        currency: &str,
+        time: DateTime<Utc>,
+    ) -> Result<Decimal, ApiError> {
+        if FiatCurrency::is_eur(currency) {
+            return Ok(dec!(1));
+        }
+        let key = self.price_key(currency, time);
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+        let price = self.source.fetch_price_eur(time, currency).await?;
+        self.prices.insert(key, price);
+        Ok(price)
+    }
+
+    /* Wallet of the currency in the current account of the exchange */
+    pub async fn snapshot(
+        &mut self,
+        currency: &str,
+        fee: Option<Decimal>,
+        time: DateTime<Utc>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let id = self.wallet_id(currency);
+        let pre_tx_balance = *self.balances.get(&id).unwrap_or(&dec!(0));
+        Ok(WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur: self.price(currency, time).await?,
+            fee,
+        })
+    }
+
+    /* Wallet outside of the exchange: we don't know its balance so it only holds the amount transfered */
+    pub async fn external_snapshot(
+        &mut self,
+        currency: &str,
+        address: &Address,
+        pre_tx_balance: Decimal,
+        time: DateTime<Utc>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            &self.source.wallet_currency(currency),
+            &Platform::Blockchain,
+            address,
+            FiatCurrency::is_fiat(currency),
+        );
+        Ok(WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur: self.price(currency, time).await?,
+            fee: None,
+        })
+    }
+
+    /* Trade between two currencies of the exchange: (currency, amount, fee) for each side */
+    pub async fn trade(
+        &mut self,
+        tx: TransactionBase,
+        sold: (&str, Decimal, Option<Decimal>),
+        bought: (&str, Decimal, Option<Decimal>),
+        exchange_pair: Option<(String, String)>,
+    ) -> Result<Transaction, ApiError> {
+        let (sold_currency, sold_amount, from_fee) = sold;
+        let (bought_currency, bought_amount, to_fee) = bought;
+        let from = self.snapshot(sold_currency, from_fee, tx.timestamp).await?;
+        let to = self.snapshot(bought_currency, to_fee, tx.timestamp).await?;
+
+        let trade_type = match (
+            FiatCurrency::is_fiat(sold_currency),
+            FiatCurrency::is_fiat(bought_currency),
+        ) {
+            (false, true) => TradeType::CryptoToFiat,
+            (true, false) => TradeType::FiatToCrypto {
+                local_cost_basis: from.price_eur * sold_amount,
+            },
+            _ => TradeType::CryptoToCrypto,
+        };
+
+        self.update_balance(sold_currency, -sold_amount - from_fee.unwrap_or(dec!(0)));
+        self.update_balance(bought_currency, bought_amount - to_fee.unwrap_or(dec!(0)));
+        Ok(Transaction::Trade {
+            tx,
+            from,
+            to,
+            exchange_pair,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            soulte: None,
+        })
+    }
+
+    /* Fee of a trade paid in a third currency (BNB, a discount token...), which the WalletSnapshots of the trade can't
+    hold as is: it is converted with the euro prices to a fee of the sold side, and taken from the balance of the
+    third currency */
+    pub async fn third_currency_fee(
+        &mut self,
+        trade: &mut Transaction,
+        currency: &str,
+        fee: Decimal,
+    ) -> Result<(), ApiError> {
+        let fee_eur = fee * self.price(currency, trade.get_tx_base().timestamp).await?;
+        self.update_balance(currency, -fee);
+        if let Transaction::Trade { from, .. } = trade {
+            from.fee = fee_eur.checked_div(from.price_eur);
+        }
+        Ok(())
+    }
+
+    /* Crypto received from outside of the exchange, whose origin is unknown unless the address is given.
+    The fee is taken from the amount received */
+    pub async fn transfer_in(
+        &mut self,
+        tx: TransactionBase,
+        currency: &str,
+        address: &Address,
+        amount: Decimal,
+        fee: Option<Decimal>,
+    ) -> Result<Transaction, ApiError> {
+        let from = self
+            .external_snapshot(currency, address, amount, tx.timestamp)
+            .await?;
+        let to = self.snapshot(currency, fee, tx.timestamp).await?;
+        self.update_balance(currency, amount - fee.unwrap_or(dec!(0)));
+        Ok(Transaction::Transfer {
+            tx,
+            from,
+            to,
+            amount,
+            income: None,
+        })
+    }
+
+    /* Crypto sent outside of the exchange, the fee being paid on top of the amount */
+    pub async fn transfer_out(
+        &mut self,
+        tx: TransactionBase,
+        currency: &str,
+        address: &Address,
+        amount: Decimal,
+        fee: Option<Decimal>,
+    ) -> Result<Transaction, ApiError> {
+        let from = self.snapshot(currency, fee, tx.timestamp).await?;
+        let to = self
+            .external_snapshot(currency, address, dec!(0), tx.timestamp)
+            .await?;
+        self.update_balance(currency, -amount - fee.unwrap_or(dec!(0)));
+        Ok(Transaction::Transfer {
+            tx,
+            from,
+            to,
+            amount,
+            income: None,
+        })
+    }
+
+    /* Fiat deposited from a bank account, the fee being taken from the amount */
+    pub async fn deposit(
+        &mut self,
+        tx: TransactionBase,
+        currency: &str,
+        amount: Decimal,
+        fee: Option<Decimal>,
+    ) -> Result<Transaction, ApiError> {
+        let to = self.snapshot(currency, fee, tx.timestamp).await?;
+        self.update_balance(currency, amount - fee.unwrap_or(dec!(0)));
+        Ok(Transaction::Deposit { tx, to, amount })
+    }
+
+    /* Fiat withdrawn to a bank account, the fee being paid on top of the amount */
+    pub async fn withdrawal(
+        &mut self,
+        tx: TransactionBase,
+        currency: &str,
+        amount: Decimal,
+        fee: Option<Decimal>,
+    ) -> Result<Transaction, ApiError> {
+        let from = self.snapshot(currency, fee, tx.timestamp).await?;
+        self.update_balance(currency, -amount - fee.unwrap_or(dec!(0)));
+        Ok(Transaction::Withdrawal { tx, from, amount })
+    }
+
+    /* Reward (interest, staking...) credited on the wallet of the currency */
+    pub async fn income(
+        &mut self,
+        tx: TransactionBase,
+        currency: &str,
+        amount: Decimal,
+        subtype: IncomeType,
+    ) -> Result<Transaction, ApiError> {
+        let snapshot = self.snapshot(currency, None, tx.timestamp).await?;
+        let income = Income::new(amount * snapshot.price_eur, subtype);
+        self.update_balance(currency, amount);
+        Ok(Transaction::Transfer {
+            tx,
+            from: snapshot.clone(),
+            to: snapshot,
+            amount,
+            income: Some(income),
+        })
+    }
+}
+
+// Fiat currencies that can be held on the exchanges
+pub enum FiatCurrency {
+    EUR,
+    USD,
+    GBP,
+    CHF,
+    CAD,
+    AUD,
+    JPY,
+    CNH,
+    TRY,
+    BRL,
+    UAH,
+    PLN,
+    RON,
+    CZK,
+    ZAR,
+    ARS,
+    MXN,
+}
+
+impl FiatCurrency {
+    pub fn from_code(s: &str) -> Option<FiatCurrency> {
+        match s {
+            "EUR" => Some(FiatCurrency::EUR),
+            "USD" => Some(FiatCurrency::USD),
+            "GBP" => Some(FiatCurrency::GBP),
+            "CHF" => Some(FiatCurrency::CHF),
+            "CAD" => Some(FiatCurrency::CAD),
+            "AUD" => Some(FiatCurrency::AUD),
+            "JPY" => Some(FiatCurrency::JPY),
+            "CNH" => Some(FiatCurrency::CNH),
+            "TRY" => Some(FiatCurrency::TRY),
+            "BRL" => Some(FiatCurrency::BRL),
+            "UAH" => Some(FiatCurrency::UAH),
+            "PLN" => Some(FiatCurrency::PLN),
+            "RON" => Some(FiatCurrency::RON),
+            "CZK" => Some(FiatCurrency::CZK),
+            "ZAR" => Some(FiatCurrency::ZAR),
+            "ARS" => Some(FiatCurrency::ARS),
+            "MXN" => Some(FiatCurrency::MXN),
+            _ => None,
+        }
+    }
+
+    pub fn is_fiat(s: &str) -> bool {
+        Self::from_code(s).is_some()
+    }
+
+    pub fn is_eur(s: &str) -> bool {
+        matches!(Self::from_code(s), Some(FiatCurrency::EUR))
+    }
+}
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     api::{
-        fetch_specific_trade_data, kraken_pairs, AssetPair, Deposit, EntryType, LedgerHistory, SubType, TradeInfo, Withdrawal
+        fetch_specific_trade_data, kraken_pairs, AssetPair, Deposit, EntryType, LedgerHistory, SubType, TradeInfo, Withdrawal, KRAKEN_PAIRS_PATH
     },
     errors::{ApiError, MappingError},
     structs::{
@@ -17,7 +17,6 @@ use crate::{
 };
 
 /* This function take existing currencies, wallets and Transactions and add the new elements  */
-#[tokio::main] // Async for calling API for getting asset price
 pub async fn create_kraken_txs(
     wallet_manager: &mut WalletManager,
     txs: &mut Vec<Transaction>,
@@ -131,6 +130,7 @@ pub async fn create_kraken_txs(
                     sold_amount: selling_amount,
                     bought_amount: buying.amount,
                     trade_type,
+                    soulte: None,
                 };
                 txs.push(tx);
             },
@@ -243,7 +243,7 @@ fn get_fee<'a>(
 
 pub async fn get_currency_price(time: String, currency: String) -> Result<Decimal, ApiError> {
     let sanitized_currency = sanitize_currency(currency);
-    let pairs = kraken_pairs().unwrap().0;
+    let pairs = kraken_pairs(KRAKEN_PAIRS_PATH).await.unwrap().0;
     if let Some(pair) = pairs
         .get(&(sanitized_currency.to_string(), String::from("ZEUR")))
         .cloned()
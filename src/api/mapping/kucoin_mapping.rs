@@ -0,0 +1,305 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        ExchangeMapper, ExchangePriceSource, FiatCurrency, KuCoinAccount, KuCoinClient,
+        KuCoinDeposit, KuCoinFill, KuCoinHistory, KuCoinLedgerEntry, KuCoinWithdrawal,
+        PriceFuture,
+    },
+    errors::{ApiError, MappingError},
+    structs::{wallet::Platform, wallet_manager::WalletManager, Transaction, TransactionBase},
+};
+
+// Maximum delay between the two ledger entries of an internal transfer
+const TRANSFER_MAX_DELAY_MS: i64 = 5000;
+
+/* Map the KuCoin history to transactions.
+
+The main, trade and margin accounts of KuCoin are distinct wallets of Platform::KuCoin, the name of the account being
+kept in the info of the wallet (see WalletManager::create_or_get_account_wallet_id). The deposits and withdrawals are made on the main account, the fills on the trade
+account (or the margin account for the margin fills).
+The ledgers are only used for the internal transfers between these accounts: the two entries of a transfer (out of
+an account and in another one) are paired in a non taxable Transfer.
+As for Binance, the balances before each transaction are recalculated from zero.
+*/
+pub async fn create_kucoin_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &KuCoinHistory,
+    client: &KuCoinClient,
+) -> Result<(), ApiError> {
+    let mut events: Vec<KuCoinEvent> = Vec::new();
+    events.extend(history.fills.iter().map(KuCoinEvent::Fill));
+    events.extend(
+        history
+            .deposits
+            .iter()
+            .filter(|deposit| deposit.status == "SUCCESS")
+            .map(KuCoinEvent::Deposit),
+    );
+    events.extend(
+        history
+            .withdrawals
+            .iter()
+            .filter(|withdrawal| withdrawal.status == "SUCCESS")
+            .map(KuCoinEvent::Withdrawal),
+    );
+    events.extend(
+        pair_transfers(&history.ledgers)
+            .into_iter()
+            .map(|(out, into)| KuCoinEvent::Transfer(out, into)),
+    );
+    events.sort_by_key(|event| event.created_at());
+
+    let mut mapper = KuCoinMapper::new(client, wallet_manager);
+    for event in events {
+        match event {
+            KuCoinEvent::Fill(fill) => mapper.map_fill(txs, fill).await?,
+            KuCoinEvent::Deposit(deposit) => mapper.map_deposit(txs, deposit).await?,
+            KuCoinEvent::Withdrawal(withdrawal) => mapper.map_withdrawal(txs, withdrawal).await?,
+            KuCoinEvent::Transfer(out, into) => mapper.map_transfer(txs, out, into).await?,
+        }
+    }
+    Ok(())
+}
+
+/* Pair each entry leaving an account for an internal transfer with the entry entering another account, of the same
+currency and amount, closest in time. The entries without counterpart (transfers to the futures account or to a
+sub-user for instance) are ignored */
+fn pair_transfers(ledgers: &[KuCoinLedgerEntry]) -> Vec<(&KuCoinLedgerEntry, &KuCoinLedgerEntry)> {
+    let mut transfers: Vec<&KuCoinLedgerEntry> = ledgers
+        .iter()
+        .filter(|entry| entry.biz_type == "Transfer")
+        .collect();
+    transfers.sort_by_key(|entry| entry.created_at);
+    let (outs, mut intos): (Vec<&KuCoinLedgerEntry>, Vec<&KuCoinLedgerEntry>) = transfers
+        .into_iter()
+        .partition(|entry| entry.direction == "out");
+
+    let mut pairs = Vec::new();
+    for out in outs {
+        let counterpart = intos
+            .iter()
+            .enumerate()
+            .filter(|(_, into)| {
+                into.currency == out.currency
+                    && into.amount == out.amount
+                    && into.account_type != out.account_type
+                    && (into.created_at - out.created_at).abs() <= TRANSFER_MAX_DELAY_MS
+            })
+            .min_by_key(|(_, into)| (into.created_at - out.created_at).abs())
+            .map(|(index, _)| index);
+        if let Some(index) = counterpart {
+            pairs.push((out, intos.remove(index)));
+        }
+    }
+    pairs
+}
+
+enum KuCoinEvent<'a> {
+    Fill(&'a KuCoinFill),
+    Deposit(&'a KuCoinDeposit),
+    Withdrawal(&'a KuCoinWithdrawal),
+    Transfer(&'a KuCoinLedgerEntry, &'a KuCoinLedgerEntry),
+}
+
+impl KuCoinEvent<'_> {
+    fn created_at(&self) -> i64 {
+        match self {
+            KuCoinEvent::Fill(fill) => fill.created_at,
+            KuCoinEvent::Deposit(deposit) => deposit.created_at,
+            KuCoinEvent::Withdrawal(withdrawal) => withdrawal.created_at,
+            KuCoinEvent::Transfer(out, _) => out.created_at,
+        }
+    }
+}
+
+fn to_datetime(millis: i64) -> Result<DateTime<Utc>, ApiError> {
+    DateTime::from_timestamp_millis(millis).ok_or(ApiError::MappingError(MappingError::Other(
+        format!("Invalid timestamp {millis}"),
+    )))
+}
+
+fn split_kucoin_symbol(symbol: &str) -> Result<(String, String), ApiError> {
+    symbol
+        .split_once('-')
+        .map(|(base, quote)| (base.to_string(), quote.to_string()))
+        .ok_or(ApiError::MappingError(MappingError::Other(format!(
+            "Unknown KuCoin symbol {symbol}"
+        ))))
+}
+
+type KuCoinMapper<'a> = ExchangeMapper<'a, KuCoinClient>;
+
+impl ExchangePriceSource for KuCoinClient {
+    fn platform(&self) -> Platform {
+        Platform::KuCoin
+    }
+
+    fn price_period(&self) -> i64 {
+        60 // Close price of the minute
+    }
+
+    fn fetch_price_eur<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_kucoin_price(self, time, currency))
+    }
+}
+
+impl KuCoinMapper<'_> {
+    async fn map_fill(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        fill: &KuCoinFill,
+    ) -> Result<(), ApiError> {
+        let (base, quote) = split_kucoin_symbol(&fill.symbol)?;
+        let time = to_datetime(fill.created_at)?;
+        let account = if fill.trade_type == "MARGIN_TRADE" {
+            KuCoinAccount::Margin
+        } else {
+            KuCoinAccount::Trade
+        };
+        let ((sold, sold_amount), (bought, bought_amount)) = if fill.side == "buy" {
+            ((&quote, fill.funds), (&base, fill.size))
+        } else {
+            ((&base, fill.size), (&quote, fill.funds))
+        };
+        if FiatCurrency::is_eur(&quote) {
+            self.set_price(&base, time, fill.price);
+        }
+
+        let mut from_fee = None;
+        let mut to_fee = None;
+        let mut third_currency_fee = None;
+        if !fill.fee.is_zero() {
+            if fill.fee_currency == *sold {
+                from_fee = Some(fill.fee);
+            } else if fill.fee_currency == *bought {
+                to_fee = Some(fill.fee);
+            } else {
+                // KCS with the discount
+                third_currency_fee = Some(fill.fee);
+            }
+        }
+
+        self.account = Some(account.name());
+        let mut tx = self
+            .trade(
+                TransactionBase {
+                    id: format!("kucoin-fill-{}", fill.trade_id),
+                    timestamp: time,
+                },
+                (sold, sold_amount, from_fee),
+                (bought, bought_amount, to_fee),
+                Some((base.clone(), quote.clone())),
+            )
+            .await?;
+        if let Some(fee) = third_currency_fee {
+            self.third_currency_fee(&mut tx, &fill.fee_currency, fee)
+                .await?;
+        }
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_deposit(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        deposit: &KuCoinDeposit,
+    ) -> Result<(), ApiError> {
+        let currency = &deposit.currency;
+        let tx = TransactionBase {
+            id: format!("kucoin-deposit-{currency}-{}", deposit.created_at),
+            timestamp: to_datetime(deposit.created_at)?,
+        };
+        let fee = Some(deposit.fee).filter(|fee| !fee.is_zero());
+        self.account = Some(KuCoinAccount::Main.name());
+        let tx = if FiatCurrency::is_fiat(currency) {
+            self.deposit(tx, currency, deposit.amount, fee).await?
+        } else {
+            // The deposit address is the KuCoin one, the origin of the funds is unknown
+            self.transfer_in(tx, currency, &None, deposit.amount, fee)
+                .await?
+        };
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_withdrawal(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        withdrawal: &KuCoinWithdrawal,
+    ) -> Result<(), ApiError> {
+        let currency = &withdrawal.currency;
+        let tx = TransactionBase {
+            id: format!("kucoin-withdrawal-{}", withdrawal.id),
+            timestamp: to_datetime(withdrawal.created_at)?,
+        };
+        let fee = Some(withdrawal.fee).filter(|fee| !fee.is_zero());
+        self.account = Some(KuCoinAccount::Main.name());
+        let tx = if FiatCurrency::is_fiat(currency) {
+            self.withdrawal(tx, currency, withdrawal.amount, fee)
+                .await?
+        } else {
+            self.transfer_out(tx, currency, &withdrawal.address, withdrawal.amount, fee)
+                .await?
+        };
+        txs.push(tx);
+        Ok(())
+    }
+
+    async fn map_transfer(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        out: &KuCoinLedgerEntry,
+        into: &KuCoinLedgerEntry,
+    ) -> Result<(), ApiError> {
+        let time = to_datetime(out.created_at)?;
+        self.account = Some(out.account_type.name());
+        let from = self.snapshot(&out.currency, None, time).await?;
+        self.update_balance(&out.currency, -out.amount);
+        self.account = Some(into.account_type.name());
+        let to = self.snapshot(&into.currency, None, time).await?;
+        self.update_balance(&into.currency, into.amount);
+        txs.push(Transaction::Transfer {
+            tx: TransactionBase {
+                id: format!("kucoin-transfer-{}", out.id),
+                timestamp: time,
+            },
+            from,
+            to,
+            amount: out.amount,
+            income: None,
+        });
+        Ok(())
+    }
+}
+
+/* Price in euro of a currency, using the EUR pair if it exists, otherwise through USDT or BTC */
+pub async fn get_kucoin_price(
+    client: &KuCoinClient,
+    time: DateTime<Utc>,
+    currency: &str,
+) -> Result<Decimal, ApiError> {
+    if FiatCurrency::is_eur(currency) {
+        return Ok(dec!(1));
+    }
+    if let Ok(price) = client.fetch_price(&format!("{currency}-EUR"), time).await {
+        return Ok(price);
+    }
+    if let Ok(price_usdt) = client.fetch_price(&format!("{currency}-USDT"), time).await {
+        let usdt_eur = client.fetch_price("USDT-EUR", time).await?;
+        return Ok(price_usdt * usdt_eur);
+    }
+    if let Ok(price_btc) = client.fetch_price(&format!("{currency}-BTC"), time).await {
+        let btc_eur = client.fetch_price("BTC-EUR", time).await?;
+        return Ok(price_btc * btc_eur);
+    }
+    Err(ApiError::CouldNotFindPrice {
+        pairs: ["EUR", "USDT", "BTC"]
+            .iter()
+            .map(|quote| (currency.to_string(), quote.to_string()))
+            .collect(),
+    })
+}
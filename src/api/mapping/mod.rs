@@ -2,5 +2,29 @@
 It
 */
 
+pub mod exchange_mapper;
+pub use exchange_mapper::*;
 pub mod kraken_mapping;
 pub use kraken_mapping::*;
+pub mod binance_mapping;
+pub use binance_mapping::*;
+pub mod coinbase_mapping;
+pub use coinbase_mapping::*;
+pub mod bitfinex_mapping;
+pub use bitfinex_mapping::*;
+pub mod kucoin_mapping;
+pub use kucoin_mapping::*;
+pub mod bitpanda_mapping;
+pub use bitpanda_mapping::*;
+pub mod crypto_com_mapping;
+pub use crypto_com_mapping::*;
+pub mod solana_mapping;
+pub use solana_mapping::*;
+pub mod cardano_mapping;
+pub use cardano_mapping::*;
+pub mod algorand_mapping;
+pub use algorand_mapping::*;
+pub mod bitcoin_mapping;
+pub use bitcoin_mapping::*;
+pub mod evm_mapping;
+pub use evm_mapping::*;
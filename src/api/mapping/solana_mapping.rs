@@ -0,0 +1,338 @@
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{CoinGeckoClient, SolanaHistory, SolanaTransaction},
+    errors::{ApiError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, TradeType, Transaction, TransactionBase,
+        WalletSnapshot,
+    },
+};
+
+const LAMPORTS_DECIMALS: u32 = 9;
+
+/* Map the transactions of a Solana address to transactions on its Platform::Blockchain wallets.
+
+The balances of the address are given by the node before and after each transaction, in SOL (preBalances and
+postBalances) and for each token (preTokenBalances and postTokenBalances, the token accounts owned by the address),
+so they don't need to be recalculated.
+- a transaction selling one currency and buying another is a swap (Trade)
+- every other change of balance is a Transfer from or to the counterparty, when it can be found in the transaction
+- the fee is paid in SOL by the first signer, it is only recorded when it is the address
+*/
+pub async fn create_solana_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    history: &SolanaHistory,
+    price_client: &CoinGeckoClient,
+) -> Result<(), ApiError> {
+    let mut transactions: Vec<&SolanaTransaction> = history.transactions.iter().collect();
+    transactions.sort_by_key(|transaction| (transaction.block_time, transaction.slot));
+
+    let mut mapper = SolanaMapper {
+        address: &history.address,
+        price_client,
+        wallet_manager,
+        prices: HashMap::new(),
+    };
+    for transaction in transactions {
+        mapper.map_transaction(txs, transaction).await?;
+    }
+    Ok(())
+}
+
+// Tokens with a price, by mint address. The others keep their mint address as currency
+const SOLANA_TOKENS: [(&str, &str); 6] = [
+    ("So11111111111111111111111111111111111111112", "SOL"), // Wrapped SOL
+    ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USDC"),
+    ("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY9cAJZ4x8WbBFb6", "USDT"),
+    ("mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So", "MSOL"),
+    ("JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN", "JUP"),
+    ("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263", "BONK"),
+];
+
+pub fn solana_token_symbol(mint: &str) -> Option<&'static str> {
+    SOLANA_TOKENS
+        .iter()
+        .find(|(token_mint, _)| *token_mint == mint)
+        .map(|(_, symbol)| *symbol)
+}
+
+/* Change of the balance of a currency of the address in a transaction, without the fee */
+struct BalanceChange {
+    currency: String,
+    pre_tx_balance: Decimal,
+    amount: Decimal,
+    counterparty: Option<String>,
+}
+
+/* Changes of the balances of the address in a transaction, the wrapped SOL being merged with SOL */
+fn balance_changes(
+    transaction: &SolanaTransaction,
+    address: &str,
+    fee: Decimal,
+) -> Vec<BalanceChange> {
+    let meta = &transaction.meta;
+    let keys = &transaction.transaction.message.account_keys;
+    let lamports = |index: usize, balances: &Vec<u64>| {
+        Decimal::new(*balances.get(index).unwrap_or(&0) as i64, LAMPORTS_DECIMALS)
+    };
+    let mut changes: Vec<BalanceChange> = Vec::new();
+
+    if let Some(index) = keys.iter().position(|key| key.pubkey == address) {
+        let pre_tx_balance = lamports(index, &meta.pre_balances);
+        let amount = lamports(index, &meta.post_balances) - pre_tx_balance + fee;
+        // The counterparty received what the address sent, or sent what it received
+        let counterparty = (0..keys.len())
+            .filter(|other| *other != index)
+            .find(|other| {
+                let mut other_change =
+                    lamports(*other, &meta.post_balances) - lamports(*other, &meta.pre_balances);
+                if *other == 0 {
+                    other_change += Decimal::new(meta.fee as i64, LAMPORTS_DECIMALS);
+                }
+                !amount.is_zero() && other_change == -amount
+            })
+            .map(|other| keys[other].pubkey.clone());
+        changes.push(BalanceChange {
+            currency: "SOL".to_string(),
+            pre_tx_balance,
+            amount,
+            counterparty,
+        });
+    }
+
+    // Balances of the tokens by owner and mint, an owner can have several accounts for the same token
+    let mut token_balances: HashMap<(String, String), (Decimal, Decimal)> = HashMap::new();
+    for balance in &meta.pre_token_balances {
+        if let Some(owner) = &balance.owner {
+            token_balances
+                .entry((owner.clone(), balance.mint.clone()))
+                .or_insert((dec!(0), dec!(0)))
+                .0 += balance.ui_token_amount.ui_amount_string;
+        }
+    }
+    for balance in &meta.post_token_balances {
+        if let Some(owner) = &balance.owner {
+            token_balances
+                .entry((owner.clone(), balance.mint.clone()))
+                .or_insert((dec!(0), dec!(0)))
+                .1 += balance.ui_token_amount.ui_amount_string;
+        }
+    }
+    let mut mints: Vec<&String> = token_balances
+        .keys()
+        .filter(|(owner, _)| owner == address)
+        .map(|(_, mint)| mint)
+        .collect();
+    mints.sort();
+    for mint in mints {
+        let (pre, post) = token_balances[&(address.to_string(), mint.clone())];
+        let amount = post - pre;
+        let counterparty = token_balances
+            .iter()
+            .find(|((owner, other_mint), (other_pre, other_post))| {
+                owner != address && other_mint == mint && *other_post - *other_pre == -amount
+            })
+            .map(|((owner, _), _)| owner.clone());
+        let currency = solana_token_symbol(mint).unwrap_or(mint).to_string();
+        match changes
+            .iter_mut()
+            .find(|change| change.currency == currency)
+        {
+            Some(change) => {
+                change.pre_tx_balance += pre;
+                change.amount += amount;
+            }
+            None => changes.push(BalanceChange {
+                currency,
+                pre_tx_balance: pre,
+                amount,
+                counterparty,
+            }),
+        }
+    }
+
+    changes.retain(|change| !change.amount.is_zero());
+    changes
+}
+
+struct SolanaMapper<'a> {
+    address: &'a str,
+    price_client: &'a CoinGeckoClient,
+    wallet_manager: &'a mut WalletManager,
+    prices: HashMap<(String, String), Decimal>, // Price of a currency for a given day
+}
+
+impl SolanaMapper<'_> {
+    async fn map_transaction(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        transaction: &SolanaTransaction,
+    ) -> Result<(), ApiError> {
+        let signature = transaction.signature();
+        let time = transaction
+            .block_time
+            .and_then(|block_time| DateTime::from_timestamp(block_time, 0))
+            .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                "Missing block time of the Solana transaction {signature}"
+            ))))?;
+        let keys = &transaction.transaction.message.account_keys;
+        let fee = match keys.first() {
+            Some(payer) if payer.pubkey == self.address => {
+                Decimal::new(transaction.meta.fee as i64, LAMPORTS_DECIMALS)
+            }
+            _ => dec!(0),
+        };
+        let changes = if transaction.is_failed() {
+            Vec::new()
+        } else {
+            balance_changes(transaction, self.address, fee)
+        };
+        let sol_pre_tx_balance = keys
+            .iter()
+            .position(|key| key.pubkey == self.address)
+            .and_then(|index| transaction.meta.pre_balances.get(index))
+            .map(|lamports| Decimal::new(*lamports as i64, LAMPORTS_DECIMALS))
+            .unwrap_or(dec!(0));
+        let fee = Some(fee).filter(|fee| !fee.is_zero());
+
+        // The fee is taken from the SOL wallet, in its own transfer when SOL is not moved by the transaction
+        if fee.is_some() && !changes.iter().any(|change| change.currency == "SOL") {
+            let snapshot = self.snapshot("SOL", sol_pre_tx_balance, fee, time).await?;
+            txs.push(Transaction::Transfer {
+                tx: TransactionBase {
+                    id: format!("solana-{signature}-fee"),
+                    timestamp: time,
+                },
+                from: snapshot.clone(),
+                to: WalletSnapshot {
+                    fee: None,
+                    ..snapshot
+                },
+                amount: dec!(0),
+                income: None,
+            });
+        }
+        let fee_of = |currency: &str| fee.filter(|_| currency == "SOL");
+
+        if let [first, second] = changes.as_slice() {
+            if first.amount.is_sign_negative() != second.amount.is_sign_negative() {
+                let (sold, bought) = if first.amount < dec!(0) {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                let from = self
+                    .snapshot(
+                        &sold.currency,
+                        sold.pre_tx_balance,
+                        fee_of(&sold.currency),
+                        time,
+                    )
+                    .await?;
+                let to = self
+                    .snapshot(
+                        &bought.currency,
+                        bought.pre_tx_balance,
+                        fee_of(&bought.currency),
+                        time,
+                    )
+                    .await?;
+                txs.push(Transaction::Trade {
+                    tx: TransactionBase {
+                        id: format!("solana-{signature}"),
+                        timestamp: time,
+                    },
+                    from,
+                    to,
+                    exchange_pair: None,
+                    sold_amount: sold.amount.abs(),
+                    bought_amount: bought.amount,
+                    trade_type: TradeType::CryptoToCrypto,
+                    soulte: None,
+                });
+                return Ok(());
+            }
+        }
+
+        for change in &changes {
+            let own = self
+                .snapshot(
+                    &change.currency,
+                    change.pre_tx_balance,
+                    fee_of(&change.currency),
+                    time,
+                )
+                .await?;
+            let external_id = self.wallet_manager.create_or_get_wallet_id(
+                &change.currency,
+                &Platform::Blockchain,
+                &change.counterparty,
+                false,
+            );
+            let external = WalletSnapshot {
+                id: external_id,
+                // We don't know the balance of the counterparty so it only holds the amount transfered
+                pre_tx_balance: change.amount.max(dec!(0)),
+                price_eur: own.price_eur,
+                fee: None,
+            };
+            let (from, to) = if change.amount > dec!(0) {
+                (external, own)
+            } else {
+                (own, external)
+            };
+            txs.push(Transaction::Transfer {
+                tx: TransactionBase {
+                    id: format!("solana-{signature}-{}", change.currency),
+                    timestamp: time,
+                },
+                from,
+                to,
+                amount: change.amount.abs(),
+                income: None,
+            });
+        }
+        Ok(())
+    }
+
+    async fn snapshot(
+        &mut self,
+        currency: &str,
+        pre_tx_balance: Decimal,
+        fee: Option<Decimal>,
+        time: DateTime<Utc>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Blockchain,
+            &Some(self.address.to_string()),
+            false,
+        );
+        let price_eur = self.price(currency, time).await?;
+        Ok(WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur,
+            fee,
+        })
+    }
+
+    /* The tokens without symbol have no price: they are mostly airdrops of worthless tokens */
+    async fn price(&mut self, currency: &str, time: DateTime<Utc>) -> Result<Decimal, ApiError> {
+        if !SOLANA_TOKENS.iter().any(|(_, symbol)| *symbol == currency) {
+            return Ok(dec!(0));
+        }
+        let key = (currency.to_string(), time.format("%Y-%m-%d").to_string());
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+        let price = self.price_client.fetch_price(currency, time).await?;
+        self.prices.insert(key, price);
+        Ok(price)
+    }
+}
@@ -1,6 +1,9 @@
 pub mod exchanges;
 pub use exchanges::*;
 
+pub mod explorers;
+pub use explorers::*;
+
 pub mod mapping;
 pub use mapping::*;
 
@@ -0,0 +1,92 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{
+        create_algorand_txs, fetch_history_algorand, AlgorandHistory, AlgorandIndexerClient,
+        CoinGeckoClient, Connector, ConnectorFuture, PriceFuture,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, save_mapped_data},
+};
+
+const ALGORAND_MAPPED_PATH: &str = ".data/algorand/algorand_mapped_data";
+
+/* Fetch and save the history of the Algorand account (env ALGORAND_ADDRESS), see KrakenConnector.
+The whole history is fetched at each run and mapped again when it changed.
+As for all the wallets outside of the exchanges, the prices come from CoinGecko */
+pub struct AlgorandConnector {
+    pub address: Option<String>,
+    pub price_client: CoinGeckoClient,
+}
+
+impl AlgorandConnector {
+    pub fn from_env() -> Self {
+        AlgorandConnector {
+            address: env::var("ALGORAND_ADDRESS").ok(),
+            price_client: CoinGeckoClient::from_env(),
+        }
+    }
+}
+
+impl Connector for AlgorandConnector {
+    type History = AlgorandHistory;
+
+    fn platform(&self) -> Platform {
+        Platform::Blockchain
+    }
+
+    fn is_configured(&self) -> bool {
+        self.address.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, AlgorandHistory> {
+        Box::pin(async move {
+            let address = self.address.as_ref().ok_or(IoError::new(
+                "ALGORAND_ADDRESS not set in .env file".to_string(),
+            ))?;
+            let client = AlgorandIndexerClient::from_env();
+            get_algorand_history(&client, address).await
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: AlgorandHistory,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            if let Some(algorand_txs) = read_mapped_data(ALGORAND_MAPPED_PATH, &history)? {
+                return Ok(algorand_txs);
+            }
+
+            let mut algorand_txs: Vec<Transaction> = Vec::new();
+            create_algorand_txs(
+                wallet_manager,
+                &mut algorand_txs,
+                &history,
+                &self.price_client,
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+
+            save_mapped_data(ALGORAND_MAPPED_PATH, &history, &algorand_txs)?;
+            Ok(algorand_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(self.price_client.fetch_price(currency, time))
+    }
+}
+
+pub async fn get_algorand_history(
+    client: &AlgorandIndexerClient,
+    address: &str,
+) -> Result<AlgorandHistory, IoError> {
+    fetch_history_algorand(client, address)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))
+}
@@ -0,0 +1,158 @@
+use std::env;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{
+    api::{
+        create_binance_txs, fetch_history_binance, get_binance_price, BinanceClient,
+        BinanceHistory, Connector, ConnectorFuture, PriceFuture,
+    },
+    errors::IoError,
+    parsing::{create_binance_csv_txs, read_binance_csv, BinanceCsvRow},
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, read_saved_data, save_data, save_mapped_data},
+};
+
+// Opening of Binance, used when BINANCE_START_DATE (YYYY-MM-DD) is not set
+const BINANCE_START_DATE: &str = "2017-07-14";
+const BINANCE_HISTORY_PATH: &str = ".data/binance/binance_history";
+const BINANCE_MAPPED_PATH: &str = ".data/binance/binance_mapped_data";
+const BINANCE_CSV_MAPPED_PATH: &str = ".data/binance/binance_csv_mapped_data";
+
+/* The history of Binance, fetched from the API or read from the Transaction History export. The history fetched
+from the API is the one since the previous fetch, with the end of this fetch */
+pub enum BinanceData {
+    Api(Box<BinanceHistory>, DateTime<Utc>),
+    Csv(Vec<BinanceCsvRow>),
+}
+
+/* Fetch and save the binance data, see KrakenConnector.
+Each fetch only asks for what happened since the previous one, which is added to the saved history. As the balances
+are recalculated from zero, the whole history is mapped again when something new was fetched.
+When BINANCE_CSV is set, the transactions are read from the Transaction History export instead of the API, and
+mapped again when the export changes.
+The prices come from the public Binance API */
+pub struct BinanceConnector {
+    pub price_client: BinanceClient,
+    pub csv_path: Option<String>,
+}
+
+impl BinanceConnector {
+    pub fn from_env() -> Self {
+        BinanceConnector {
+            price_client: BinanceClient::public_from_env(),
+            csv_path: env::var("BINANCE_CSV").ok(),
+        }
+    }
+}
+
+impl Connector for BinanceConnector {
+    type History = BinanceData;
+
+    fn platform(&self) -> Platform {
+        Platform::Binance
+    }
+
+    fn is_configured(&self) -> bool {
+        env::var("BINANCE_KEY").is_ok() || self.csv_path.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, BinanceData> {
+        Box::pin(async move {
+            match &self.csv_path {
+                Some(csv_path) => read_binance_csv(csv_path)
+                    .map(BinanceData::Csv)
+                    .map_err(|e| IoError::new(e.to_string())),
+                None => {
+                    let client = BinanceClient::from_env();
+                    let (history, end) = get_binance_history(&client).await?;
+                    Ok(BinanceData::Api(Box::new(history), end))
+                }
+            }
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: BinanceData,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut binance_txs: Vec<Transaction> = Vec::new();
+            match history {
+                BinanceData::Api(new_history, end) => {
+                    let mut history = read_saved_binance_history()?
+                        .map(|(history, _)| history)
+                        .unwrap_or_default();
+                    history.merge(*new_history);
+                    if let Some(binance_txs) = read_mapped_data(BINANCE_MAPPED_PATH, &history)? {
+                        return Ok(binance_txs);
+                    }
+
+                    create_binance_txs(
+                        wallet_manager,
+                        &mut binance_txs,
+                        &history,
+                        &self.price_client,
+                    )
+                    .await
+                    .map_err(|e| IoError::new(e.to_string()))?;
+
+                    // The history is saved once mapped, so a failed mapping is done again at the next run
+                    save_mapped_data(BINANCE_MAPPED_PATH, &history, &binance_txs)?;
+                    save_data(BINANCE_HISTORY_PATH, &(history, end))?;
+                }
+                BinanceData::Csv(rows) => {
+                    if let Some(binance_txs) = read_mapped_data(BINANCE_CSV_MAPPED_PATH, &rows)? {
+                        return Ok(binance_txs);
+                    }
+                    create_binance_csv_txs(
+                        wallet_manager,
+                        &mut binance_txs,
+                        &rows,
+                        &self.price_client,
+                    )
+                    .await
+                    .map_err(|e| IoError::new(e.to_string()))?;
+                    save_mapped_data(BINANCE_CSV_MAPPED_PATH, &rows, &binance_txs)?;
+                }
+            }
+            Ok(binance_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_binance_price(&self.price_client, time, currency))
+    }
+}
+
+/* The history saved by the previous run, with the end of its fetch */
+fn read_saved_binance_history() -> Result<Option<(BinanceHistory, DateTime<Utc>)>, IoError> {
+    read_saved_data(BINANCE_HISTORY_PATH)
+}
+
+/* Fetch the history since the previous fetch, or since BINANCE_START_DATE for the first one */
+pub async fn get_binance_history(
+    client: &BinanceClient,
+) -> Result<(BinanceHistory, DateTime<Utc>), IoError> {
+    let previous = read_saved_binance_history()?;
+    let start = match &previous {
+        // The operations still pending at the previous fetch are fetched again, to be updated
+        Some((_, previous_end)) => *previous_end - Duration::days(1),
+        None => {
+            let start_date =
+                env::var("BINANCE_START_DATE").unwrap_or(BINANCE_START_DATE.to_string());
+            NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+                .map_err(|e| IoError::new(e.to_string()))?
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        }
+    };
+    let previous_history = previous.map(|(history, _)| history).unwrap_or_default();
+    let end = Utc::now();
+    let history = fetch_history_binance(client, start, end, &previous_history)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))?;
+    Ok((history, end))
+}
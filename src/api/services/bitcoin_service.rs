@@ -0,0 +1,99 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{
+        create_bitcoin_txs, fetch_history_bitcoin, BitcoinHistory, CoinGeckoClient, Connector,
+        ConnectorFuture, EsploraClient, PriceFuture, BITCOIN_GAP_LIMIT,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, save_mapped_data},
+};
+
+const BITCOIN_MAPPED_PATH: &str = ".data/bitcoin/bitcoin_mapped_data";
+
+/* Fetch and save the history of the Bitcoin wallet (env BITCOIN_XPUB, an xpub, ypub or zpub), scanning its addresses
+until BITCOIN_GAP_LIMIT unused ones in a row (20 by default), see KrakenConnector.
+The whole history is fetched at each run and mapped again when it changed.
+As for all the wallets outside of the exchanges, the prices come from CoinGecko */
+pub struct BitcoinConnector {
+    pub xpub: Option<String>,
+    pub gap_limit: u32,
+    pub price_client: CoinGeckoClient,
+}
+
+impl BitcoinConnector {
+    pub fn from_env() -> Self {
+        BitcoinConnector {
+            xpub: env::var("BITCOIN_XPUB").ok(),
+            gap_limit: env::var("BITCOIN_GAP_LIMIT")
+                .ok()
+                .and_then(|gap_limit| gap_limit.parse().ok())
+                .unwrap_or(BITCOIN_GAP_LIMIT),
+            price_client: CoinGeckoClient::from_env(),
+        }
+    }
+}
+
+impl Connector for BitcoinConnector {
+    type History = BitcoinHistory;
+
+    fn platform(&self) -> Platform {
+        Platform::Blockchain
+    }
+
+    fn is_configured(&self) -> bool {
+        self.xpub.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, BitcoinHistory> {
+        Box::pin(async move {
+            let xpub = self.xpub.as_ref().ok_or(IoError::new(
+                "BITCOIN_XPUB not set in .env file".to_string(),
+            ))?;
+            let client = EsploraClient::from_env();
+            get_bitcoin_history(&client, xpub, self.gap_limit).await
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: BitcoinHistory,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            if let Some(bitcoin_txs) = read_mapped_data(BITCOIN_MAPPED_PATH, &history)? {
+                return Ok(bitcoin_txs);
+            }
+
+            let mut bitcoin_txs: Vec<Transaction> = Vec::new();
+            create_bitcoin_txs(
+                wallet_manager,
+                &mut bitcoin_txs,
+                &history,
+                &self.price_client,
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+
+            save_mapped_data(BITCOIN_MAPPED_PATH, &history, &bitcoin_txs)?;
+            Ok(bitcoin_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(self.price_client.fetch_price(currency, time))
+    }
+}
+
+pub async fn get_bitcoin_history(
+    client: &EsploraClient,
+    xpub: &str,
+    gap_limit: u32,
+) -> Result<BitcoinHistory, IoError> {
+    fetch_history_bitcoin(client, xpub, gap_limit)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))
+}
@@ -0,0 +1,163 @@
+use std::env;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{
+    api::{
+        create_bitfinex_txs, fetch_history_bitfinex, get_bitfinex_price, BitfinexClient,
+        BitfinexHistory, Connector, ConnectorFuture, PriceFuture,
+    },
+    errors::IoError,
+    parsing::{read_bitfinex_ledgers_csv, read_bitfinex_movements_csv, read_bitfinex_trades_csv},
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, read_saved_data, save_data, save_mapped_data},
+};
+
+// Default start of the history, used when BITFINEX_START_DATE (YYYY-MM-DD) is not set
+const BITFINEX_START_DATE: &str = "2013-01-01";
+
+const BITFINEX_HISTORY_PATH: &str = ".data/bitfinex/bitfinex_history";
+const BITFINEX_MAPPED_PATH: &str = ".data/bitfinex/bitfinex_mapped_data";
+
+/* The history fetched from the API since the previous fetch, with the end of this fetch, and the csv reports */
+pub struct BitfinexData {
+    pub api: Option<(BitfinexHistory, DateTime<Utc>)>,
+    pub reports: BitfinexHistory,
+}
+
+/* Fetch and save the bitfinex data, see KrakenConnector.
+The history is fetched when BITFINEX_KEY_ID is set, each fetch only asking for what happened since the previous one,
+which is added to the saved history. It is completed with the csv reports given by BITFINEX_TRADES_CSV,
+BITFINEX_MOVEMENTS_CSV and BITFINEX_LEDGERS_CSV: an operation both fetched and in a report is only mapped once.
+The whole history is mapped again when something new was fetched or when the reports changed.
+The prices come from the public Bitfinex API */
+pub struct BitfinexConnector {
+    pub price_client: BitfinexClient,
+}
+
+impl BitfinexConnector {
+    pub fn from_env() -> Self {
+        BitfinexConnector {
+            price_client: BitfinexClient::public_from_env(),
+        }
+    }
+}
+
+impl Connector for BitfinexConnector {
+    type History = BitfinexData;
+
+    fn platform(&self) -> Platform {
+        Platform::Bitfinex
+    }
+
+    fn is_configured(&self) -> bool {
+        env::var("BITFINEX_KEY_ID").is_ok()
+            || env::var("BITFINEX_TRADES_CSV").is_ok()
+            || env::var("BITFINEX_MOVEMENTS_CSV").is_ok()
+            || env::var("BITFINEX_LEDGERS_CSV").is_ok()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, BitfinexData> {
+        Box::pin(async move {
+            let api = if env::var("BITFINEX_KEY_ID").is_ok() {
+                let client = BitfinexClient::from_env();
+                Some(get_bitfinex_history(&client).await?)
+            } else {
+                None
+            };
+            Ok(BitfinexData {
+                api,
+                reports: read_bitfinex_reports()?,
+            })
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        data: BitfinexData,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut api_history = read_saved_bitfinex_history()?
+                .map(|(history, _)| history)
+                .unwrap_or_default();
+            let end = data.api.map(|(new_history, end)| {
+                api_history.update(new_history);
+                end
+            });
+            let mut history = api_history.clone();
+            history.merge(data.reports);
+            if let Some(bitfinex_txs) = read_mapped_data(BITFINEX_MAPPED_PATH, &history)? {
+                return Ok(bitfinex_txs);
+            }
+
+            let mut bitfinex_txs: Vec<Transaction> = Vec::new();
+            create_bitfinex_txs(
+                wallet_manager,
+                &mut bitfinex_txs,
+                &history,
+                &self.price_client,
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+
+            // The history is saved once mapped, so a failed mapping is done again at the next run
+            save_mapped_data(BITFINEX_MAPPED_PATH, &history, &bitfinex_txs)?;
+            if let Some(end) = end {
+                save_data(BITFINEX_HISTORY_PATH, &(api_history, end))?;
+            }
+            Ok(bitfinex_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_bitfinex_price(&self.price_client, time, currency))
+    }
+}
+
+/* Read the csv reports that are set, they are read again at each run so they are not saved */
+pub fn read_bitfinex_reports() -> Result<BitfinexHistory, IoError> {
+    let mut history = BitfinexHistory::default();
+    if let Ok(file_path) = env::var("BITFINEX_TRADES_CSV") {
+        history.trades =
+            read_bitfinex_trades_csv(&file_path).map_err(|e| IoError::new(e.to_string()))?;
+    }
+    if let Ok(file_path) = env::var("BITFINEX_MOVEMENTS_CSV") {
+        history.movements =
+            read_bitfinex_movements_csv(&file_path).map_err(|e| IoError::new(e.to_string()))?;
+    }
+    if let Ok(file_path) = env::var("BITFINEX_LEDGERS_CSV") {
+        history.ledgers =
+            read_bitfinex_ledgers_csv(&file_path).map_err(|e| IoError::new(e.to_string()))?;
+    }
+    Ok(history)
+}
+
+/* The history fetched by the previous runs, with the end of the last fetch */
+fn read_saved_bitfinex_history() -> Result<Option<(BitfinexHistory, DateTime<Utc>)>, IoError> {
+    read_saved_data(BITFINEX_HISTORY_PATH)
+}
+
+/* Fetch the history since the previous fetch, or since BITFINEX_START_DATE for the first one */
+pub async fn get_bitfinex_history(
+    client: &BitfinexClient,
+) -> Result<(BitfinexHistory, DateTime<Utc>), IoError> {
+    let start = match read_saved_bitfinex_history()? {
+        // The movements still pending at the previous fetch are fetched again, to be updated
+        Some((_, previous_end)) => previous_end - Duration::days(1),
+        None => {
+            let start_date =
+                env::var("BITFINEX_START_DATE").unwrap_or(BITFINEX_START_DATE.to_string());
+            NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+                .map_err(|e| IoError::new(e.to_string()))?
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        }
+    };
+    let end = Utc::now();
+    let history = fetch_history_bitfinex(client, start, end)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))?;
+    Ok((history, end))
+}
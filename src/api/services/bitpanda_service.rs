@@ -0,0 +1,84 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{
+        create_bitpanda_txs, fetch_history_bitpanda, get_spot_price, BitpandaClient,
+        BitpandaHistory, Connector, ConnectorFuture, PriceFuture,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, save_mapped_data},
+};
+
+const BITPANDA_MAPPED_PATH: &str = ".data/bitpanda/bitpanda_mapped_data";
+
+/* Fetch and save the bitpanda data, see KrakenConnector.
+The API can't be asked for what happened since a date, so the whole history is fetched at each run and mapped again
+when it changed. Bitpanda has no historical prices, the prices are the Coinbase spot prices */
+pub struct BitpandaConnector {
+    pub client: Option<BitpandaClient>,
+}
+
+impl BitpandaConnector {
+    pub fn from_env() -> Self {
+        BitpandaConnector {
+            client: env::var("BITPANDA_KEY")
+                .is_ok()
+                .then(BitpandaClient::from_env),
+        }
+    }
+
+    fn client(&self) -> Result<&BitpandaClient, IoError> {
+        self.client.as_ref().ok_or(IoError::new(
+            "BITPANDA_KEY not set in .env file".to_string(),
+        ))
+    }
+}
+
+impl Connector for BitpandaConnector {
+    type History = BitpandaHistory;
+
+    fn platform(&self) -> Platform {
+        Platform::Bitpanda
+    }
+
+    fn is_configured(&self) -> bool {
+        self.client.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, BitpandaHistory> {
+        Box::pin(async move { get_bitpanda_history(self.client()?).await })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: BitpandaHistory,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            if let Some(bitpanda_txs) = read_mapped_data(BITPANDA_MAPPED_PATH, &history)? {
+                return Ok(bitpanda_txs);
+            }
+
+            let mut bitpanda_txs: Vec<Transaction> = Vec::new();
+            create_bitpanda_txs(wallet_manager, &mut bitpanda_txs, &history, self.client()?)
+                .await
+                .map_err(|e| IoError::new(e.to_string()))?;
+
+            save_mapped_data(BITPANDA_MAPPED_PATH, &history, &bitpanda_txs)?;
+            Ok(bitpanda_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_spot_price(time, currency))
+    }
+}
+
+pub async fn get_bitpanda_history(client: &BitpandaClient) -> Result<BitpandaHistory, IoError> {
+    fetch_history_bitpanda(client)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))
+}
@@ -0,0 +1,92 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{
+        create_cardano_txs, fetch_history_cardano, BlockfrostClient, CardanoHistory,
+        CoinGeckoClient, Connector, ConnectorFuture, PriceFuture,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, save_mapped_data},
+};
+
+const CARDANO_MAPPED_PATH: &str = ".data/cardano/cardano_mapped_data";
+
+/* Fetch and save the history of the Cardano stake key (env CARDANO_STAKE_ADDRESS), see KrakenConnector.
+The whole history is fetched at each run and mapped again when it changed.
+As for all the wallets outside of the exchanges, the prices come from CoinGecko */
+pub struct CardanoConnector {
+    pub stake_address: Option<String>,
+    pub price_client: CoinGeckoClient,
+}
+
+impl CardanoConnector {
+    pub fn from_env() -> Self {
+        CardanoConnector {
+            stake_address: env::var("CARDANO_STAKE_ADDRESS").ok(),
+            price_client: CoinGeckoClient::from_env(),
+        }
+    }
+}
+
+impl Connector for CardanoConnector {
+    type History = CardanoHistory;
+
+    fn platform(&self) -> Platform {
+        Platform::Blockchain
+    }
+
+    fn is_configured(&self) -> bool {
+        self.stake_address.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, CardanoHistory> {
+        Box::pin(async move {
+            let stake_address = self.stake_address.as_ref().ok_or(IoError::new(
+                "CARDANO_STAKE_ADDRESS not set in .env file".to_string(),
+            ))?;
+            let client = BlockfrostClient::from_env();
+            get_cardano_history(&client, stake_address).await
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: CardanoHistory,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            if let Some(cardano_txs) = read_mapped_data(CARDANO_MAPPED_PATH, &history)? {
+                return Ok(cardano_txs);
+            }
+
+            let mut cardano_txs: Vec<Transaction> = Vec::new();
+            create_cardano_txs(
+                wallet_manager,
+                &mut cardano_txs,
+                &history,
+                &self.price_client,
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+
+            save_mapped_data(CARDANO_MAPPED_PATH, &history, &cardano_txs)?;
+            Ok(cardano_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(self.price_client.fetch_price(currency, time))
+    }
+}
+
+pub async fn get_cardano_history(
+    client: &BlockfrostClient,
+    stake_address: &str,
+) -> Result<CardanoHistory, IoError> {
+    fetch_history_cardano(client, stake_address)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))
+}
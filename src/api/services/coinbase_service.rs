@@ -0,0 +1,84 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{
+        create_coinbase_txs, fetch_history_coinbase, get_coinbase_price, CoinbaseClient,
+        CoinbaseHistory, Connector, ConnectorFuture, PriceFuture,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, save_mapped_data},
+};
+
+const COINBASE_MAPPED_PATH: &str = ".data/coinbase/coinbase_mapped_data";
+
+/* Fetch and save the coinbase data, see KrakenConnector.
+The API can't be asked for what happened since a date, so the whole history is fetched at each run and mapped again
+when it changed. The prices come from the public Coinbase API */
+pub struct CoinbaseConnector {
+    pub price_client: CoinbaseClient,
+}
+
+impl CoinbaseConnector {
+    pub fn from_env() -> Self {
+        CoinbaseConnector {
+            price_client: CoinbaseClient::public_from_env(),
+        }
+    }
+}
+
+impl Connector for CoinbaseConnector {
+    type History = CoinbaseHistory;
+
+    fn platform(&self) -> Platform {
+        Platform::Coinbase
+    }
+
+    fn is_configured(&self) -> bool {
+        env::var("COINBASE_KEY").is_ok()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, CoinbaseHistory> {
+        Box::pin(async move {
+            let client = CoinbaseClient::from_env();
+            get_coinbase_history(&client).await
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: CoinbaseHistory,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            if let Some(coinbase_txs) = read_mapped_data(COINBASE_MAPPED_PATH, &history)? {
+                return Ok(coinbase_txs);
+            }
+
+            let mut coinbase_txs: Vec<Transaction> = Vec::new();
+            create_coinbase_txs(
+                wallet_manager,
+                &mut coinbase_txs,
+                &history,
+                &self.price_client,
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+
+            save_mapped_data(COINBASE_MAPPED_PATH, &history, &coinbase_txs)?;
+            Ok(coinbase_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_coinbase_price(&self.price_client, time, currency))
+    }
+}
+
+pub async fn get_coinbase_history(client: &CoinbaseClient) -> Result<CoinbaseHistory, IoError> {
+    fetch_history_coinbase(client)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))
+}
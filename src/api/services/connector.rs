@@ -0,0 +1,173 @@
+use std::{future::Future, pin::Pin};
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::{
+    api::{
+        AlgorandConnector, BinanceConnector, BitcoinConnector, BitfinexConnector,
+        BitpandaConnector, CardanoConnector, CoinbaseClient, CoinbaseConnector,
+        CryptoComConnector, EvmConnector, ExchangePriceSource, KrakenConnector, KuCoinConnector,
+        SolanaConnector,
+    },
+    errors::{ApiError, IoError},
+    parsing::{
+        GenericCsvConnector, KoinlyConnector, LedgerLiveConnector, NexoConnector,
+        StandardFormatConnector,
+    },
+    structs::{wallet_manager::WalletManager, Platform, Transaction},
+};
+
+pub type PriceFuture<'a> = Pin<Box<dyn Future<Output = Result<Decimal, ApiError>> + 'a>>;
+
+pub type ConnectorFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, IoError>> + 'a>>;
+
+/* A connector brings the history of a platform: it fetches the raw data (API or exports) it doesn't have yet,
+maps it to transactions and gives the prices of the currencies of its wallets.
+A new platform only needs a connector added to default_connectors to be imported and priced. */
+pub trait Connector {
+    /* The raw data of the platform, as given by its API or its exports */
+    type History: 'static;
+
+    /* The platform of the wallets created by the connector */
+    fn platform(&self) -> Platform;
+
+    /* Whether the environment has what the connector needs (keys, exports...) */
+    fn is_configured(&self) -> bool;
+
+    /* Fetch the history since the previous fetch: the connector keeps what it has already fetched */
+    fn fetch_history(&self) -> ConnectorFuture<'_, Self::History>;
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: Self::History,
+    ) -> ConnectorFuture<'a, Vec<Transaction>>;
+
+    /* Price in euros of the currency at the given time */
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a>;
+}
+
+/* The part of a connector that doesn't depend on its raw data, so that the connectors can be kept together */
+pub trait DynConnector {
+    fn platform(&self) -> Platform;
+
+    fn is_configured(&self) -> bool;
+
+    fn fetch_transactions<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+    ) -> ConnectorFuture<'a, Vec<Transaction>>;
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a>;
+}
+
+impl<C: Connector> DynConnector for C {
+    fn platform(&self) -> Platform {
+        Connector::platform(self)
+    }
+
+    fn is_configured(&self) -> bool {
+        Connector::is_configured(self)
+    }
+
+    fn fetch_transactions<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let history = self.fetch_history().await?;
+            self.map_history(wallet_manager, history).await
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Connector::get_price(self, time, currency)
+    }
+}
+
+#[derive(Default)]
+pub struct ConnectorRegistry {
+    connectors: Vec<Box<dyn DynConnector>>,
+    /* Prices the wallets of the platforms without connector (Platform::Other): the ones created by the imports,
+    named after a Koinly wallet, a generic CSV mapping or saved in the standard format */
+    fallback: Option<Box<dyn ExchangePriceSource>>,
+}
+
+impl ConnectorRegistry {
+    pub fn register<C: Connector + 'static>(&mut self, connector: C) {
+        self.connectors.push(Box::new(connector));
+    }
+
+    pub fn set_fallback<S: ExchangePriceSource + 'static>(&mut self, source: S) {
+        self.fallback = Some(Box::new(source));
+    }
+
+    /* The connector of the platform, if there is one. When several connectors create wallets of the platform
+    (the blockchains), the first one registered gives the prices */
+    pub fn get(&self, platform: &Platform) -> Option<&dyn DynConnector> {
+        self.connectors
+            .iter()
+            .find(|connector| connector.platform() == *platform)
+            .map(|connector| connector.as_ref())
+    }
+
+    /* The connectors that can be used with the current environment, in their order of registration */
+    pub fn configured(&self) -> impl Iterator<Item = &dyn DynConnector> {
+        self.connectors
+            .iter()
+            .filter(|connector| connector.is_configured())
+            .map(|connector| connector.as_ref())
+    }
+
+    /* Whether the wallets of the platform can be priced */
+    pub fn prices(&self, platform: &Platform) -> bool {
+        self.get(platform).is_some()
+            || (matches!(platform, Platform::Other(_)) && self.fallback.is_some())
+    }
+
+    /* Price in euros of the currency on the platform, given by the connector of the platform or, for the other
+    platforms, by the fallback */
+    pub async fn get_price(
+        &self,
+        platform: &Platform,
+        time: DateTime<Utc>,
+        currency: &str,
+    ) -> Result<Decimal, ApiError> {
+        if let Some(connector) = self.get(platform) {
+            return connector.get_price(time, currency).await;
+        }
+        match (platform, &self.fallback) {
+            (Platform::Other(_), Some(fallback)) => fallback.fetch_price_eur(time, currency).await,
+            _ => Err(ApiError::NoConnector {
+                platform: format!("{platform:?}"),
+            }),
+        }
+    }
+}
+
+/* The connectors of the platforms, the ones that are configured being imported by main. Built once by main, which
+gives it to the PortfolioManager for the prices */
+pub fn default_connectors() -> Result<ConnectorRegistry, IoError> {
+    let mut registry = ConnectorRegistry::default();
+    registry.register(KrakenConnector::from_env()?);
+    registry.register(BinanceConnector::from_env());
+    registry.register(CoinbaseConnector::from_env());
+    registry.register(BitfinexConnector::from_env());
+    registry.register(KuCoinConnector::from_env());
+    registry.register(BitpandaConnector::from_env());
+    registry.register(CryptoComConnector::from_env());
+    registry.register(SolanaConnector::from_env());
+    registry.register(CardanoConnector::from_env());
+    registry.register(AlgorandConnector::from_env());
+    registry.register(BitcoinConnector::from_env());
+    registry.register(EvmConnector::from_env());
+    registry.register(KoinlyConnector::from_env());
+    registry.register(NexoConnector::from_env());
+    registry.register(LedgerLiveConnector::from_env());
+    registry.register(GenericCsvConnector::from_env());
+    registry.register(StandardFormatConnector::from_env());
+    // As for their mapping, the wallets of the imports on other platforms are priced with Coinbase
+    registry.set_fallback(CoinbaseClient::public_from_env());
+    Ok(registry)
+}
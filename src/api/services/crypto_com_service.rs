@@ -0,0 +1,141 @@
+use std::env;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{
+    api::{
+        create_crypto_com_txs, fetch_history_crypto_com, get_coinbase_price,
+        read_crypto_com_app_csv, CoinbaseClient, Connector, ConnectorFuture, CryptoComAppRow,
+        CryptoComClient, CryptoComHistory, PriceFuture,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, read_saved_data, save_data, save_mapped_data},
+};
+
+// Default start of the history, used when CRYPTO_COM_START_DATE (YYYY-MM-DD) is not set
+const CRYPTO_COM_START_DATE: &str = "2019-11-14";
+
+const CRYPTO_COM_HISTORY_PATH: &str = ".data/crypto_com/crypto_com_history";
+const CRYPTO_COM_MAPPED_PATH: &str = ".data/crypto_com/crypto_com_mapped_data";
+
+/* The Exchange history fetched since the previous fetch, with the end of this fetch, and the rows of the App export */
+pub struct CryptoComSources {
+    pub exchange: Option<(CryptoComHistory, DateTime<Utc>)>,
+    pub app_rows: Vec<CryptoComAppRow>,
+}
+
+/* Fetch and save the crypto.com data, see KrakenConnector.
+The Exchange is fetched when CRYPTO_COM_KEY is set, each fetch only asking for what happened since the previous one,
+which is added to the saved history. The App export is read again at each run when CRYPTO_COM_APP_CSV is set.
+The whole history is mapped again when something new was fetched or when the export changed.
+Crypto.com has no historical prices, the prices come from the public Coinbase API */
+pub struct CryptoComConnector {
+    pub price_client: CoinbaseClient,
+}
+
+impl CryptoComConnector {
+    pub fn from_env() -> Self {
+        CryptoComConnector {
+            price_client: CoinbaseClient::public_from_env(),
+        }
+    }
+}
+
+impl Connector for CryptoComConnector {
+    type History = CryptoComSources;
+
+    fn platform(&self) -> Platform {
+        Platform::CryptoCom
+    }
+
+    fn is_configured(&self) -> bool {
+        env::var("CRYPTO_COM_KEY").is_ok() || env::var("CRYPTO_COM_APP_CSV").is_ok()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, CryptoComSources> {
+        Box::pin(get_crypto_com_history())
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        data: CryptoComSources,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut history = read_saved_crypto_com_history()?
+                .map(|(history, _)| history)
+                .unwrap_or_default();
+            let end = data.exchange.map(|(new_history, end)| {
+                history.merge(new_history);
+                end
+            });
+            history.app_rows = data.app_rows;
+            if let Some(crypto_com_txs) = read_mapped_data(CRYPTO_COM_MAPPED_PATH, &history)? {
+                return Ok(crypto_com_txs);
+            }
+
+            let mut crypto_com_txs: Vec<Transaction> = Vec::new();
+            create_crypto_com_txs(
+                wallet_manager,
+                &mut crypto_com_txs,
+                &history,
+                &self.price_client,
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+
+            // The history is saved once mapped, so a failed mapping is done again at the next run
+            save_mapped_data(CRYPTO_COM_MAPPED_PATH, &history, &crypto_com_txs)?;
+            if let Some(end) = end {
+                history.app_rows = Vec::new();
+                save_data(CRYPTO_COM_HISTORY_PATH, &(history, end))?;
+            }
+            Ok(crypto_com_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_coinbase_price(&self.price_client, time, currency))
+    }
+}
+
+/* The Exchange history fetched by the previous runs, with the end of the last fetch */
+fn read_saved_crypto_com_history() -> Result<Option<(CryptoComHistory, DateTime<Utc>)>, IoError> {
+    read_saved_data(CRYPTO_COM_HISTORY_PATH)
+}
+
+/* Fetch the Exchange history since the previous fetch, or since CRYPTO_COM_START_DATE for the first one, and read
+the App export */
+pub async fn get_crypto_com_history() -> Result<CryptoComSources, IoError> {
+    let exchange = if env::var("CRYPTO_COM_KEY").is_ok() {
+        let client = CryptoComClient::from_env();
+        let start = match read_saved_crypto_com_history()? {
+            // The movements still pending at the previous fetch are fetched again, to be updated
+            Some((_, previous_end)) => previous_end - Duration::days(1),
+            None => {
+                let start_date =
+                    env::var("CRYPTO_COM_START_DATE").unwrap_or(CRYPTO_COM_START_DATE.to_string());
+                NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+                    .map_err(|e| IoError::new(e.to_string()))?
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc()
+            }
+        };
+        let end = Utc::now();
+        let history = fetch_history_crypto_com(&client, start, end)
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+        Some((history, end))
+    } else {
+        None
+    };
+    let app_rows = match env::var("CRYPTO_COM_APP_CSV") {
+        Ok(app_csv) => {
+            read_crypto_com_app_csv(&app_csv).map_err(|e| IoError::new(e.to_string()))?
+        }
+        Err(_) => Vec::new(),
+    };
+    Ok(CryptoComSources { exchange, app_rows })
+}
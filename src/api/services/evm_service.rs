@@ -0,0 +1,118 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{
+        create_evm_txs, fetch_history_evm, CoinGeckoClient, Connector, ConnectorFuture,
+        EtherscanClient, EvmChain, EvmHistory, PriceFuture,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, save_mapped_data},
+};
+
+/* Fetch and save the history of the EVM address (env EVM_ADDRESS) on each chain of EVM_CHAINS (comma separated,
+ethereum by default), see KrakenConnector.
+The whole history of each chain is fetched at each run, and mapped again when it changed. The address has its own
+wallets on each chain, so each chain is mapped and saved on its own.
+As for all the wallets outside of the exchanges, the prices come from CoinGecko */
+pub struct EvmConnector {
+    pub address: Option<String>,
+    pub chains: String,
+    pub price_client: CoinGeckoClient,
+}
+
+impl EvmConnector {
+    pub fn from_env() -> Self {
+        EvmConnector {
+            address: env::var("EVM_ADDRESS").ok(),
+            chains: env::var("EVM_CHAINS").unwrap_or("ethereum".to_string()),
+            price_client: CoinGeckoClient::from_env(),
+        }
+    }
+}
+
+impl Connector for EvmConnector {
+    // The history of each chain
+    type History = Vec<EvmHistory>;
+
+    fn platform(&self) -> Platform {
+        Platform::Blockchain
+    }
+
+    fn is_configured(&self) -> bool {
+        self.address.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, Vec<EvmHistory>> {
+        Box::pin(async move {
+            let address = self
+                .address
+                .as_ref()
+                .ok_or(IoError::new("EVM_ADDRESS not set in .env file".to_string()))?;
+            let client = EtherscanClient::from_env();
+            let mut histories = Vec::new();
+            for chain in self.chains.split(',') {
+                let chain =
+                    EvmChain::parse(chain.trim()).map_err(|e| IoError::new(e.to_string()))?;
+                histories.push(get_evm_history(&client, &chain, address).await?);
+            }
+            Ok(histories)
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        histories: Vec<EvmHistory>,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut evm_txs: Vec<Transaction> = Vec::new();
+            for history in &histories {
+                let mapped_path = evm_mapped_path(&history.chain, &history.address);
+                let chain_txs = match read_mapped_data(&mapped_path, history)? {
+                    Some(chain_txs) => chain_txs,
+                    None => {
+                        let mut chain_txs: Vec<Transaction> = Vec::new();
+                        create_evm_txs(
+                            wallet_manager,
+                            &mut chain_txs,
+                            history,
+                            &self.price_client,
+                        )
+                        .await
+                        .map_err(|e| IoError::new(e.to_string()))?;
+                        save_mapped_data(&mapped_path, history, &chain_txs)?;
+                        chain_txs
+                    }
+                };
+                evm_txs.extend(chain_txs);
+            }
+            Ok(evm_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(self.price_client.fetch_price(currency, time))
+    }
+}
+
+/* Transactions mapped from the history of the address on the chain */
+fn evm_mapped_path(chain: &EvmChain, address: &str) -> String {
+    format!(
+        ".data/evm/{}_{}_mapped_data",
+        chain.name,
+        address.to_lowercase()
+    )
+}
+
+pub async fn get_evm_history(
+    client: &EtherscanClient,
+    chain: &EvmChain,
+    address: &str,
+) -> Result<EvmHistory, IoError> {
+    fetch_history_evm(client, chain, address)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))
+}
@@ -1,25 +1,29 @@
 /* This is used to get price of a wallet depending on a Platform */
 
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 
 use crate::{
-    api::kraken_mapping,
+    api::{coinbase_mapping, CoinbaseClient, ConnectorRegistry},
     errors::ApiError,
-    structs::{Platform, Transaction, Wallet},
+    structs::{Transaction, Wallet},
 };
 
-pub async fn get_price_api(tx: &Transaction, wallet: &Wallet) -> Result<Decimal, ApiError> {
+/* The price is given by the connector of the platform of the wallet */
+pub async fn get_price_api(
+    tx: &Transaction,
+    wallet: &Wallet,
+    connectors: &ConnectorRegistry,
+) -> Result<Decimal, ApiError> {
     let time = tx.get_tx_base().timestamp;
-    let currency = wallet.get().currency.clone();
+    connectors
+        .get_price(&wallet.get().platform, time, &wallet.get().currency)
+        .await
+}
 
-    match wallet.get().platform {
-        Platform::Kraken => {
-            let price =
-                kraken_mapping::get_currency_price(time.timestamp().to_string(), currency).await?;
-            return Ok(price);
-        }
-        Platform::Binance => todo!(),
-        Platform::Blockchain => todo!(),
-        Platform::Other(_) => todo!(),
-    }
+/* Price of the platforms without price history (Bitpanda, Nexo...): the Coinbase spot price, available for most
+currencies without key */
+pub async fn get_spot_price(time: DateTime<Utc>, currency: &str) -> Result<Decimal, ApiError> {
+    let client = CoinbaseClient::public_from_env();
+    coinbase_mapping::get_coinbase_price(&client, time, currency).await
 }
@@ -1,98 +1,159 @@
-use std::fs::File;
+use std::{env, fs::File};
 
+use chrono::{DateTime, Utc};
+use hashbrown::HashSet;
 use rmp_serde::Serializer;
 use serde::Serialize;
 
 use crate::{
     api::{
-        create_kraken_txs, fetch_assets_pair, fetch_history_kraken, map_asset_pairs, HistoryResponse, KrakenPairs, Tier,
+        create_kraken_txs, fetch_assets_pair, fetch_history_kraken, get_currency_price, map_asset_pairs, Connector,
+        ConnectorFuture, HistoryResponse, KrakenPairs, LedgerHistory, PriceFuture, Tier,
     },
     errors::IoError,
-    structs::{transaction::Transaction, wallet_manager::WalletManager},
-    utils::{create_directories_if_needed, file_exists},
+    parsing::read_kraken_csv,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{create_directories_if_needed, file_exists, read_saved_data, save_data},
 };
 
+const KRAKEN_HISTORY_PATH: &str = ".data/kraken/kraken_history";
+const KRAKEN_MAPPED_PATH: &str = ".data/kraken/kraken_mapped_data";
+pub const KRAKEN_PAIRS_PATH: &str = ".data/kraken/kraken_pairs";
+
 /* Fetch and save the kraken data: Even though we keep the global list of all the transactions in another file (see TransactionsManager),
 we still want to keep the specific data of <kraken> (or any exchange) somewhere. It allows us to easily "deactivate/remove" the exchange
 or put it again withtout fetching the data again. It also allows for easy update of the data withtout having to handle the full vector of
 transactions.
+The raw history is saved in history_path, each fetch only bringing the ledger entries after the last saved one. Only these
+entries are mapped, the transactions mapped before being kept in mapped_path.
+When KRAKEN_LEDGERS_CSV and KRAKEN_TRADES_CSV are set, the history is read from the exports of Kraken instead of the API.
 */
-pub fn handle_kraken_data(
-    wallet_manager: &mut WalletManager,
-) -> Result<Vec<Transaction>, IoError> {
-    let mut kraken_txs: Vec<Transaction> = Vec::new();
-
-    let pairs: KrakenPairs = kraken_pairs().unwrap();
+pub struct KrakenConnector {
+    pub history_path: String,
+    pub mapped_path: String,
+    pub pairs_path: String,
+    pub csv_paths: Option<(String, String)>, // Ledgers and trades exports
+}
 
-    let file_path = ".data/kraken/kraken_mapped_data";
-    if !file_exists(file_path) {
-        let response = get_kraken_history()?;
-        create_kraken_txs(
-            wallet_manager,
-            &mut kraken_txs,
-            response.0,
-            response.1,
-            response.2,
-            response.3,
-            pairs.get(),
-        )
-        .map_err(|e| IoError::new(e.to_string()))?;
-
-        println!("{:?}", kraken_txs);
-        let file = File::create(file_path).expect("Unable to create file");
-        let mut writer = Serializer::new(file);
-        kraken_txs
-            .serialize(&mut writer)
-            .map_err(|e| IoError::new(e.to_string()))?;
-    } else {
-        let file = File::open(file_path).map_err(|e| IoError::new(e.to_string()))?;
-        let txs: Vec<Transaction> =
-            rmp_serde::from_read(file).map_err(|e| IoError::new(e.to_string()))?;
-        kraken_txs = txs;
+impl KrakenConnector {
+    pub fn from_env() -> Result<Self, IoError> {
+        let csv_paths = match env::var("KRAKEN_LEDGERS_CSV") {
+            Ok(ledgers_path) => {
+                let trades_path = env::var("KRAKEN_TRADES_CSV").map_err(|_| {
+                    IoError::new("KRAKEN_TRADES_CSV must be set with KRAKEN_LEDGERS_CSV".to_string())
+                })?;
+                Some((ledgers_path, trades_path))
+            }
+            Err(_) => None,
+        };
+        Ok(KrakenConnector {
+            history_path: KRAKEN_HISTORY_PATH.to_string(),
+            mapped_path: KRAKEN_MAPPED_PATH.to_string(),
+            pairs_path: KRAKEN_PAIRS_PATH.to_string(),
+            csv_paths,
+        })
     }
-    return Ok(kraken_txs);
 }
 
-pub fn kraken_pairs() -> Result<KrakenPairs, IoError> {
-    let file_path = ".data/kraken/kraken_pairs";
+impl Connector for KrakenConnector {
+    type History = HistoryResponse;
 
-    if !file_exists(file_path) {
-        let asset_pairs_raw = fetch_assets_pair().unwrap().result.unwrap().pairs;
-        let asset_pairs = map_asset_pairs(asset_pairs_raw);
+    fn platform(&self) -> Platform {
+        Platform::Kraken
+    }
 
-        create_directories_if_needed(file_path);
-        let file = File::create(file_path).expect("Unable to create file");
-        let mut writer = Serializer::new(file);
-        asset_pairs
-            .serialize(&mut writer)
+    fn is_configured(&self) -> bool {
+        env::var("KRAKEN_KEY").is_ok() || self.csv_paths.is_some()
+    }
+
+    /* The new ledger entries, with all the trades, deposits and withdrawals they may refer to */
+    fn fetch_history(&self) -> ConnectorFuture<'_, HistoryResponse> {
+        Box::pin(async move {
+            let (mut ledger, mut trades, mut deposits, mut withdrawals): HistoryResponse =
+                read_saved_data(&self.history_path)?.unwrap_or_default();
+            let last_time = ledger.last().map(|entry| entry.time);
+            let response = match &self.csv_paths {
+                Some((ledgers_path, trades_path)) => read_kraken_csv(ledgers_path, trades_path),
+                None => fetch_history_kraken(Tier::Intermediate, last_time).await,
+            }
             .map_err(|e| IoError::new(e.to_string()))?;
 
-        return Ok(asset_pairs);
-    } else {
-        let file = File::open(file_path).map_err(|e| IoError::new(e.to_string()))?;
-        let deserialized_map: KrakenPairs =
-            rmp_serde::from_read(file).map_err(|e| IoError::new(e.to_string()))?;
-        return Ok(deserialized_map);
+            // The exports are always complete, the API may give again an entry at the same time
+            let new_ledger: Vec<LedgerHistory> = response
+                .0
+                .into_iter()
+                .filter(|entry| last_time.is_none_or(|time| entry.time > time))
+                .collect();
+            ledger.extend(new_ledger.iter().cloned());
+            trades.extend(response.1);
+            deposits.extend(response.2);
+            withdrawals.extend(response.3);
+
+            let history = (ledger, trades, deposits, withdrawals);
+            save_data(&self.history_path, &history)?;
+            Ok((new_ledger, history.1, history.2, history.3))
+        })
     }
-}
 
-pub fn get_kraken_history() -> Result<HistoryResponse, IoError> {
-    let file_path = ".data/kraken/kraken_history";
+    /* Map the new ledger entries and add them to the transactions mapped at the previous runs */
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: HistoryResponse,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let pairs: KrakenPairs = kraken_pairs(&self.pairs_path).await?;
+            let mut new_txs: Vec<Transaction> = Vec::new();
+            let (ledger, trades, deposits, withdrawals) = history;
+            create_kraken_txs(
+                wallet_manager,
+                &mut new_txs,
+                ledger,
+                trades,
+                deposits,
+                withdrawals,
+                pairs.get(),
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
 
+            // A transaction mapped again (a trade whose entries were split between two fetches) replaces the saved one
+            let new_ids: HashSet<String> = new_txs.iter().map(|tx| tx.get_id().clone()).collect();
+            let mut kraken_txs: Vec<Transaction> = read_saved_data::<Vec<Transaction>>(&self.mapped_path)?
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|tx| !new_ids.contains(tx.get_id()))
+                .collect();
+            kraken_txs.extend(new_txs);
+            save_data(&self.mapped_path, &kraken_txs)?;
+            Ok(kraken_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_currency_price(
+            time.timestamp().to_string(),
+            currency.to_string(),
+        ))
+    }
+}
+
+pub async fn kraken_pairs(file_path: &str) -> Result<KrakenPairs, IoError> {
     if !file_exists(file_path) {
-        let response = fetch_history_kraken(Tier::Intermediate).unwrap();
+        let asset_pairs_raw = fetch_assets_pair().await.unwrap().result.unwrap().pairs;
+        let asset_pairs = map_asset_pairs(asset_pairs_raw);
 
         create_directories_if_needed(file_path);
         let file = File::create(file_path).expect("Unable to create file");
         let mut writer = Serializer::new(file);
-        response
+        asset_pairs
             .serialize(&mut writer)
             .map_err(|e| IoError::new(e.to_string()))?;
 
-        return Ok(response);
+        return Ok(asset_pairs);
     } else {
         let file = File::open(file_path).map_err(|e| IoError::new(e.to_string()))?;
-        let deserialized_map: HistoryResponse =
+        let deserialized_map: KrakenPairs =
             rmp_serde::from_read(file).map_err(|e| IoError::new(e.to_string()))?;
         return Ok(deserialized_map);
     }
@@ -0,0 +1,119 @@
+use std::env;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{
+    api::{
+        create_kucoin_txs, fetch_history_kucoin, get_kucoin_price, Connector, ConnectorFuture,
+        KuCoinClient, KuCoinHistory, PriceFuture,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, read_saved_data, save_data, save_mapped_data},
+};
+
+// Default start of the history, used when KUCOIN_START_DATE (YYYY-MM-DD) is not set
+const KUCOIN_START_DATE: &str = "2017-09-15";
+
+const KUCOIN_HISTORY_PATH: &str = ".data/kucoin/kucoin_history";
+const KUCOIN_MAPPED_PATH: &str = ".data/kucoin/kucoin_mapped_data";
+
+/* Fetch and save the kucoin data, see KrakenConnector.
+Each fetch only asks for what happened since the previous one, which is added to the saved history. As the balances
+are recalculated from zero, the whole history is mapped again when something new was fetched.
+The prices come from the public KuCoin API */
+pub struct KuCoinConnector {
+    pub price_client: KuCoinClient,
+}
+
+impl KuCoinConnector {
+    pub fn from_env() -> Self {
+        KuCoinConnector {
+            price_client: KuCoinClient::public_from_env(),
+        }
+    }
+}
+
+impl Connector for KuCoinConnector {
+    // The history fetched since the previous fetch, with the end of this fetch
+    type History = (KuCoinHistory, DateTime<Utc>);
+
+    fn platform(&self) -> Platform {
+        Platform::KuCoin
+    }
+
+    fn is_configured(&self) -> bool {
+        env::var("KUCOIN_KEY").is_ok()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, (KuCoinHistory, DateTime<Utc>)> {
+        Box::pin(async move {
+            let client = KuCoinClient::from_env();
+            get_kucoin_history(&client).await
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        (new_history, end): (KuCoinHistory, DateTime<Utc>),
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut history = read_saved_kucoin_history()?
+                .map(|(history, _)| history)
+                .unwrap_or_default();
+            history.merge(new_history);
+            if let Some(kucoin_txs) = read_mapped_data(KUCOIN_MAPPED_PATH, &history)? {
+                return Ok(kucoin_txs);
+            }
+
+            let mut kucoin_txs: Vec<Transaction> = Vec::new();
+            create_kucoin_txs(
+                wallet_manager,
+                &mut kucoin_txs,
+                &history,
+                &self.price_client,
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+
+            // The history is saved once mapped, so a failed mapping is done again at the next run
+            save_mapped_data(KUCOIN_MAPPED_PATH, &history, &kucoin_txs)?;
+            save_data(KUCOIN_HISTORY_PATH, &(history, end))?;
+            Ok(kucoin_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(get_kucoin_price(&self.price_client, time, currency))
+    }
+}
+
+/* The history fetched by the previous runs, with the end of the last fetch */
+fn read_saved_kucoin_history() -> Result<Option<(KuCoinHistory, DateTime<Utc>)>, IoError> {
+    read_saved_data(KUCOIN_HISTORY_PATH)
+}
+
+/* Fetch the history since the previous fetch, or since KUCOIN_START_DATE for the first one */
+pub async fn get_kucoin_history(
+    client: &KuCoinClient,
+) -> Result<(KuCoinHistory, DateTime<Utc>), IoError> {
+    let start = match read_saved_kucoin_history()? {
+        // The operations still pending at the previous fetch are fetched again, to be updated
+        Some((_, previous_end)) => previous_end - Duration::days(1),
+        None => {
+            let start_date =
+                env::var("KUCOIN_START_DATE").unwrap_or(KUCOIN_START_DATE.to_string());
+            NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+                .map_err(|e| IoError::new(e.to_string()))?
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        }
+    };
+    let end = Utc::now();
+    let history = fetch_history_kucoin(client, start, end)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))?;
+    Ok((history, end))
+}
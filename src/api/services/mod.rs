@@ -1,5 +1,30 @@
 pub mod kraken_service;
 pub use kraken_service::*;
+pub mod binance_service;
+pub use binance_service::*;
+pub mod coinbase_service;
+pub use coinbase_service::*;
+pub mod bitfinex_service;
+pub use bitfinex_service::*;
+pub mod kucoin_service;
+pub use kucoin_service::*;
+pub mod bitpanda_service;
+pub use bitpanda_service::*;
+pub mod crypto_com_service;
+pub use crypto_com_service::*;
+pub mod solana_service;
+pub use solana_service::*;
+pub mod cardano_service;
+pub use cardano_service::*;
+pub mod algorand_service;
+pub use algorand_service::*;
+pub mod bitcoin_service;
+pub use bitcoin_service::*;
+pub mod evm_service;
+pub use evm_service::*;
 
 pub mod get_price;
 pub use get_price::*;
+
+pub mod connector;
+pub use connector::*;
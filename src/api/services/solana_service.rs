@@ -0,0 +1,92 @@
+use std::env;
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    api::{
+        create_solana_txs, fetch_history_solana, CoinGeckoClient, Connector, ConnectorFuture,
+        PriceFuture, SolanaHistory, SolanaRpcClient,
+    },
+    errors::IoError,
+    structs::{transaction::Transaction, wallet_manager::WalletManager, Platform},
+    utils::{read_mapped_data, save_mapped_data},
+};
+
+const SOLANA_MAPPED_PATH: &str = ".data/solana/solana_mapped_data";
+
+/* Fetch and save the history of the Solana address (env SOLANA_ADDRESS), see KrakenConnector.
+The whole history is fetched at each run and mapped again when it changed.
+As for all the wallets outside of the exchanges, the prices come from CoinGecko */
+pub struct SolanaConnector {
+    pub address: Option<String>,
+    pub price_client: CoinGeckoClient,
+}
+
+impl SolanaConnector {
+    pub fn from_env() -> Self {
+        SolanaConnector {
+            address: env::var("SOLANA_ADDRESS").ok(),
+            price_client: CoinGeckoClient::from_env(),
+        }
+    }
+}
+
+impl Connector for SolanaConnector {
+    type History = SolanaHistory;
+
+    fn platform(&self) -> Platform {
+        Platform::Blockchain
+    }
+
+    fn is_configured(&self) -> bool {
+        self.address.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, SolanaHistory> {
+        Box::pin(async move {
+            let address = self.address.as_ref().ok_or(IoError::new(
+                "SOLANA_ADDRESS not set in .env file".to_string(),
+            ))?;
+            let client = SolanaRpcClient::from_env();
+            get_solana_history(&client, address).await
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        history: SolanaHistory,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            if let Some(solana_txs) = read_mapped_data(SOLANA_MAPPED_PATH, &history)? {
+                return Ok(solana_txs);
+            }
+
+            let mut solana_txs: Vec<Transaction> = Vec::new();
+            create_solana_txs(
+                wallet_manager,
+                &mut solana_txs,
+                &history,
+                &self.price_client,
+            )
+            .await
+            .map_err(|e| IoError::new(e.to_string()))?;
+
+            save_mapped_data(SOLANA_MAPPED_PATH, &history, &solana_txs)?;
+            Ok(solana_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(self.price_client.fetch_price(currency, time))
+    }
+}
+
+pub async fn get_solana_history(
+    client: &SolanaRpcClient,
+    address: &str,
+) -> Result<SolanaHistory, IoError> {
+    fetch_history_solana(client, address)
+        .await
+        .map_err(|e| IoError::new(e.to_string()))
+}
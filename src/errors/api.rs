@@ -6,6 +6,7 @@ pub enum ApiError {
     MappingError(MappingError),
     CouldNotFindPrice { pairs: Vec<(String, String)> },
     DeserializationError(String),
+    NoConnector { platform: String },
 }
 
 #[derive(Debug, Clone)]
@@ -31,6 +32,9 @@ impl fmt::Display for ApiError {
             ApiError::DeserializationError(e) => {
                 write!(f, "Error during serde deserialisation: {e} ")
             }
+            ApiError::NoConnector { platform } => {
+                write!(f, "No connector gives the prices of {platform}")
+            }
         }
     }
 }
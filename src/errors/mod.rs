@@ -6,3 +6,6 @@ pub use io_error::*;
 
 pub mod portfolio_history_error;
 pub use portfolio_history_error::*;
+
+pub mod tax_report_error;
+pub use tax_report_error::*;
@@ -0,0 +1,30 @@
+use std::fmt;
+
+use crate::structs::TransactionId;
+
+#[derive(Debug, Clone)]
+pub enum TaxReportError {
+    MissingPortfolio { tx_id: TransactionId },
+    MissingCostBasis { tx_id: TransactionId },
+    PortfolioNotCalculated { tx_id: TransactionId },
+    ZeroPortfolioValue { tx_id: TransactionId },
+}
+
+impl fmt::Display for TaxReportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaxReportError::MissingPortfolio { tx_id } => {
+                write!(f, "No portfolio found for the taxable Tx {tx_id}")
+            }
+            TaxReportError::MissingCostBasis { tx_id } => {
+                write!(f, "No global cost basis found for the taxable Tx {tx_id}")
+            }
+            TaxReportError::PortfolioNotCalculated { tx_id } => {
+                write!(f, "The portfolio total value was not calculated for the taxable Tx {tx_id}")
+            }
+            TaxReportError::ZeroPortfolioValue { tx_id } => {
+                write!(f, "The portfolio total value is zero for the taxable Tx {tx_id}")
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt;
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+use super::{format_amount, Form2086, EXEMPTION_THRESHOLD};
+
+/* Prélèvement forfaitaire unique (PFU or "flat tax"): 12.8% of income tax + 17.2% of prélèvements sociaux */
+pub const PFU_INCOME_TAX_RATE: Decimal = dec!(0.128);
+pub const SOCIAL_CHARGES_RATE: Decimal = dec!(0.172);
+
+/* Yearly summary to fill the form 2042-C (boxes 3AN and 3BN) from the form 2086 of the same year.
+
+The gains and losses of all the cessions of the year are netted. A net loss is declared in 3BN but can't be
+deducted from other incomes nor carried over to the following years.
+The boxes are in whole euros, as on the declaration: the half euro is rounded up, not to the nearest even euro.
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Form2042C {
+    pub year: i32,
+    pub total_sell_price: Decimal, // Sum of the lines 213, used for the exemption threshold
+    pub net_gains: Decimal,        // Sum of the lines 224
+    pub is_exempt: bool,
+    pub box_3an: Decimal, // Plus-value
+    pub box_3bn: Decimal, // Moins-value
+    pub pfu: TaxAmounts,
+    pub bareme: TaxAmounts, // Option for the barème progressif, with the given marginal rate
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxAmounts {
+    pub income_tax_rate: Decimal,
+    pub income_tax: Decimal,
+    pub social_charges: Decimal,
+    pub total: Decimal,
+}
+
+impl TaxAmounts {
+    fn new(taxable_gains: Decimal, income_tax_rate: Decimal) -> Self {
+        let income_tax = (taxable_gains * income_tax_rate).round_dp(2);
+        let social_charges = (taxable_gains * SOCIAL_CHARGES_RATE).round_dp(2);
+        Self {
+            income_tax_rate,
+            income_tax,
+            social_charges,
+            total: income_tax + social_charges,
+        }
+    }
+}
+
+fn round_to_euro(amount: Decimal) -> Decimal {
+    amount.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+}
+
+/* marginal_rate is the "taux marginal d'imposition" of the household (0, 0.11, 0.30, 0.41 or 0.45) used for the barème option */
+pub fn calculate_form_2042c(form_2086: &Form2086, marginal_rate: Decimal) -> Form2042C {
+    let total_sell_price = form_2086.total_sell_price;
+    let net_gains = form_2086.total_gains;
+    let is_exempt = form_2086.is_exempt;
+
+    let (box_3an, box_3bn) = if is_exempt {
+        (dec!(0), dec!(0))
+    } else if net_gains >= dec!(0) {
+        (round_to_euro(net_gains), dec!(0))
+    } else {
+        (dec!(0), round_to_euro(net_gains.abs()))
+    };
+
+    Form2042C {
+        year: form_2086.year,
+        total_sell_price,
+        net_gains,
+        is_exempt,
+        box_3an,
+        box_3bn,
+        pfu: TaxAmounts::new(box_3an, PFU_INCOME_TAX_RATE),
+        bareme: TaxAmounts::new(box_3an, marginal_rate),
+    }
+}
+
+impl fmt::Display for Form2042C {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Formulaire 2042-C - Année {}", self.year)?;
+        writeln!(f, "Total des prix de cession: {}", format_amount(self.total_sell_price))?;
+        if self.is_exempt {
+            writeln!(f, "Total des cessions inférieur ou égal à {EXEMPTION_THRESHOLD}€: exonération (article 150 VH bis)")?;
+        }
+        writeln!(f, "Case 3AN (plus-value): {}", self.box_3an)?;
+        writeln!(f, "Case 3BN (moins-value): {}", self.box_3bn)?;
+        writeln!(
+            f,
+            "PFU: impôt sur le revenu {} + prélèvements sociaux {} = {}",
+            format_amount(self.pfu.income_tax),
+            format_amount(self.pfu.social_charges),
+            format_amount(self.pfu.total)
+        )?;
+        write!(
+            f,
+            "Barème progressif (TMI {}%): impôt sur le revenu {} + prélèvements sociaux {} = {}",
+            (self.bareme.income_tax_rate * dec!(100)).normalize(),
+            format_amount(self.bareme.income_tax),
+            format_amount(self.bareme.social_charges),
+            format_amount(self.bareme.total)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use crate::functions::Cession2086;
+
+    use super::*;
+
+    fn cession(sell_price: Decimal, gains: Decimal) -> Cession2086 {
+        Cession2086 {
+            tx_id: "test".to_string(),
+            date: Utc::now(),
+            pf_total_value: dec!(10000),
+            sell_price,
+            fee: dec!(0),
+            sell_price_net_of_fee: sell_price,
+            soulte: dec!(0),
+            sell_price_net_of_soulte: sell_price,
+            net_sell_price: sell_price,
+            total_acquisition_price: dec!(0),
+            previous_fractions: dec!(0),
+            previous_soultes: dec!(0),
+            net_acquisition_price: dec!(0),
+            gains,
+        }
+    }
+
+    fn form(cessions: Vec<Cession2086>) -> Form2086 {
+        let total_gains = cessions.iter().map(|cession| cession.gains).sum();
+        let total_sell_price: Decimal = cessions.iter().map(|cession| cession.sell_price).sum();
+        Form2086 {
+            year: 2023,
+            cessions,
+            total_gains,
+            total_sell_price,
+            is_exempt: total_sell_price <= EXEMPTION_THRESHOLD,
+        }
+    }
+
+    #[test]
+    fn net_gains() {
+        let form_2086 = form(vec![
+            cession(dec!(1000), dec!(400.4)),
+            cession(dec!(500), dec!(-100)),
+        ]);
+        let form_2042c = calculate_form_2042c(&form_2086, dec!(0.30));
+
+        assert!(!form_2042c.is_exempt);
+        assert_eq!(form_2042c.box_3an, dec!(300));
+        assert_eq!(form_2042c.box_3bn, dec!(0));
+        assert_eq!(form_2042c.pfu.income_tax, dec!(38.4));
+        assert_eq!(form_2042c.pfu.social_charges, dec!(51.6));
+        assert_eq!(form_2042c.pfu.total, dec!(90));
+        assert_eq!(form_2042c.bareme.income_tax, dec!(90));
+        assert_eq!(form_2042c.bareme.total, dec!(141.6));
+    }
+
+    #[test]
+    fn net_loss() {
+        let form_2086 = form(vec![cession(dec!(1000), dec!(-250.6))]);
+        let form_2042c = calculate_form_2042c(&form_2086, dec!(0.11));
+
+        assert_eq!(form_2042c.box_3an, dec!(0));
+        assert_eq!(form_2042c.box_3bn, dec!(251));
+        assert_eq!(form_2042c.pfu.total, dec!(0));
+        assert_eq!(form_2042c.bareme.total, dec!(0));
+    }
+
+    #[test]
+    fn half_euro_rounding() {
+        let form_2086 = form(vec![cession(dec!(1000), dec!(100.5))]);
+        assert_eq!(calculate_form_2042c(&form_2086, dec!(0.30)).box_3an, dec!(101));
+
+        let form_2086 = form(vec![cession(dec!(1000), dec!(-100.5))]);
+        assert_eq!(calculate_form_2042c(&form_2086, dec!(0.30)).box_3bn, dec!(101));
+    }
+
+    #[test]
+    fn exemption_threshold() {
+        let form_2086 = form(vec![cession(dec!(200), dec!(150)), cession(dec!(105), dec!(50))]);
+        let form_2042c = calculate_form_2042c(&form_2086, dec!(0.30));
+
+        assert!(form_2042c.is_exempt);
+        assert_eq!(form_2042c.box_3an, dec!(0));
+        assert_eq!(form_2042c.pfu.total, dec!(0));
+
+        let form_2086 = form(vec![cession(dec!(200), dec!(150)), cession(dec!(105.01), dec!(50))]);
+        assert!(!calculate_form_2042c(&form_2086, dec!(0.30)).is_exempt);
+    }
+}
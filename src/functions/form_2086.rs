@@ -0,0 +1,391 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Paris;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::TaxReportError,
+    structs::{GlobalCostBasisManager, PortfolioManager, TransactionId, TransactionManager},
+};
+
+use super::{
+    calculate_tax_gains, calculate_yearly_cession, get_sell_price_and_fee, tax_year, EXEMPTION_THRESHOLD,
+};
+
+/* Representation of the "Formulaire 2086" (Déclaration des plus ou moins-values réalisées
+à l'occasion de cessions d'actifs numériques) for a given year.
+
+Each taxable transaction of the year is a "cession", which correspond to a column of the official form.
+The lines are numbered as in the official form: https://www.impots.gouv.fr/formulaire/2086/
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Form2086 {
+    pub year: i32,
+    pub cessions: Vec<Cession2086>,
+    pub total_gains: Decimal, // Plus-value ou moins-value globale: sum of all the lines 224
+    pub total_sell_price: Decimal, // Sum of all the lines 213
+    pub is_exempt: bool, // Total of the cessions below the exemption threshold: nothing to declare for the year
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cession2086 {
+    pub tx_id: TransactionId,
+    pub date: DateTime<Utc>,                   // 211
+    pub pf_total_value: Decimal,               // 212
+    pub sell_price: Decimal,                   // 213
+    pub fee: Decimal,                          // 214
+    pub sell_price_net_of_fee: Decimal,        // 215 = 213 - 214
+    pub soulte: Decimal,                       // 216 (positive if received, negative if paid)
+    pub sell_price_net_of_soulte: Decimal,     // 217 = 213 - 216
+    pub net_sell_price: Decimal,               // 218 = 215 - 216
+    pub total_acquisition_price: Decimal,      // 220
+    pub previous_fractions: Decimal,           // 221
+    pub previous_soultes: Decimal,             // 222
+    pub net_acquisition_price: Decimal,        // 223 = 220 - 221 - 222
+    pub gains: Decimal,                        // 224 = 218 - (223 * 217 / 212)
+}
+
+/* Number of cessions per page on the official form */
+pub const CESSIONS_PER_PAGE: usize = 5;
+
+/* Walk through all the transactions and create the form 2086 for the taxable transactions of the given year.
+The portfolio history and the global cost basis history must have been calculated beforehand.
+*/
+pub fn generate_form_2086(
+    year: i32,
+    transactions_manager: &TransactionManager,
+    portfolio_manager: &PortfolioManager,
+    global_cost_basis_manager: &GlobalCostBasisManager,
+) -> Result<Form2086, TaxReportError> {
+    let mut cessions = Vec::new();
+    for tx in transactions_manager.get() {
+        let timestamp = tx.get_tx_base().timestamp;
+        if !tx.is_taxable() || tax_year(&timestamp) != year {
+            continue;
+        }
+        let tx_id = tx.get_id();
+        let portfolio = portfolio_manager
+            .portfolio_history
+            .get(tx_id)
+            .ok_or(TaxReportError::MissingPortfolio { tx_id: tx_id.clone() })?;
+        if !portfolio.is_pf_total_calculated {
+            return Err(TaxReportError::PortfolioNotCalculated { tx_id: tx_id.clone() });
+        }
+        // The gains are weighted by the portfolio total value (line 212)
+        if portfolio.pf_total_value.is_zero() {
+            return Err(TaxReportError::ZeroPortfolioValue { tx_id: tx_id.clone() });
+        }
+        let cost_basis = global_cost_basis_manager
+            .global_cost_basis_history
+            .get(tx_id)
+            .ok_or(TaxReportError::MissingCostBasis { tx_id: tx_id.clone() })?;
+        let (sell_price, fee) = get_sell_price_and_fee(tx).unwrap_or((dec!(0), dec!(0)));
+
+        let soulte = tx.get_soulte();
+        let pf_total_value = portfolio.pf_total_value;
+        let sell_price_net_of_fee = sell_price - fee;
+        let sell_price_net_of_soulte = sell_price - soulte;
+        let net_sell_price = sell_price_net_of_fee - soulte;
+        let total_acquisition_price = cost_basis.pf_total_cost;
+        let previous_fractions = cost_basis.pf_consumed_fractions;
+        let previous_soultes = cost_basis.pf_soultes;
+        let net_acquisition_price =
+            total_acquisition_price - previous_fractions - previous_soultes;
+        let gains = calculate_tax_gains(tx, portfolio, cost_basis);
+
+        cessions.push(Cession2086 {
+            tx_id: tx_id.clone(),
+            date: timestamp,
+            pf_total_value,
+            sell_price,
+            fee,
+            sell_price_net_of_fee,
+            soulte,
+            sell_price_net_of_soulte,
+            net_sell_price,
+            total_acquisition_price,
+            previous_fractions,
+            previous_soultes,
+            net_acquisition_price,
+            gains,
+        });
+    }
+
+    let total_gains = cessions.iter().map(|cession| cession.gains).sum();
+    let yearly_cessions = calculate_yearly_cession(year, transactions_manager.get());
+    Ok(Form2086 {
+        year,
+        cessions,
+        total_gains,
+        total_sell_price: yearly_cessions.total_sell_price,
+        is_exempt: yearly_cessions.is_exempt,
+    })
+}
+
+impl Form2086 {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /* The cessions grouped as on the official form pages */
+    pub fn pages(&self) -> Vec<&[Cession2086]> {
+        self.cessions.chunks(CESSIONS_PER_PAGE).collect()
+    }
+}
+
+/* The lines of the form holding an amount, in order (number, label). The date (211) is handled separately */
+pub const AMOUNT_LINES: [(u16, &str); 12] = [
+    (212, "Valeur globale du portefeuille au moment de la cession"),
+    (213, "Prix de cession"),
+    (214, "Frais de cession"),
+    (215, "Prix de cession net des frais"),
+    (216, "Soulte reçue ou versée lors de la cession"),
+    (217, "Prix de cession net des soultes"),
+    (218, "Prix de cession net des frais et soultes"),
+    (220, "Prix total d'acquisition"),
+    (221, "Fractions de capital initial des cessions antérieures"),
+    (222, "Soultes reçues en cas d'échanges antérieurs"),
+    (223, "Prix total d'acquisition net"),
+    (224, "Plus-values et moins-values"),
+];
+
+impl Cession2086 {
+    /* The amounts in the same order as AMOUNT_LINES */
+    pub fn amounts(&self) -> [Decimal; 12] {
+        [
+            self.pf_total_value,
+            self.sell_price,
+            self.fee,
+            self.sell_price_net_of_fee,
+            self.soulte,
+            self.sell_price_net_of_soulte,
+            self.net_sell_price,
+            self.total_acquisition_price,
+            self.previous_fractions,
+            self.previous_soultes,
+            self.net_acquisition_price,
+            self.gains,
+        ]
+    }
+}
+
+/* Amounts are written in euro with two decimals in every rendering of the form */
+pub fn format_amount(value: Decimal) -> String {
+    format!("{:.2}", value.round_dp(2))
+}
+
+pub fn format_date(date: &DateTime<Utc>) -> String {
+    date.with_timezone(&Paris).format("%d/%m/%Y").to_string()
+}
+
+const LABEL_WIDTH: usize = 62;
+const COLUMN_WIDTH: usize = 16;
+
+/* Plain-text rendering following the layout of the official form: one column per cession, 5 cessions per page */
+impl fmt::Display for Form2086 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Formulaire 2086 - Année {}", self.year)?;
+        let pages = self.pages();
+        for (index, page) in pages.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(f, "Page {}/{}", index + 1, pages.len())?;
+            write!(f, "{:<LABEL_WIDTH$}", "")?;
+            for (column, _) in page.iter().enumerate() {
+                let header = format!("Cession {}", index * CESSIONS_PER_PAGE + column + 1);
+                write!(f, "{header:>COLUMN_WIDTH$}")?;
+            }
+            writeln!(f)?;
+
+            write!(f, "{:<LABEL_WIDTH$}", "211 Date de la cession")?;
+            for cession in page.iter() {
+                write!(f, "{:>COLUMN_WIDTH$}", format_date(&cession.date))?;
+            }
+            writeln!(f)?;
+
+            for (line, (number, label)) in AMOUNT_LINES.iter().enumerate() {
+                write!(f, "{:<LABEL_WIDTH$}", format!("{number} {label}"))?;
+                for cession in page.iter() {
+                    write!(f, "{:>COLUMN_WIDTH$}", format_amount(cession.amounts()[line]))?;
+                }
+                writeln!(f)?;
+            }
+        }
+        writeln!(f)?;
+        if self.is_exempt {
+            writeln!(
+                f,
+                "Total des prix de cession ({}) inférieur ou égal à {EXEMPTION_THRESHOLD}€: exonération (article 150 VH bis)",
+                format_amount(self.total_sell_price)
+            )?;
+        }
+        write!(f, "Plus-value ou moins-value globale: {}", format_amount(self.total_gains))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use hashbrown::HashMap;
+
+    use crate::structs::{
+        GlobalCostBasis, Persistable, Portfolio, TradeType, Transaction, TransactionBase,
+        WalletSnapshot,
+    };
+
+    use super::*;
+
+    fn sell_btc(id: &str, timestamp: DateTime<Utc>, sold_amount: Decimal, price_eur: Decimal) -> Transaction {
+        Transaction::Trade {
+            tx: TransactionBase {
+                id: id.to_string(),
+                timestamp,
+            },
+            from: WalletSnapshot {
+                id: "btc".to_string(),
+                pre_tx_balance: dec!(3),
+                fee: None,
+                price_eur,
+            },
+            to: WalletSnapshot {
+                id: "eur".to_string(),
+                pre_tx_balance: dec!(0),
+                fee: None,
+                price_eur: dec!(1),
+            },
+            exchange_pair: None,
+            sold_amount,
+            bought_amount: sold_amount * price_eur,
+            trade_type: TradeType::CryptoToFiat,
+            soulte: None,
+        }
+    }
+
+    fn portfolio(tx_id: &str, pf_total_value: Decimal) -> Portfolio {
+        Portfolio {
+            tx_id: tx_id.to_string(),
+            wallet_snaps: HashMap::new(),
+            is_taxable: true,
+            pf_total_value,
+            is_pf_total_calculated: true,
+        }
+    }
+
+    #[test]
+    fn two_cessions_in_year() {
+        let mut transactions_manager = TransactionManager::new_non_persistent().unwrap();
+        let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+        let mut cost_basis_manager = GlobalCostBasisManager::new_non_persistent().unwrap();
+
+        transactions_manager.extend(vec![
+            sell_btc("test", Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap(), dec!(1.125), dec!(400)),
+            sell_btc("test2", Utc.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap(), dec!(1.875), dec!(693.33333333333333333333333333)),
+            sell_btc("test3", Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(), dec!(0), dec!(1)),
+        ]);
+        portfolio_manager.portfolio_history.insert("test".to_string(), portfolio("test", dec!(1200)));
+        portfolio_manager.portfolio_history.insert("test2".to_string(), portfolio("test2", dec!(1300)));
+        cost_basis_manager.global_cost_basis_history.insert(
+            "test".to_string(),
+            GlobalCostBasis { pf_total_cost: dec!(1000), pf_cost_basis: dec!(1000), ..GlobalCostBasis::new() },
+        );
+        cost_basis_manager.global_cost_basis_history.insert(
+            "test2".to_string(),
+            GlobalCostBasis {
+                pf_total_cost: dec!(1000),
+                pf_cost_basis: dec!(625),
+                pf_consumed_fractions: dec!(375),
+                pf_soultes: dec!(0),
+            },
+        );
+
+        let form = generate_form_2086(2023, &transactions_manager, &portfolio_manager, &cost_basis_manager).unwrap();
+
+        assert_eq!(form.cessions.len(), 2);
+        assert_eq!(form.cessions[0].sell_price, dec!(450));
+        assert_eq!(form.cessions[0].gains, dec!(75));
+        assert_eq!(form.cessions[1].total_acquisition_price, dec!(1000));
+        assert_eq!(form.cessions[1].previous_fractions, dec!(375));
+        assert_eq!(form.cessions[1].net_acquisition_price, dec!(625));
+        assert_eq!(form.cessions[1].gains.round_dp(10), dec!(675));
+        assert_eq!(form.total_gains.round_dp(10), dec!(750));
+        assert_eq!(form.total_sell_price.round_dp(10), dec!(1750));
+        assert!(!form.is_exempt);
+
+        let json = form.to_json().unwrap();
+        let deserialized: Form2086 = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, form);
+
+        let text = form.to_string();
+        assert!(text.contains("211 Date de la cession"));
+        assert!(text.contains("01/06/2023"));
+        assert!(text.contains("Plus-value ou moins-value globale: 750.00"));
+    }
+
+    #[test]
+    fn missing_cost_basis() {
+        let mut transactions_manager = TransactionManager::new_non_persistent().unwrap();
+        let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+        let cost_basis_manager = GlobalCostBasisManager::new_non_persistent().unwrap();
+
+        transactions_manager.push(sell_btc("test", Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap(), dec!(1), dec!(400)));
+        portfolio_manager.portfolio_history.insert("test".to_string(), portfolio("test", dec!(1200)));
+
+        let result = generate_form_2086(2023, &transactions_manager, &portfolio_manager, &cost_basis_manager);
+        assert!(matches!(result, Err(TaxReportError::MissingCostBasis { .. })));
+    }
+
+    #[test]
+    fn zero_portfolio_value() {
+        let mut transactions_manager = TransactionManager::new_non_persistent().unwrap();
+        let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+        let mut cost_basis_manager = GlobalCostBasisManager::new_non_persistent().unwrap();
+
+        transactions_manager.push(sell_btc("test", Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap(), dec!(1), dec!(400)));
+        portfolio_manager.portfolio_history.insert("test".to_string(), portfolio("test", dec!(0)));
+        cost_basis_manager.global_cost_basis_history.insert("test".to_string(), GlobalCostBasis::new());
+
+        let result = generate_form_2086(2023, &transactions_manager, &portfolio_manager, &cost_basis_manager);
+        assert!(matches!(result, Err(TaxReportError::ZeroPortfolioValue { .. })));
+    }
+
+    #[test]
+    fn cession_with_soulte_at_new_year() {
+        let mut transactions_manager = TransactionManager::new_non_persistent().unwrap();
+        let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+        let mut cost_basis_manager = GlobalCostBasisManager::new_non_persistent().unwrap();
+
+        // 31/12/2022 at 23:30 UTC is already 2023 in Paris
+        let mut cession = sell_btc("test", Utc.with_ymd_and_hms(2022, 12, 31, 23, 30, 0).unwrap(), dec!(1.5), dec!(400));
+        if let Transaction::Trade { soulte, .. } = &mut cession {
+            *soulte = Some(dec!(100));
+        }
+        transactions_manager.push(cession);
+        portfolio_manager.portfolio_history.insert("test".to_string(), portfolio("test", dec!(1000)));
+        cost_basis_manager.global_cost_basis_history.insert(
+            "test".to_string(),
+            GlobalCostBasis {
+                pf_total_cost: dec!(1000),
+                pf_cost_basis: dec!(800),
+                pf_consumed_fractions: dec!(0),
+                pf_soultes: dec!(200),
+            },
+        );
+
+        let form_2022 = generate_form_2086(2022, &transactions_manager, &portfolio_manager, &cost_basis_manager).unwrap();
+        assert!(form_2022.cessions.is_empty());
+
+        let form = generate_form_2086(2023, &transactions_manager, &portfolio_manager, &cost_basis_manager).unwrap();
+        assert_eq!(form.cessions.len(), 1);
+        let cession = &form.cessions[0];
+        assert_eq!(cession.soulte, dec!(100));
+        assert_eq!(cession.sell_price_net_of_soulte, dec!(500));
+        assert_eq!(cession.net_sell_price, dec!(500));
+        assert_eq!(cession.previous_soultes, dec!(200));
+        assert_eq!(cession.net_acquisition_price, dec!(800));
+        assert_eq!(cession.gains, dec!(100));
+        assert_eq!(form.total_sell_price, dec!(600));
+        assert!(form.to_string().contains("01/01/2023"));
+    }
+}
@@ -0,0 +1,267 @@
+use std::fs;
+
+use crate::{
+    errors::IoError,
+    utils::create_directories_if_needed,
+};
+
+use super::{
+    format_amount, format_date, Cession2086, Form2086, AMOUNT_LINES, CESSIONS_PER_PAGE,
+    EXEMPTION_THRESHOLD,
+};
+
+/* Render the form 2086 as a PDF mirroring the layout of the official Cerfa annexe:
+one column per cession, 5 cessions per page and continuation pages ("suite") when there are more.
+
+The PDF is written by hand (no dependency) with the standard Helvetica fonts, so it can be generated offline.
+The output is deterministic (no creation date, no random id), which allows comparing it with a golden file.
+*/
+
+// A4 landscape, in points
+const PAGE_WIDTH: f32 = 842.0;
+const PAGE_HEIGHT: f32 = 595.0;
+const MARGIN: f32 = 30.0;
+const LABEL_COLUMN_WIDTH: f32 = 332.0;
+const CESSION_COLUMN_WIDTH: f32 = (PAGE_WIDTH - 2.0 * MARGIN - LABEL_COLUMN_WIDTH) / CESSIONS_PER_PAGE as f32;
+const ROW_HEIGHT: f32 = 24.0;
+const TABLE_TOP: f32 = 505.0;
+const FONT_SIZE: f32 = 8.0;
+const PADDING: f32 = 4.0;
+
+pub fn render_form_2086_pdf(form: &Form2086) -> Vec<u8> {
+    let mut pages = form.pages();
+    if pages.is_empty() {
+        // We still want an empty form for a year without cessions
+        pages.push(&[]);
+    }
+    let contents: Vec<String> = pages
+        .iter()
+        .enumerate()
+        .map(|(index, page)| page_content(form, page, index, pages.len()))
+        .collect();
+
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    let kids: Vec<String> = (0..contents.len()).map(|i| format!("{} 0 R", 5 + 2 * i)).collect();
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), kids.len()).into_bytes());
+    objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec());
+    objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec());
+    for (i, content) in contents.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                6 + 2 * i
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(content.as_bytes());
+        stream.extend_from_slice(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf: Vec<u8> = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n", objects.len() + 1).as_bytes(),
+    );
+    pdf
+}
+
+pub fn save_form_2086_pdf(form: &Form2086, path: &str) -> Result<(), IoError> {
+    create_directories_if_needed(path);
+    fs::write(path, render_form_2086_pdf(form)).map_err(|e| IoError::new(e.to_string()))
+}
+
+/* The content stream (drawing instructions) of one page */
+fn page_content(form: &Form2086, page: &[Cession2086], index: usize, page_count: usize) -> String {
+    let mut content = String::new();
+    let title = format!(
+        "Formulaire 2086 - Déclaration des plus ou moins-values réalisées sur actifs numériques - Année {}",
+        form.year
+    );
+    text(&mut content, "F2", 11.0, MARGIN, PAGE_HEIGHT - MARGIN - 11.0, &title);
+    let subtitle = if index == 0 {
+        format!("Page {}/{}", index + 1, page_count)
+    } else {
+        format!("Page {}/{} - Suite (annexe)", index + 1, page_count)
+    };
+    text(&mut content, "F1", FONT_SIZE, MARGIN, PAGE_HEIGHT - MARGIN - 28.0, &subtitle);
+
+    let mut rows: Vec<(String, Vec<String>)> = Vec::new();
+    rows.push((
+        String::from("Détermination de la plus ou moins-value"),
+        (0..CESSIONS_PER_PAGE)
+            .map(|column| format!("Cession {}", index * CESSIONS_PER_PAGE + column + 1))
+            .collect(),
+    ));
+    rows.push((
+        String::from("211 Date de la cession"),
+        page.iter().map(|cession| format_date(&cession.date)).collect(),
+    ));
+    for (line, (number, label)) in AMOUNT_LINES.iter().enumerate() {
+        let values = page.iter().map(|cession| format_amount(cession.amounts()[line])).collect();
+        rows.push((format!("{number} {label}"), values));
+    }
+
+    content.push_str("0.5 w\n");
+    for (row, (label, values)) in rows.iter().enumerate() {
+        let top = TABLE_TOP - row as f32 * ROW_HEIGHT;
+        let bottom = top - ROW_HEIGHT;
+        let baseline = bottom + (ROW_HEIGHT - FONT_SIZE) / 2.0 + 1.0;
+        let font = if row == 0 { "F2" } else { "F1" };
+
+        rectangle(&mut content, MARGIN, bottom, LABEL_COLUMN_WIDTH, ROW_HEIGHT);
+        text(&mut content, font, FONT_SIZE, MARGIN + PADDING, baseline, label);
+        for column in 0..CESSIONS_PER_PAGE {
+            let left = MARGIN + LABEL_COLUMN_WIDTH + column as f32 * CESSION_COLUMN_WIDTH;
+            rectangle(&mut content, left, bottom, CESSION_COLUMN_WIDTH, ROW_HEIGHT);
+            if let Some(value) = values.get(column) {
+                // Right aligned as on the official form
+                let x = left + CESSION_COLUMN_WIDTH - PADDING - text_width(value, FONT_SIZE);
+                text(&mut content, font, FONT_SIZE, x, baseline, value);
+            }
+        }
+    }
+
+    if index + 1 == page_count {
+        let bottom = TABLE_TOP - rows.len() as f32 * ROW_HEIGHT - 2.0 * ROW_HEIGHT;
+        let total = format!(
+            "Plus-value ou moins-value globale (à reporter case 3AN ou 3BN de la déclaration 2042-C) : {} €",
+            format_amount(form.total_gains)
+        );
+        text(&mut content, "F2", 10.0, MARGIN, bottom, &total);
+        if form.is_exempt {
+            let exemption = format!(
+                "Total des prix de cession ({} €) inférieur ou égal à {EXEMPTION_THRESHOLD} € : exonération (article 150 VH bis du CGI)",
+                format_amount(form.total_sell_price)
+            );
+            text(&mut content, "F1", 10.0, MARGIN, bottom - 16.0, &exemption);
+        }
+    }
+    content
+}
+
+fn rectangle(content: &mut String, x: f32, y: f32, width: f32, height: f32) {
+    content.push_str(&format!("{x:.2} {y:.2} {width:.2} {height:.2} re S\n"));
+}
+
+fn text(content: &mut String, font: &str, size: f32, x: f32, y: f32, value: &str) {
+    content.push_str(&format!("BT /{font} {size} Tf {x:.2} {y:.2} Td ({}) Tj ET\n", escape(value)));
+}
+
+/* Text in the content stream is encoded in WinAnsi, which match latin-1 for the french characters */
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '€' => escaped.push_str("\\200"),
+            c if c.is_ascii() => escaped.push(c),
+            c if (c as u32) < 256 => escaped.push_str(&format!("\\{:03o}", c as u32)),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/* Approximation of the Helvetica width, only used for the right alignment of amounts and dates */
+fn text_width(value: &str, size: f32) -> f32 {
+    let units: u32 = value
+        .chars()
+        .map(|c| match c {
+            '.' | ',' | '/' | ' ' => 278,
+            '-' => 333,
+            _ => 556,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const GOLDEN_FILE: &str = "src/tests/fixtures/form_2086_golden.pdf";
+
+    fn cession(index: u32) -> Cession2086 {
+        let sell_price = Decimal::from(100 * index);
+        Cession2086 {
+            tx_id: format!("tx{index}"),
+            date: Utc.with_ymd_and_hms(2023, index, 10, 12, 0, 0).unwrap(),
+            pf_total_value: dec!(10000),
+            sell_price,
+            fee: dec!(1.5),
+            sell_price_net_of_fee: sell_price - dec!(1.5),
+            soulte: dec!(0),
+            sell_price_net_of_soulte: sell_price,
+            net_sell_price: sell_price - dec!(1.5),
+            total_acquisition_price: dec!(5000),
+            previous_fractions: dec!(0),
+            previous_soultes: dec!(0),
+            net_acquisition_price: dec!(5000),
+            gains: sell_price / dec!(2) - dec!(1.5),
+        }
+    }
+
+    #[test]
+    fn golden_file() {
+        let cessions: Vec<Cession2086> = (1..=6).map(cession).collect();
+        let total_gains = cessions.iter().map(|cession| cession.gains).sum();
+        let total_sell_price = cessions.iter().map(|cession| cession.sell_price).sum();
+        let form = Form2086 {
+            year: 2023,
+            cessions,
+            total_gains,
+            total_sell_price,
+            is_exempt: false,
+        };
+
+        let pdf = render_form_2086_pdf(&form);
+        // Set UPDATE_GOLDEN=1 to regenerate the file after an intended change of the layout
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            fs::write(GOLDEN_FILE, &pdf).unwrap();
+        }
+        let golden = fs::read(GOLDEN_FILE).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4"));
+        assert!(pdf.windows(9).any(|window| window == b"/Count 2 "));
+        assert_eq!(pdf, golden);
+    }
+
+    #[test]
+    fn empty_year() {
+        let form = Form2086 {
+            year: 2023,
+            cessions: Vec::new(),
+            total_gains: dec!(0),
+            total_sell_price: dec!(0),
+            is_exempt: true,
+        };
+        let pdf = render_form_2086_pdf(&form);
+        assert!(pdf.windows(9).any(|window| window == b"/Count 1 "));
+        assert!(pdf.windows(14).any(|window| window == b"exon\\351ration"));
+    }
+
+    #[test]
+    fn escape_text() {
+        assert_eq!(escape("Année (2023) €"), "Ann\\351e \\(2023\\) \\200");
+    }
+}
@@ -1,6 +1,17 @@
 pub mod pfu;
 pub use pfu::*;
 
+pub mod yearly_cessions;
+pub use yearly_cessions::*;
+
+pub mod form_2086;
+pub use form_2086::*;
+
+pub mod form_2086_pdf;
+pub use form_2086_pdf::*;
+
+pub mod form_2042c;
+pub use form_2042c::*;
 
 pub mod check_missing_trades;
 pub use check_missing_trades::*;
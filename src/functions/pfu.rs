@@ -4,11 +4,22 @@ use rust_decimal_macros::dec;
 
 /* Calculate the french "plus-value" to fill the form 2086
 
-plus_value =  prix_cession - (acquisition_pf_net * prix_cession / valeur_pf )
+plus_value =  prix_cession - frais - soulte - (acquisition_pf_net * (prix_cession - soulte) / valeur_pf )
 
 The transaction must be taxable, otherwise it will panic !
 */
 pub fn calculate_tax_gains(tx: &Transaction, portfolio: &Portfolio, cost_basis: &GlobalCostBasis) -> Decimal {
+    match get_sell_price_and_fee(tx) {
+        Some((sell_price, fee)) => {
+            let pf_total_value = portfolio.pf_total_value;
+            _calculate_tax(sell_price, fee, tx.get_soulte(), cost_basis, pf_total_value)
+        }
+        None => dec!(0),
+    }
+}
+
+/* Get the "prix de cession" (line 213) and the "frais de cession" (line 214) of a Trade or a Transfer, in euro */
+pub fn get_sell_price_and_fee(tx: &Transaction) -> Option<(Decimal, Decimal)> {
     match tx {
         Transaction::Transfer {
             amount,
@@ -20,22 +31,22 @@ pub fn calculate_tax_gains(tx: &Transaction, portfolio: &Portfolio, cost_basis:
             to, from,
             ..
         } => {
-            let pf_total_value = portfolio.pf_total_value;
-            let sell_price: Decimal = Decimal::from(*amount) * from.price_eur;
+            let sell_price: Decimal = *amount * from.price_eur;
             let fee = to.fee.unwrap_or(dec!(0)) * to.price_eur + from.fee.unwrap_or(dec!(0)) * from.price_eur;
-            return _calculate_tax(sell_price, fee,cost_basis, pf_total_value);
+            Some((sell_price, fee))
         }
-        _ => dec!(0),
+        _ => None,
     }
 }
 
 pub fn _calculate_tax(
     sell_price: Decimal,
     total_fee: Decimal,
+    soulte: Decimal,
     cost_basis: &GlobalCostBasis,
     pf_total_value: Decimal,
 ) -> Decimal {
-    return (sell_price - total_fee) - calculate_weigted_price(sell_price, cost_basis.pf_cost_basis, pf_total_value);
+    return (sell_price - total_fee - soulte) - calculate_weigted_price(sell_price - soulte, cost_basis.pf_cost_basis, pf_total_value);
 }
 
 /* (acquisition_pf_net * prix_cession / valeur_pf ) */
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Datelike, Utc};
+use chrono_tz::Europe::Paris;
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+use crate::structs::{Transaction, TransactionId};
+
+use super::get_sell_price_and_fee;
+
+/* Article 150 VH bis: the gains are exempted when the total of the "prix de cession" of the year is below or equal to 305€ */
+pub const EXEMPTION_THRESHOLD: Decimal = dec!(305);
+
+/* The tax year of a transaction: the calendar year in France (Europe/Paris), not in UTC */
+pub fn tax_year(timestamp: &DateTime<Utc>) -> i32 {
+    timestamp.with_timezone(&Paris).year()
+}
+
+/* Aggregation of the taxable cessions (Trade or Transfer) of a year.
+
+The exemption only concerns the taxation of the year: the cessions still consume a fraction of the portfolio
+acquisition price, so the GlobalCostBasis must be updated the same way for the following years (see GlobalCostBasisManager).
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YearlyCessions {
+    pub year: i32,
+    pub tx_ids: Vec<TransactionId>,
+    pub total_sell_price: Decimal, // Sum of sold_amount * from.price_eur
+    pub is_exempt: bool,
+}
+
+impl YearlyCessions {
+    pub fn new(year: i32) -> Self {
+        Self {
+            year,
+            tx_ids: Vec::new(),
+            total_sell_price: dec!(0),
+            is_exempt: true,
+        }
+    }
+
+    fn add(&mut self, tx: &Transaction) {
+        if let Some((sell_price, _fee)) = get_sell_price_and_fee(tx) {
+            self.tx_ids.push(tx.get_id().clone());
+            self.total_sell_price += sell_price;
+            self.is_exempt = self.total_sell_price <= EXEMPTION_THRESHOLD;
+        }
+    }
+}
+
+/* Aggregate the taxable cessions of every year */
+pub fn calculate_yearly_cessions(txs: &[Transaction]) -> HashMap<i32, YearlyCessions> {
+    let mut years: HashMap<i32, YearlyCessions> = HashMap::new();
+    for tx in txs.iter().filter(|tx| tx.is_taxable()) {
+        let year = tax_year(&tx.get_tx_base().timestamp);
+        years.entry(year).or_insert_with(|| YearlyCessions::new(year)).add(tx);
+    }
+    years
+}
+
+/* Aggregate the taxable cessions of a specific year: a year without cession is exempted */
+pub fn calculate_yearly_cession(year: i32, txs: &[Transaction]) -> YearlyCessions {
+    calculate_yearly_cessions(txs)
+        .remove(&year)
+        .unwrap_or_else(|| YearlyCessions::new(year))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use crate::structs::{TradeType, TransactionBase, WalletSnapshot};
+
+    use super::*;
+
+    fn trade(id: &str, timestamp: DateTime<Utc>, sold_amount: Decimal, trade_type: TradeType) -> Transaction {
+        Transaction::Trade {
+            tx: TransactionBase {
+                id: id.to_string(),
+                timestamp,
+            },
+            from: WalletSnapshot {
+                id: "btc".to_string(),
+                pre_tx_balance: dec!(10),
+                fee: None,
+                price_eur: dec!(100),
+            },
+            to: WalletSnapshot {
+                id: "eur".to_string(),
+                pre_tx_balance: dec!(0),
+                fee: None,
+                price_eur: dec!(1),
+            },
+            exchange_pair: None,
+            sold_amount,
+            bought_amount: sold_amount * dec!(100),
+            trade_type,
+            soulte: None,
+        }
+    }
+
+    #[test]
+    fn threshold_per_year() {
+        let txs = vec![
+            trade("a", Utc.with_ymd_and_hms(2022, 5, 1, 0, 0, 0).unwrap(), dec!(2), TradeType::CryptoToFiat),
+            trade("b", Utc.with_ymd_and_hms(2022, 8, 1, 0, 0, 0).unwrap(), dec!(1.05), TradeType::CryptoToFiat),
+            trade("c", Utc.with_ymd_and_hms(2023, 5, 1, 0, 0, 0).unwrap(), dec!(3), TradeType::CryptoToFiat),
+            trade("d", Utc.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap(), dec!(5), TradeType::CryptoToCrypto),
+        ];
+
+        let years = calculate_yearly_cessions(&txs);
+        let year_2022 = years.get(&2022).unwrap();
+        assert_eq!(year_2022.total_sell_price, dec!(305));
+        assert!(year_2022.is_exempt);
+        assert_eq!(year_2022.tx_ids, vec!["a".to_string(), "b".to_string()]);
+
+        let year_2023 = calculate_yearly_cession(2023, &txs);
+        assert_eq!(year_2023.total_sell_price, dec!(300));
+        assert!(year_2023.is_exempt);
+
+        let mut txs = txs;
+        txs.push(trade("e", Utc.with_ymd_and_hms(2023, 9, 1, 0, 0, 0).unwrap(), dec!(0.06), TradeType::CryptoToFiat));
+        let year_2023 = calculate_yearly_cession(2023, &txs);
+        assert_eq!(year_2023.total_sell_price, dec!(306));
+        assert!(!year_2023.is_exempt);
+
+        assert_eq!(calculate_yearly_cession(2021, &txs), YearlyCessions::new(2021));
+    }
+}
@@ -5,9 +5,14 @@ pub mod parsing;
 pub mod structs;
 pub mod tests;
 pub mod utils;
-use api::handle_kraken_data;
+use api::default_connectors;
+use chrono::{Datelike, Utc};
 use dotenv::dotenv;
-use functions::calculate_tax_gains;
+use functions::{calculate_form_2042c, calculate_tax_gains, generate_form_2086, save_form_2086_pdf};
+use parsing::export_standard_format_data;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::{env, str::FromStr};
 use structs::{global_cost_basis_manager::GlobalCostBasisManager, Persistable, TransactionManager, WalletManager};
 
 use crate::structs::PortfolioManager;
@@ -19,17 +24,25 @@ fn main() {
     let mut transactions_manager = TransactionManager::new().unwrap();
     let mut portfolio_manager = PortfolioManager::new().unwrap();
     let mut global_cost_basis_manager = GlobalCostBasisManager::new().unwrap();
+    // The imports and the prices call the APIs
+    let runtime = tokio::runtime::Runtime::new().unwrap();
 
-    let kraken_txs = handle_kraken_data(&mut wallet_manager).unwrap();
-    transactions_manager.extend_update(kraken_txs);
+    // The connectors import the platforms configured in the .env file and give the prices of their wallets
+    let connectors = default_connectors().unwrap();
+    for connector in connectors.configured() {
+        let connector_txs = runtime.block_on(connector.fetch_transactions(&mut wallet_manager)).unwrap();
+        transactions_manager.extend_update(connector_txs);
+    }
 
     transactions_manager.sort();
+    export_standard_format_data(&transactions_manager, &wallet_manager).unwrap();
 
-    portfolio_manager
-        .calculate_portfolio_history(
+    runtime
+        .block_on(portfolio_manager.calculate_portfolio_history(
             transactions_manager.get(),
             &wallet_manager.wallets,
-        )
+            &connectors,
+        ))
         .unwrap();
 
     global_cost_basis_manager.calculate_full_cost_basis(transactions_manager.get(),&portfolio_manager.portfolio_history);
@@ -43,4 +56,18 @@ fn main() {
             println!("tax: {tax}");
         }
     }
+
+    // The declaration is made for the previous year
+    let year = Utc::now().year() - 1;
+    let form_2086 = generate_form_2086(year, &transactions_manager, &portfolio_manager, &global_cost_basis_manager).unwrap();
+    println!("{form_2086}");
+    save_form_2086_pdf(&form_2086, &format!(".data/form_2086_{year}.pdf")).unwrap();
+
+    // Taux marginal d'imposition, only used to compare the PFU with the option for the barème progressif
+    let marginal_rate = env::var("MARGINAL_TAX_RATE")
+        .ok()
+        .and_then(|rate| Decimal::from_str(&rate).ok())
+        .unwrap_or(dec!(0.30));
+    let form_2042c = calculate_form_2042c(&form_2086, marginal_rate);
+    println!("{form_2042c}");
 }
@@ -0,0 +1,388 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{BinanceClient, ExchangeMapper, FiatCurrency},
+    errors::{ApiError, MappingError},
+    structs::{wallet_manager::WalletManager, IncomeType, Transaction, TransactionBase},
+};
+
+/* Row of the Transaction History exported from Binance (Orders > Transaction History > Export):
+User_ID,UTC_Time,Account,Operation,Coin,Change,Remark
+Change is negative for the funds leaving the account. A trade is split in several rows (Buy, Sell, Fee, Transaction
+Related, or Transaction Buy / Spend / Fee on the recent exports) sharing the same time */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinanceCsvRow {
+    #[serde(rename = "UTC_Time")]
+    pub utc_time: String,
+    #[serde(rename = "Account")]
+    pub account: String,
+    #[serde(rename = "Operation")]
+    pub operation: String,
+    #[serde(rename = "Coin")]
+    pub coin: String,
+    #[serde(rename = "Change")]
+    pub change: Decimal,
+}
+
+impl BinanceCsvRow {
+    /* The time is in UTC, as YYYY-MM-DD HH:MM:SS or YY-MM-DD HH:MM:SS on the older exports */
+    pub fn timestamp(&self) -> Result<DateTime<Utc>, ApiError> {
+        let format = match self.utc_time.split('-').next() {
+            Some(year) if year.len() == 2 => "%y-%m-%d %H:%M:%S",
+            _ => "%Y-%m-%d %H:%M:%S",
+        };
+        NaiveDateTime::parse_from_str(&self.utc_time, format)
+            .map(|date| date.and_utc())
+            .map_err(|e| ApiError::DeserializationError(format!("{}: {e}", self.utc_time)))
+    }
+}
+
+pub fn read_binance_csv(file_path: &str) -> Result<Vec<BinanceCsvRow>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_path)
+        .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+    let mut rows = Vec::new();
+    for record in reader.deserialize::<BinanceCsvRow>() {
+        rows.push(record.map_err(|e| ApiError::DeserializationError(e.to_string()))?);
+    }
+    Ok(rows)
+}
+
+/* Operations that are incomes */
+pub fn binance_csv_income_type(operation: &str) -> Option<IncomeType> {
+    match operation {
+        "Simple Earn Flexible Interest" | "Savings Interest" => Some(IncomeType::Interest),
+        "Simple Earn Locked Rewards"
+        | "POS savings interest"
+        | "ETH 2.0 Staking Rewards"
+        | "Launchpool Interest"
+        | "Launchpool Airdrop" => Some(IncomeType::Staking),
+        "Distribution" | "Airdrop Assets" => Some(IncomeType::Airdrop),
+        "Commission History" | "Referral Kickback" | "Commission Rebate" => {
+            Some(IncomeType::Income)
+        }
+        _ => None,
+    }
+}
+
+/* Operations of the rows making a trade, grouped by time and account */
+fn is_binance_csv_trade(operation: &str) -> bool {
+    matches!(
+        operation,
+        "Buy"
+            | "Sell"
+            | "Fee"
+            | "Transaction Related"
+            | "Transaction Buy"
+            | "Transaction Spend"
+            | "Transaction Sold"
+            | "Transaction Revenue"
+            | "Transaction Fee"
+            | "Binance Convert"
+    )
+}
+
+fn is_binance_csv_fee(operation: &str) -> bool {
+    matches!(operation, "Fee" | "Transaction Fee")
+}
+
+fn is_binance_csv_dust(operation: &str) -> bool {
+    operation.eq_ignore_ascii_case("Small assets exchange BNB")
+}
+
+/* Map the rows of a Binance Transaction History to transactions.
+
+All the accounts (Spot, Funding, Earn, ...) share the Binance wallet of each coin, so the moves between them
+(subscriptions and redemptions of Simple Earn, transfers between accounts) are ignored, as the other operations.
+- the trade rows sharing a time and an account are the trades of this second (see BinanceCsvTrade), the fee being
+  held by the WalletSnapshot of its coin, or converted to a fee of the sold coin when it is paid in a third coin (see
+  ExchangeMapper::third_currency_fee)
+- the Small assets exchange BNB rows sharing a time are one conversion trade per small asset, the BNB received being
+  split according to the value of each asset
+- Simple Earn, Launchpool, Distribution and the commissions are incomes (see binance_csv_income_type)
+- Deposit and Withdraw are Deposits and Withdrawals for the fiat, Transfers from or to outside otherwise
+As for the API, the balances are recalculated from zero (see ExchangeMapper).
+*/
+pub async fn create_binance_csv_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    rows: &[BinanceCsvRow],
+    client: &BinanceClient,
+) -> Result<(), ApiError> {
+    let mut events: Vec<(DateTime<Utc>, BinanceCsvEvent)> = Vec::new();
+    let mut groups: HashMap<(DateTime<Utc>, &str, bool), usize> = HashMap::new();
+    for row in rows {
+        let time = row.timestamp()?;
+        let (is_trade, is_dust) = (
+            is_binance_csv_trade(&row.operation),
+            is_binance_csv_dust(&row.operation),
+        );
+        if !is_trade && !is_dust {
+            events.push((time, BinanceCsvEvent::Row(row)));
+            continue;
+        }
+        let key = (time, row.account.as_str(), is_dust);
+        match groups.get(&key) {
+            Some(index) => match &mut events[*index].1 {
+                BinanceCsvEvent::Trade(group) | BinanceCsvEvent::Dust(group) => group.push(row),
+                BinanceCsvEvent::Row(_) => {}
+            },
+            None => {
+                groups.insert(key, events.len());
+                let event = if is_dust {
+                    BinanceCsvEvent::Dust(vec![row])
+                } else {
+                    BinanceCsvEvent::Trade(vec![row])
+                };
+                events.push((time, event));
+            }
+        }
+    }
+    // The exports are from the most recent on some versions
+    events.sort_by_key(|(time, _)| *time);
+
+    let mut mapper = BinanceCsvMapper::new(client, wallet_manager);
+    for (time, event) in events {
+        match event {
+            BinanceCsvEvent::Trade(group) => mapper.map_csv_trades(txs, &group, time).await?,
+            BinanceCsvEvent::Dust(group) => mapper.map_csv_dust(txs, &group, time).await?,
+            BinanceCsvEvent::Row(row) => mapper.map_csv_row(txs, row, time).await?,
+        }
+    }
+    Ok(())
+}
+
+enum BinanceCsvEvent<'a> {
+    Trade(Vec<&'a BinanceCsvRow>),
+    Dust(Vec<&'a BinanceCsvRow>),
+    Row(&'a BinanceCsvRow),
+}
+
+/* Id of the transaction of a row, the export having none: binance-csv-simple-earn-flexible-interest-BTC-1675209600 */
+fn binance_csv_id(operation: &str, coin: &str, time: DateTime<Utc>) -> String {
+    let operation = operation.to_lowercase().replace(' ', "-");
+    format!("binance-csv-{operation}-{coin}-{}", time.timestamp())
+}
+
+/* A trade of the export: (coin, amount) of each side, and the fees by coin.
+The rows of the trades made in the same second are in a single group. They follow each other in the export, so a
+row starts a new trade when its side already has another coin in the current one, the rows of the same coin being
+the fills of the current trade. A fee goes with the nearest trade having its coin, or with the current one when it is
+paid in a third coin */
+#[derive(Default)]
+struct BinanceCsvTrade<'a> {
+    sold: Option<(&'a str, Decimal)>,
+    bought: Option<(&'a str, Decimal)>,
+    fees: Vec<(&'a str, Decimal)>,
+}
+
+impl<'a> BinanceCsvTrade<'a> {
+    fn has_coin(&self, coin: &str) -> bool {
+        [self.sold, self.bought]
+            .iter()
+            .any(|side| matches!(side, Some((side_coin, _)) if *side_coin == coin))
+    }
+
+    fn split(group: &[&'a BinanceCsvRow]) -> Vec<BinanceCsvTrade<'a>> {
+        let mut trades: Vec<BinanceCsvTrade> = vec![BinanceCsvTrade::default()];
+        let mut fees = Vec::new();
+        for row in group {
+            let amount = row.change.abs();
+            if is_binance_csv_fee(&row.operation) {
+                fees.push((trades.len() - 1, row.coin.as_str(), amount));
+                continue;
+            }
+            let current = trades.last_mut().unwrap();
+            let side = if row.change.is_sign_negative() {
+                &mut current.sold
+            } else {
+                &mut current.bought
+            };
+            match side {
+                Some((coin, total)) if *coin == row.coin => *total += amount,
+                None => *side = Some((row.coin.as_str(), amount)),
+                Some(_) => {
+                    let mut trade = BinanceCsvTrade::default();
+                    if row.change.is_sign_negative() {
+                        trade.sold = Some((row.coin.as_str(), amount));
+                    } else {
+                        trade.bought = Some((row.coin.as_str(), amount));
+                    }
+                    trades.push(trade);
+                }
+            }
+        }
+        for (current, coin, fee) in fees {
+            let index = (0..trades.len())
+                .filter(|index| trades[*index].has_coin(coin))
+                .min_by_key(|index| index.abs_diff(current))
+                .unwrap_or(current);
+            trades[index].fees.push((coin, fee));
+        }
+        trades
+    }
+}
+
+type BinanceCsvMapper<'a> = ExchangeMapper<'a, BinanceClient>;
+
+impl BinanceCsvMapper<'_> {
+    async fn map_csv_trades(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        group: &[&BinanceCsvRow],
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        for trade in BinanceCsvTrade::split(group) {
+            self.map_csv_trade(txs, trade, time).await?;
+        }
+        Ok(())
+    }
+
+    async fn map_csv_trade(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        trade: BinanceCsvTrade<'_>,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let (Some((sold_coin, sold_amount)), Some((bought_coin, bought_amount))) =
+            (trade.sold, trade.bought)
+        else {
+            return Err(ApiError::MappingError(MappingError::Other(format!(
+                "Binance trade rows at {time} can't be paired"
+            ))));
+        };
+
+        // When one side is the euro, the trade gives the price of the other
+        if FiatCurrency::is_eur(sold_coin) && !bought_amount.is_zero() {
+            self.set_price(bought_coin, time, sold_amount / bought_amount);
+        } else if FiatCurrency::is_eur(bought_coin) && !sold_amount.is_zero() {
+            self.set_price(sold_coin, time, bought_amount / sold_amount);
+        }
+
+        let mut from_fee = None;
+        let mut to_fee = None;
+        let mut third_coin_fees = Vec::new();
+        for (coin, fee) in trade.fees {
+            if fee.is_zero() {
+                continue;
+            }
+            if coin == sold_coin {
+                *from_fee.get_or_insert(dec!(0)) += fee;
+            } else if coin == bought_coin {
+                *to_fee.get_or_insert(dec!(0)) += fee;
+            } else {
+                third_coin_fees.push((coin, fee));
+            }
+        }
+
+        let mut tx = self
+            .trade(
+                TransactionBase {
+                    id: format!(
+                        "binance-csv-trade-{sold_coin}-{bought_coin}-{}",
+                        time.timestamp()
+                    ),
+                    timestamp: time,
+                },
+                (sold_coin, sold_amount, from_fee),
+                (bought_coin, bought_amount, to_fee),
+                None, // The export doesn't tell which coin is the base of the pair
+            )
+            .await?;
+        for (coin, fee) in third_coin_fees {
+            self.third_currency_fee(&mut tx, coin, fee).await?;
+        }
+        txs.push(tx);
+        Ok(())
+    }
+
+    /* The export only gives the total of BNB received for all the small assets converted at once */
+    async fn map_csv_dust(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        group: &[&BinanceCsvRow],
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let bnb_amount: Decimal = group
+            .iter()
+            .filter(|row| row.change.is_sign_positive())
+            .map(|row| row.change)
+            .sum();
+        let mut assets = Vec::new();
+        for row in group.iter().filter(|row| row.change.is_sign_negative()) {
+            let value = -row.change * self.price(&row.coin, time).await?;
+            assets.push((row.coin.as_str(), -row.change, value));
+        }
+        let total_value: Decimal = assets.iter().map(|(_, _, value)| *value).sum();
+
+        let mut remaining = bnb_amount;
+        let count = assets.len();
+        for (index, (coin, amount, value)) in assets.into_iter().enumerate() {
+            let share = if index + 1 == count {
+                remaining
+            } else if total_value.is_zero() {
+                (bnb_amount / Decimal::from(count)).round_dp(8)
+            } else {
+                (bnb_amount * value / total_value).round_dp(8)
+            };
+            remaining -= share;
+            let tx = self
+                .trade(
+                    TransactionBase {
+                        id: format!("binance-csv-dust-{coin}-{}", time.timestamp()),
+                        timestamp: time,
+                    },
+                    (coin, amount, None),
+                    ("BNB", share, None),
+                    None,
+                )
+                .await?;
+            txs.push(tx);
+        }
+        Ok(())
+    }
+
+    async fn map_csv_row(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &BinanceCsvRow,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let tx = TransactionBase {
+            id: binance_csv_id(&row.operation, &row.coin, time),
+            timestamp: time,
+        };
+        let amount = row.change.abs();
+        let is_fiat = FiatCurrency::is_fiat(&row.coin);
+
+        if let Some(subtype) = binance_csv_income_type(&row.operation) {
+            txs.push(self.income(tx, &row.coin, amount, subtype).await?);
+            return Ok(());
+        }
+
+        // The export doesn't give the addresses, the origin or destination of the crypto is unknown
+        let tx = match row.operation.as_str() {
+            "Deposit" | "Fiat Deposit" if is_fiat => {
+                self.deposit(tx, &row.coin, amount, None).await?
+            }
+            "Deposit" | "Fiat Deposit" => {
+                self.transfer_in(tx, &row.coin, &None, amount, None).await?
+            }
+            "Withdraw" | "Fiat Withdraw" | "Fiat Withdrawal" if is_fiat => {
+                self.withdrawal(tx, &row.coin, amount, None).await?
+            }
+            "Withdraw" | "Fiat Withdraw" | "Fiat Withdrawal" => {
+                self.transfer_out(tx, &row.coin, &None, amount, None)
+                    .await?
+            }
+            _ => return Ok(()),
+        };
+        txs.push(tx);
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    api::{BitfinexLedgerEntry, BitfinexMovement, BitfinexTrade},
+    errors::ApiError,
+};
+
+/* Row of the trades report exported from Bitfinex (Reports > Trades):
+#,PAIR,AMOUNT,PRICE,FEE,FEE PERC,FEE CURRENCY,DATE,ORDER ID
+The AMOUNT is positive for a buy, the FEE is negative, as in the API */
+#[derive(Debug, Deserialize)]
+pub struct BitfinexTradeRecord {
+    #[serde(rename = "#")]
+    pub id: i64,
+    #[serde(rename = "PAIR")]
+    pub pair: String,
+    #[serde(rename = "AMOUNT")]
+    pub amount: Decimal,
+    #[serde(rename = "PRICE")]
+    pub price: Decimal,
+    #[serde(rename = "FEE")]
+    pub fee: Decimal,
+    #[serde(rename = "FEE CURRENCY")]
+    pub fee_currency: String,
+    #[serde(rename = "DATE")]
+    pub date: String,
+    #[serde(rename = "ORDER ID")]
+    pub order_id: i64,
+}
+
+/* Row of the movements report exported from Bitfinex (Reports > Deposits & Withdrawals):
+#,DATE,CURRENCY,STATUS,AMOUNT,FEES,DESTINATION,TRANSACTION ID
+The AMOUNT is negative for a withdrawal, the FEES are negative. The DATE is the last update of the movement */
+#[derive(Debug, Deserialize)]
+pub struct BitfinexMovementRecord {
+    #[serde(rename = "#")]
+    pub id: i64,
+    #[serde(rename = "DATE")]
+    pub date: String,
+    #[serde(rename = "CURRENCY")]
+    pub currency: String,
+    #[serde(rename = "STATUS")]
+    pub status: String,
+    #[serde(rename = "AMOUNT")]
+    pub amount: Decimal,
+    #[serde(rename = "FEES")]
+    pub fees: Decimal,
+    #[serde(rename = "DESTINATION")]
+    pub destination: Option<String>,
+    #[serde(rename = "TRANSACTION ID")]
+    pub transaction_id: Option<String>,
+}
+
+/* Row of the ledgers report exported from Bitfinex (Reports > Ledgers):
+#,DESCRIPTION,CURRENCY,AMOUNT,BALANCE,DATE,WALLET */
+#[derive(Debug, Deserialize)]
+pub struct BitfinexLedgerRecord {
+    #[serde(rename = "#")]
+    pub id: i64,
+    #[serde(rename = "DESCRIPTION")]
+    pub description: String,
+    #[serde(rename = "CURRENCY")]
+    pub currency: String,
+    #[serde(rename = "AMOUNT")]
+    pub amount: Decimal,
+    #[serde(rename = "BALANCE")]
+    pub balance: Decimal,
+    #[serde(rename = "DATE")]
+    pub date: String,
+}
+
+/* The reports are read into the structures of the API, keeping the Bitfinex ids, so they are mapped by
+create_bitfinex_txs to the same transactions and can be merged with the fetched history (see BitfinexHistory::merge) */
+pub fn read_bitfinex_trades_csv(file_path: &str) -> Result<Vec<BitfinexTrade>, ApiError> {
+    read_report::<BitfinexTradeRecord>(file_path)?
+        .into_iter()
+        .map(|record| {
+            Ok(BitfinexTrade {
+                id: record.id,
+                symbol: bitfinex_report_symbol(&record.pair),
+                mts: bitfinex_report_mts(&record.date)?,
+                order_id: record.order_id,
+                exec_amount: record.amount,
+                exec_price: record.price,
+                fee: record.fee,
+                fee_currency: record.fee_currency,
+            })
+        })
+        .collect()
+}
+
+pub fn read_bitfinex_movements_csv(file_path: &str) -> Result<Vec<BitfinexMovement>, ApiError> {
+    read_report::<BitfinexMovementRecord>(file_path)?
+        .into_iter()
+        .map(|record| {
+            let mts = bitfinex_report_mts(&record.date)?;
+            Ok(BitfinexMovement {
+                id: record.id,
+                currency: record.currency,
+                mts_started: mts,
+                mts_updated: mts,
+                status: record.status.to_uppercase(),
+                amount: record.amount,
+                fees: record.fees,
+                destination_address: record.destination.filter(|address| !address.is_empty()),
+                transaction_id: record.transaction_id.filter(|id| !id.is_empty()),
+            })
+        })
+        .collect()
+}
+
+pub fn read_bitfinex_ledgers_csv(file_path: &str) -> Result<Vec<BitfinexLedgerEntry>, ApiError> {
+    read_report::<BitfinexLedgerRecord>(file_path)?
+        .into_iter()
+        .map(|record| {
+            Ok(BitfinexLedgerEntry {
+                id: record.id,
+                currency: record.currency,
+                mts: bitfinex_report_mts(&record.date)?,
+                amount: record.amount,
+                balance: record.balance,
+                description: record.description,
+            })
+        })
+        .collect()
+}
+
+fn read_report<T: DeserializeOwned>(file_path: &str) -> Result<Vec<T>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_path)
+        .map_err(|e| ApiError::DeserializationError(format!("{file_path}: {e}")))?;
+    let mut rows = Vec::new();
+    for record in reader.deserialize::<T>() {
+        rows.push(
+            record.map_err(|e| ApiError::DeserializationError(format!("{file_path}: {e}")))?,
+        );
+    }
+    Ok(rows)
+}
+
+/* The pairs of the reports are BTC/EUR where the API symbols are tBTCEUR (or tTESTBTC:TESTUSD) */
+fn bitfinex_report_symbol(pair: &str) -> String {
+    match pair.split_once('/') {
+        Some((base, quote)) => format!("t{base}:{quote}"),
+        None => format!("t{pair}"),
+    }
+}
+
+/* The dates of the reports are in UTC, as YY-MM-DD HH:MM:SS by default or with the full year */
+fn bitfinex_report_mts(date: &str) -> Result<i64, ApiError> {
+    let format = match date.split('-').next() {
+        Some(year) if year.len() == 2 => "%y-%m-%d %H:%M:%S%.f",
+        _ => "%Y-%m-%d %H:%M:%S%.f",
+    };
+    NaiveDateTime::parse_from_str(date, format)
+        .map(|date| date.and_utc().timestamp_millis())
+        .map_err(|e| ApiError::DeserializationError(format!("{date}: {e}")))
+}
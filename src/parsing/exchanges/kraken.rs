@@ -0,0 +1,194 @@
+use chrono::NaiveDateTime;
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    api::{Deposit, EntryType, HistoryResponse, LedgerHistory, SubType, TradeInfo, Withdrawal},
+    errors::ApiError,
+};
+
+/* Row of the ledgers.csv exported from Kraken (History > Export > Ledgers):
+"txid","refid","time","type","subtype","aclass","asset","wallet","amount","fee","balance"
+Older exports don't have the wallet column, newer ones add a subclass column */
+#[derive(Debug, Deserialize)]
+pub struct KrakenLedgerRecord {
+    pub refid: String,
+    pub time: String,
+    pub r#type: String,
+    pub subtype: Option<String>,
+    pub aclass: String,
+    pub asset: String,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub balance: Decimal,
+}
+
+/* Row of the trades.csv exported from Kraken (History > Export > Trades):
+"txid","ordertxid","pair","time","type","ordertype","price","cost","fee","vol","margin","misc","ledgers"
+The txid of a trade is the refid of its two ledger entries */
+#[derive(Debug, Deserialize)]
+pub struct KrakenTradeRecord {
+    pub txid: String,
+    pub ordertxid: String,
+    pub pair: String,
+    pub time: String,
+    pub r#type: String,
+    pub ordertype: String,
+    pub price: Decimal,
+    pub cost: String,
+    pub fee: Decimal,
+    pub vol: String,
+    pub margin: String,
+    pub misc: Option<String>,
+    #[serde(alias = "posttxid")]
+    pub postxid: Option<String>,
+    pub trade_id: Option<i64>,
+    pub maker: Option<bool>,
+}
+
+/* Read the exports of Kraken into the structures returned by the API (see fetch_history_kraken), so they are mapped
+by create_kraken_txs the same way. The exports don't have the deposits and withdrawals details: they are rebuilt from
+the ledger entries, without the transaction id nor the address */
+pub fn read_kraken_csv(
+    ledgers_path: &str,
+    trades_path: &str,
+) -> Result<HistoryResponse, ApiError> {
+    let mut ledger: Vec<LedgerHistory> = Vec::new();
+    for record in read_csv::<KrakenLedgerRecord>(ledgers_path)? {
+        ledger.push(LedgerHistory {
+            refid: record.refid,
+            time: kraken_csv_time(&record.time)?,
+            r#type: kraken_csv_entry_type(&record.r#type),
+            subtype: kraken_csv_subtype(record.subtype.as_deref().unwrap_or("")),
+            aclass: record.aclass,
+            asset: kraken_csv_asset(&record.asset),
+            amount: record.amount,
+            fee: record.fee,
+            balance: record.balance,
+        });
+    }
+    // Same order as the API, a trade having its two entries next to each other
+    ledger.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let mut trades: HashMap<String, TradeInfo> = HashMap::new();
+    for record in read_csv::<KrakenTradeRecord>(trades_path)? {
+        let trade = TradeInfo {
+            ordertxid: record.ordertxid,
+            postxid: record.postxid.unwrap_or_default(),
+            pair: record.pair,
+            time: kraken_csv_time(&record.time)?,
+            r#type: record.r#type,
+            ordertype: record.ordertype,
+            price: record.price,
+            cost: record.cost,
+            fee: record.fee,
+            vol: record.vol,
+            margin: record.margin,
+            misc: record.misc.unwrap_or_default(),
+            trade_id: record.trade_id.unwrap_or_default(),
+            maker: record.maker.unwrap_or_default(),
+            posstatus: None,
+            cprice: None,
+            ccost: None,
+            cfee: None,
+            cvol: None,
+            cmargin: None,
+            net: None,
+            trades: None,
+        };
+        trades.insert(record.txid, trade);
+    }
+
+    let mut deposits: HashMap<String, Deposit> = HashMap::new();
+    let mut withdrawals: HashMap<String, Withdrawal> = HashMap::new();
+    for entry in &ledger {
+        match entry.r#type {
+            EntryType::Deposit => {
+                deposits.insert(
+                    entry.refid.clone(),
+                    Deposit {
+                        method: String::new(),
+                        network: None,
+                        aclass: entry.aclass.clone(),
+                        asset: entry.asset.clone(),
+                        refid: entry.refid.clone(),
+                        txid: String::new(),
+                        info: None,
+                        amount: entry.amount,
+                        fee: entry.fee,
+                        time: entry.time as i32,
+                        status: String::from("Success"),
+                        status_prop: None,
+                        originators: None,
+                    },
+                );
+            }
+            EntryType::Withdrawal => {
+                withdrawals.insert(
+                    entry.refid.clone(),
+                    Withdrawal {
+                        method: String::new(),
+                        network: None,
+                        aclass: entry.aclass.clone(),
+                        asset: entry.asset.clone(),
+                        refid: entry.refid.clone(),
+                        txid: String::new(),
+                        info: None,
+                        amount: entry.amount.abs(),
+                        fee: entry.fee,
+                        time: entry.time as i32,
+                        status: String::from("Success"),
+                        status_prop: None,
+                        key: None,
+                    },
+                );
+            }
+            _ => (),
+        }
+    }
+
+    Ok((ledger, trades, deposits, withdrawals))
+}
+
+fn read_csv<T: for<'a> Deserialize<'a>>(file_path: &str) -> Result<Vec<T>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_path)
+        .map_err(|e| ApiError::DeserializationError(format!("{file_path}: {e}")))?;
+    let mut rows = Vec::new();
+    for record in reader.deserialize::<T>() {
+        rows.push(
+            record.map_err(|e| ApiError::DeserializationError(format!("{file_path}: {e}")))?,
+        );
+    }
+    Ok(rows)
+}
+
+/* The exports have the UTC time as "2024-03-01 10:15:42", with or without fractional seconds. The API gives it as
+seconds since the epoch */
+fn kraken_csv_time(time: &str) -> Result<f64, ApiError> {
+    let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.f")
+        .map_err(|e| ApiError::DeserializationError(format!("{time}: {e}")))?
+        .and_utc();
+    Ok(time.timestamp_micros() as f64 / 1_000_000.0)
+}
+
+/* Types unknown to the API structures (earn, ...) are kept as None so they are ignored by the mapping */
+fn kraken_csv_entry_type(entry_type: &str) -> EntryType {
+    serde_json::from_value(serde_json::Value::String(entry_type.to_lowercase()))
+        .unwrap_or(EntryType::None)
+}
+
+fn kraken_csv_subtype(subtype: &str) -> SubType {
+    serde_json::from_value(serde_json::Value::String(subtype.to_lowercase()))
+        .unwrap_or(SubType::Empty)
+}
+
+/* Recent exports use the usual codes of the fiat currencies (EUR) where the API uses the Kraken ones (ZEUR) */
+fn kraken_csv_asset(asset: &str) -> String {
+    match asset {
+        "EUR" | "USD" | "GBP" | "CAD" | "AUD" | "JPY" => format!("Z{asset}"),
+        _ => asset.to_string(),
+    }
+}
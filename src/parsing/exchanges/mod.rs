@@ -1 +1,11 @@
+pub mod binance;
+pub use binance::*;
 
+pub mod bitfinex;
+pub use bitfinex::*;
+
+pub mod kraken;
+pub use kraken::*;
+
+pub mod nexo;
+pub use nexo::*;
@@ -0,0 +1,432 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::{
+    api::{coinbase_mapping, CoinbaseClient, Connector, ConnectorFuture, PriceFuture},
+    errors::{ApiError, IoError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Income, IncomeType, TradeType,
+        Transaction, TransactionBase, WalletSnapshot,
+    },
+};
+
+const NEXO: &str = "Nexo";
+// The funds locked in a fixed term are apart from the savings wallet, they are told apart by their address
+const SAVINGS: &str = "savings";
+const TERM: &str = "term";
+
+/* Row of the transactions csv exported from Nexo (Transactions > Export):
+Transaction,Type,Input Currency,Input Amount,Output Currency,Output Amount,USD Equivalent,Details,Date / Time (UTC)
+The amounts leaving the account are negative. The details start with the status of the transaction (approved / ...) */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NexoTransaction {
+    #[serde(rename = "Transaction")]
+    pub id: String,
+    #[serde(rename = "Type")]
+    pub kind: String,
+    #[serde(rename = "Input Currency")]
+    pub input_currency: String,
+    #[serde(rename = "Input Amount")]
+    pub input_amount: Decimal,
+    #[serde(rename = "Output Currency")]
+    pub output_currency: String,
+    #[serde(rename = "Output Amount")]
+    pub output_amount: Decimal,
+    #[serde(rename = "Details")]
+    pub details: String,
+    #[serde(rename = "Date / Time (UTC)")]
+    pub date: String,
+}
+
+impl NexoTransaction {
+    pub fn timestamp(&self) -> Result<DateTime<Utc>, ApiError> {
+        NaiveDateTime::parse_from_str(&self.date, "%Y-%m-%d %H:%M:%S")
+            .map(|date| date.and_utc())
+            .map_err(|e| ApiError::DeserializationError(format!("{}: {e}", self.date)))
+    }
+
+    fn is_approved(&self) -> bool {
+        !self.details.starts_with("rejected") && !self.details.starts_with("pending")
+    }
+}
+
+pub fn read_nexo_csv(file_path: &str) -> Result<Vec<NexoTransaction>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_path)
+        .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+    let mut rows = Vec::new();
+    for record in reader.deserialize::<NexoTransaction>() {
+        rows.push(record.map_err(|e| ApiError::DeserializationError(e.to_string()))?);
+    }
+    Ok(rows)
+}
+
+/* Read and map the Nexo export (env NEXO_CSV), see KoinlyConnector. Nexo has no public prices, they come from
+Coinbase */
+pub struct NexoConnector {
+    pub csv_path: Option<String>,
+    pub price_client: CoinbaseClient,
+}
+
+impl NexoConnector {
+    pub fn from_env() -> Self {
+        NexoConnector {
+            csv_path: env::var("NEXO_CSV").ok(),
+            price_client: CoinbaseClient::public_from_env(),
+        }
+    }
+}
+
+impl Connector for NexoConnector {
+    type History = Vec<NexoTransaction>;
+
+    fn platform(&self) -> Platform {
+        Platform::Other(NEXO.to_string())
+    }
+
+    fn is_configured(&self) -> bool {
+        self.csv_path.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, Vec<NexoTransaction>> {
+        Box::pin(async move {
+            let file_path = self
+                .csv_path
+                .as_ref()
+                .ok_or(IoError::new("NEXO_CSV not set in .env file".to_string()))?;
+            read_nexo_csv(file_path).map_err(|e| IoError::new(e.to_string()))
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        rows: Vec<NexoTransaction>,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut nexo_txs: Vec<Transaction> = Vec::new();
+            create_nexo_txs(wallet_manager, &mut nexo_txs, &rows, &self.price_client)
+                .await
+                .map_err(|e| IoError::new(e.to_string()))?;
+            Ok(nexo_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(coinbase_mapping::get_coinbase_price(
+            &self.price_client,
+            time,
+            currency,
+        ))
+    }
+}
+
+/* Types of rows that are incomes */
+pub fn nexo_income_type(kind: &str) -> Option<IncomeType> {
+    match kind {
+        "Interest" | "Fixed Term Interest" => Some(IncomeType::Interest),
+        "Exchange Cashback" => Some(IncomeType::Income),
+        _ => None,
+    }
+}
+
+/* Map the rows of a Nexo export to transactions.
+
+Nexo is not a platform of its own: its wallets are Platform::Other("Nexo"), the savings wallet without address and
+the fixed term one with the "term" address.
+- Interest and Fixed Term Interest are incomes of type Interest, Exchange Cashback an income
+- Exchange is a trade, typed by the fiat side
+- Locking and Unlocking Term Deposit are transfers between the savings and the term wallets, so they are not taxable
+- Deposit To Exchange and Withdrawal are Deposits and Withdrawals for the fiat, Transfers from or to outside otherwise
+The other types (loans, ...) and the rows not approved are ignored. The USD equivalent isn't used, the prices come from
+Coinbase. As for Binance, the balances are recalculated from zero.
+*/
+pub async fn create_nexo_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    rows: &[NexoTransaction],
+    price_client: &CoinbaseClient,
+) -> Result<(), ApiError> {
+    let mut rows: Vec<(DateTime<Utc>, &NexoTransaction)> = rows
+        .iter()
+        .filter(|row| row.is_approved())
+        .map(|row| row.timestamp().map(|time| (time, row)))
+        .collect::<Result<_, _>>()?;
+    rows.sort_by_key(|(time, _)| *time);
+
+    let mut mapper = NexoMapper {
+        price_client,
+        wallet_manager,
+        balances: HashMap::new(),
+        prices: HashMap::new(),
+    };
+    for (time, row) in rows {
+        mapper.map_row(txs, row, time).await?;
+    }
+    Ok(())
+}
+
+// Fiat currencies of Nexo, the ones of the Nexo exchange having a X suffix (EURX)
+pub enum FiatNexo {
+    EUR,
+    USD,
+    GBP,
+}
+
+impl FiatNexo {
+    pub fn from_code(s: &str) -> Option<FiatNexo> {
+        match s {
+            "EUR" | "EURX" => Some(FiatNexo::EUR),
+            "USD" | "USDX" => Some(FiatNexo::USD),
+            "GBP" | "GBPX" => Some(FiatNexo::GBP),
+            _ => None,
+        }
+    }
+
+    pub fn is_fiat(s: &str) -> bool {
+        Self::from_code(s).is_some()
+    }
+
+    pub fn is_eur(s: &str) -> bool {
+        matches!(Self::from_code(s), Some(FiatNexo::EUR))
+    }
+
+    /* Currency of the fiat, without the suffix of the Nexo exchange */
+    pub fn currency(s: &str) -> String {
+        match Self::from_code(s) {
+            Some(FiatNexo::EUR) => "EUR".to_string(),
+            Some(FiatNexo::USD) => "USD".to_string(),
+            Some(FiatNexo::GBP) => "GBP".to_string(),
+            None => s.to_string(),
+        }
+    }
+}
+
+struct NexoMapper<'a> {
+    price_client: &'a CoinbaseClient,
+    wallet_manager: &'a mut WalletManager,
+    balances: HashMap<(String, &'static str), Decimal>, // Balance of each currency of the savings and term wallets
+    prices: HashMap<(String, String), Decimal>,         // Price of a currency for a given day
+}
+
+impl NexoMapper<'_> {
+    async fn map_row(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &NexoTransaction,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let tx = TransactionBase {
+            id: format!("nexo-{}", row.id),
+            timestamp: time,
+        };
+        let input_currency = FiatNexo::currency(&row.input_currency);
+        let output_currency = FiatNexo::currency(&row.output_currency);
+        let input_amount = row.input_amount.abs();
+        let output_amount = row.output_amount.abs();
+
+        if let Some(subtype) = nexo_income_type(&row.kind) {
+            let snapshot = self.snapshot(&output_currency, SAVINGS, time, None).await?;
+            let income = Income::new(output_amount * snapshot.price_eur, subtype);
+            self.update_balance(&output_currency, SAVINGS, output_amount);
+            txs.push(Transaction::Transfer {
+                tx,
+                from: snapshot.clone(),
+                to: snapshot,
+                amount: output_amount,
+                income: Some(income),
+            });
+            return Ok(());
+        }
+
+        match row.kind.as_str() {
+            "Exchange" => {
+                // The fiat side gives the price of the other one
+                let fiat_price = |fiat: &str, fiat_amount: Decimal, amount: Decimal| {
+                    Some(fiat_amount / amount)
+                        .filter(|_| FiatNexo::is_eur(fiat) && !amount.is_zero())
+                };
+                let from = self
+                    .snapshot(
+                        &input_currency,
+                        SAVINGS,
+                        time,
+                        fiat_price(&output_currency, output_amount, input_amount),
+                    )
+                    .await?;
+                let to = self
+                    .snapshot(
+                        &output_currency,
+                        SAVINGS,
+                        time,
+                        fiat_price(&input_currency, input_amount, output_amount),
+                    )
+                    .await?;
+                let trade_type = match (
+                    FiatNexo::is_fiat(&input_currency),
+                    FiatNexo::is_fiat(&output_currency),
+                ) {
+                    (true, false) => TradeType::FiatToCrypto {
+                        local_cost_basis: input_amount * from.price_eur,
+                    },
+                    (false, true) => TradeType::CryptoToFiat,
+                    _ => TradeType::CryptoToCrypto,
+                };
+                self.update_balance(&input_currency, SAVINGS, -input_amount);
+                self.update_balance(&output_currency, SAVINGS, output_amount);
+                txs.push(Transaction::Trade {
+                    tx,
+                    from,
+                    to,
+                    exchange_pair: None,
+                    sold_amount: input_amount,
+                    bought_amount: output_amount,
+                    trade_type,
+                    soulte: None,
+                });
+            }
+            "Locking Term Deposit" | "Unlocking Term Deposit" => {
+                let (from_account, to_account) = if row.kind == "Locking Term Deposit" {
+                    (SAVINGS, TERM)
+                } else {
+                    (TERM, SAVINGS)
+                };
+                let from = self
+                    .snapshot(&input_currency, from_account, time, None)
+                    .await?;
+                let to = self
+                    .snapshot(&input_currency, to_account, time, Some(from.price_eur))
+                    .await?;
+                self.update_balance(&input_currency, from_account, -input_amount);
+                self.update_balance(&input_currency, to_account, input_amount);
+                txs.push(Transaction::Transfer {
+                    tx,
+                    from,
+                    to,
+                    amount: input_amount,
+                    income: None,
+                });
+            }
+            "Deposit To Exchange" | "Deposit" | "Top up Crypto" => {
+                let snapshot = self.snapshot(&output_currency, SAVINGS, time, None).await?;
+                self.update_balance(&output_currency, SAVINGS, output_amount);
+                if FiatNexo::is_fiat(&output_currency) {
+                    txs.push(Transaction::Deposit {
+                        tx,
+                        to: snapshot,
+                        amount: output_amount,
+                    });
+                } else {
+                    let external = self.external_snapshot(
+                        &output_currency,
+                        output_amount,
+                        snapshot.price_eur,
+                    );
+                    txs.push(Transaction::Transfer {
+                        tx,
+                        from: external,
+                        to: snapshot,
+                        amount: output_amount,
+                        income: None,
+                    });
+                }
+            }
+            "Withdrawal" => {
+                let snapshot = self.snapshot(&input_currency, SAVINGS, time, None).await?;
+                self.update_balance(&input_currency, SAVINGS, -input_amount);
+                if FiatNexo::is_fiat(&input_currency) {
+                    txs.push(Transaction::Withdrawal {
+                        tx,
+                        from: snapshot,
+                        amount: input_amount,
+                    });
+                } else {
+                    let external =
+                        self.external_snapshot(&input_currency, dec!(0), snapshot.price_eur);
+                    txs.push(Transaction::Transfer {
+                        tx,
+                        from: snapshot,
+                        to: external,
+                        amount: input_amount,
+                        income: None,
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn snapshot(
+        &mut self,
+        currency: &str,
+        account: &'static str,
+        time: DateTime<Utc>,
+        price_eur: Option<Decimal>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let address = Some(account.to_string()).filter(|_| account != SAVINGS);
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Other(NEXO.to_string()),
+            &address,
+            FiatNexo::is_fiat(currency),
+        );
+        let price_eur = match price_eur {
+            Some(price) => price,
+            None => self.price(currency, time).await?,
+        };
+        Ok(WalletSnapshot {
+            id,
+            pre_tx_balance: *self
+                .balances
+                .get(&(currency.to_string(), account))
+                .unwrap_or(&dec!(0)),
+            price_eur,
+            fee: None,
+        })
+    }
+
+    /* Wallet outside of Nexo: the export doesn't give its address, it only holds the amount transfered */
+    fn external_snapshot(
+        &mut self,
+        currency: &str,
+        pre_tx_balance: Decimal,
+        price_eur: Decimal,
+    ) -> WalletSnapshot {
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Blockchain,
+            &None,
+            false,
+        );
+        WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur,
+            fee: None,
+        }
+    }
+
+    fn update_balance(&mut self, currency: &str, account: &'static str, change: Decimal) {
+        *self
+            .balances
+            .entry((currency.to_string(), account))
+            .or_insert(dec!(0)) += change;
+    }
+
+    async fn price(&mut self, currency: &str, time: DateTime<Utc>) -> Result<Decimal, ApiError> {
+        let key = (currency.to_string(), time.format("%Y-%m-%d").to_string());
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+        let price =
+            coinbase_mapping::get_coinbase_price(self.price_client, time, currency).await?;
+        self.prices.insert(key, price);
+        Ok(price)
+    }
+}
@@ -0,0 +1,707 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use std::env;
+
+use crate::{
+    api::{coinbase_mapping, CoinbaseClient, Connector, ConnectorFuture, PriceFuture},
+    errors::{ApiError, IoError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Income, IncomeType, TradeType,
+        Transaction, TransactionBase, WalletSnapshot,
+    },
+    utils::read_file,
+};
+
+/* Description of the CSV export of a platform, read from a TOML or a JSON file, e.g.:
+
+platform = "Bitstamp"
+delimiter = ","                  # optional, "," by default
+decimal_separator = "."          # optional, "." by default, the other one of "." and "," is a thousands separator
+id_column = "ID"                 # optional, the line of the row otherwise
+type_columns = ["Type", "Subtype"]
+amount_column = "Amount"
+currency_column = "Amount currency"
+quote_amount_column = "Value"    # for the trades: the amount paid for a buy, received for a sell
+quote_currency_column = "Value currency"
+fee_amount_column = "Fee"
+fee_currency_column = "Fee currency"
+fiat = ["EUR", "USD"]            # optional, EUR USD GBP and CHF by default
+
+[date]
+columns = ["Datetime"]           # joined by a space when the date and the time are apart
+format = "%Y-%m-%dT%H:%M:%SZ"    # chrono format
+timezone = "UTC"                 # optional, IANA name of the timezone of the dates when they don't give it
+
+[types]                          # value of the type columns (joined by a space) to the kind of the row
+"Market Buy" = "buy"
+"Deposit" = "deposit"
+
+When a currency column is not given, the currency is read after the amount in its cell ("0.5 BTC"). The fee
+currency is by default the quote currency for the trades and the currency of the amount otherwise.
+*/
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvMapping {
+    pub platform: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: char,
+    pub date: CsvDateMapping,
+    pub id_column: Option<String>,
+    pub type_columns: Vec<String>,
+    pub types: HashMap<String, CsvRowKind>,
+    pub amount_column: String,
+    pub currency_column: Option<String>,
+    pub quote_amount_column: Option<String>,
+    pub quote_currency_column: Option<String>,
+    pub fee_amount_column: Option<String>,
+    pub fee_currency_column: Option<String>,
+    #[serde(default = "default_fiat")]
+    pub fiat: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CsvDateMapping {
+    pub columns: Vec<String>,
+    pub format: String,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_decimal_separator() -> char {
+    '.'
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_fiat() -> Vec<String> {
+    ["EUR", "USD", "GBP", "CHF"]
+        .iter()
+        .map(|fiat| fiat.to_string())
+        .collect()
+}
+
+/* Kinds of rows: a trade (the amount is the one bought or sold), a move from or to outside of the platform, an
+income, or a row without effect on the balances (orders, internal moves...) */
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvRowKind {
+    Buy,
+    Sell,
+    Deposit,
+    Withdrawal,
+    Airdrop,
+    Hardfork,
+    Income,
+    Interest,
+    Mining,
+    Staking,
+    Gift,
+    Donation,
+    Ignore,
+}
+
+impl CsvRowKind {
+    fn income_type(&self) -> Option<IncomeType> {
+        match self {
+            CsvRowKind::Airdrop => Some(IncomeType::Airdrop),
+            CsvRowKind::Hardfork => Some(IncomeType::Hardfork),
+            CsvRowKind::Income => Some(IncomeType::Income),
+            CsvRowKind::Interest => Some(IncomeType::Interest),
+            CsvRowKind::Mining => Some(IncomeType::Mining),
+            CsvRowKind::Staking => Some(IncomeType::Staking),
+            CsvRowKind::Gift => Some(IncomeType::Gift),
+            CsvRowKind::Donation => Some(IncomeType::Donation),
+            _ => None,
+        }
+    }
+}
+
+impl CsvMapping {
+    /* The format is given by the extension of the file: .toml, JSON otherwise */
+    pub fn from_file(file_path: &str) -> Result<Self, ApiError> {
+        let content = read_file(file_path)
+            .map_err(|e| ApiError::DeserializationError(format!("{file_path}: {e}")))?;
+        if file_path.ends_with(".toml") {
+            toml::from_str(&content)
+                .map_err(|e| ApiError::DeserializationError(format!("{file_path}: {e}")))
+        } else {
+            serde_json::from_str(&content)
+                .map_err(|e| ApiError::DeserializationError(format!("{file_path}: {e}")))
+        }
+    }
+
+    pub fn platform(&self) -> Platform {
+        match self.platform.to_lowercase().as_str() {
+            "binance" => Platform::Binance,
+            "bitfinex" => Platform::Bitfinex,
+            "bitpanda" => Platform::Bitpanda,
+            "coinbase" => Platform::Coinbase,
+            "crypto.com" | "cryptocom" => Platform::CryptoCom,
+            "kraken" => Platform::Kraken,
+            "kucoin" => Platform::KuCoin,
+            _ => Platform::Other(self.platform.clone()),
+        }
+    }
+
+    fn is_fiat(&self, currency: &str) -> bool {
+        self.fiat.iter().any(|fiat| fiat == currency)
+    }
+
+    fn parse_decimal(&self, cell: &str) -> Result<Decimal, ApiError> {
+        let thousands_separator = if self.decimal_separator == ',' {
+            '.'
+        } else {
+            ','
+        };
+        let number: String = cell
+            .chars()
+            .filter(|c| *c != thousands_separator && !c.is_whitespace())
+            .map(|c| if c == self.decimal_separator { '.' } else { c })
+            .collect();
+        number
+            .parse()
+            .map_err(|e| ApiError::DeserializationError(format!("{cell}: {e}")))
+    }
+
+    /* Amount of a cell, with the currency written after it if any: "-0,5 BTC" */
+    fn parse_amount(&self, cell: &str) -> Result<(Decimal, Option<String>), ApiError> {
+        let cell = cell.trim();
+        match cell.rsplit_once(' ') {
+            Some((amount, currency)) if currency.chars().any(|c| c.is_ascii_alphabetic()) => Ok((
+                self.parse_decimal(amount)?.abs(),
+                Some(currency.to_string()),
+            )),
+            _ => Ok((self.parse_decimal(cell)?.abs(), None)),
+        }
+    }
+
+    fn parse_date(&self, date: &str) -> Result<DateTime<Utc>, ApiError> {
+        let error = |e: String| ApiError::DeserializationError(format!("{date}: {e}"));
+        if self.date.format.contains("%z") || self.date.format.contains("%:z") {
+            return DateTime::parse_from_str(date, &self.date.format)
+                .map(|date| date.with_timezone(&Utc))
+                .map_err(|e| error(e.to_string()));
+        }
+        let naive = NaiveDateTime::parse_from_str(date, &self.date.format).or_else(|e| {
+            NaiveDate::parse_from_str(date, &self.date.format)
+                .map(|day| day.and_hms_opt(0, 0, 0).unwrap())
+                .map_err(|_| error(e.to_string()))
+        })?;
+        let timezone: Tz = self
+            .date
+            .timezone
+            .parse()
+            .map_err(|e: chrono_tz::ParseError| error(e.to_string()))?;
+        // At the end of the daylight saving time, the hour repeated is taken the first time
+        timezone
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|date| date.with_timezone(&Utc))
+            .ok_or(error(format!("no such time in {}", self.date.timezone)))
+    }
+}
+
+/* Row of the CSV once read through its mapping, the amounts being positive */
+#[derive(Debug, Clone)]
+pub struct GenericCsvRow {
+    pub id: String,
+    pub time: DateTime<Utc>,
+    pub kind: CsvRowKind,
+    pub amount: Decimal,
+    pub currency: String,
+    pub quote: Option<(Decimal, String)>,
+    pub fee: Option<(Decimal, String)>,
+}
+
+/* Read the rows of a CSV with its mapping, without the ignored ones. A type missing from the mapping is an error
+so that no operation is silently left out */
+pub fn read_generic_csv(
+    file_path: &str,
+    mapping: &CsvMapping,
+) -> Result<Vec<GenericCsvRow>, ApiError> {
+    let error = |line: usize, message: String| {
+        ApiError::MappingError(MappingError::Other(format!(
+            "{file_path} line {line}: {message}"
+        )))
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .delimiter(mapping.delimiter as u8)
+        .from_path(file_path)
+        .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+    let headers = reader
+        .headers()
+        .map_err(|e| ApiError::DeserializationError(e.to_string()))?
+        .clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.trim() == name)
+            .ok_or(error(1, format!("missing column {name}")))
+    };
+    let optional_column = |name: &Option<String>| name.as_deref().map(column).transpose();
+    let type_columns = mapping
+        .type_columns
+        .iter()
+        .map(|name| column(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let date_columns = mapping
+        .date
+        .columns
+        .iter()
+        .map(|name| column(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let id_column = optional_column(&mapping.id_column)?;
+    let amount_column = column(&mapping.amount_column)?;
+    let currency_column = optional_column(&mapping.currency_column)?;
+    let quote_amount_column = optional_column(&mapping.quote_amount_column)?;
+    let quote_currency_column = optional_column(&mapping.quote_currency_column)?;
+    let fee_amount_column = optional_column(&mapping.fee_amount_column)?;
+    let fee_currency_column = optional_column(&mapping.fee_currency_column)?;
+    let id_prefix = mapping.platform.to_lowercase().replace(' ', "-");
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let line = index + 2;
+        let record = record.map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+        let cell = |index: usize| record.get(index).unwrap_or("").trim();
+        let joined = |indexes: &[usize]| {
+            indexes
+                .iter()
+                .map(|index| cell(*index))
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let optional_cell =
+            |index: Option<usize>| index.map(cell).filter(|value| !value.is_empty());
+
+        let row_type = joined(&type_columns);
+        let kind = *mapping
+            .types
+            .get(&row_type)
+            .ok_or(error(line, format!("type \"{row_type}\" is not mapped")))?;
+        if kind == CsvRowKind::Ignore {
+            continue;
+        }
+
+        // The currency of an amount is in its own column or written after it
+        let amount_with_currency = |amount: &str, currency_column: Option<usize>| {
+            let (amount, currency) = mapping.parse_amount(amount)?;
+            Ok::<_, ApiError>((
+                amount,
+                optional_cell(currency_column)
+                    .map(|currency| currency.to_string())
+                    .or(currency),
+            ))
+        };
+        let (amount, currency) = amount_with_currency(cell(amount_column), currency_column)?;
+        let currency = currency.ok_or(error(line, "missing currency".to_string()))?;
+        let quote = match optional_cell(quote_amount_column) {
+            Some(quote_amount) => {
+                match amount_with_currency(quote_amount, quote_currency_column)? {
+                    (amount, Some(currency)) => Some((amount, currency)),
+                    (_, None) => return Err(error(line, "missing quote currency".to_string())),
+                }
+            }
+            None => None,
+        };
+        let fee = match optional_cell(fee_amount_column) {
+            Some(fee_amount) => {
+                let (fee, fee_currency) = amount_with_currency(fee_amount, fee_currency_column)?;
+                let default_currency = match (kind, &quote) {
+                    (CsvRowKind::Buy | CsvRowKind::Sell, Some((_, quote_currency))) => {
+                        quote_currency.clone()
+                    }
+                    _ => currency.clone(),
+                };
+                Some((fee, fee_currency.unwrap_or(default_currency))).filter(|_| !fee.is_zero())
+            }
+            None => None,
+        };
+        let id = match optional_cell(id_column) {
+            Some(id) => id.to_string(),
+            None => line.to_string(),
+        };
+
+        rows.push(GenericCsvRow {
+            id: format!("{id_prefix}-{id}"),
+            time: mapping.parse_date(&joined(&date_columns))?,
+            kind,
+            amount,
+            currency,
+            quote,
+            fee,
+        });
+    }
+    Ok(rows)
+}
+
+/* Read and map the CSV exports of the env GENERIC_CSV_IMPORTS, a list of mapping=csv separated by commas:
+GENERIC_CSV_IMPORTS=mappings/bitstamp.toml=exports/bitstamp.csv,mappings/bison.json=exports/bison.csv
+As the other exports, they are read again at each run. The wallets are on the platforms of the mappings: the ones of
+a known platform are priced by its connector, the others (Platform::Other) by the fallback of the ConnectorRegistry */
+pub struct GenericCsvConnector {
+    pub imports: Option<String>,
+    pub price_client: CoinbaseClient,
+}
+
+impl GenericCsvConnector {
+    pub fn from_env() -> Self {
+        GenericCsvConnector {
+            imports: env::var("GENERIC_CSV_IMPORTS").ok(),
+            price_client: CoinbaseClient::public_from_env(),
+        }
+    }
+}
+
+impl Connector for GenericCsvConnector {
+    // The rows of each export, with the mapping they were read with
+    type History = Vec<(CsvMapping, Vec<GenericCsvRow>)>;
+
+    fn platform(&self) -> Platform {
+        Platform::Other("Generic CSV".to_string())
+    }
+
+    fn is_configured(&self) -> bool {
+        self.imports.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, Vec<(CsvMapping, Vec<GenericCsvRow>)>> {
+        Box::pin(async move {
+            let imports = self.imports.as_ref().ok_or(IoError::new(
+                "GENERIC_CSV_IMPORTS not set in .env file".to_string(),
+            ))?;
+            let mut exports = Vec::new();
+            for import in imports
+                .split(',')
+                .filter(|import| !import.trim().is_empty())
+            {
+                let (mapping_path, csv_path) =
+                    import.trim().split_once('=').ok_or(IoError::new(format!(
+                        "{import} is not mapping=csv in GENERIC_CSV_IMPORTS"
+                    )))?;
+                let mapping = CsvMapping::from_file(mapping_path)
+                    .map_err(|e| IoError::new(e.to_string()))?;
+                let rows = read_generic_csv(csv_path, &mapping)
+                    .map_err(|e| IoError::new(e.to_string()))?;
+                exports.push((mapping, rows));
+            }
+            Ok(exports)
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        exports: Vec<(CsvMapping, Vec<GenericCsvRow>)>,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut generic_txs: Vec<Transaction> = Vec::new();
+            for (mapping, rows) in &exports {
+                create_generic_csv_txs(
+                    wallet_manager,
+                    &mut generic_txs,
+                    rows,
+                    mapping,
+                    &self.price_client,
+                )
+                .await
+                .map_err(|e| IoError::new(e.to_string()))?;
+            }
+            Ok(generic_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(coinbase_mapping::get_coinbase_price(
+            &self.price_client,
+            time,
+            currency,
+        ))
+    }
+}
+
+/* Map the rows of a CSV read with its mapping to transactions on the wallets of the platform of the mapping.
+- buy and sell are Trades between the currency of the amount and the quote currency, typed by the fiat side
+- the deposits and withdrawals are Deposits and Withdrawals for the fiat, Transfers from or to outside otherwise
+- the income kinds are incomes of the corresponding IncomeType
+The amounts don't include the fee, which is held by the WalletSnapshot of its currency, or by a Transfer of 0 when
+it is paid in a third currency. The euro side of a trade gives the price, otherwise the price comes from Coinbase. As
+for Binance, the balances are recalculated from zero.
+*/
+pub async fn create_generic_csv_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    rows: &[GenericCsvRow],
+    mapping: &CsvMapping,
+    price_client: &CoinbaseClient,
+) -> Result<(), ApiError> {
+    let mut rows: Vec<&GenericCsvRow> = rows.iter().collect();
+    rows.sort_by_key(|row| row.time);
+
+    let mut mapper = GenericCsvMapper {
+        price_client,
+        wallet_manager,
+        mapping,
+        platform: mapping.platform(),
+        balances: HashMap::new(),
+        prices: HashMap::new(),
+    };
+    for row in rows {
+        mapper.map_row(txs, row).await?;
+    }
+    Ok(())
+}
+
+struct GenericCsvMapper<'a> {
+    price_client: &'a CoinbaseClient,
+    wallet_manager: &'a mut WalletManager,
+    mapping: &'a CsvMapping,
+    platform: Platform,
+    balances: HashMap<String, Decimal>, // Balance of each currency on the platform before the current row
+    prices: HashMap<(String, String), Decimal>, // Price of a currency for a given day
+}
+
+impl GenericCsvMapper<'_> {
+    async fn map_row(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &GenericCsvRow,
+    ) -> Result<(), ApiError> {
+        let tx = TransactionBase {
+            id: row.id.clone(),
+            timestamp: row.time,
+        };
+        let (amount, currency) = (row.amount, row.currency.as_str());
+
+        match row.kind {
+            CsvRowKind::Buy | CsvRowKind::Sell => {
+                let (quote_amount, quote_currency) =
+                    row.quote
+                        .as_ref()
+                        .ok_or(ApiError::MappingError(MappingError::Other(format!(
+                            "Missing quote amount of the trade {}",
+                            row.id
+                        ))))?;
+                let ((sold_currency, sold_amount), (bought_currency, bought_amount)) =
+                    if row.kind == CsvRowKind::Buy {
+                        ((quote_currency.as_str(), *quote_amount), (currency, amount))
+                    } else {
+                        ((currency, amount), (quote_currency.as_str(), *quote_amount))
+                    };
+                // The euro side gives the price of the other one
+                let price = |other: &str, other_amount: Decimal, amount: Decimal| {
+                    Some(other_amount / amount).filter(|_| other == "EUR" && !amount.is_zero())
+                };
+                let (from_fee, to_fee) = match &row.fee {
+                    Some((fee, fee_currency)) if fee_currency == sold_currency => {
+                        (Some(*fee), None)
+                    }
+                    Some((fee, fee_currency)) if fee_currency == bought_currency => {
+                        (None, Some(*fee))
+                    }
+                    Some(_) => (self.fee(txs, row, sold_currency).await?, None),
+                    None => (None, None),
+                };
+                let from = self
+                    .snapshot(
+                        sold_currency,
+                        from_fee,
+                        row.time,
+                        price(bought_currency, bought_amount, sold_amount),
+                    )
+                    .await?;
+                let to = self
+                    .snapshot(
+                        bought_currency,
+                        to_fee,
+                        row.time,
+                        price(sold_currency, sold_amount, bought_amount),
+                    )
+                    .await?;
+                let trade_type = match (
+                    self.mapping.is_fiat(sold_currency),
+                    self.mapping.is_fiat(bought_currency),
+                ) {
+                    (true, false) => TradeType::FiatToCrypto {
+                        local_cost_basis: sold_amount * from.price_eur,
+                    },
+                    (false, true) => TradeType::CryptoToFiat,
+                    _ => TradeType::CryptoToCrypto,
+                };
+                self.update_balance(sold_currency, -sold_amount - from_fee.unwrap_or(dec!(0)));
+                self.update_balance(bought_currency, bought_amount - to_fee.unwrap_or(dec!(0)));
+                txs.push(Transaction::Trade {
+                    tx,
+                    from,
+                    to,
+                    exchange_pair: Some((currency.to_string(), quote_currency.clone())),
+                    sold_amount,
+                    bought_amount,
+                    trade_type,
+                    soulte: None,
+                });
+            }
+            CsvRowKind::Deposit => {
+                let fee = self.fee(txs, row, currency).await?;
+                let to = self.snapshot(currency, fee, row.time, None).await?;
+                self.update_balance(currency, amount - fee.unwrap_or(dec!(0)));
+                if self.mapping.is_fiat(currency) {
+                    txs.push(Transaction::Deposit { tx, to, amount });
+                } else {
+                    let from = self.external_snapshot(currency, amount, to.price_eur);
+                    txs.push(Transaction::Transfer {
+                        tx,
+                        from,
+                        to,
+                        amount,
+                        income: None,
+                    });
+                }
+            }
+            CsvRowKind::Withdrawal => {
+                let fee = self.fee(txs, row, currency).await?;
+                let from = self.snapshot(currency, fee, row.time, None).await?;
+                self.update_balance(currency, -amount - fee.unwrap_or(dec!(0)));
+                if self.mapping.is_fiat(currency) {
+                    txs.push(Transaction::Withdrawal { tx, from, amount });
+                } else {
+                    let to = self.external_snapshot(currency, dec!(0), from.price_eur);
+                    txs.push(Transaction::Transfer {
+                        tx,
+                        from,
+                        to,
+                        amount,
+                        income: None,
+                    });
+                }
+            }
+            kind => {
+                if let Some(subtype) = kind.income_type() {
+                    let fee = self.fee(txs, row, currency).await?;
+                    let snapshot = self.snapshot(currency, fee, row.time, None).await?;
+                    let income = Income::new(amount * snapshot.price_eur, subtype);
+                    self.update_balance(currency, amount - fee.unwrap_or(dec!(0)));
+                    txs.push(Transaction::Transfer {
+                        tx,
+                        from: WalletSnapshot {
+                            fee: None,
+                            ..snapshot.clone()
+                        },
+                        to: snapshot,
+                        amount,
+                        income: Some(income),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /* Fee of the row held by the WalletSnapshot of the currency. When it is paid in another currency, it is
+    recorded in its own Transfer of 0 */
+    async fn fee(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &GenericCsvRow,
+        currency: &str,
+    ) -> Result<Option<Decimal>, ApiError> {
+        let Some((fee, fee_currency)) = &row.fee else {
+            return Ok(None);
+        };
+        if fee_currency == currency {
+            return Ok(Some(*fee));
+        }
+        let snapshot = self
+            .snapshot(fee_currency, Some(*fee), row.time, None)
+            .await?;
+        self.update_balance(fee_currency, -*fee);
+        txs.push(Transaction::Transfer {
+            tx: TransactionBase {
+                id: format!("{}-fee", row.id),
+                timestamp: row.time,
+            },
+            from: snapshot.clone(),
+            to: WalletSnapshot {
+                fee: None,
+                ..snapshot
+            },
+            amount: dec!(0),
+            income: None,
+        });
+        Ok(None)
+    }
+
+    async fn snapshot(
+        &mut self,
+        currency: &str,
+        fee: Option<Decimal>,
+        time: DateTime<Utc>,
+        price_eur: Option<Decimal>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            &self.platform,
+            &None,
+            self.mapping.is_fiat(currency),
+        );
+        let price_eur = match price_eur {
+            Some(price) => price,
+            None => self.price(currency, time).await?,
+        };
+        Ok(WalletSnapshot {
+            id,
+            pre_tx_balance: *self.balances.get(currency).unwrap_or(&dec!(0)),
+            price_eur,
+            fee,
+        })
+    }
+
+    /* Wallet outside of the platform: the export doesn't give its address, it only holds the amount transfered */
+    fn external_snapshot(
+        &mut self,
+        currency: &str,
+        pre_tx_balance: Decimal,
+        price_eur: Decimal,
+    ) -> WalletSnapshot {
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Blockchain,
+            &None,
+            false,
+        );
+        WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur,
+            fee: None,
+        }
+    }
+
+    fn update_balance(&mut self, currency: &str, change: Decimal) {
+        *self.balances.entry(currency.to_string()).or_insert(dec!(0)) += change;
+    }
+
+    async fn price(&mut self, currency: &str, time: DateTime<Utc>) -> Result<Decimal, ApiError> {
+        let key = (currency.to_string(), time.format("%Y-%m-%d").to_string());
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+        let price =
+            coinbase_mapping::get_coinbase_price(self.price_client, time, currency).await?;
+        self.prices.insert(key, price);
+        Ok(price)
+    }
+}
@@ -1,5 +1,14 @@
-// pub mod exchanges;
-// pub use exchanges::*;
+pub mod exchanges;
+pub use exchanges::*;
 
-// pub mod tools;
-// pub use tools::*;
+pub mod tools;
+pub use tools::*;
+
+pub mod wallets;
+pub use wallets::*;
+
+pub mod generic_csv;
+pub use generic_csv::*;
+
+pub mod standard_format;
+pub use standard_format::*;
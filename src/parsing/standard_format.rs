@@ -0,0 +1,671 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{env, fs::File};
+
+use crate::{
+    api::{coinbase_mapping, CoinbaseClient, Connector, ConnectorFuture, PriceFuture},
+    errors::IoError,
+    structs::{
+        wallet_manager::WalletManager, Income, IncomeType, Owner, Platform, TradeType,
+        Transaction, TransactionBase, TransactionManager, Wallet, WalletBase, WalletId,
+        WalletSnapshot,
+    },
+    utils::create_directories_if_needed,
+};
+
+/* Standard format of the transactions and of the wallets, to save them outside of the MessagePack data or to
+import them from another tool. It is the same flat records for the CSV and the JSON, the names and the values
+below being stable: a change of them would change STANDARD_FORMAT_VERSION.
+
+JSON: a single file {"version": 1, "wallets": [...], "transactions": [...]}
+CSV: a file of wallets and a file of transactions, each with a header of the fields below.
+
+Wallet:
+    id                    id of the wallet, used by the transactions
+    kind                  fiat | crypto
+    currency              e.g. BTC
+    platform              binance | bitfinex | bitpanda | coinbase | crypto_com | kraken | kucoin | blockchain | other
+    platform_name         name of the platform when it is other
+    address               optional, e.g. the address or the xpub of a blockchain wallet
+    owner                 user | platform | other
+    balance
+    info                  optional
+
+Transaction:
+    id
+    timestamp             RFC 3339, e.g. 2024-01-31T12:00:00Z
+    kind                  transfer | trade | deposit | withdrawal
+    from_wallet           the wallet sending the funds (transfer, trade, withdrawal)
+    from_pre_tx_balance
+    from_price_eur
+    from_fee              optional
+    to_wallet             the wallet receiving the funds (transfer, trade, deposit)
+    to_pre_tx_balance
+    to_price_eur
+    to_fee                optional
+    amount                transfer, deposit, withdrawal
+    sold_amount           trade
+    bought_amount         trade
+    trade_type            trade: fiat_to_crypto | crypto_to_fiat | crypto_to_crypto
+    local_cost_basis      trade fiat_to_crypto
+    exchange_pair_base    optional, trade
+    exchange_pair_quote   optional, trade
+    soulte                optional, trade: cash balance in euro, received if positive, paid if negative
+    income_type           optional, transfer: airdrop | hardfork | income | interest | mining | staking | gift
+                          | donation | other
+    income_name           name of the income when it is other
+    income_value          transfer with an income_type
+
+The decimals are written as strings (e.g. "0.015"). An empty CSV cell is an absent field, so an empty address,
+info or income name can't be told apart from a missing one.
+*/
+pub const STANDARD_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletKind {
+    Fiat,
+    Crypto,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformKind {
+    Binance,
+    Bitfinex,
+    Bitpanda,
+    Coinbase,
+    CryptoCom,
+    Kraken,
+    #[serde(rename = "kucoin")]
+    KuCoin,
+    Blockchain,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OwnerKind {
+    User,
+    Platform,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    Transfer,
+    Trade,
+    Deposit,
+    Withdrawal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeKind {
+    FiatToCrypto,
+    CryptoToFiat,
+    CryptoToCrypto,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncomeKind {
+    Airdrop,
+    Hardfork,
+    Income,
+    Interest,
+    Mining,
+    Staking,
+    Gift,
+    Donation,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalletRecord {
+    pub id: WalletId,
+    pub kind: WalletKind,
+    pub currency: String,
+    pub platform: PlatformKind,
+    pub platform_name: Option<String>,
+    pub address: Option<String>,
+    pub owner: OwnerKind,
+    pub balance: Decimal,
+    pub info: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub kind: TransactionKind,
+    pub from_wallet: Option<WalletId>,
+    pub from_pre_tx_balance: Option<Decimal>,
+    pub from_price_eur: Option<Decimal>,
+    pub from_fee: Option<Decimal>,
+    pub to_wallet: Option<WalletId>,
+    pub to_pre_tx_balance: Option<Decimal>,
+    pub to_price_eur: Option<Decimal>,
+    pub to_fee: Option<Decimal>,
+    pub amount: Option<Decimal>,
+    pub sold_amount: Option<Decimal>,
+    pub bought_amount: Option<Decimal>,
+    pub trade_type: Option<TradeKind>,
+    pub local_cost_basis: Option<Decimal>,
+    pub exchange_pair_base: Option<String>,
+    pub exchange_pair_quote: Option<String>,
+    pub soulte: Option<Decimal>,
+    pub income_type: Option<IncomeKind>,
+    pub income_name: Option<String>,
+    pub income_value: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StandardDocument {
+    pub version: u32,
+    pub wallets: Vec<WalletRecord>,
+    pub transactions: Vec<TransactionRecord>,
+}
+
+impl From<&Wallet> for WalletRecord {
+    fn from(wallet: &Wallet) -> Self {
+        let base = wallet.get();
+        let (platform, platform_name) = match &base.platform {
+            Platform::Binance => (PlatformKind::Binance, None),
+            Platform::Bitfinex => (PlatformKind::Bitfinex, None),
+            Platform::Bitpanda => (PlatformKind::Bitpanda, None),
+            Platform::Coinbase => (PlatformKind::Coinbase, None),
+            Platform::CryptoCom => (PlatformKind::CryptoCom, None),
+            Platform::Kraken => (PlatformKind::Kraken, None),
+            Platform::KuCoin => (PlatformKind::KuCoin, None),
+            Platform::Blockchain => (PlatformKind::Blockchain, None),
+            Platform::Other(name) => (PlatformKind::Other, Some(name.clone())),
+        };
+        WalletRecord {
+            id: base.id.clone(),
+            kind: if wallet.is_crypto() {
+                WalletKind::Crypto
+            } else {
+                WalletKind::Fiat
+            },
+            currency: base.currency.clone(),
+            platform,
+            platform_name,
+            address: base.address.clone(),
+            owner: match base.owner {
+                Owner::User => OwnerKind::User,
+                Owner::Platform => OwnerKind::Platform,
+                Owner::Other => OwnerKind::Other,
+            },
+            balance: base.balance,
+            info: base.info.clone(),
+        }
+    }
+}
+
+impl WalletRecord {
+    pub fn to_wallet(&self) -> Result<Wallet, IoError> {
+        let platform = match self.platform {
+            PlatformKind::Binance => Platform::Binance,
+            PlatformKind::Bitfinex => Platform::Bitfinex,
+            PlatformKind::Bitpanda => Platform::Bitpanda,
+            PlatformKind::Coinbase => Platform::Coinbase,
+            PlatformKind::CryptoCom => Platform::CryptoCom,
+            PlatformKind::Kraken => Platform::Kraken,
+            PlatformKind::KuCoin => Platform::KuCoin,
+            PlatformKind::Blockchain => Platform::Blockchain,
+            PlatformKind::Other => Platform::Other(self.platform_name.clone().ok_or(
+                IoError::new(format!("wallet {}: missing platform_name", self.id)),
+            )?),
+        };
+        let base = WalletBase {
+            id: self.id.clone(),
+            currency: self.currency.clone(),
+            platform,
+            address: self.address.clone(),
+            owner: match self.owner {
+                OwnerKind::User => Owner::User,
+                OwnerKind::Platform => Owner::Platform,
+                OwnerKind::Other => Owner::Other,
+            },
+            balance: self.balance,
+            info: self.info.clone(),
+        };
+        Ok(match self.kind {
+            WalletKind::Fiat => Wallet::Fiat(base),
+            WalletKind::Crypto => Wallet::Crypto(base),
+        })
+    }
+}
+
+impl From<&Transaction> for TransactionRecord {
+    fn from(tx: &Transaction) -> Self {
+        let base = tx.get_tx_base();
+        let mut record = TransactionRecord {
+            id: base.id.clone(),
+            timestamp: base.timestamp,
+            kind: TransactionKind::Transfer,
+            from_wallet: None,
+            from_pre_tx_balance: None,
+            from_price_eur: None,
+            from_fee: None,
+            to_wallet: None,
+            to_pre_tx_balance: None,
+            to_price_eur: None,
+            to_fee: None,
+            amount: None,
+            sold_amount: None,
+            bought_amount: None,
+            trade_type: None,
+            local_cost_basis: None,
+            exchange_pair_base: None,
+            exchange_pair_quote: None,
+            soulte: None,
+            income_type: None,
+            income_name: None,
+            income_value: None,
+        };
+        match tx {
+            Transaction::Transfer {
+                from,
+                to,
+                amount,
+                income,
+                ..
+            } => {
+                record.set_from(from);
+                record.set_to(to);
+                record.amount = Some(*amount);
+                if let Some(income) = income {
+                    let (income_type, income_name) = match income.get_subtype() {
+                        IncomeType::Airdrop => (IncomeKind::Airdrop, None),
+                        IncomeType::Hardfork => (IncomeKind::Hardfork, None),
+                        IncomeType::Income => (IncomeKind::Income, None),
+                        IncomeType::Interest => (IncomeKind::Interest, None),
+                        IncomeType::Mining => (IncomeKind::Mining, None),
+                        IncomeType::Staking => (IncomeKind::Staking, None),
+                        IncomeType::Gift => (IncomeKind::Gift, None),
+                        IncomeType::Donation => (IncomeKind::Donation, None),
+                        IncomeType::Other(name) => (IncomeKind::Other, Some(name.clone())),
+                    };
+                    record.income_type = Some(income_type);
+                    record.income_name = income_name;
+                    record.income_value = Some(income.get_value());
+                }
+            }
+            Transaction::Trade {
+                from,
+                to,
+                exchange_pair,
+                sold_amount,
+                bought_amount,
+                trade_type,
+                soulte,
+                ..
+            } => {
+                record.kind = TransactionKind::Trade;
+                record.set_from(from);
+                record.set_to(to);
+                record.sold_amount = Some(*sold_amount);
+                record.bought_amount = Some(*bought_amount);
+                record.trade_type = Some(match trade_type {
+                    TradeType::FiatToCrypto { local_cost_basis } => {
+                        record.local_cost_basis = Some(*local_cost_basis);
+                        TradeKind::FiatToCrypto
+                    }
+                    TradeType::CryptoToFiat => TradeKind::CryptoToFiat,
+                    TradeType::CryptoToCrypto => TradeKind::CryptoToCrypto,
+                });
+                if let Some((base, quote)) = exchange_pair {
+                    record.exchange_pair_base = Some(base.clone());
+                    record.exchange_pair_quote = Some(quote.clone());
+                }
+                record.soulte = *soulte;
+            }
+            Transaction::Deposit { to, amount, .. } => {
+                record.kind = TransactionKind::Deposit;
+                record.set_to(to);
+                record.amount = Some(*amount);
+            }
+            Transaction::Withdrawal { from, amount, .. } => {
+                record.kind = TransactionKind::Withdrawal;
+                record.set_from(from);
+                record.amount = Some(*amount);
+            }
+        }
+        record
+    }
+}
+
+impl TransactionRecord {
+    fn set_from(&mut self, snapshot: &WalletSnapshot) {
+        self.from_wallet = Some(snapshot.id.clone());
+        self.from_pre_tx_balance = Some(snapshot.pre_tx_balance);
+        self.from_price_eur = Some(snapshot.price_eur);
+        self.from_fee = snapshot.fee;
+    }
+
+    fn set_to(&mut self, snapshot: &WalletSnapshot) {
+        self.to_wallet = Some(snapshot.id.clone());
+        self.to_pre_tx_balance = Some(snapshot.pre_tx_balance);
+        self.to_price_eur = Some(snapshot.price_eur);
+        self.to_fee = snapshot.fee;
+    }
+
+    fn required<T: Clone>(&self, value: &Option<T>, field: &str) -> Result<T, IoError> {
+        value.clone().ok_or(IoError::new(format!(
+            "transaction {}: missing {field}",
+            self.id
+        )))
+    }
+
+    fn sender_snapshot(&self) -> Result<WalletSnapshot, IoError> {
+        Ok(WalletSnapshot {
+            id: self.required(&self.from_wallet, "from_wallet")?,
+            pre_tx_balance: self.required(&self.from_pre_tx_balance, "from_pre_tx_balance")?,
+            price_eur: self.required(&self.from_price_eur, "from_price_eur")?,
+            fee: self.from_fee,
+        })
+    }
+
+    fn receiver_snapshot(&self) -> Result<WalletSnapshot, IoError> {
+        Ok(WalletSnapshot {
+            id: self.required(&self.to_wallet, "to_wallet")?,
+            pre_tx_balance: self.required(&self.to_pre_tx_balance, "to_pre_tx_balance")?,
+            price_eur: self.required(&self.to_price_eur, "to_price_eur")?,
+            fee: self.to_fee,
+        })
+    }
+
+    /* The fields of another kind of transaction are ignored, a missing field of its kind is an error */
+    pub fn to_transaction(&self) -> Result<Transaction, IoError> {
+        let tx = TransactionBase {
+            id: self.id.clone(),
+            timestamp: self.timestamp,
+        };
+        Ok(match self.kind {
+            TransactionKind::Transfer => {
+                let income = match &self.income_type {
+                    None => None,
+                    Some(income_type) => {
+                        let subtype = match income_type {
+                            IncomeKind::Airdrop => IncomeType::Airdrop,
+                            IncomeKind::Hardfork => IncomeType::Hardfork,
+                            IncomeKind::Income => IncomeType::Income,
+                            IncomeKind::Interest => IncomeType::Interest,
+                            IncomeKind::Mining => IncomeType::Mining,
+                            IncomeKind::Staking => IncomeType::Staking,
+                            IncomeKind::Gift => IncomeType::Gift,
+                            IncomeKind::Donation => IncomeType::Donation,
+                            IncomeKind::Other => {
+                                IncomeType::Other(self.required(&self.income_name, "income_name")?)
+                            }
+                        };
+                        Some(Income::new(
+                            self.required(&self.income_value, "income_value")?,
+                            subtype,
+                        ))
+                    }
+                };
+                Transaction::Transfer {
+                    tx,
+                    from: self.sender_snapshot()?,
+                    to: self.receiver_snapshot()?,
+                    amount: self.required(&self.amount, "amount")?,
+                    income,
+                }
+            }
+            TransactionKind::Trade => {
+                let trade_type = match self.required(&self.trade_type, "trade_type")? {
+                    TradeKind::FiatToCrypto => TradeType::FiatToCrypto {
+                        local_cost_basis: self
+                            .required(&self.local_cost_basis, "local_cost_basis")?,
+                    },
+                    TradeKind::CryptoToFiat => TradeType::CryptoToFiat,
+                    TradeKind::CryptoToCrypto => TradeType::CryptoToCrypto,
+                };
+                let exchange_pair = match (&self.exchange_pair_base, &self.exchange_pair_quote) {
+                    (Some(base), Some(quote)) => Some((base.clone(), quote.clone())),
+                    (None, None) => None,
+                    _ => {
+                        return Err(IoError::new(format!(
+                            "transaction {}: incomplete exchange pair",
+                            self.id
+                        )))
+                    }
+                };
+                Transaction::Trade {
+                    tx,
+                    from: self.sender_snapshot()?,
+                    to: self.receiver_snapshot()?,
+                    exchange_pair,
+                    sold_amount: self.required(&self.sold_amount, "sold_amount")?,
+                    bought_amount: self.required(&self.bought_amount, "bought_amount")?,
+                    trade_type,
+                    soulte: self.soulte,
+                }
+            }
+            TransactionKind::Deposit => Transaction::Deposit {
+                tx,
+                to: self.receiver_snapshot()?,
+                amount: self.required(&self.amount, "amount")?,
+            },
+            TransactionKind::Withdrawal => Transaction::Withdrawal {
+                tx,
+                from: self.sender_snapshot()?,
+                amount: self.required(&self.amount, "amount")?,
+            },
+        })
+    }
+}
+
+/* Wallets sorted by id so that the exports of the same data are identical */
+fn wallet_records(wallet_manager: &WalletManager) -> Vec<WalletRecord> {
+    let mut wallets: Vec<WalletRecord> = wallet_manager
+        .wallets
+        .values()
+        .map(WalletRecord::from)
+        .collect();
+    wallets.sort_by(|a, b| a.id.cmp(&b.id));
+    wallets
+}
+
+/* Add the wallets to the wallet manager and map the transactions, checking that their wallets are known */
+fn import_records(
+    wallet_manager: &mut WalletManager,
+    wallets: &[WalletRecord],
+    transactions: &[TransactionRecord],
+) -> Result<Vec<Transaction>, IoError> {
+    for record in wallets {
+        wallet_manager.insert_wallet(record.to_wallet()?);
+    }
+    let mut txs = Vec::new();
+    for record in transactions {
+        for wallet_id in [&record.from_wallet, &record.to_wallet]
+            .into_iter()
+            .flatten()
+        {
+            if !wallet_manager.wallets.contains_key(wallet_id) {
+                return Err(IoError::new(format!(
+                    "transaction {}: unknown wallet {wallet_id}",
+                    record.id
+                )));
+            }
+        }
+        txs.push(record.to_transaction()?);
+    }
+    Ok(txs)
+}
+
+fn write_csv<T: Serialize>(path: &str, records: &[T]) -> Result<(), IoError> {
+    create_directories_if_needed(path);
+    let mut writer = csv::Writer::from_path(path).map_err(|e| IoError::new(e.to_string()))?;
+    for record in records {
+        writer
+            .serialize(record)
+            .map_err(|e| IoError::new(e.to_string()))?;
+    }
+    writer.flush().map_err(|e| IoError::new(e.to_string()))
+}
+
+fn read_standard_csv<T: for<'de> Deserialize<'de>>(path: &str) -> Result<Vec<T>, IoError> {
+    let mut reader = csv::Reader::from_path(path).map_err(|e| IoError::new(e.to_string()))?;
+    reader
+        .deserialize()
+        .map(|record| record.map_err(|e| IoError::new(format!("{path}: {e}"))))
+        .collect()
+}
+
+pub fn export_standard_csv(
+    transaction_manager: &TransactionManager,
+    wallet_manager: &WalletManager,
+    transactions_path: &str,
+    wallets_path: &str,
+) -> Result<(), IoError> {
+    let transactions: Vec<TransactionRecord> = transaction_manager
+        .get()
+        .iter()
+        .map(TransactionRecord::from)
+        .collect();
+    write_csv(wallets_path, &wallet_records(wallet_manager))?;
+    write_csv(transactions_path, &transactions)
+}
+
+pub fn import_standard_csv(
+    wallet_manager: &mut WalletManager,
+    transactions_path: &str,
+    wallets_path: &str,
+) -> Result<Vec<Transaction>, IoError> {
+    let wallets: Vec<WalletRecord> = read_standard_csv(wallets_path)?;
+    let transactions: Vec<TransactionRecord> = read_standard_csv(transactions_path)?;
+    import_records(wallet_manager, &wallets, &transactions)
+}
+
+pub fn export_standard_json(
+    transaction_manager: &TransactionManager,
+    wallet_manager: &WalletManager,
+    path: &str,
+) -> Result<(), IoError> {
+    let document = StandardDocument {
+        version: STANDARD_FORMAT_VERSION,
+        wallets: wallet_records(wallet_manager),
+        transactions: transaction_manager
+            .get()
+            .iter()
+            .map(TransactionRecord::from)
+            .collect(),
+    };
+    create_directories_if_needed(path);
+    let file = File::create(path).map_err(|e| IoError::new(e.to_string()))?;
+    serde_json::to_writer_pretty(file, &document).map_err(|e| IoError::new(e.to_string()))
+}
+
+pub fn import_standard_json(
+    wallet_manager: &mut WalletManager,
+    path: &str,
+) -> Result<Vec<Transaction>, IoError> {
+    let file = File::open(path).map_err(|e| IoError::new(e.to_string()))?;
+    let document: StandardDocument =
+        serde_json::from_reader(file).map_err(|e| IoError::new(format!("{path}: {e}")))?;
+    if document.version != STANDARD_FORMAT_VERSION {
+        return Err(IoError::new(format!(
+            "{path}: unsupported version {}",
+            document.version
+        )));
+    }
+    import_records(wallet_manager, &document.wallets, &document.transactions)
+}
+
+/* Import the data of STANDARD_JSON, or of STANDARD_TRANSACTIONS_CSV and STANDARD_WALLETS_CSV. The wallets keep the
+platforms they were saved with: the ones of a known platform are priced by its connector, the others (Platform::Other)
+by the fallback of the ConnectorRegistry */
+pub struct StandardFormatConnector {
+    pub json_path: Option<String>,
+    pub transactions_csv_path: Option<String>,
+    pub wallets_csv_path: Option<String>,
+    pub price_client: CoinbaseClient,
+}
+
+impl StandardFormatConnector {
+    pub fn from_env() -> Self {
+        StandardFormatConnector {
+            json_path: env::var("STANDARD_JSON").ok(),
+            transactions_csv_path: env::var("STANDARD_TRANSACTIONS_CSV").ok(),
+            wallets_csv_path: env::var("STANDARD_WALLETS_CSV").ok(),
+            price_client: CoinbaseClient::public_from_env(),
+        }
+    }
+}
+
+impl Connector for StandardFormatConnector {
+    // The records are read with the wallets they refer to, when they are imported
+    type History = ();
+
+    fn platform(&self) -> Platform {
+        Platform::Other("Standard format".to_string())
+    }
+
+    fn is_configured(&self) -> bool {
+        self.json_path.is_some() || self.transactions_csv_path.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        _history: (),
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut txs = Vec::new();
+            if let Some(path) = &self.json_path {
+                txs.extend(import_standard_json(wallet_manager, path)?);
+            }
+            if let Some(transactions_path) = &self.transactions_csv_path {
+                let wallets_path = self
+                    .wallets_csv_path
+                    .as_ref()
+                    .ok_or(IoError::new("STANDARD_WALLETS_CSV must be set".to_string()))?;
+                txs.extend(import_standard_csv(
+                    wallet_manager,
+                    transactions_path,
+                    wallets_path,
+                )?);
+            }
+            Ok(txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(coinbase_mapping::get_coinbase_price(
+            &self.price_client,
+            time,
+            currency,
+        ))
+    }
+}
+
+/* Export the data to EXPORT_JSON, and to EXPORT_TRANSACTIONS_CSV and EXPORT_WALLETS_CSV */
+pub fn export_standard_format_data(
+    transaction_manager: &TransactionManager,
+    wallet_manager: &WalletManager,
+) -> Result<(), IoError> {
+    if let Ok(path) = env::var("EXPORT_JSON") {
+        export_standard_json(transaction_manager, wallet_manager, &path)?;
+    }
+    if let Ok(transactions_path) = env::var("EXPORT_TRANSACTIONS_CSV") {
+        let wallets_path = env::var("EXPORT_WALLETS_CSV")
+            .map_err(|_| IoError::new("EXPORT_WALLETS_CSV must be set".to_string()))?;
+        export_standard_csv(
+            transaction_manager,
+            wallet_manager,
+            &transactions_path,
+            &wallets_path,
+        )?;
+    }
+    Ok(())
+}
@@ -1,56 +1,600 @@
-use csv::ReaderBuilder;
-use std::error::Error;
-use std::fs::File;
-use std::io::prelude::*;
-
-#[derive(Debug)]
-struct Transaction {
-    id: String,
-    date_utc: String,
-    transaction_type: String,
-    tag: String,
-    from_wallet: String,
-    from_wallet_id: String,
-    from_amount: String,
-    from_currency: String,
-    to_wallet: String,
-    to_wallet_id: String,
-    to_amount: String,
-    to_currency: String,
-    fee_amount: String,
-    fee_currency: String,
-    net_worth_amount: String,
-    net_worth_currency: String,
-    fee_worth_amount: String,
-    fee_worth_currency: String,
-    net_value: String,
-    fee_value: String,
-    value_currency: String,
-    deleted: String,
-    from_source: String,
-    to_source: String,
-    negative_balances: String,
-    missing_rates: String,
-    missing_cost_basis: String,
-    synced_to_accounting_at_utc: String,
-    tx_src: String,
-    tx_dest: String,
-    tx_hash: String,
-    description: String,
-}
-
-fn main() -> Result<(), Box<dyn Error>> {
-    // Open the CSV file
-    let mut file = File::open("transactions.csv")?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-
-    // Parse CSV data
-    let mut rdr = ReaderBuilder::new().from_reader(contents.as_bytes());
-    // for result in rdr.deserialize::<Transaction>() {
-    //     let record = result?;
-    //     println!("{:?}", record);
-    // }
+use chrono::{DateTime, NaiveDateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::env;
 
+use crate::{
+    api::{coinbase_mapping, CoinbaseClient, Connector, ConnectorFuture, PriceFuture},
+    errors::{ApiError, IoError, MappingError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Address, Income, IncomeType, TradeType,
+        Transaction, TransactionBase, WalletId, WalletSnapshot,
+    },
+};
+
+/* Row of the transactions csv exported from Koinly (Transactions > Export > Transactions):
+ID,Date (UTC),Type,Tag,From Wallet,From Wallet ID,From Amount,From Currency,To Wallet,To Wallet ID,To Amount,To Currency,
+Fee Amount,Fee Currency,Net Worth Amount,Net Worth Currency,Fee Worth Amount,Fee Worth Currency,Net Value,Fee Value,
+Value Currency,Deleted,From Source,To Source,Negative Balances,Missing Rates,Missing Cost Basis,
+Synced To Accounting At (UTC),TxSrc,TxDest,TxHash,Description
+The amounts don't include the fee. The net worth is the value of the transaction in the net worth currency. */
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KoinlyTransaction {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(rename = "Date (UTC)")]
+    pub date_utc: String,
+    #[serde(rename = "Type")]
+    pub transaction_type: String,
+    #[serde(rename = "Tag")]
+    pub tag: Option<String>,
+    #[serde(rename = "From Wallet")]
+    pub from_wallet: Option<String>,
+    #[serde(rename = "From Wallet ID")]
+    pub from_wallet_id: Option<String>,
+    #[serde(rename = "From Amount")]
+    pub from_amount: Option<Decimal>,
+    #[serde(rename = "From Currency")]
+    pub from_currency: Option<String>,
+    #[serde(rename = "To Wallet")]
+    pub to_wallet: Option<String>,
+    #[serde(rename = "To Wallet ID")]
+    pub to_wallet_id: Option<String>,
+    #[serde(rename = "To Amount")]
+    pub to_amount: Option<Decimal>,
+    #[serde(rename = "To Currency")]
+    pub to_currency: Option<String>,
+    #[serde(rename = "Fee Amount")]
+    pub fee_amount: Option<Decimal>,
+    #[serde(rename = "Fee Currency")]
+    pub fee_currency: Option<String>,
+    #[serde(rename = "Net Worth Amount")]
+    pub net_worth_amount: Option<Decimal>,
+    #[serde(rename = "Net Worth Currency")]
+    pub net_worth_currency: Option<String>,
+    #[serde(rename = "Deleted")]
+    pub deleted: Option<String>,
+    #[serde(rename = "TxSrc")]
+    pub tx_src: Option<String>,
+    #[serde(rename = "TxDest")]
+    pub tx_dest: Option<String>,
+    #[serde(rename = "TxHash")]
+    pub tx_hash: Option<String>,
+    #[serde(rename = "Description")]
+    pub description: Option<String>,
+}
+
+impl KoinlyTransaction {
+    pub fn timestamp(&self) -> Result<DateTime<Utc>, ApiError> {
+        let date = self.date_utc.trim_end_matches(" UTC");
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+            .map(|date| date.and_utc())
+            .map_err(|e| ApiError::DeserializationError(format!("{}: {e}", self.date_utc)))
+    }
+
+    /* Tag as a lowercase identifier: "Lending interest" and "lending_interest" are the same */
+    pub fn tag(&self) -> Option<String> {
+        self.tag
+            .as_ref()
+            .map(|tag| tag.trim().to_lowercase().replace(' ', "_"))
+            .filter(|tag| !tag.is_empty())
+    }
+
+    fn is_deleted(&self) -> bool {
+        self.deleted
+            .as_ref()
+            .is_some_and(|deleted| deleted.eq_ignore_ascii_case("true"))
+    }
+}
+
+pub fn read_koinly_csv(file_path: &str) -> Result<Vec<KoinlyTransaction>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_path)
+        .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+    let mut rows = Vec::new();
+    for record in reader.deserialize::<KoinlyTransaction>() {
+        rows.push(record.map_err(|e| ApiError::DeserializationError(e.to_string()))?);
+    }
+    Ok(rows)
+}
+
+/* Read and map the Koinly export (env KOINLY_CSV). The file is read again at each run, so it is not saved.
+The wallets of the export are on the platforms recognized by their name (see koinly_platform): the ones of a known
+platform are priced by its connector, the others (Platform::Other, as the Lost wallet) by the fallback of the
+ConnectorRegistry */
+pub struct KoinlyConnector {
+    pub csv_path: Option<String>,
+    pub price_client: CoinbaseClient,
+}
+
+impl KoinlyConnector {
+    pub fn from_env() -> Self {
+        KoinlyConnector {
+            csv_path: env::var("KOINLY_CSV").ok(),
+            price_client: CoinbaseClient::public_from_env(),
+        }
+    }
+}
+
+impl Connector for KoinlyConnector {
+    type History = Vec<KoinlyTransaction>;
+
+    fn platform(&self) -> Platform {
+        Platform::Other("Koinly".to_string())
+    }
+
+    fn is_configured(&self) -> bool {
+        self.csv_path.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, Vec<KoinlyTransaction>> {
+        Box::pin(async move {
+            let file_path = self
+                .csv_path
+                .as_ref()
+                .ok_or(IoError::new("KOINLY_CSV not set in .env file".to_string()))?;
+            read_koinly_csv(file_path).map_err(|e| IoError::new(e.to_string()))
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        rows: Vec<KoinlyTransaction>,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let mut koinly_txs: Vec<Transaction> = Vec::new();
+            create_koinly_txs(wallet_manager, &mut koinly_txs, &rows, &self.price_client)
+                .await
+                .map_err(|e| IoError::new(e.to_string()))?;
+            Ok(koinly_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(coinbase_mapping::get_coinbase_price(
+            &self.price_client,
+            time,
+            currency,
+        ))
+    }
+}
+
+/* Tags of Koinly for the crypto received from nowhere */
+pub fn koinly_income_type(tag: &str) -> Option<IncomeType> {
+    match tag {
+        "reward" | "income" | "other_income" | "cashback" => Some(IncomeType::Income),
+        "airdrop" => Some(IncomeType::Airdrop),
+        "fork" => Some(IncomeType::Hardfork),
+        "mining" => Some(IncomeType::Mining),
+        "staking" => Some(IncomeType::Staking),
+        "lending_interest" | "interest" => Some(IncomeType::Interest),
+        "gift" => Some(IncomeType::Gift),
+        "donation" => Some(IncomeType::Donation),
+        _ => None,
+    }
+}
+
+/* Platform of a Koinly wallet, recognized by its name. The wallets of the blockchains keep their name */
+pub fn koinly_platform(wallet_name: &str) -> Platform {
+    let name = wallet_name.to_lowercase();
+    if name.contains("binance") {
+        Platform::Binance
+    } else if name.contains("bitfinex") {
+        Platform::Bitfinex
+    } else if name.contains("bitpanda") {
+        Platform::Bitpanda
+    } else if name.contains("coinbase") {
+        Platform::Coinbase
+    } else if name.contains("crypto.com") {
+        Platform::CryptoCom
+    } else if name.contains("kraken") {
+        Platform::Kraken
+    } else if name.contains("kucoin") {
+        Platform::KuCoin
+    } else {
+        Platform::Other(wallet_name.to_string())
+    }
+}
+
+/* Map the rows of a Koinly export to transactions.
+
+Each Koinly wallet is a wallet of the platform recognized by its name, its address being the Koinly wallet id so the
+imported history doesn't mix with the one fetched from the APIs:
+- exchange, buy and sell are Trades, typed by the fiat side
+- transfer is a Transfer between two wallets
+- the fiat deposits and withdrawals are Deposits and Withdrawals
+- the crypto deposits tagged as an income (reward, airdrop, staking...) are incomes, the others a Transfer from outside
+- the crypto withdrawals tagged lost or stolen go to a Platform::Other("Lost") wallet, the ones tagged cost are only
+a fee, the others a Transfer to outside
+When the net worth is in EUR, it gives the price of the currencies, otherwise the prices come from Coinbase.
+The deleted rows are ignored. As for Binance, the balances are recalculated from zero.
+*/
+pub async fn create_koinly_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    rows: &[KoinlyTransaction],
+    price_client: &CoinbaseClient,
+) -> Result<(), ApiError> {
+    let mut rows: Vec<(DateTime<Utc>, &KoinlyTransaction)> = rows
+        .iter()
+        .filter(|row| !row.is_deleted())
+        .map(|row| row.timestamp().map(|time| (time, row)))
+        .collect::<Result<_, _>>()?;
+    rows.sort_by_key(|(time, _)| *time);
+
+    let mut mapper = KoinlyMapper {
+        price_client,
+        wallet_manager,
+        balances: HashMap::new(),
+        prices: HashMap::new(),
+    };
+    for (time, row) in rows {
+        mapper.map_row(txs, row, time).await?;
+    }
     Ok(())
 }
+
+// Fiat currencies of the Koinly wallets
+pub enum FiatKoinly {
+    EUR,
+    USD,
+    GBP,
+    CHF,
+    CAD,
+    AUD,
+}
+
+impl FiatKoinly {
+    pub fn from_code(s: &str) -> Option<FiatKoinly> {
+        match s {
+            "EUR" => Some(FiatKoinly::EUR),
+            "USD" => Some(FiatKoinly::USD),
+            "GBP" => Some(FiatKoinly::GBP),
+            "CHF" => Some(FiatKoinly::CHF),
+            "CAD" => Some(FiatKoinly::CAD),
+            "AUD" => Some(FiatKoinly::AUD),
+            _ => None,
+        }
+    }
+
+    pub fn is_fiat(s: &str) -> bool {
+        Self::from_code(s).is_some()
+    }
+
+    pub fn is_eur(s: &str) -> bool {
+        matches!(Self::from_code(s), Some(FiatKoinly::EUR))
+    }
+}
+
+/* Side of a row: the currency leaving or reaching a Koinly wallet */
+struct KoinlySide {
+    wallet_id: WalletId,
+    currency: String,
+    amount: Decimal,
+    price_eur: Decimal,
+}
+
+struct KoinlyMapper<'a> {
+    price_client: &'a CoinbaseClient,
+    wallet_manager: &'a mut WalletManager,
+    balances: HashMap<WalletId, Decimal>,
+    prices: HashMap<(String, String), Decimal>, // Price of a currency for a given day
+}
+
+impl KoinlyMapper<'_> {
+    async fn map_row(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &KoinlyTransaction,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let tx = TransactionBase {
+            id: format!("koinly-{}", row.id),
+            timestamp: time,
+        };
+        let from = self
+            .side(
+                row,
+                &row.from_wallet,
+                &row.from_wallet_id,
+                row.from_amount,
+                &row.from_currency,
+                time,
+            )
+            .await?;
+        let to = self
+            .side(
+                row,
+                &row.to_wallet,
+                &row.to_wallet_id,
+                row.to_amount,
+                &row.to_currency,
+                time,
+            )
+            .await?;
+        let fee = row.fee_amount.filter(|fee| !fee.is_zero());
+        let fee_currency = row.fee_currency.clone().unwrap_or_default();
+        let tag = row.tag();
+
+        match (row.transaction_type.as_str(), from, to) {
+            ("exchange" | "buy" | "sell", Some(from), Some(to)) => {
+                let from_fee = fee.filter(|_| fee_currency == from.currency);
+                let to_fee = fee.filter(|_| from_fee.is_none() && fee_currency == to.currency);
+                if fee.is_some() && from_fee.is_none() && to_fee.is_none() {
+                    self.push_fee(txs, row, time).await?;
+                }
+                let trade_type = match (
+                    FiatKoinly::is_fiat(&from.currency),
+                    FiatKoinly::is_fiat(&to.currency),
+                ) {
+                    (true, false) => TradeType::FiatToCrypto {
+                        local_cost_basis: from.amount * from.price_eur,
+                    },
+                    (false, true) => TradeType::CryptoToFiat,
+                    _ => TradeType::CryptoToCrypto,
+                };
+                let from_snapshot = self.snapshot(&from, from_fee);
+                let to_snapshot = self.snapshot(&to, to_fee);
+                self.update_balance(&from.wallet_id, -from.amount - from_fee.unwrap_or(dec!(0)));
+                self.update_balance(&to.wallet_id, to.amount - to_fee.unwrap_or(dec!(0)));
+                txs.push(Transaction::Trade {
+                    tx,
+                    from: from_snapshot,
+                    to: to_snapshot,
+                    exchange_pair: None,
+                    sold_amount: from.amount,
+                    bought_amount: to.amount,
+                    trade_type,
+                    soulte: None,
+                });
+            }
+            ("transfer", Some(from), Some(to)) => {
+                // The fee is what was sent but not received when Koinly doesn't give it
+                let fee = fee.or(Some(from.amount - to.amount).filter(|fee| *fee > dec!(0)));
+                let from_snapshot = self.snapshot(&from, fee);
+                let to_snapshot = WalletSnapshot {
+                    price_eur: from.price_eur,
+                    ..self.snapshot(&to, None)
+                };
+                self.update_balance(&from.wallet_id, -to.amount - fee.unwrap_or(dec!(0)));
+                self.update_balance(&to.wallet_id, to.amount);
+                txs.push(Transaction::Transfer {
+                    tx,
+                    from: from_snapshot,
+                    to: to_snapshot,
+                    amount: to.amount,
+                    income: None,
+                });
+            }
+            (_, None, Some(to)) if FiatKoinly::is_fiat(&to.currency) => {
+                let snapshot = self.snapshot(&to, fee);
+                self.update_balance(&to.wallet_id, to.amount - fee.unwrap_or(dec!(0)));
+                txs.push(Transaction::Deposit {
+                    tx,
+                    to: snapshot,
+                    amount: to.amount,
+                });
+            }
+            (_, Some(from), None) if FiatKoinly::is_fiat(&from.currency) => {
+                let snapshot = self.snapshot(&from, fee);
+                self.update_balance(&from.wallet_id, -from.amount - fee.unwrap_or(dec!(0)));
+                txs.push(Transaction::Withdrawal {
+                    tx,
+                    from: snapshot,
+                    amount: from.amount,
+                });
+            }
+            (_, None, Some(to)) => {
+                let snapshot = self.snapshot(&to, fee);
+                self.update_balance(&to.wallet_id, to.amount - fee.unwrap_or(dec!(0)));
+                match tag.as_deref().and_then(koinly_income_type) {
+                    Some(subtype) => txs.push(Transaction::Transfer {
+                        tx,
+                        from: WalletSnapshot {
+                            fee: None,
+                            ..snapshot.clone()
+                        },
+                        to: snapshot,
+                        amount: to.amount,
+                        income: Some(Income::new(to.amount * to.price_eur, subtype)),
+                    }),
+                    None => {
+                        let external = self.external_snapshot(
+                            &to.currency,
+                            &Platform::Blockchain,
+                            &row.tx_src,
+                            to.amount,
+                            to.price_eur,
+                        );
+                        txs.push(Transaction::Transfer {
+                            tx,
+                            from: external,
+                            to: snapshot,
+                            amount: to.amount,
+                            income: None,
+                        });
+                    }
+                }
+            }
+            (_, Some(from), None) => match tag.as_deref() {
+                Some("cost") => {
+                    // The amount is a fee paid outside of a transaction, like a margin or a service fee
+                    let fee = from.amount + fee.unwrap_or(dec!(0));
+                    let snapshot = self.snapshot(&from, Some(fee));
+                    self.update_balance(&from.wallet_id, -fee);
+                    txs.push(Transaction::Transfer {
+                        tx,
+                        from: snapshot.clone(),
+                        to: WalletSnapshot {
+                            fee: None,
+                            ..snapshot
+                        },
+                        amount: dec!(0),
+                        income: None,
+                    });
+                }
+                _ => {
+                    let (platform, address) = match tag.as_deref() {
+                        Some("lost" | "stolen") => (Platform::Other("Lost".to_string()), None),
+                        _ => (Platform::Blockchain, row.tx_dest.clone()),
+                    };
+                    let snapshot = self.snapshot(&from, fee);
+                    let external = self.external_snapshot(
+                        &from.currency,
+                        &platform,
+                        &address,
+                        dec!(0),
+                        from.price_eur,
+                    );
+                    self.update_balance(&from.wallet_id, -from.amount - fee.unwrap_or(dec!(0)));
+                    txs.push(Transaction::Transfer {
+                        tx,
+                        from: snapshot,
+                        to: external,
+                        amount: from.amount,
+                        income: None,
+                    });
+                }
+            },
+            _ => {
+                return Err(ApiError::MappingError(MappingError::Other(format!(
+                    "Unsupported Koinly transaction {} of type {}",
+                    row.id, row.transaction_type
+                ))))
+            }
+        }
+        Ok(())
+    }
+
+    /* Fee paid in another currency than the ones of the trade, taken from the wallet of this currency */
+    async fn push_fee(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &KoinlyTransaction,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let fee = row.fee_amount.unwrap_or(dec!(0));
+        let Some(side) = self
+            .side(
+                row,
+                &row.from_wallet,
+                &row.from_wallet_id,
+                Some(fee),
+                &row.fee_currency,
+                time,
+            )
+            .await?
+        else {
+            return Ok(());
+        };
+        let snapshot = self.snapshot(&side, Some(fee));
+        self.update_balance(&side.wallet_id, -fee);
+        txs.push(Transaction::Transfer {
+            tx: TransactionBase {
+                id: format!("koinly-{}-fee", row.id),
+                timestamp: time,
+            },
+            from: snapshot.clone(),
+            to: WalletSnapshot {
+                fee: None,
+                ..snapshot
+            },
+            amount: dec!(0),
+            income: None,
+        });
+        Ok(())
+    }
+
+    /* The side is None when the row has no wallet or currency on it (the from side of a deposit...) */
+    async fn side(
+        &mut self,
+        row: &KoinlyTransaction,
+        wallet: &Option<String>,
+        wallet_id: &Option<String>,
+        amount: Option<Decimal>,
+        currency: &Option<String>,
+        time: DateTime<Utc>,
+    ) -> Result<Option<KoinlySide>, ApiError> {
+        let (Some(wallet), Some(currency)) = (
+            wallet.as_ref().filter(|wallet| !wallet.is_empty()),
+            currency.as_ref().filter(|currency| !currency.is_empty()),
+        ) else {
+            return Ok(None);
+        };
+        let amount = amount.unwrap_or(dec!(0));
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            &koinly_platform(wallet),
+            &Some(wallet_id.clone().unwrap_or(wallet.clone())),
+            FiatKoinly::is_fiat(currency),
+        );
+        let net_worth_eur = row.net_worth_amount.filter(|_| {
+            row.net_worth_currency
+                .as_ref()
+                .is_some_and(|net_worth_currency| FiatKoinly::is_eur(net_worth_currency))
+        });
+        let price_eur = match net_worth_eur {
+            _ if FiatKoinly::is_eur(currency) => dec!(1),
+            Some(net_worth) if !amount.is_zero() => net_worth / amount,
+            _ => self.price(currency, time).await?,
+        };
+        Ok(Some(KoinlySide {
+            wallet_id: id,
+            currency: currency.clone(),
+            amount,
+            price_eur,
+        }))
+    }
+
+    fn snapshot(&self, side: &KoinlySide, fee: Option<Decimal>) -> WalletSnapshot {
+        WalletSnapshot {
+            id: side.wallet_id.clone(),
+            pre_tx_balance: *self.balances.get(&side.wallet_id).unwrap_or(&dec!(0)),
+            price_eur: side.price_eur,
+            fee,
+        }
+    }
+
+    /* Wallet outside of Koinly: we don't know its balance so it only holds the amount transfered */
+    fn external_snapshot(
+        &mut self,
+        currency: &str,
+        platform: &Platform,
+        address: &Address,
+        pre_tx_balance: Decimal,
+        price_eur: Decimal,
+    ) -> WalletSnapshot {
+        let address = address.clone().filter(|address| !address.is_empty());
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            currency,
+            platform,
+            &address,
+            FiatKoinly::is_fiat(currency),
+        );
+        WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur,
+            fee: None,
+        }
+    }
+
+    fn update_balance(&mut self, wallet_id: &WalletId, change: Decimal) {
+        *self.balances.entry(wallet_id.clone()).or_insert(dec!(0)) += change;
+    }
+
+    async fn price(&mut self, currency: &str, time: DateTime<Utc>) -> Result<Decimal, ApiError> {
+        let key = (currency.to_string(), time.format("%Y-%m-%d").to_string());
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+        let price =
+            coinbase_mapping::get_coinbase_price(self.price_client, time, currency).await?;
+        self.prices.insert(key, price);
+        Ok(price)
+    }
+}
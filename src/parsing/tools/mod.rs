@@ -1,2 +1,2 @@
-// pub mod koinly;
-// pub use koinly::*;
+pub mod koinly;
+pub use koinly::*;
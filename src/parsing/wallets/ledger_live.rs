@@ -0,0 +1,392 @@
+use chrono::{DateTime, Utc};
+use hashbrown::HashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use std::env;
+
+use crate::{
+    api::{
+        coinbase_mapping, CoinGeckoClient, CoinbaseClient, Connector, ConnectorFuture, PriceFuture,
+    },
+    errors::{ApiError, IoError},
+    structs::{
+        wallet::Platform, wallet_manager::WalletManager, Income, IncomeType, Transaction,
+        TransactionBase, WalletSnapshot,
+    },
+};
+
+/* Row of the operations csv exported from Ledger Live (Settings > Accounts > Export operations):
+Operation Date,Status,Currency Ticker,Operation Type,Operation Amount,Operation Fees,Operation Hash,Account Name,
+Account xpub,Countervalue Ticker,Countervalue at Operation Date,Countervalue at CSV Export
+The amount is negative for the operations leaving the account, and then includes the fees. The fees of a token
+operation are empty: they are paid by the parent account in a FEES operation of the same hash. */
+#[derive(Debug, Clone, Deserialize)]
+pub struct LedgerLiveOperation {
+    #[serde(rename = "Operation Date")]
+    pub date: String,
+    #[serde(rename = "Status")]
+    pub status: Option<String>,
+    #[serde(rename = "Currency Ticker")]
+    pub ticker: String,
+    #[serde(rename = "Operation Type")]
+    pub operation_type: String,
+    #[serde(rename = "Operation Amount")]
+    pub amount: Decimal,
+    #[serde(rename = "Operation Fees")]
+    pub fees: Option<Decimal>,
+    #[serde(rename = "Operation Hash")]
+    pub hash: String,
+    #[serde(rename = "Account xpub")]
+    pub xpub: String,
+    #[serde(rename = "Countervalue Ticker")]
+    pub countervalue_ticker: Option<String>,
+    #[serde(rename = "Countervalue at Operation Date")]
+    pub countervalue: Option<Decimal>,
+}
+
+impl LedgerLiveOperation {
+    pub fn timestamp(&self) -> Result<DateTime<Utc>, ApiError> {
+        DateTime::parse_from_rfc3339(&self.date)
+            .map(|date| date.with_timezone(&Utc))
+            .map_err(|e| ApiError::DeserializationError(format!("{}: {e}", self.date)))
+    }
+
+    /* A failed operation only costs its fees */
+    fn has_failed(&self) -> bool {
+        self.status
+            .as_ref()
+            .is_some_and(|status| status.eq_ignore_ascii_case("failed"))
+    }
+
+    fn fees(&self) -> Decimal {
+        self.fees.unwrap_or(dec!(0)).abs()
+    }
+
+    /* Price given by the countervalue of the operation when it is in euro */
+    fn price_eur(&self) -> Option<Decimal> {
+        match (&self.countervalue_ticker, self.countervalue) {
+            (Some(ticker), Some(countervalue)) if ticker == "EUR" && !self.amount.is_zero() => {
+                Some(countervalue.abs() / self.amount.abs())
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn read_ledger_live_csv(file_path: &str) -> Result<Vec<LedgerLiveOperation>, ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_path)
+        .map_err(|e| ApiError::DeserializationError(e.to_string()))?;
+    let mut rows = Vec::new();
+    for record in reader.deserialize::<LedgerLiveOperation>() {
+        rows.push(record.map_err(|e| ApiError::DeserializationError(e.to_string()))?);
+    }
+    Ok(rows)
+}
+
+/* Read and map the Ledger Live export (env LEDGER_LIVE_CSV), see KoinlyConnector.
+As for all the wallets outside of the exchanges, the prices come from CoinGecko */
+pub struct LedgerLiveConnector {
+    pub csv_path: Option<String>,
+    pub price_client: CoinGeckoClient,
+}
+
+impl LedgerLiveConnector {
+    pub fn from_env() -> Self {
+        LedgerLiveConnector {
+            csv_path: env::var("LEDGER_LIVE_CSV").ok(),
+            price_client: CoinGeckoClient::from_env(),
+        }
+    }
+}
+
+impl Connector for LedgerLiveConnector {
+    type History = Vec<LedgerLiveOperation>;
+
+    fn platform(&self) -> Platform {
+        Platform::Blockchain
+    }
+
+    fn is_configured(&self) -> bool {
+        self.csv_path.is_some()
+    }
+
+    fn fetch_history(&self) -> ConnectorFuture<'_, Vec<LedgerLiveOperation>> {
+        Box::pin(async move {
+            let file_path = self.csv_path.as_ref().ok_or(IoError::new(
+                "LEDGER_LIVE_CSV not set in .env file".to_string(),
+            ))?;
+            read_ledger_live_csv(file_path).map_err(|e| IoError::new(e.to_string()))
+        })
+    }
+
+    fn map_history<'a>(
+        &'a self,
+        wallet_manager: &'a mut WalletManager,
+        rows: Vec<LedgerLiveOperation>,
+    ) -> ConnectorFuture<'a, Vec<Transaction>> {
+        Box::pin(async move {
+            let price_client = CoinbaseClient::public_from_env();
+            let mut ledger_live_txs: Vec<Transaction> = Vec::new();
+            create_ledger_live_txs(wallet_manager, &mut ledger_live_txs, &rows, &price_client)
+                .await
+                .map_err(|e| IoError::new(e.to_string()))?;
+            Ok(ledger_live_txs)
+        })
+    }
+
+    fn get_price<'a>(&'a self, time: DateTime<Utc>, currency: &'a str) -> PriceFuture<'a> {
+        Box::pin(self.price_client.fetch_price(currency, time))
+    }
+}
+
+/* Map the operations of the Ledger Live accounts to transactions.
+
+Each account is a Platform::Blockchain wallet whose address is the xpub of the account (the address for the
+account based blockchains, the parent one for the tokens), as the wallets of create_bitcoin_txs, so the
+withdrawals of the exchanges to these wallets can be matched with them.
+- IN is a Transfer from outside, OUT a Transfer to outside holding the fees
+- an OUT received by another account of the export (same hash and currency) is a Transfer between the two wallets
+- REWARD is an income (IncomeType::Staking)
+- the other operations (FEES, DELEGATE, ...) and the failed ones only cost their fees, in a Transfer of 0
+The countervalue gives the price when it is in euro, otherwise the price comes from Coinbase. As for Binance, the
+balances are recalculated from zero.
+*/
+pub async fn create_ledger_live_txs(
+    wallet_manager: &mut WalletManager,
+    txs: &mut Vec<Transaction>,
+    rows: &[LedgerLiveOperation],
+    price_client: &CoinbaseClient,
+) -> Result<(), ApiError> {
+    let mut rows: Vec<(DateTime<Utc>, &LedgerLiveOperation)> = rows
+        .iter()
+        .map(|row| row.timestamp().map(|time| (time, row)))
+        .collect::<Result<_, _>>()?;
+    // Ledger Live exports the most recent operations first
+    rows.sort_by_key(|(time, _)| *time);
+
+    // The moves between two accounts of the export are an OUT and an IN of the same hash
+    let moves = |operation_type: &str| -> HashMap<(&str, &str), &LedgerLiveOperation> {
+        rows.iter()
+            .filter(|(_, row)| row.operation_type == operation_type && !row.has_failed())
+            .map(|(_, row)| ((row.hash.as_str(), row.ticker.as_str()), *row))
+            .collect()
+    };
+    let (sent, received) = (moves("OUT"), moves("IN"));
+
+    let mut mapper = LedgerLiveMapper {
+        price_client,
+        wallet_manager,
+        balances: HashMap::new(),
+        prices: HashMap::new(),
+    };
+    for (time, row) in &rows {
+        let key = (row.hash.as_str(), row.ticker.as_str());
+        let is_other_account = |other: &LedgerLiveOperation| other.xpub != row.xpub;
+        match row.operation_type.as_str() {
+            _ if row.has_failed() => mapper.map_fees(txs, row, *time).await?,
+            "OUT" => {
+                let destination = received
+                    .get(&key)
+                    .copied()
+                    .filter(|destination| is_other_account(destination));
+                mapper.map_out(txs, row, destination, *time).await?
+            }
+            // Already mapped with the OUT of the sending account
+            "IN" if sent
+                .get(&key)
+                .is_some_and(|source| is_other_account(source)) => {}
+            "IN" => mapper.map_in(txs, row, *time).await?,
+            "REWARD" | "REWARD_PAYOUT" => mapper.map_reward(txs, row, *time).await?,
+            _ => mapper.map_fees(txs, row, *time).await?,
+        }
+    }
+    Ok(())
+}
+
+struct LedgerLiveMapper<'a> {
+    price_client: &'a CoinbaseClient,
+    wallet_manager: &'a mut WalletManager,
+    balances: HashMap<(String, String), Decimal>, // Balance of each (currency, xpub) before the current operation
+    prices: HashMap<(String, String), Decimal>,   // Price of a currency for a given day
+}
+
+impl LedgerLiveMapper<'_> {
+    async fn map_in(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &LedgerLiveOperation,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        // The fees of an IN are paid by the sender
+        let amount = row.amount.abs();
+        let to = self.snapshot(row, &row.xpub, None, time).await?;
+        let from = self.external_snapshot(&row.ticker, amount, to.price_eur);
+        self.update_balance(&row.ticker, &row.xpub, amount);
+        txs.push(Transaction::Transfer {
+            tx: TransactionBase {
+                id: format!("ledger-live-{}", row.hash),
+                timestamp: time,
+            },
+            from,
+            to,
+            amount,
+            income: None,
+        });
+        Ok(())
+    }
+
+    async fn map_out(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &LedgerLiveOperation,
+        destination: Option<&LedgerLiveOperation>,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let fee = Some(row.fees()).filter(|fee| !fee.is_zero());
+        let amount = row.amount.abs() - row.fees();
+        let from = self.snapshot(row, &row.xpub, fee, time).await?;
+        let to = match destination {
+            Some(destination) => {
+                let to = self
+                    .snapshot(destination, &destination.xpub, None, time)
+                    .await?;
+                self.update_balance(&row.ticker, &destination.xpub, amount);
+                to
+            }
+            None => self.external_snapshot(&row.ticker, dec!(0), from.price_eur),
+        };
+        self.update_balance(&row.ticker, &row.xpub, -row.amount.abs());
+        txs.push(Transaction::Transfer {
+            tx: TransactionBase {
+                id: format!("ledger-live-{}", row.hash),
+                timestamp: time,
+            },
+            from,
+            to,
+            amount,
+            income: None,
+        });
+        Ok(())
+    }
+
+    async fn map_reward(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &LedgerLiveOperation,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let amount = row.amount.abs();
+        let snapshot = self.snapshot(row, &row.xpub, None, time).await?;
+        let income = Income::new(amount * snapshot.price_eur, IncomeType::Staking);
+        self.update_balance(&row.ticker, &row.xpub, amount);
+        txs.push(Transaction::Transfer {
+            tx: TransactionBase {
+                id: format!("ledger-live-{}", row.hash),
+                timestamp: time,
+            },
+            from: snapshot.clone(),
+            to: snapshot,
+            amount,
+            income: Some(income),
+        });
+        Ok(())
+    }
+
+    /* Operation only costing its fees, as the FEES paid by the parent account of a token */
+    async fn map_fees(
+        &mut self,
+        txs: &mut Vec<Transaction>,
+        row: &LedgerLiveOperation,
+        time: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let fee = row.fees();
+        if fee.is_zero() {
+            return Ok(());
+        }
+        let snapshot = self.snapshot(row, &row.xpub, Some(fee), time).await?;
+        self.update_balance(&row.ticker, &row.xpub, -fee);
+        txs.push(Transaction::Transfer {
+            tx: TransactionBase {
+                id: format!("ledger-live-{}-fee", row.hash),
+                timestamp: time,
+            },
+            from: snapshot.clone(),
+            to: WalletSnapshot {
+                fee: None,
+                ..snapshot
+            },
+            amount: dec!(0),
+            income: None,
+        });
+        Ok(())
+    }
+
+    async fn snapshot(
+        &mut self,
+        row: &LedgerLiveOperation,
+        xpub: &str,
+        fee: Option<Decimal>,
+        time: DateTime<Utc>,
+    ) -> Result<WalletSnapshot, ApiError> {
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            &row.ticker,
+            &Platform::Blockchain,
+            &Some(xpub.to_string()),
+            false,
+        );
+        let price_eur = match row.price_eur() {
+            Some(price) => price,
+            None => self.price(&row.ticker, time).await?,
+        };
+        Ok(WalletSnapshot {
+            id,
+            pre_tx_balance: *self
+                .balances
+                .get(&(row.ticker.clone(), xpub.to_string()))
+                .unwrap_or(&dec!(0)),
+            price_eur,
+            fee,
+        })
+    }
+
+    /* Wallet outside of the export: its address is unknown, it only holds the amount transfered */
+    fn external_snapshot(
+        &mut self,
+        ticker: &str,
+        pre_tx_balance: Decimal,
+        price_eur: Decimal,
+    ) -> WalletSnapshot {
+        let id = self.wallet_manager.create_or_get_wallet_id(
+            ticker,
+            &Platform::Blockchain,
+            &None,
+            false,
+        );
+        WalletSnapshot {
+            id,
+            pre_tx_balance,
+            price_eur,
+            fee: None,
+        }
+    }
+
+    fn update_balance(&mut self, ticker: &str, xpub: &str, change: Decimal) {
+        *self
+            .balances
+            .entry((ticker.to_string(), xpub.to_string()))
+            .or_insert(dec!(0)) += change;
+    }
+
+    async fn price(&mut self, ticker: &str, time: DateTime<Utc>) -> Result<Decimal, ApiError> {
+        let key = (ticker.to_string(), time.format("%Y-%m-%d").to_string());
+        if let Some(price) = self.prices.get(&key) {
+            return Ok(*price);
+        }
+        let price = coinbase_mapping::get_coinbase_price(self.price_client, time, ticker).await?;
+        self.prices.insert(key, price);
+        Ok(price)
+    }
+}
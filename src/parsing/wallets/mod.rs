@@ -0,0 +1,2 @@
+pub mod ledger_live;
+pub use ledger_live::*;
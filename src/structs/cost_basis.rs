@@ -1,11 +1,42 @@
 use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
 
 /* Representation of the total portfolio cost basis at a specific point in time (before the associated transaction).
 This is calculated iteratively after having transformed the data.
+
+The form 2086 needs the detail of the net acquisition price (line 223), so we keep the cumulative amounts that were
+removed from the total acquisition price (line 220):
+    pf_cost_basis = pf_total_cost - pf_consumed_fractions - pf_soultes
 */
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalCostBasis {
     pub pf_cost_basis: Decimal, // Cost basis of the portfolio in euro (acquisition NET)
     pub pf_total_cost: Decimal, // Cost basis of the portfolio in euro (acquisition BRUTE)
+    #[serde(default)]
+    pub pf_consumed_fractions: Decimal, // Sum of the fractions of initial capital included in the previous cessions (line 221)
+    #[serde(default)]
+    pub pf_soultes: Decimal, // Sum of the soultes received during previous exchanges (line 222)
+}
+
+impl GlobalCostBasis {
+    pub fn new() -> Self {
+        GlobalCostBasis {
+            pf_cost_basis: dec!(0),
+            pf_total_cost: dec!(0),
+            pf_consumed_fractions: dec!(0),
+            pf_soultes: dec!(0),
+        }
+    }
+
+    /* Check that the net acquisition price can be reconciled with the detail of the form 2086 (220 - 221 - 222 = 223) */
+    pub fn is_reconciled(&self) -> bool {
+        self.pf_total_cost - self.pf_consumed_fractions - self.pf_soultes == self.pf_cost_basis
+    }
+}
+
+impl Default for GlobalCostBasis {
+    fn default() -> Self {
+        Self::new()
+    }
 }
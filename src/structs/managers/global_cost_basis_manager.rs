@@ -22,10 +22,7 @@ pub struct GlobalCostBasisManager {
 impl GlobalCostBasisManager{
 
     pub fn calculate_full_cost_basis(&mut self, txs: &Vec<Transaction>, portfolios: &HashMap<TransactionId,Portfolio>) {
-        let mut global_cost_basis = GlobalCostBasis {
-            pf_cost_basis: dec!(0),
-            pf_total_cost: dec!(0),
-        };
+        let mut global_cost_basis = GlobalCostBasis::new();
         for tx in txs {
             self.global_cost_basis_history.insert(tx.get_id().to_string(), global_cost_basis.clone());
             let portfolio = portfolios.get(tx.get_id());
@@ -43,23 +40,34 @@ impl GlobalCostBasisManager{
                 amount,
                 ..
             } => {
-                return self.calculate_new_cost_basis(to, from, portfolio, &current_pf, *amount);
+                return self.calculate_new_cost_basis(to, from, portfolio, &current_pf, *amount, dec!(0));
             }
             Transaction::Trade {
                 to,
                 from,
                 sold_amount,
                 trade_type,
+                soulte,
                 ..
             } => {
                 let added_cost = match trade_type {
                     TradeType::FiatToCrypto { local_cost_basis } => *local_cost_basis,
                     _ => dec!(0),
                 };
-                let new_gcs = self.calculate_new_cost_basis(to, from, portfolio, &current_pf, *sold_amount);
+                let soulte = soulte.unwrap_or(dec!(0));
+                let new_gcs = self.calculate_new_cost_basis(to, from, portfolio, &current_pf, *sold_amount, soulte);
+                // The soulte of a cession is on its own column (line 216). The soulte received during an exchange is deducted
+                // from the acquisition price of the following cessions (line 222), the soulte paid is added to it
+                let is_cession = portfolio.is_some_and(|portfolio| portfolio.is_taxable);
+                let (soulte_received, soulte_paid) = match is_cession {
+                    true => (dec!(0), dec!(0)),
+                    false => (soulte.max(dec!(0)), (-soulte).max(dec!(0))),
+                };
                 return GlobalCostBasis{
-                    pf_cost_basis: new_gcs.pf_cost_basis + added_cost,
-                    pf_total_cost: new_gcs.pf_total_cost + added_cost,
+                    pf_cost_basis: new_gcs.pf_cost_basis + added_cost + soulte_paid - soulte_received,
+                    pf_total_cost: new_gcs.pf_total_cost + added_cost + soulte_paid,
+                    pf_soultes: new_gcs.pf_soultes + soulte_received,
+                    ..new_gcs
                 }
             }
             _ => current_pf, // ignoring the fiat deposit and withdrawal as they don't change the cost basis, they are here for accounting
@@ -75,6 +83,7 @@ impl GlobalCostBasisManager{
         portfolio: Option<&Portfolio>,
         current_pf: &GlobalCostBasis,
         amount: Decimal,
+        soulte: Decimal,
     ) -> GlobalCostBasis {
         let current_cost_basis = current_pf.pf_cost_basis;
         let current_total_cost = current_pf.pf_total_cost;
@@ -84,7 +93,9 @@ impl GlobalCostBasisManager{
         if let Some(portfolio) = portfolio {
             if portfolio.is_taxable {
                 // Selling of Crypto - Taxable event
-                let sell_price: Decimal = Decimal::from(amount) * from.price_eur;
+                // Even when the year is exempted (total of cessions <= 305€), the fraction of the acquisition price is consumed
+                // The fraction is the one of the sell price net of the soulte (line 217)
+                let sell_price: Decimal = Decimal::from(amount) * from.price_eur - soulte;
                 let weigted_price =
                     calculate_weigted_price(sell_price, current_cost_basis, portfolio.pf_total_value);
                     
@@ -95,6 +106,8 @@ impl GlobalCostBasisManager{
         return GlobalCostBasis {
             pf_cost_basis: current_cost_basis - cost_basis_adjustment + fee,
             pf_total_cost: current_total_cost + fee,
+            pf_consumed_fractions: current_pf.pf_consumed_fractions + cost_basis_adjustment,
+            pf_soultes: current_pf.pf_soultes,
         };
     }
 
@@ -144,6 +157,8 @@ mod tests {
         return GlobalCostBasis {
             pf_cost_basis: cost_basis,
             pf_total_cost: total_cost,
+            pf_consumed_fractions: total_cost - cost_basis,
+            pf_soultes: dec!(0),
         };
     }
 
@@ -246,6 +261,7 @@ mod tests {
             sold_amount: dec!(5),
             bought_amount: dec!(20000),
             trade_type: TradeType::CryptoToFiat,
+            soulte: None,
         };
 
         let portfolio = Portfolio {
@@ -264,6 +280,8 @@ mod tests {
 
         assert_eq!(next_cost_basis.pf_total_cost, dec!(18000));
         assert_eq!(next_cost_basis.pf_cost_basis, dec!(18000) - dec!(11250));
+        assert_eq!(next_cost_basis.pf_consumed_fractions, dec!(11250));
+        assert!(next_cost_basis.is_reconciled());
 
 
     }
@@ -295,12 +313,10 @@ mod tests {
             trade_type: TradeType::FiatToCrypto {
                 local_cost_basis: dec!(1000),
             },
+            soulte: None,
         };
 
-        let init_pf = GlobalCostBasis {
-            pf_cost_basis: dec!(0),
-            pf_total_cost: dec!(0),
-        };
+        let init_pf = GlobalCostBasis::new();
         let cost_basis_manager = GlobalCostBasisManager::new_non_persistent().unwrap();
         let current_pf = cost_basis_manager.calculate_cost_basis(&mut tx0, None,init_pf.clone());
 
@@ -325,6 +341,7 @@ mod tests {
             sold_amount: dec!(1.125),
             bought_amount: dec!(450),
             trade_type: TradeType::CryptoToFiat,
+            soulte: None,
         };
 
         let portfolio = Portfolio {
@@ -343,6 +360,8 @@ mod tests {
 
         assert_eq!(new_pf.pf_total_cost, dec!(1000));
         assert_eq!(new_pf.pf_cost_basis, dec!(1000) - dec!(375));
+        assert_eq!(new_pf.pf_consumed_fractions, dec!(375));
+        assert!(new_pf.is_reconciled());
 
         let mut tx2 = Transaction::Trade {
             tx: TransactionBase {
@@ -365,6 +384,7 @@ mod tests {
             sold_amount: dec!(1.875),
             bought_amount: dec!(1300),
             trade_type: TradeType::CryptoToFiat,
+            soulte: None,
         };
 
         let portfolio2 = Portfolio {
@@ -383,7 +403,92 @@ mod tests {
         assert_eq!(new_pf2.pf_total_cost, dec!(1000));
 
         assert_eq!(new_pf2.pf_cost_basis, dec!(0));
+        assert_eq!(new_pf2.pf_consumed_fractions, dec!(1000));
+        assert!(new_pf2.is_reconciled());
+
+
+    }
+
+    #[test]
+    fn trades_with_soultes() {
+        let (btc_wallet, eur_wallet, eth_wallet) = create_wallets();
+        let cost_basis_manager = GlobalCostBasisManager::new_non_persistent().unwrap();
+        let init_pf = get_pf(dec!(1000), dec!(1000));
+
+        // Exchange of crypto assets with a soulte received: not taxable, the soulte is deducted from the acquisition price
+        let exchange = Transaction::Trade {
+            tx: TransactionBase {
+                id: "exchange".to_string(),
+                timestamp: Utc::now(),
+            },
+            from: WalletSnapshot {
+                id: btc_wallet.get_id().to_string(),
+                pre_tx_balance: dec!(3),
+                fee: None,
+                price_eur: dec!(400),
+            },
+            to: WalletSnapshot {
+                id: eth_wallet.get_id().to_string(),
+                pre_tx_balance: dec!(0),
+                fee: None,
+                price_eur: dec!(500),
+            },
+            exchange_pair: None,
+            sold_amount: dec!(1),
+            bought_amount: dec!(0.4),
+            trade_type: TradeType::CryptoToCrypto,
+            soulte: Some(dec!(200)),
+        };
+
+        let current_pf = cost_basis_manager.calculate_cost_basis(&exchange, None, init_pf);
 
+        assert_eq!(current_pf.pf_total_cost, dec!(1000));
+        assert_eq!(current_pf.pf_cost_basis, dec!(800));
+        assert_eq!(current_pf.pf_soultes, dec!(200));
+        assert!(current_pf.is_reconciled());
 
+        // Cession with a soulte received: the soulte is deducted from the sell price (lines 216 to 218)
+        let cession = Transaction::Trade {
+            tx: TransactionBase {
+                id: "cession".to_string(),
+                timestamp: Utc::now(),
+            },
+            from: WalletSnapshot {
+                id: btc_wallet.get_id().to_string(),
+                pre_tx_balance: dec!(2),
+                fee: None,
+                price_eur: dec!(400),
+            },
+            to: WalletSnapshot {
+                id: eur_wallet.get_id().to_string(),
+                pre_tx_balance: dec!(0),
+                fee: None,
+                price_eur: dec!(1),
+            },
+            exchange_pair: Some(("BTC".to_string(), "EUR".to_string())),
+            sold_amount: dec!(1.5),
+            bought_amount: dec!(600),
+            trade_type: TradeType::CryptoToFiat,
+            soulte: Some(dec!(100)),
+        };
+
+        let portfolio = Portfolio {
+            tx_id: "cession".to_string(),
+            wallet_snaps: HashMap::new(),
+            is_taxable: true,
+            pf_total_value: dec!(1000),
+            is_pf_total_calculated: true,
+        };
+
+        let gains = calculate_tax_gains(&cession, &portfolio, &current_pf);
+        assert_eq!(gains, dec!(100));
+
+        let new_pf = cost_basis_manager.calculate_cost_basis(&cession, Some(&portfolio), current_pf);
+
+        assert_eq!(new_pf.pf_total_cost, dec!(1000));
+        assert_eq!(new_pf.pf_cost_basis, dec!(400));
+        assert_eq!(new_pf.pf_consumed_fractions, dec!(400));
+        assert_eq!(new_pf.pf_soultes, dec!(200));
+        assert!(new_pf.is_reconciled());
     }
 }
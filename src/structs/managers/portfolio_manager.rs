@@ -4,7 +4,7 @@ use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    api::get_price_api,
+    api::{get_price_api, ConnectorRegistry},
     errors::PortfolioHistoryError,
     structs::{PortfolioWalletSnapshot, Transaction, TransactionId, Wallet, WalletId, WalletSnapshot},
 };
@@ -75,11 +75,11 @@ impl Drop for PortfolioManager {
 
 impl PortfolioManager {
 
-    #[tokio::main]
     pub async fn calculate_portfolio_history(
         &mut self,
         txs: &Vec<Transaction>,
         wallets: &HashMap<String, Wallet>,
+        connectors: &ConnectorRegistry,
     ) -> Result<(), PortfolioHistoryError> {
         let mut state: HashMap<WalletId, PortfolioWalletSnapshot> = HashMap::new();
         for tx in txs {
@@ -88,7 +88,7 @@ impl PortfolioManager {
             if  self.portfolio_history.get(&tx_id).is_none(){
                 self.portfolio_history.insert(tx_id.to_string(), Portfolio::new(tx_id.to_string(),is_taxable));
             }
-            self._calculate(tx, is_taxable,&mut state, wallets).await?;
+            self._calculate(tx, is_taxable,&mut state, wallets, connectors).await?;
             if is_taxable{
                 let pf_total_value = self.calculate_total_value(&tx_id).unwrap();
                 let portfolio = self.portfolio_history.get_mut(&tx_id).unwrap();
@@ -113,6 +113,7 @@ impl PortfolioManager {
         is_taxable: bool,
         previous_state: &mut HashMap<WalletId, PortfolioWalletSnapshot>,
         wallets: &HashMap<String, Wallet>,
+        connectors: &ConnectorRegistry,
     ) -> Result<(), PortfolioHistoryError> {
         match transaction {
             Transaction::Trade {
@@ -131,7 +132,7 @@ impl PortfolioManager {
                 if is_taxable {
                     // If taxable we need the price and to insert/update the history
                     let new_state = self
-                        .get_price_if_needed(previous_state, transaction, wallets)
+                        .get_price_if_needed(previous_state, transaction, wallets, connectors)
                         .await?;
                     let portfolio = self.portfolio_history.get_mut(&tx.id).unwrap();
                     portfolio.wallet_snaps = new_state;
@@ -160,7 +161,7 @@ impl PortfolioManager {
                 if is_taxable {
                     // If taxable we need the price and to insert/update the history
                     let new_state = self
-                        .get_price_if_needed(previous_state, transaction, wallets)
+                        .get_price_if_needed(previous_state, transaction, wallets, connectors)
                         .await?;
                     let portfolio = self.portfolio_history.get_mut(&tx.id).unwrap();
                     portfolio.wallet_snaps = new_state;
@@ -278,6 +279,7 @@ impl PortfolioManager {
         state: &mut HashMap<WalletId, PortfolioWalletSnapshot>,
         transaction: &Transaction,
         wallets: &HashMap<String, Wallet>,
+        connectors: &ConnectorRegistry,
     ) -> Result<HashMap<WalletId, PortfolioWalletSnapshot>, PortfolioHistoryError> {
         let tx = transaction.get_tx_base();
         let existing_state = self.portfolio_history.get(&tx.id);
@@ -294,7 +296,7 @@ impl PortfolioManager {
 
             // Else: if the price didn't exist before OR the wallet didn't exist: get the price
             let wallet = wallets.get(id).unwrap();
-            let price = get_price_api(transaction, wallet)
+            let price = get_price_api(transaction, wallet, connectors)
                 .await
                 .map_err(|e| PortfolioHistoryError::FailureGettingPrice(e))?;
             wallet_snap.price_eur = Some(price);
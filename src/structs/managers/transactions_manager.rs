@@ -131,6 +131,7 @@ mod tests {
             sold_amount: dec!(1),
             bought_amount: dec!(1300),
             trade_type: TradeType::CryptoToFiat,
+            soulte: None,
         };
 
         let tx2 = Transaction::Trade {
@@ -154,6 +155,7 @@ mod tests {
             sold_amount: dec!(2),
             bought_amount: dec!(1400),
             trade_type: TradeType::CryptoToFiat,
+            soulte: None,
         };
 
         assert_ne!(tx1, tx2);
@@ -194,6 +196,7 @@ mod tests {
             sold_amount: dec!(1),
             bought_amount: dec!(1300),
             trade_type: TradeType::CryptoToFiat,
+            soulte: None,
         };
 
         let tx2 = Transaction::Trade {
@@ -217,6 +220,7 @@ mod tests {
             sold_amount: dec!(21),
             bought_amount: dec!(1400),
             trade_type: TradeType::CryptoToFiat,
+            soulte: None,
         };
 
         assert_ne!(tx1, tx2);
@@ -1,7 +1,11 @@
 use hashbrown::HashMap;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-use crate::structs::{Wallet, WalletIdMap};
+use crate::{
+    structs::{Address, Owner, Platform, Wallet, WalletBase, WalletId, WalletIdMap},
+    utils::generate_id,
+};
 
 use super::Persistable;
 
@@ -38,6 +42,104 @@ impl Persistable for WalletManager {
     }
 }
 
+impl WalletManager {
+    /* Get the id of the wallet (currency, platform, address) or create it if it doesn't exist yet */
+    pub fn create_or_get_wallet_id(
+        &mut self,
+        currency: &str,
+        platform: &Platform,
+        address: &Address,
+        is_fiat: bool,
+    ) -> WalletId {
+        let currency = currency.to_string();
+        if let Some(id) = self.wallet_ids.get(&currency, platform, address) {
+            return id;
+        }
+        let wallet_base = WalletBase {
+            id: generate_id(),
+            currency: currency.clone(),
+            platform: platform.clone(),
+            address: address.clone(),
+            owner: Owner::User,
+            balance: Decimal::ZERO,
+            info: None,
+        };
+        let wallet = if is_fiat {
+            Wallet::Fiat(wallet_base)
+        } else {
+            Wallet::Crypto(wallet_base)
+        };
+        let wallet_id = wallet.get_id();
+        self.wallet_ids
+            .insert(currency, platform.clone(), address.clone(), wallet_id.clone());
+        self.wallets.insert(wallet_id.clone(), wallet);
+        wallet_id
+    }
+
+    /* Get the id of the wallet of an account of a platform (e.g. the main, trade and margin accounts of KuCoin) or
+    create it. The accounts share the address of the platform (None), so their wallets are told apart by the name of
+    the account, kept in the info of the wallet, and are looked up among the wallets rather than in the wallet ids */
+    pub fn create_or_get_account_wallet_id(
+        &mut self,
+        currency: &str,
+        platform: &Platform,
+        account: &str,
+        is_fiat: bool,
+    ) -> WalletId {
+        let existing = self.wallets.values().map(Wallet::get).find(|base| {
+            base.currency == currency
+                && base.platform == *platform
+                && base.address.is_none()
+                && base.info.as_deref() == Some(account)
+        });
+        if let Some(base) = existing {
+            return base.id.clone();
+        }
+        let wallet_base = WalletBase {
+            id: generate_id(),
+            currency: currency.to_string(),
+            platform: platform.clone(),
+            address: None,
+            owner: Owner::User,
+            balance: Decimal::ZERO,
+            info: Some(account.to_string()),
+        };
+        let wallet = if is_fiat {
+            Wallet::Fiat(wallet_base)
+        } else {
+            Wallet::Crypto(wallet_base)
+        };
+        let wallet_id = wallet.get_id();
+        self.wallets.insert(wallet_id.clone(), wallet);
+        wallet_id
+    }
+
+    /* Add a wallet keeping its id, e.g. when importing previously exported data. The wallets of the accounts of a
+    platform (see create_or_get_account_wallet_id) share the key of the wallet of the platform: the key goes to the
+    first wallet having it, and to the wallet without info over the ones of an account */
+    pub fn insert_wallet(&mut self, wallet: Wallet) {
+        let base = wallet.get();
+        let is_account = |base: &WalletBase| base.address.is_none() && base.info.is_some();
+        let registered = self
+            .wallet_ids
+            .get(&base.currency, &base.platform, &base.address)
+            .and_then(|id| self.wallets.get(&id));
+        let replaces = match registered {
+            Some(registered) => is_account(registered.get()) && !is_account(base),
+            None => true,
+        };
+        if replaces {
+            self.wallet_ids.insert(
+                base.currency.clone(),
+                base.platform.clone(),
+                base.address.clone(),
+                base.id.clone(),
+            );
+        }
+        self.wallets.insert(base.id.clone(), wallet);
+    }
+}
+
 impl Drop for WalletManager {
     fn drop(&mut self) {
         if self.persist{
@@ -79,6 +181,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_create_or_get_wallet_id() {
+        let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+
+        let id = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Binance, &None, false);
+        let same_id = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Binance, &None, false);
+        let address = Some("bc1q".to_string());
+        let other_id =
+            wallet_manager.create_or_get_wallet_id("BTC", &Platform::Blockchain, &address, false);
+
+        assert_eq!(id, same_id);
+        assert_ne!(id, other_id);
+        assert!(wallet_manager.wallets.get(&id).unwrap().is_crypto());
+        assert_eq!(wallet_manager.wallets.get(&other_id).unwrap().get().address, address);
+    }
+
+    #[test]
+    fn test_create_or_get_account_wallet_id() {
+        let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+
+        let main = wallet_manager.create_or_get_account_wallet_id("BTC", &Platform::KuCoin, "main", false);
+        let same_main = wallet_manager.create_or_get_account_wallet_id("BTC", &Platform::KuCoin, "main", false);
+        let trade = wallet_manager.create_or_get_account_wallet_id("BTC", &Platform::KuCoin, "trade", false);
+
+        assert_eq!(main, same_main);
+        assert_ne!(main, trade);
+        let wallet = wallet_manager.wallets.get(&trade).unwrap().get();
+        assert_eq!(wallet.address, None);
+        assert_eq!(wallet.info, Some("trade".to_string()));
+    }
+
+    #[test]
+    fn test_insert_wallet() {
+        let mut source = WalletManager::new_non_persistent().unwrap();
+        let kucoin = source.create_or_get_wallet_id("BTC", &Platform::KuCoin, &None, false);
+        let trade = source.create_or_get_account_wallet_id("BTC", &Platform::KuCoin, "trade", false);
+
+        // Whatever the order, the wallet of the trade account doesn't take the place of the one of the platform
+        for ids in [[&trade, &kucoin], [&kucoin, &trade]] {
+            let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+            for id in ids {
+                wallet_manager.insert_wallet(source.wallets.get(id).unwrap().clone());
+            }
+            assert_eq!(
+                wallet_manager.create_or_get_wallet_id("BTC", &Platform::KuCoin, &None, false),
+                kucoin
+            );
+            assert_eq!(
+                wallet_manager.create_or_get_account_wallet_id("BTC", &Platform::KuCoin, "trade", false),
+                trade
+            );
+            assert_eq!(wallet_manager.wallets.len(), 2);
+        }
+    }
+
     #[test]
     fn test_drop() {
         {
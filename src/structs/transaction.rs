@@ -49,6 +49,8 @@ pub enum Transaction {
         sold_amount: Decimal,
         bought_amount: Decimal,
         trade_type: TradeType,
+        #[serde(default)]
+        soulte: Option<Decimal>, // Cash balance of the exchange in euro (line 216): received if positive, paid if negative
     },
     Deposit {
         tx: TransactionBase,
@@ -80,6 +82,20 @@ pub struct Income {
     subtype : IncomeType
 }
 
+impl Income {
+    pub fn new(value: Decimal, subtype: IncomeType) -> Self {
+        Income { value, subtype }
+    }
+
+    pub fn get_value(&self) -> Decimal {
+        self.value
+    }
+
+    pub fn get_subtype(&self) -> &IncomeType {
+        &self.subtype
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum IncomeType{
     Airdrop,
@@ -114,6 +130,14 @@ impl Transaction {
         }
     }
 
+    /* The soulte of a trade, zero for the other transactions */
+    pub fn get_soulte(&self) -> Decimal {
+        match self {
+            Transaction::Trade { soulte, .. } => soulte.unwrap_or(dec!(0)),
+            _ => dec!(0),
+        }
+    }
+
     pub fn is_trade_or_transfer(&self) -> bool {
         match self {
             Transaction::Trade { .. } => true,
@@ -101,7 +101,12 @@ pub enum Owner {
 #[derive(Hash, Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum Platform {
     Binance,
+    Bitfinex,
+    Bitpanda,
+    Coinbase,
+    CryptoCom,
     Kraken,
+    KuCoin,
     Blockchain,
     Other(String),
 }
@@ -0,0 +1,237 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{create_algorand_txs, fetch_history_algorand, AlgorandIndexerClient, CoinGeckoClient},
+    structs::{wallet_manager::WalletManager, IncomeType, Persistable, Platform, Transaction},
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+const ADDRESS: &str = "E5CMZUIMOUZ3243K3CIPTXK4VMVNWJ5QPVIAXFET6KONYQQMWLQACAQ";
+const EXCHANGE: &str = "VMT3OKOZZRGLDQAJMBYAIRUSIFM6SKMNB6KSV3R5KVAIXDQLDNYQCAQ";
+const APP: &str = "UFZM5XFOI5DUWYK4KTKRBJOYJKG6UMBS5FMFQ5BQWQJVHC7D6MZQCAQ";
+const FRIEND: &str = "ZXSIKN6KFQUAQT7VMCBG2DTDRC34K6SRJF5GZNLPHFZITZJP6QNQCAQ";
+const DEPOSIT: &str = "YO47W6FEKLHC7SIM74LAQUICGVID4O3SO2B3OHD757XFIGMLVVRQ";
+const OPT_IN: &str = "TTWJRQ7NBAA3MBWL7QED4TA3GVGJHCCP24ZLHONOHWCPZBRWOMQQ";
+const SWAP_PAY: &str = "AJP6F47NZ42OYJZVSRHML76PTOWGQKSWVOD7IZIMA3565UQS3EQA";
+const SWAP_CALL: &str = "HSG4EC4HHX7Z357O4ZR6AHXQHRCTURHKGR3YBI5YLIHJVMCJ3EPQ";
+const GOVERNANCE: &str = "X6KCFIVAJKWSDAH22CIT6HG7RKOTPAH5A5EBPUSWSBINBKAJLU5A";
+const USDC_CLOSE: &str = "KRI5PI32ZMSGMF43KADMZIOXWTRZCVYCPSRRXK7LIVSQ3L34IL5Q";
+const ALGO_CLOSE: &str = "FMFM74IH53RMDQGAK76L5TE73HW2CP55RBCXJYSJT5LR2IGWRIGQ";
+
+fn algorand_routes() -> Vec<MockRoute> {
+    let transactions = format!("/v2/accounts/{ADDRESS}/transactions");
+    vec![
+        MockRoute::fixture(&transactions, "algorand/transactions_page3.json")
+            .with_query("next=tok3"),
+        MockRoute::fixture(&transactions, "algorand/transactions_page2.json")
+            .with_query("next=tok2"),
+        MockRoute::fixture(&transactions, "algorand/transactions_page1.json"),
+        MockRoute::fixture("/v2/assets/31566704", "algorand/asset_usdc.json"),
+        MockRoute::fixture("/v2/assets/27165954", "algorand/asset_planet.json"),
+        MockRoute::fixture("/coins/algorand/history", "algorand/history_algorand.json"),
+        MockRoute::fixture("/coins/usd-coin/history", "algorand/history_usd_coin.json"),
+    ]
+}
+
+#[tokio::test]
+async fn algorand_history_to_transactions() {
+    let server = MockServer::start(algorand_routes());
+    let client = AlgorandIndexerClient::new(server.url.clone(), Some("test-token".to_string()));
+    let price_client = CoinGeckoClient::new(server.url.clone(), None);
+
+    let history = fetch_history_algorand(&client, ADDRESS).await.unwrap();
+    assert_eq!(history.transactions.len(), 7);
+    // The assets of the inner transactions are fetched too
+    assert_eq!(history.assets.len(), 2);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_algorand_txs(&mut wallet_manager, &mut txs, &history, &price_client)
+        .await
+        .unwrap();
+
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            format!("algorand-{DEPOSIT}"),
+            format!("algorand-{DEPOSIT}-rewards"),
+            format!("algorand-{OPT_IN}-fee"),
+            format!("algorand-{SWAP_PAY}"),
+            format!("algorand-{SWAP_CALL}-fee"),
+            format!("algorand-{SWAP_CALL}-0"),
+            format!("algorand-{SWAP_CALL}-1"),
+            format!("algorand-{GOVERNANCE}"),
+            format!("algorand-{USDC_CLOSE}-fee"),
+            format!("algorand-{USDC_CLOSE}"),
+            format!("algorand-{USDC_CLOSE}-close"),
+            format!("algorand-{ALGO_CLOSE}"),
+            format!("algorand-{ALGO_CLOSE}-close"),
+        ]
+    );
+
+    let own = |wallet_manager: &mut WalletManager, currency: &str| {
+        wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Blockchain,
+            &Some(ADDRESS.to_string()),
+            false,
+        )
+    };
+    let algo = own(&mut wallet_manager, "ALGO");
+    let usdc = own(&mut wallet_manager, "USDC");
+    let address_of = |wallet_manager: &WalletManager, id: &String| {
+        wallet_manager
+            .wallets
+            .get(id)
+            .unwrap()
+            .get()
+            .address
+            .clone()
+            .unwrap()
+    };
+
+    // Reception from an exchange, which paid the fee
+    match &txs[0] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(to.id, algo);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.fee, None);
+            assert_eq!(to.price_eur, dec!(0.5));
+            assert_eq!(*amount, dec!(100));
+            assert_eq!(address_of(&wallet_manager, &from.id), EXCHANGE);
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Participation rewards paid with the reception
+    match &txs[1] {
+        Transaction::Transfer {
+            from,
+            amount,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(from.id, algo);
+            assert_eq!(from.pre_tx_balance, dec!(100));
+            assert_eq!(*amount, dec!(0.005));
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+            assert_eq!(income.get_value(), dec!(0.0025));
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    // The opt-in only costs its fee
+    match &txs[2] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, algo);
+            assert_eq!(from.pre_tx_balance, dec!(100.005));
+            assert_eq!(from.fee, Some(dec!(0.001)));
+            assert_eq!(to.fee, None);
+            assert_eq!(*amount, dec!(0));
+        }
+        _ => panic!("Expected a fee transfer"),
+    }
+
+    // The payment of the swap carries its own fee
+    match &txs[3] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, algo);
+            assert_eq!(from.pre_tx_balance, dec!(100.004));
+            assert_eq!(from.fee, Some(dec!(0.001)));
+            assert_eq!(*amount, dec!(60));
+            assert_eq!(address_of(&wallet_manager, &to.id), APP);
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Assets sent by the inner transactions of the application call
+    match &txs[5] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(to.id, usdc);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.price_eur, dec!(0.95));
+            assert_eq!(*amount, dec!(50));
+            assert_eq!(address_of(&wallet_manager, &from.id), APP);
+        }
+        _ => panic!("Expected a transfer"),
+    }
+    match &txs[6] {
+        Transaction::Transfer { to, amount, .. } => {
+            assert_eq!(to.id, own(&mut wallet_manager, "ASA-27165954"));
+            assert_eq!(to.price_eur, dec!(0));
+            assert_eq!(*amount, dec!(5));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Governance rewards
+    match &txs[7] {
+        Transaction::Transfer {
+            from,
+            amount,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(from.id, algo);
+            assert_eq!(from.pre_tx_balance, dec!(40.001));
+            assert_eq!(*amount, dec!(12));
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+            assert_eq!(income.get_value(), dec!(6));
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    // Opt-out of USDC sending the remaining balance to the exchange
+    match &txs[10] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, usdc);
+            assert_eq!(from.pre_tx_balance, dec!(30));
+            assert_eq!(from.fee, None);
+            assert_eq!(*amount, dec!(30));
+            assert_eq!(address_of(&wallet_manager, &to.id), EXCHANGE);
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Closing of the account
+    match (&txs[11], &txs[12]) {
+        (
+            Transaction::Transfer {
+                from, to, amount, ..
+            },
+            Transaction::Transfer {
+                from: close_from,
+                to: close_to,
+                amount: close_amount,
+                ..
+            },
+        ) => {
+            assert_eq!(from.pre_tx_balance, dec!(52));
+            assert_eq!(from.fee, Some(dec!(0.001)));
+            assert_eq!(*amount, dec!(10));
+            assert_eq!(address_of(&wallet_manager, &to.id), FRIEND);
+            assert_eq!(close_from.pre_tx_balance, dec!(41.999));
+            assert_eq!(close_from.fee, None);
+            assert_eq!(*close_amount, dec!(41.999));
+            assert_eq!(address_of(&wallet_manager, &close_to.id), EXCHANGE);
+        }
+        _ => panic!("Expected transfers"),
+    }
+
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .filter(|request| !request.path.starts_with("/coins"))
+        .all(|request| request.header("X-Indexer-API-Token") == Some(&"test-token".to_string())));
+}
@@ -0,0 +1,273 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::BinanceClient,
+    parsing::{create_binance_csv_txs, read_binance_csv},
+    structs::{
+        wallet_manager::WalletManager, IncomeType, Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+#[tokio::test]
+async fn binance_csv_to_transactions() {
+    let server = MockServer::start(vec![
+        MockRoute::fixture("/api/v3/klines", "binance/klines_btceur.json")
+            .with_query("symbol=BTCEUR"),
+        MockRoute::fixture("/api/v3/klines", "binance/klines_bnbeur.json")
+            .with_query("symbol=BNBEUR"),
+        MockRoute::fixture("/api/v3/klines", "binance/klines_ethusdt.json")
+            .with_query("symbol=ETHUSDT"),
+        MockRoute::fixture("/api/v3/klines", "binance/klines_eurusdt.json")
+            .with_query("symbol=EURUSDT"),
+        MockRoute::new(
+            "/api/v3/klines",
+            r#"{"code": -1121, "msg": "Invalid symbol."}"#,
+        )
+        .with_status(400),
+    ]);
+    let client = BinanceClient::new(server.url.clone(), String::new(), String::new());
+
+    let rows = read_binance_csv("src/tests/fixtures/binance/transaction_history.csv").unwrap();
+    assert_eq!(rows.len(), 18);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_binance_csv_txs(&mut wallet_manager, &mut txs, &rows, &client)
+        .await
+        .unwrap();
+
+    // From the oldest, the rows of a trade or of a small assets exchange being grouped, the subscription ignored
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "binance-csv-deposit-EUR-1675245600",
+            "binance-csv-trade-EUR-BTC-1675249200",
+            "binance-csv-simple-earn-flexible-interest-BTC-1675296000",
+            "binance-csv-distribution-BNB-1675339200",
+            "binance-csv-trade-BTC-ETH-1675414800",
+            "binance-csv-launchpool-interest-BNB-1675468800",
+            "binance-csv-dust-ETH-1675591200",
+            "binance-csv-dust-BTC-1675591200",
+            "binance-csv-withdraw-BTC-1675677600",
+            "binance-csv-fiat-withdraw-EUR-1675764000",
+        ]
+    );
+
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &Platform::Binance, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Binance, &None, false);
+    let bnb = wallet_manager.create_or_get_wallet_id("BNB", &Platform::Binance, &None, false);
+
+    match &txs[0] {
+        Transaction::Deposit { to, amount, .. } => {
+            assert_eq!(to.id, eur);
+            assert_eq!(*amount, dec!(1000));
+        }
+        _ => panic!("Expected a deposit"),
+    }
+
+    // Two fills and their fee, the euro side giving the price
+    match &txs[1] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(from.fee, None);
+            assert_eq!(to.id, btc);
+            assert_eq!(to.fee, Some(dec!(0.00002)));
+            assert_eq!(to.price_eur, dec!(25000));
+            assert_eq!(*sold_amount, dec!(500));
+            assert_eq!(*bought_amount, dec!(0.02));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(500)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[2] {
+        Transaction::Transfer {
+            to,
+            amount,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(to.id, btc);
+            assert_eq!(to.pre_tx_balance, dec!(0.01998));
+            assert_eq!(*amount, dec!(0.0001));
+            assert_eq!(income.get_value(), dec!(2));
+            assert_eq!(*income.get_subtype(), IncomeType::Interest);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match &txs[3] {
+        Transaction::Transfer {
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(income.get_value(), dec!(125));
+            assert_eq!(*income.get_subtype(), IncomeType::Airdrop);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    // The fee paid in BNB (0.25€) is a fee of the BTC sold, taken from the BNB wallet
+    match &txs[4] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.02008));
+            assert_eq!(from.fee, Some(dec!(0.0000125)));
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.price_eur, dec!(1500));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[5] {
+        Transaction::Transfer {
+            to,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(to.id, bnb);
+            assert_eq!(to.pre_tx_balance, dec!(0.499));
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    // The BNB received is split according to the value of each small asset (3€ of ETH, 1€ of BTC)
+    match (&txs[6], &txs[7]) {
+        (
+            Transaction::Trade {
+                to: eth_to,
+                sold_amount: eth_sold,
+                bought_amount: eth_bought,
+                ..
+            },
+            Transaction::Trade {
+                from: btc_from,
+                to: btc_to,
+                bought_amount: btc_bought,
+                ..
+            },
+        ) => {
+            assert_eq!(eth_to.id, bnb);
+            assert_eq!(eth_to.pre_tx_balance, dec!(0.509));
+            assert_eq!(*eth_sold, dec!(0.002));
+            assert_eq!(*eth_bought, dec!(0.012));
+            assert_eq!(btc_from.pre_tx_balance, dec!(0.01008));
+            assert_eq!(btc_to.pre_tx_balance, dec!(0.521));
+            assert_eq!(*btc_bought, dec!(0.004));
+        }
+        _ => panic!("Expected trades"),
+    }
+
+    match &txs[8] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.01003));
+            assert_eq!(*amount, dec!(0.005));
+            assert_eq!(
+                wallet_manager.wallets.get(&to.id).unwrap().get().platform,
+                Platform::Blockchain
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[9] {
+        Transaction::Withdrawal { from, amount, .. } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(500));
+            assert_eq!(*amount, dec!(200));
+        }
+        _ => panic!("Expected a withdrawal"),
+    }
+}
+
+#[tokio::test]
+async fn binance_csv_trades_of_the_same_second() {
+    // The euro side of each trade gives the price of BTC, no price is fetched
+    let server = MockServer::start(vec![]);
+    let client = BinanceClient::new(server.url.clone(), String::new(), String::new());
+
+    let rows = read_binance_csv("src/tests/fixtures/binance/transaction_history_same_second.csv")
+        .unwrap();
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_binance_csv_txs(&mut wallet_manager, &mut txs, &rows, &client)
+        .await
+        .unwrap();
+
+    // A purchase then a sale of BTC in the same second, each with its fee
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "binance-csv-deposit-EUR-1675245600",
+            "binance-csv-trade-EUR-BTC-1675249200",
+            "binance-csv-trade-BTC-EUR-1675249200",
+        ]
+    );
+
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &Platform::Binance, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Binance, &None, false);
+    match (&txs[1], &txs[2]) {
+        (
+            Transaction::Trade {
+                from: buy_from,
+                to: buy_to,
+                sold_amount: buy_sold,
+                bought_amount: buy_bought,
+                ..
+            },
+            Transaction::Trade {
+                from: sell_from,
+                to: sell_to,
+                sold_amount: sell_sold,
+                bought_amount: sell_bought,
+                trade_type,
+                ..
+            },
+        ) => {
+            assert_eq!(buy_from.id, eur);
+            assert_eq!(buy_from.pre_tx_balance, dec!(1000));
+            assert_eq!(*buy_sold, dec!(200));
+            assert_eq!(buy_to.id, btc);
+            assert_eq!(buy_to.fee, Some(dec!(0.000008)));
+            assert_eq!(*buy_bought, dec!(0.008));
+
+            assert_eq!(sell_from.id, btc);
+            assert_eq!(sell_from.pre_tx_balance, dec!(0.007992));
+            assert_eq!(sell_from.fee, None);
+            assert_eq!(sell_from.price_eur, dec!(25000));
+            assert_eq!(*sell_sold, dec!(0.004));
+            assert_eq!(sell_to.id, eur);
+            assert_eq!(sell_to.pre_tx_balance, dec!(800));
+            assert_eq!(sell_to.fee, Some(dec!(0.1)));
+            assert_eq!(*sell_bought, dec!(100));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected trades"),
+    }
+}
@@ -0,0 +1,399 @@
+use chrono::{TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use rust_decimal_macros::dec;
+use sha2::Sha256;
+
+use crate::{
+    api::{
+        create_binance_txs, fetch_history_binance, BinanceClient, BinanceConnector,
+        BinanceHistory, ConnectorRegistry,
+    },
+    structs::{
+        portfolio_manager::PortfolioManager, wallet_manager::WalletManager, IncomeType,
+        Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRequest, MockRoute, MockServer},
+};
+
+fn binance_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture("/api/v3/exchangeInfo", "binance/exchange_info.json"),
+        MockRoute::fixture("/api/v3/account", "binance/account.json"),
+        MockRoute::fixture("/api/v3/myTrades", "binance/my_trades_btceur.json")
+            .with_query("symbol=BTCEUR"),
+        MockRoute::fixture("/api/v3/myTrades", "binance/my_trades_ethbtc.json")
+            .with_query("symbol=ETHBTC"),
+        MockRoute::new("/api/v3/myTrades", "[]"),
+        MockRoute::fixture("/sapi/v1/capital/deposit/hisrec", "binance/deposits.json"),
+        MockRoute::fixture(
+            "/sapi/v1/capital/withdraw/history",
+            "binance/withdrawals.json",
+        ),
+        MockRoute::fixture("/sapi/v1/fiat/orders", "binance/fiat_deposits.json")
+            .with_query("transactionType=0"),
+        MockRoute::fixture("/sapi/v1/fiat/orders", "binance/fiat_withdrawals.json")
+            .with_query("transactionType=1"),
+        MockRoute::fixture(
+            "/sapi/v1/convert/tradeFlow",
+            "binance/convert_trade_flow.json",
+        ),
+        MockRoute::fixture("/sapi/v1/asset/dribblet", "binance/dribblet.json"),
+        MockRoute::fixture(
+            "/sapi/v1/simple-earn/flexible/history/rewardsRecord",
+            "binance/flexible_rewards.json",
+        )
+        .with_query("type=REALTIME"),
+        MockRoute::new(
+            "/sapi/v1/simple-earn/flexible/history/rewardsRecord",
+            r#"{"rows": [], "total": 0}"#,
+        ),
+        MockRoute::fixture(
+            "/sapi/v1/simple-earn/locked/history/rewardsRecord",
+            "binance/locked_rewards.json",
+        ),
+        MockRoute::fixture("/api/v3/klines", "binance/klines_btceur.json")
+            .with_query("symbol=BTCEUR"),
+        MockRoute::fixture("/api/v3/klines", "binance/klines_bnbeur.json")
+            .with_query("symbol=BNBEUR"),
+        MockRoute::fixture("/api/v3/klines", "binance/klines_ethusdt.json")
+            .with_query("symbol=ETHUSDT"),
+        MockRoute::fixture("/api/v3/klines", "binance/klines_eurusdt.json")
+            .with_query("symbol=EURUSDT"),
+        MockRoute::new(
+            "/api/v3/klines",
+            r#"{"code": -1121, "msg": "Invalid symbol."}"#,
+        )
+        .with_status(400),
+    ]
+}
+
+#[tokio::test]
+async fn binance_history_to_transactions() {
+    let server = MockServer::start(binance_routes());
+    let client = BinanceClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    );
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 7, 1, 0, 0, 0).unwrap();
+
+    let history = fetch_history_binance(&client, start, end, &BinanceHistory::default())
+        .await
+        .unwrap();
+    assert_eq!(history.trades.len(), 2);
+    assert_eq!(history.converts.len(), 1); // The failed conversion is ignored
+    assert_eq!(history.earn_rewards.len(), 2);
+    assert_eq!(history.fiat_deposits.len(), 1);
+    assert_eq!(history.fiat_withdrawals.len(), 2);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_binance_txs(&mut wallet_manager, &mut txs, &history, &client)
+        .await
+        .unwrap();
+
+    let ids: Vec<&str> = txs.iter().map(|tx| tx.get_id().as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "binance-deposit-769800519366885376",
+            "binance-fiat-deposit-7d76d611-0568-4f43-afb6-24cac7767365",
+            "binance-trade-BTCEUR-28457",
+            "binance-convert-940708407462087195",
+            "binance-trade-ETHBTC-9121",
+            "binance-dust-45178372831-ETH",
+            "binance-earn-BTC-1682899200000-flexible-REALTIME",
+            "binance-earn-BNB-1682902800000-locked-123123",
+            "binance-withdrawal-b6ae22b3aa844210a7041aee7589627c",
+            "binance-fiat-withdrawal-a1c7b0e2-3f7e-4bb6-9c55-2f0e1d8f4c21",
+        ]
+    );
+
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Binance, &None, false);
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &Platform::Binance, &None, true);
+    let eth = wallet_manager.create_or_get_wallet_id("ETH", &Platform::Binance, &None, false);
+    let bnb = wallet_manager.create_or_get_wallet_id("BNB", &Platform::Binance, &None, false);
+
+    match &txs[1] {
+        Transaction::Deposit { to, amount, .. } => {
+            assert_eq!(to.id, eur);
+            assert_eq!(to.fee, Some(dec!(1)));
+            assert_eq!(*amount, dec!(501));
+        }
+        _ => panic!("Expected a fiat deposit"),
+    }
+
+    match &txs[2] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.5));
+            assert_eq!(from.price_eur, dec!(20000));
+            assert_eq!(to.id, eur);
+            assert_eq!(to.pre_tx_balance, dec!(500));
+            assert_eq!(to.fee, Some(dec!(2)));
+            assert_eq!(*sold_amount, dec!(0.1));
+            assert_eq!(*bought_amount, dec!(2000));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[4] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.35));
+            // The commission of 0.001 BNB (0.25€) is converted in BTC
+            assert_eq!(from.fee, Some(dec!(0.0000125)));
+            assert_eq!(to.id, eth);
+            assert_eq!(to.price_eur, dec!(1500)); // Through ETHUSDT and EURUSDT
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[5] {
+        Transaction::Trade {
+            from,
+            to,
+            bought_amount,
+            ..
+        } => {
+            assert_eq!(from.id, eth);
+            assert_eq!(from.pre_tx_balance, dec!(1));
+            assert_eq!(to.id, bnb);
+            assert_eq!(to.pre_tx_balance, dec!(0.999));
+            assert_eq!(to.fee, Some(dec!(0.00002)));
+            assert_eq!(*bought_amount, dec!(0.00092));
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match (&txs[6], &txs[7]) {
+        (
+            Transaction::Transfer {
+                income: Some(interest),
+                ..
+            },
+            Transaction::Transfer {
+                income: Some(staking),
+                ..
+            },
+        ) => {
+            assert_eq!(*interest.get_subtype(), IncomeType::Interest);
+            assert_eq!(interest.get_value(), dec!(0.2));
+            assert_eq!(*staking.get_subtype(), IncomeType::Staking);
+            assert_eq!(staking.get_value(), dec!(2.5));
+        }
+        _ => panic!("Expected two incomes"),
+    }
+
+    match &txs[8] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.28001));
+            assert_eq!(from.fee, Some(dec!(0.0002)));
+            assert_eq!(*amount, dec!(0.2));
+            let wallet = wallet_manager.wallets.get(&to.id).unwrap().get();
+            assert_eq!(wallet.platform, Platform::Blockchain);
+            assert_eq!(
+                wallet.address,
+                Some("bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string())
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[9] {
+        Transaction::Withdrawal { from, amount, .. } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(2498));
+            assert_eq!(from.fee, Some(dec!(1.5)));
+            assert_eq!(*amount, dec!(998.5));
+        }
+        _ => panic!("Expected a fiat withdrawal"),
+    }
+
+    // The prices missing at a taxable transaction are fetched by the portfolio from the same server
+    let mut connectors = ConnectorRegistry::default();
+    connectors.register(BinanceConnector {
+        price_client: client,
+        csv_path: None,
+    });
+    let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+    portfolio_manager
+        .calculate_portfolio_history(&txs, &wallet_manager.wallets, &connectors)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn binance_signed_requests() {
+    let server = MockServer::start(binance_routes());
+    let client = BinanceClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    );
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    fetch_history_binance(
+        &client,
+        start,
+        start + chrono::Duration::days(10),
+        &BinanceHistory::default(),
+    )
+    .await
+    .unwrap();
+
+    let requests = server.requests();
+    let account = requests
+        .iter()
+        .find(|request| request.path == "/api/v3/account")
+        .unwrap();
+    assert_eq!(
+        account.header("X-MBX-APIKEY"),
+        Some(&"test-key".to_string())
+    );
+    let (payload, signature) = account.query.split_once("&signature=").unwrap();
+    assert!(payload.contains("timestamp="));
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"test-secret").unwrap();
+    mac.update(payload.as_bytes());
+    assert_eq!(signature, hex::encode(mac.finalize().into_bytes()));
+
+    // Public endpoints are neither signed nor authenticated
+    let info = requests
+        .iter()
+        .find(|request| request.path == "/api/v3/exchangeInfo")
+        .unwrap();
+    assert_eq!(info.header("X-MBX-APIKEY"), None);
+    assert!(!info.query.contains("signature"));
+}
+
+/* Routes of an account without any operation but its trades, the deposits and withdrawals being given apart */
+fn trade_routes(deposits: Vec<MockRoute>) -> Vec<MockRoute> {
+    let mut routes = vec![
+        MockRoute::fixture("/api/v3/exchangeInfo", "binance/exchange_info.json"),
+        MockRoute::new(
+            "/api/v3/account",
+            r#"{"balances": [{"asset": "EUR", "free": "100.00000000", "locked": "0.00000000"}]}"#,
+        ),
+        MockRoute::fixture("/api/v3/myTrades", "binance/my_trades_btceur.json")
+            .with_query("symbol=BTCEUR"),
+        MockRoute::fixture("/api/v3/myTrades", "binance/my_trades_ethbtc.json")
+            .with_query("symbol=ETHBTC"),
+        MockRoute::new("/api/v3/myTrades", "[]"),
+    ];
+    routes.extend(deposits);
+    routes.extend(vec![
+        MockRoute::new("/sapi/v1/capital/deposit/hisrec", "[]"),
+        MockRoute::new("/sapi/v1/capital/withdraw/history", "[]"),
+        MockRoute::new("/sapi/v1/fiat/orders", r#"{"data": []}"#),
+        MockRoute::new("/sapi/v1/convert/tradeFlow", r#"{"list": []}"#),
+        MockRoute::new("/sapi/v1/asset/dribblet", r#"{"userAssetDribblets": []}"#),
+        MockRoute::new(
+            "/sapi/v1/simple-earn/flexible/history/rewardsRecord",
+            r#"{"rows": [], "total": 0}"#,
+        ),
+        MockRoute::new(
+            "/sapi/v1/simple-earn/locked/history/rewardsRecord",
+            r#"{"rows": [], "total": 0}"#,
+        ),
+    ]);
+    routes
+}
+
+#[tokio::test]
+async fn binance_trades_of_assets_never_held() {
+    let server = MockServer::start(trade_routes(Vec::new()));
+    let client = BinanceClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    );
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap();
+
+    // Only EUR is held: BTC is found by its trade against EUR, then ETH by its trade against BTC
+    let history = fetch_history_binance(&client, start, end, &BinanceHistory::default())
+        .await
+        .unwrap();
+    let trades: Vec<(&str, u64)> = history
+        .trades
+        .iter()
+        .map(|trade| (trade.symbol.as_str(), trade.id))
+        .collect();
+    assert_eq!(trades, vec![("BTCEUR", 28457), ("ETHBTC", 9121)]);
+    let searched = |requests: &[MockRequest], symbol: &str| {
+        requests
+            .iter()
+            .filter(|request| request.path == "/api/v3/myTrades")
+            .find(|request| request.query.contains(&format!("symbol={symbol}&")))
+            .map(|request| request.query.clone())
+    };
+    let requests = server.requests();
+    assert!(searched(&requests, "ETHUSDT").is_some());
+
+    // The next fetch starts from the trades already fetched
+    let next = fetch_history_binance(&client, end, end + chrono::Duration::days(1), &history)
+        .await
+        .unwrap();
+    let requests = server.requests()[requests.len()..].to_vec();
+    assert!(searched(&requests, "BTCEUR")
+        .unwrap()
+        .contains("fromId=28458&"));
+    assert!(searched(&requests, "ETHBTC")
+        .unwrap()
+        .contains("fromId=9122&"));
+    assert!(searched(&requests, "BNBEUR").unwrap().contains("fromId=0&"));
+
+    // The recorded responses give the same trades again, they are not duplicated
+    let mut merged = history;
+    merged.merge(next);
+    assert_eq!(merged.trades.len(), 2);
+}
+
+#[tokio::test]
+async fn binance_deposits_paginated() {
+    let deposit = |id: usize| {
+        format!(
+            r#"{{"id": "{id}", "amount": "1", "coin": "BTC", "network": "BTC", "status": 1, "address": "", "txId": "", "insertTime": 1673308800000}}"#
+        )
+    };
+    let page = |ids: std::ops::Range<usize>| {
+        format!("[{}]", ids.map(deposit).collect::<Vec<String>>().join(","))
+    };
+    let server = MockServer::start(trade_routes(vec![
+        MockRoute::new("/sapi/v1/capital/deposit/hisrec", &page(0..1000)).with_query("offset=0"),
+        MockRoute::new("/sapi/v1/capital/deposit/hisrec", &page(1000..1001))
+            .with_query("offset=1000"),
+    ]));
+    let client = BinanceClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    );
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let history = fetch_history_binance(
+        &client,
+        start,
+        start + chrono::Duration::days(30),
+        &BinanceHistory::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(history.deposits.len(), 1001);
+}
@@ -0,0 +1,199 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        create_bitcoin_txs, fetch_history_bitcoin, BitcoinAccount, CoinGeckoClient, EsploraClient,
+    },
+    structs::{wallet_manager::WalletManager, IncomeType, Persistable, Platform, Transaction},
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+// Test vectors of BIP44, BIP49 and BIP84 (mnemonic "abandon abandon ... about", first account)
+const XPUB: &str = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+const YPUB: &str = "ypub6Ww3ibxVfGzLrAH1PNcjyAWenMTbbAosGNB6VvmSEgytSER9azLDWCxoJwW7Ke7icmizBMXrzBx9979FfaHxHcrArf3zbeJJJUZPf663zsP";
+const ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+const RECEIVE_0: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+const RECEIVE_1: &str = "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g";
+const RECEIVE_2: &str = "bc1qp59yckz4ae5c4efgw2s5wfyvrz0ala7rgvuz8z";
+const CHANGE_0: &str = "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el";
+const EXCHANGE: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+const MERCHANT: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+
+const TX_A: &str = "559aead08264d5795d3909718cdd05abd49572e84fe55590eef31a88a08fdffd";
+const TX_B: &str = "df7e70e5021544f4834bbee64a9e3789febc4be81470df629cad6ddb03320a5c";
+const TX_C: &str = "6b23c0d5f35d1b11f9b683f0b0a617355deb11277d91ae091d399c655b87940d";
+const TX_D: &str = "3f39d5c348e5b79d06e842c114e6cc571583bbf44e4b0ebfda1a01ec05745d43";
+const TX_E: &str = "a9f51566bd6705f7ea6ad54bb9deb449f795582d6529a0e22207b8981233ec58";
+
+#[test]
+fn bitcoin_addresses_derivation() {
+    let legacy = BitcoinAccount::parse(XPUB).unwrap();
+    assert_eq!(
+        legacy.derive_address(0, 0).unwrap(),
+        "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"
+    );
+    let nested_segwit = BitcoinAccount::parse(YPUB).unwrap();
+    assert_eq!(
+        nested_segwit.derive_address(0, 0).unwrap(),
+        "37VucYSaXLCAsxYyAPfbSi9eh4iEcbShgf"
+    );
+    let native_segwit = BitcoinAccount::parse(ZPUB).unwrap();
+    assert_eq!(native_segwit.derive_address(0, 0).unwrap(), RECEIVE_0);
+    assert_eq!(native_segwit.derive_address(0, 1).unwrap(), RECEIVE_1);
+    assert_eq!(native_segwit.derive_address(1, 0).unwrap(), CHANGE_0);
+
+    assert!(BitcoinAccount::parse("not an extended key").is_err());
+}
+
+fn esplora_routes() -> Vec<MockRoute> {
+    let account = BitcoinAccount::parse(ZPUB).unwrap();
+    let mut routes = vec![
+        MockRoute::fixture(
+            &format!("/address/{RECEIVE_0}/txs/chain"),
+            "bitcoin/txs_receive_0.json",
+        ),
+        MockRoute::fixture(
+            &format!("/address/{RECEIVE_1}/txs/chain"),
+            "bitcoin/txs_receive_1.json",
+        ),
+        MockRoute::fixture(
+            &format!("/address/{RECEIVE_2}/txs/chain"),
+            "bitcoin/txs_receive_2.json",
+        ),
+        MockRoute::fixture(
+            &format!("/address/{CHANGE_0}/txs/chain"),
+            "bitcoin/txs_change_0.json",
+        ),
+        MockRoute::fixture("/coins/bitcoin/history", "bitcoin/history_bitcoin.json"),
+    ];
+    // With a gap limit of 2, the scan stops after the two first unused addresses of each chain
+    for (chain, index) in [(0, 3), (0, 4), (1, 1), (1, 2)] {
+        let address = account.derive_address(chain, index).unwrap();
+        routes.push(MockRoute::new(
+            &format!("/address/{address}/txs/chain"),
+            "[]",
+        ));
+    }
+    routes
+}
+
+#[tokio::test]
+async fn bitcoin_history_to_transactions() {
+    let server = MockServer::start(esplora_routes());
+    let client = EsploraClient::new(server.url.clone());
+    let price_client = CoinGeckoClient::new(server.url.clone(), None);
+
+    let history = fetch_history_bitcoin(&client, ZPUB, 2).await.unwrap();
+    assert_eq!(
+        history.addresses,
+        vec![RECEIVE_0, RECEIVE_1, RECEIVE_2, CHANGE_0]
+    );
+    // The transactions between the addresses are only fetched once
+    assert_eq!(history.transactions.len(), 5);
+    // No address past the gap limit was requested
+    assert_eq!(
+        server
+            .requests()
+            .iter()
+            .filter(|request| request.path.starts_with("/address/"))
+            .count(),
+        8
+    );
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_bitcoin_txs(&mut wallet_manager, &mut txs, &history, &price_client)
+        .await
+        .unwrap();
+
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            format!("bitcoin-{TX_A}"),
+            format!("bitcoin-{TX_B}"),
+            format!("bitcoin-{TX_C}"),
+            format!("bitcoin-{TX_D}-fee"),
+            format!("bitcoin-{TX_E}"),
+        ]
+    );
+
+    let btc = wallet_manager.create_or_get_wallet_id(
+        "BTC",
+        &Platform::Blockchain,
+        &Some(ZPUB.to_string()),
+        false,
+    );
+    let address_of = |wallet_manager: &WalletManager, id: &String| {
+        wallet_manager
+            .wallets
+            .get(id)
+            .unwrap()
+            .get()
+            .address
+            .clone()
+            .unwrap()
+    };
+
+    // Withdrawal from an exchange, which paid the fee and got its change back
+    match &txs[0] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(to.id, btc);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.fee, None);
+            assert_eq!(to.price_eur, dec!(40000));
+            assert_eq!(*amount, dec!(0.01));
+            assert_eq!(address_of(&wallet_manager, &from.id), EXCHANGE);
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Payment spending both receive addresses, the change going to the change address
+    match &txs[2] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.015));
+            assert_eq!(from.fee, Some(dec!(0.00001)));
+            assert_eq!(to.fee, None);
+            assert_eq!(*amount, dec!(0.012));
+            assert_eq!(address_of(&wallet_manager, &to.id), MERCHANT);
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Change moved to a receive address: only the fee leaves the wallet
+    match &txs[3] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(to.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.00299));
+            assert_eq!(from.fee, Some(dec!(0.00001)));
+            assert_eq!(to.fee, None);
+            assert_eq!(*amount, dec!(0));
+        }
+        _ => panic!("Expected a fee transfer"),
+    }
+
+    match &txs[4] {
+        Transaction::Transfer {
+            from,
+            amount,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.00298));
+            assert_eq!(*amount, dec!(0.001));
+            assert_eq!(*income.get_subtype(), IncomeType::Mining);
+            assert_eq!(income.get_value(), dec!(40));
+        }
+        _ => panic!("Expected an income"),
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{TimeZone, Utc};
+use hashbrown::HashSet;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{create_bitfinex_txs, fetch_history_bitfinex, BitfinexClient, BitfinexHistory},
+    parsing::{read_bitfinex_ledgers_csv, read_bitfinex_movements_csv, read_bitfinex_trades_csv},
+    structs::{wallet_manager::WalletManager, Persistable, Platform, TradeType, Transaction},
+    tests::{bitfinex_integration_test::bitfinex_routes, mock_server::MockServer},
+};
+
+fn read_reports() -> BitfinexHistory {
+    BitfinexHistory {
+        trades: read_bitfinex_trades_csv("src/tests/fixtures/bitfinex/trades_report.csv").unwrap(),
+        movements: read_bitfinex_movements_csv("src/tests/fixtures/bitfinex/movements_report.csv")
+            .unwrap(),
+        ledgers: read_bitfinex_ledgers_csv("src/tests/fixtures/bitfinex/ledgers_report.csv")
+            .unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn bitfinex_reports_to_transactions() {
+    let server = MockServer::start(bitfinex_routes());
+    let client = BitfinexClient::new(
+        server.url.clone(),
+        server.url.clone(),
+        String::new(),
+        String::new(),
+    );
+
+    let reports = read_reports();
+    assert_eq!(reports.trades.len(), 3);
+    // Same values as the API, the pair being written BTC/EUR
+    let trade = reports
+        .trades
+        .iter()
+        .find(|trade| trade.id == 1201)
+        .unwrap();
+    assert_eq!(trade.symbol, "tBTC:EUR");
+    assert_eq!(trade.mts, 1675209600000);
+    assert_eq!(trade.exec_amount, dec!(-0.1));
+    assert_eq!(trade.fee, dec!(-4.2));
+    assert_eq!(reports.movements[1].mts_updated, 1673308800000);
+    assert_eq!(reports.movements[0].destination_address, None);
+    assert_eq!(reports.ledgers[1].mts, 1682899200000);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_bitfinex_txs(&mut wallet_manager, &mut txs, &reports, &client)
+        .await
+        .unwrap();
+
+    let ids: Vec<&str> = txs.iter().map(|tx| tx.get_id().as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "bitfinex-movement-13105603",
+            "bitfinex-movement-13105604",
+            "bitfinex-trade-1201",
+            "bitfinex-trade-1202",
+            "bitfinex-ledger-2531822315",
+            "bitfinex-trade-1204",
+            "bitfinex-movement-13105607",
+            "bitfinex-ledger-2531822318",
+        ]
+    );
+
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &Platform::Bitfinex, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Bitfinex, &None, false);
+
+    match &txs[5] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(3095.8));
+            assert_eq!(to.id, btc);
+            assert_eq!(to.pre_tx_balance, dec!(0.10255));
+            assert_eq!(to.fee, Some(dec!(0.0001)));
+            assert_eq!(to.price_eur, dec!(24000));
+            assert_eq!(*sold_amount, dec!(1200));
+            assert_eq!(*bought_amount, dec!(0.05));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(1200)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[6] {
+        Transaction::Withdrawal { from, amount, .. } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(1895.8));
+            assert_eq!(from.fee, Some(dec!(1)));
+            assert_eq!(*amount, dec!(500));
+        }
+        _ => panic!("Expected a withdrawal"),
+    }
+}
+
+#[tokio::test]
+async fn bitfinex_reports_merged_with_api() {
+    let server = MockServer::start(bitfinex_routes());
+    let client = BitfinexClient::new(
+        server.url.clone(),
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    )
+    .with_page_limit(2);
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 7, 1, 0, 0, 0).unwrap();
+
+    let mut history = fetch_history_bitfinex(&client, start, end).await.unwrap();
+    history.merge(read_reports());
+    // Only the operations missing from the API are added
+    assert_eq!(history.trades.len(), 4);
+    assert_eq!(history.movements.len(), 5);
+    assert_eq!(history.ledgers.len(), 4);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_bitfinex_txs(&mut wallet_manager, &mut txs, &history, &client)
+        .await
+        .unwrap();
+
+    let ids: HashSet<&String> = txs.iter().map(|tx| tx.get_id()).collect();
+    assert_eq!(txs.len(), 11);
+    assert_eq!(ids.len(), txs.len());
+    assert!(ids.contains(&"bitfinex-trade-1204".to_string()));
+    assert!(ids.contains(&"bitfinex-movement-13105607".to_string()));
+    assert!(ids.contains(&"bitfinex-ledger-2531822318".to_string()));
+}
@@ -0,0 +1,238 @@
+use chrono::{TimeZone, Utc};
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        create_bitfinex_txs, fetch_history_bitfinex, BitfinexClient, BitfinexConnector,
+        ConnectorRegistry,
+    },
+    structs::{
+        portfolio_manager::PortfolioManager, wallet_manager::WalletManager, IncomeType,
+        Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+pub fn bitfinex_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture("/v2/auth/r/trades/hist", "bitfinex/trades_page3.json")
+            .with_body("\"end\":1675209600000"),
+        MockRoute::fixture("/v2/auth/r/trades/hist", "bitfinex/trades_page2.json")
+            .with_body("\"end\":1677628800000"),
+        MockRoute::fixture("/v2/auth/r/trades/hist", "bitfinex/trades_page1.json"),
+        MockRoute::new("/v2/auth/r/movements/hist", "[]").with_body("\"end\":1672876800000"),
+        MockRoute::fixture("/v2/auth/r/movements/hist", "bitfinex/movements.json"),
+        MockRoute::new("/v2/auth/r/ledgers/hist", "[]").with_body("\"end\":1675209600000"),
+        MockRoute::fixture("/v2/auth/r/ledgers/hist", "bitfinex/ledgers.json"),
+        MockRoute::fixture(
+            "/v2/candles/trade:1m:tBTCEUR/hist",
+            "bitfinex/candles_btceur.json",
+        ),
+        MockRoute::fixture(
+            "/v2/candles/trade:1m:tETHEUR/hist",
+            "bitfinex/candles_etheur.json",
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn bitfinex_history_to_transactions() {
+    let server = MockServer::start(bitfinex_routes());
+    let client = BitfinexClient::new(
+        server.url.clone(),
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    )
+    .with_page_limit(2);
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 7, 1, 0, 0, 0).unwrap();
+
+    let history = fetch_history_bitfinex(&client, start, end).await.unwrap();
+    assert_eq!(history.trades.len(), 3); // Paginated by end, the trade at the end of a page is not duplicated
+    assert_eq!(history.movements.len(), 4);
+    assert_eq!(history.ledgers.len(), 3);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_bitfinex_txs(&mut wallet_manager, &mut txs, &history, &client)
+        .await
+        .unwrap();
+
+    let ids: Vec<&str> = txs.iter().map(|tx| tx.get_id().as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "bitfinex-movement-13105603",
+            "bitfinex-movement-13105604",
+            "bitfinex-trade-1201",
+            "bitfinex-trade-1202",
+            "bitfinex-trade-1203",
+            "bitfinex-ledger-2531822315",
+            "bitfinex-ledger-2531822316",
+            "bitfinex-movement-13105605",
+        ]
+    );
+    assert!(matches!(txs[0], Transaction::Deposit { amount, .. } if amount == dec!(1000)));
+
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &Platform::Bitfinex, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Bitfinex, &None, false);
+    let eth = wallet_manager.create_or_get_wallet_id("ETH", &Platform::Bitfinex, &None, false);
+
+    match &txs[2] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.3));
+            assert_eq!(from.price_eur, dec!(21000));
+            assert_eq!(to.id, eur);
+            assert_eq!(to.fee, Some(dec!(4.2)));
+            assert_eq!(*sold_amount, dec!(0.1));
+            assert_eq!(*bought_amount, dec!(2100));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[3] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.2));
+            assert_eq!(from.price_eur, dec!(25000));
+            assert_eq!(to.id, eth);
+            assert_eq!(to.fee, Some(dec!(0.003)));
+            assert_eq!(to.price_eur, dec!(1600));
+            assert_eq!(*sold_amount, dec!(0.0975));
+            assert_eq!(*bought_amount, dec!(1.5));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[4] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(3095.8));
+            assert_eq!(from.fee, Some(dec!(1.7)));
+            assert_eq!(*sold_amount, dec!(850));
+            assert_eq!(to.pre_tx_balance, dec!(1.497));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(850)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match (&txs[5], &txs[6]) {
+        (
+            Transaction::Transfer {
+                from,
+                income: Some(interest),
+                ..
+            },
+            Transaction::Transfer {
+                income: Some(staking),
+                ..
+            },
+        ) => {
+            assert_eq!(from.id, btc);
+            assert_eq!(*interest.get_subtype(), IncomeType::Interest);
+            assert_eq!(interest.get_value(), dec!(1.25));
+            assert_eq!(*staking.get_subtype(), IncomeType::Staking);
+            assert_eq!(staking.get_value(), dec!(16));
+        }
+        _ => panic!("Expected two incomes"),
+    }
+
+    match &txs[7] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.pre_tx_balance, dec!(0.10255));
+            assert_eq!(from.fee, Some(dec!(0.0004)));
+            assert_eq!(*amount, dec!(0.1));
+            let wallet = wallet_manager.wallets.get(&to.id).unwrap().get();
+            assert_eq!(wallet.platform, Platform::Blockchain);
+            assert_eq!(
+                wallet.address,
+                Some("bc1qm34lsc65zpw79lxes69zkqmk6ee3ewf0j77s3h".to_string())
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // The prices missing at a taxable transaction are fetched by the portfolio from the same server
+    let mut connectors = ConnectorRegistry::default();
+    connectors.register(BitfinexConnector {
+        price_client: client,
+    });
+    let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+    portfolio_manager
+        .calculate_portfolio_history(&txs, &wallet_manager.wallets, &connectors)
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    let trades = requests
+        .iter()
+        .find(|request| request.path == "/v2/auth/r/trades/hist")
+        .unwrap();
+    assert_eq!(trades.method, "POST");
+    assert_eq!(trades.header("bfx-apikey"), Some(&"test-key".to_string()));
+    assert!(trades.header("bfx-nonce").is_some());
+    assert_eq!(
+        trades
+            .header("bfx-signature")
+            .map(|signature| signature.len()),
+        Some(96)
+    );
+}
+
+#[tokio::test]
+async fn bitfinex_history_updated_by_a_new_fetch() {
+    let server = MockServer::start(bitfinex_routes());
+    let client = BitfinexClient::new(
+        server.url.clone(),
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    )
+    .with_page_limit(2);
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 7, 1, 0, 0, 0).unwrap();
+
+    let mut history = fetch_history_bitfinex(&client, start, end).await.unwrap();
+    let mut fetched = fetch_history_bitfinex(&client, start, end).await.unwrap();
+    fetched.movements[0].status = "COMPLETED".to_string();
+    let id = fetched.movements[0].id;
+    history.update(fetched);
+
+    // The operations fetched again replace the saved ones
+    assert_eq!(history.trades.len(), 3);
+    assert_eq!(history.movements.len(), 4);
+    assert_eq!(history.ledgers.len(), 3);
+    let movement = history.movements.iter().find(|movement| movement.id == id);
+    assert_eq!(movement.unwrap().status, "COMPLETED");
+}
@@ -0,0 +1,158 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{create_bitpanda_txs, fetch_history_bitpanda, BitpandaClient},
+    structs::{
+        wallet_manager::WalletManager, IncomeType, Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+fn bitpanda_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture(
+            "/v1/wallets/transactions",
+            "bitpanda/wallet_transactions.json",
+        ),
+        MockRoute::fixture("/v1/wallets", "bitpanda/wallets.json"),
+        MockRoute::fixture(
+            "/v1/fiatwallets/transactions",
+            "bitpanda/fiat_transactions.json",
+        ),
+        MockRoute::fixture("/v1/fiatwallets", "bitpanda/fiatwallets.json"),
+        MockRoute::fixture("/v1/trades", "bitpanda/trades_page2.json").with_query("cursor=b9f3e1"),
+        MockRoute::fixture("/v1/trades", "bitpanda/trades_page1.json"),
+    ]
+}
+
+#[tokio::test]
+async fn bitpanda_history_to_transactions() {
+    let server = MockServer::start(bitpanda_routes());
+    let client = BitpandaClient::new(server.url.clone(), "test-key".to_string());
+
+    let history = fetch_history_bitpanda(&client).await.unwrap();
+    assert_eq!(history.wallets.len(), 3);
+    assert_eq!(history.fiat_wallets.len(), 1);
+    assert_eq!(history.trades.len(), 4);
+    assert_eq!(history.wallet_transactions.len(), 5);
+    assert_eq!(history.fiat_transactions.len(), 4);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_bitpanda_txs(&mut wallet_manager, &mut txs, &history, &client)
+        .await
+        .unwrap();
+
+    let ids: Vec<&str> = txs.iter().map(|tx| tx.get_id().as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "bitpanda-fiat-f1",
+            "bitpanda-trade-t1",
+            "bitpanda-fiat-f2",
+            "bitpanda-trade-t2",
+            "bitpanda-income-w2",
+            "bitpanda-trade-t3",
+            "bitpanda-withdrawal-w4",
+            "bitpanda-deposit-w5",
+            "bitpanda-fiat-f4",
+        ]
+    );
+
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &Platform::Bitpanda, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Bitpanda, &None, false);
+    let best = wallet_manager.create_or_get_wallet_id("BEST", &Platform::Bitpanda, &None, false);
+
+    // Buy of the savings plan, paid with the direct debit credited on the fiat wallet
+    match &txs[3] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(50));
+            assert_eq!(to.id, btc);
+            assert_eq!(to.pre_tx_balance, dec!(0.005));
+            assert_eq!(to.price_eur, dec!(25000));
+            assert_eq!(*sold_amount, dec!(50));
+            assert_eq!(*bought_amount, dec!(0.002));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(50)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[4] {
+        Transaction::Transfer {
+            from,
+            to,
+            amount,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(from.id, best);
+            assert_eq!(to.id, best);
+            assert_eq!(*amount, dec!(10));
+            assert_eq!(*income.get_subtype(), IncomeType::Income);
+            assert_eq!(income.get_value(), dec!(4.5));
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    // Sell whose fee is paid in BEST: 0.5 BEST at 0.50 € put on the sold BTC
+    match &txs[5] {
+        Transaction::Trade {
+            from,
+            to,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.007));
+            assert_eq!(from.price_eur, dec!(28000));
+            assert_eq!(from.fee, Some(dec!(0.25) / dec!(28000)));
+            assert_eq!(to.id, eur);
+            assert_eq!(to.fee, None);
+            assert_eq!(*bought_amount, dec!(28));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[6] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.006));
+            assert_eq!(from.fee, Some(dec!(0.0001)));
+            assert_eq!(from.price_eur, dec!(25000));
+            assert_eq!(*amount, dec!(0.003));
+            let wallet = wallet_manager.wallets.get(&to.id).unwrap().get();
+            assert_eq!(wallet.platform, Platform::Blockchain);
+            assert_eq!(
+                wallet.address,
+                Some("bc1qa5wkgaew2dkv56kfvj49j0av5nml45x9ek9hz6".to_string())
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    assert!(
+        matches!(&txs[8], Transaction::Withdrawal { from, amount, .. } if from.pre_tx_balance == dec!(28) && *amount == dec!(20))
+    );
+
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .all(|request| request.header("X-Api-Key") == Some(&"test-key".to_string())));
+}
@@ -0,0 +1,170 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{create_cardano_txs, fetch_history_cardano, BlockfrostClient, CoinGeckoClient},
+    structs::{wallet_manager::WalletManager, IncomeType, Persistable, Platform, Transaction},
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+const STAKE_ADDRESS: &str = "stake1u9ylzsgxaa6xctf4juup682ar3juj85n8tx3hthnljg47zctvm3rc";
+const ADDRESS_A: &str = "addr1q9ylzsgxaa6xctf4juup682ar3juj85n8tx3hthnljg47zqgk4hha8y2mxk0ftlvj6ct7kl7nwhrzpsn8ryx3ajmvqsmnj3fa";
+const ADDRESS_B: &str = "addr1qxw2dg4ktdjw0ftnpw5xy5e7cfdk5k4ljzp3sdwx6ls6hkzylzsgxaa6xctf4juup682ar3juj85n8tx3hthnljg47zqyt5ls8";
+const HASH_1: &str = "8d4b5e1f0c2a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4";
+const HASH_2: &str = "1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90a";
+const HASH_3: &str = "f0e1d2c3b4a5968778695a4b3c2d1e0ff0e1d2c3b4a5968778695a4b3c2d1e0f";
+
+fn cardano_routes() -> Vec<MockRoute> {
+    let mut routes = vec![
+        MockRoute::fixture(
+            &format!("/accounts/{STAKE_ADDRESS}/addresses"),
+            "cardano/addresses.json",
+        ),
+        MockRoute::fixture(
+            &format!("/accounts/{STAKE_ADDRESS}/rewards"),
+            "cardano/rewards.json",
+        ),
+        MockRoute::fixture(
+            &format!("/addresses/{ADDRESS_A}/transactions"),
+            "cardano/transactions_a.json",
+        ),
+        MockRoute::fixture(
+            &format!("/addresses/{ADDRESS_B}/transactions"),
+            "cardano/transactions_b.json",
+        ),
+        MockRoute::fixture(
+            &format!("/txs/{HASH_3}/withdrawals"),
+            "cardano/withdrawals_3.json",
+        ),
+        MockRoute::fixture("/epochs/392", "cardano/epoch_392.json"),
+        MockRoute::fixture("/epochs/393", "cardano/epoch_393.json"),
+        MockRoute::fixture("/coins/cardano/history", "cardano/history_cardano.json"),
+    ];
+    for (index, hash) in [HASH_1, HASH_2, HASH_3].iter().enumerate() {
+        let number = index + 1;
+        routes.push(MockRoute::fixture(
+            &format!("/txs/{hash}"),
+            &format!("cardano/tx_{number}.json"),
+        ));
+        routes.push(MockRoute::fixture(
+            &format!("/txs/{hash}/utxos"),
+            &format!("cardano/utxos_{number}.json"),
+        ));
+    }
+    routes
+}
+
+#[tokio::test]
+async fn cardano_history_to_transactions() {
+    let server = MockServer::start(cardano_routes());
+    let client = BlockfrostClient::new(server.url.clone(), "test-project".to_string());
+    let price_client = CoinGeckoClient::new(server.url.clone(), None);
+
+    let history = fetch_history_cardano(&client, STAKE_ADDRESS).await.unwrap();
+    assert_eq!(history.addresses.len(), 2);
+    // The transactions between the two addresses are only fetched once
+    assert_eq!(history.transactions.len(), 3);
+    assert_eq!(history.rewards.len(), 2);
+    assert_eq!(history.epochs.len(), 2);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_cardano_txs(&mut wallet_manager, &mut txs, &history, &price_client)
+        .await
+        .unwrap();
+
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            format!("cardano-{HASH_1}"),
+            format!("cardano-{HASH_2}-fee"),
+            format!("cardano-reward-{STAKE_ADDRESS}-390"),
+            format!("cardano-reward-{STAKE_ADDRESS}-391"),
+            format!("cardano-{HASH_3}"),
+        ]
+    );
+
+    let ada = wallet_manager.create_or_get_wallet_id(
+        "ADA",
+        &Platform::Blockchain,
+        &Some(STAKE_ADDRESS.to_string()),
+        false,
+    );
+
+    // Reception from an exchange, which paid the fee
+    match &txs[0] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(to.id, ada);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.fee, None);
+            assert_eq!(to.price_eur, dec!(0.25));
+            assert_eq!(*amount, dec!(100));
+            let exchange = wallet_manager.wallets.get(&from.id).unwrap().get();
+            assert!(exchange
+                .address
+                .as_ref()
+                .unwrap()
+                .starts_with("addr1qxck0l4s"));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Registration of the stake key: the deposit stays in the wallet, only the fee is paid
+    match &txs[1] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, ada);
+            assert_eq!(from.pre_tx_balance, dec!(100));
+            assert_eq!(from.fee, Some(dec!(0.18)));
+            assert_eq!(to.fee, None);
+            assert_eq!(*amount, dec!(0));
+        }
+        _ => panic!("Expected a fee transfer"),
+    }
+
+    match &txs[2] {
+        Transaction::Transfer {
+            from,
+            amount,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(from.id, ada);
+            assert_eq!(from.pre_tx_balance, dec!(99.82));
+            assert_eq!(*amount, dec!(1.5));
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+            assert_eq!(income.get_value(), dec!(0.375));
+            assert_eq!(txs[2].get_tx_base().timestamp.timestamp(), 1673000000);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    // Payment withdrawing the rewards, which were already counted in the wallet
+    match &txs[4] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, ada);
+            assert_eq!(from.pre_tx_balance, dec!(102.92));
+            assert_eq!(from.fee, Some(dec!(0.2)));
+            assert_eq!(*amount, dec!(50));
+            let friend = wallet_manager.wallets.get(&to.id).unwrap().get();
+            assert_eq!(friend.platform, Platform::Blockchain);
+            assert!(friend
+                .address
+                .as_ref()
+                .unwrap()
+                .starts_with("addr1q8kv3ahf"));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .filter(|request| !request.path.starts_with("/coins"))
+        .all(|request| request.header("project_id") == Some(&"test-project".to_string())));
+}
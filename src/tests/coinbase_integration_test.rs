@@ -0,0 +1,251 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        create_coinbase_txs, fetch_history_coinbase, BitcoinConnector, CoinGeckoClient,
+        CoinbaseClient, CoinbaseConnector, ConnectorRegistry, BITCOIN_GAP_LIMIT,
+    },
+    structs::{
+        portfolio_manager::PortfolioManager, wallet_manager::WalletManager, IncomeType,
+        Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+fn coinbase_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture("/v2/accounts", "coinbase/accounts_page2.json")
+            .with_query("starting_after=8d4b5e1f-btc"),
+        MockRoute::fixture("/v2/accounts", "coinbase/accounts_page1.json"),
+        MockRoute::fixture(
+            "/v2/accounts/2bbf394c-eur/transactions",
+            "coinbase/transactions_eur.json",
+        ),
+        MockRoute::fixture(
+            "/v2/accounts/8d4b5e1f-btc/transactions",
+            "coinbase/transactions_btc.json",
+        ),
+        MockRoute::fixture(
+            "/v2/accounts/58542935-eth/transactions",
+            "coinbase/transactions_eth.json",
+        ),
+        MockRoute::fixture(
+            "/api/v3/brokerage/orders/historical/fills",
+            "coinbase/fills_page2.json",
+        )
+        .with_query("cursor=789100"),
+        MockRoute::fixture(
+            "/api/v3/brokerage/orders/historical/fills",
+            "coinbase/fills_page1.json",
+        ),
+        MockRoute::fixture("/v2/prices/ETH-EUR/spot", "coinbase/spot_eth_eur.json"),
+        MockRoute::fixture("/v2/prices/BTC-EUR/spot", "coinbase/spot_btc_eur.json"),
+        // The wallets outside of Coinbase are priced by CoinGecko
+        MockRoute::fixture("/coins/bitcoin/history", "coingecko/history_bitcoin.json"),
+    ]
+}
+
+#[tokio::test]
+async fn coinbase_history_to_transactions() {
+    let server = MockServer::start(coinbase_routes());
+    let client = CoinbaseClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    );
+
+    let history = fetch_history_coinbase(&client).await.unwrap();
+    assert_eq!(history.accounts.len(), 3);
+    assert_eq!(history.transactions.len(), 13);
+    assert_eq!(history.fills.len(), 2);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_coinbase_txs(&mut wallet_manager, &mut txs, &history, &client)
+        .await
+        .unwrap();
+
+    let ids: Vec<&str> = txs.iter().map(|tx| tx.get_id().as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "coinbase-eur-dep-1",
+            "coinbase-btc-buy-1",
+            "coinbase-fill-e1f2a3b4",
+            "coinbase-btc-send-1",
+            "coinbase-btc-trade-1",
+            "coinbase-btc-send-2",
+            "coinbase-btc-sell-1",
+            "coinbase-eth-stake-1",
+            "coinbase-fill-c5d6e7f8",
+        ]
+    );
+
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &Platform::Coinbase, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Coinbase, &None, false);
+    let eth = wallet_manager.create_or_get_wallet_id("ETH", &Platform::Coinbase, &None, false);
+
+    match &txs[1] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(to.id, btc);
+            assert_eq!(to.price_eur, dec!(20000));
+            assert_eq!(*sold_amount, dec!(500));
+            assert_eq!(*bought_amount, dec!(0.025));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(500)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    // Advanced Trade fill: the commission is paid in the quote currency
+    match &txs[2] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            ..
+        } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(500));
+            assert_eq!(from.fee, Some(dec!(1.2)));
+            assert_eq!(*sold_amount, dec!(200));
+            assert_eq!(to.pre_tx_balance, dec!(0.025));
+            assert_eq!(*bought_amount, dec!(0.01));
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[3] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.035));
+            assert_eq!(from.fee, Some(dec!(0.0001)));
+            assert_eq!(from.price_eur, dec!(22500));
+            assert_eq!(*amount, dec!(0.01));
+            let wallet = wallet_manager.wallets.get(&to.id).unwrap().get();
+            assert_eq!(wallet.platform, Platform::Blockchain);
+            assert_eq!(
+                wallet.address,
+                Some("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".to_string())
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[4] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.0249));
+            assert_eq!(to.id, eth);
+            assert_eq!(*sold_amount, dec!(0.005));
+            assert_eq!(*bought_amount, dec!(0.08));
+            assert_eq!(to.price_eur, dec!(1625));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a conversion"),
+    }
+
+    match &txs[6] {
+        Transaction::Trade {
+            from,
+            to,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.pre_tx_balance, dec!(0.0219));
+            assert_eq!(to.id, eur);
+            assert_eq!(*bought_amount, dec!(25));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[7] {
+        Transaction::Transfer {
+            from,
+            to,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(from.id, eth);
+            assert_eq!(to.id, eth);
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+            assert_eq!(income.get_value(), dec!(1.6));
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    // Prices not given by Coinbase in the transaction come from the spot price of the day
+    match &txs[8] {
+        Transaction::Trade {
+            from,
+            to,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, eth);
+            assert_eq!(from.pre_tx_balance, dec!(0.081));
+            assert_eq!(from.price_eur, dec!(1700));
+            assert_eq!(to.id, btc);
+            assert_eq!(to.price_eur, dec!(26000));
+            assert_eq!(to.fee, Some(dec!(0.0000065)));
+            assert_eq!(*bought_amount, dec!(0.0013));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    // The prices missing at a taxable transaction are fetched by the portfolio from the same server, the ones of the
+    // bitcoin sent outside of Coinbase from CoinGecko
+    let mut connectors = ConnectorRegistry::default();
+    connectors.register(CoinbaseConnector {
+        price_client: client,
+    });
+    connectors.register(BitcoinConnector {
+        xpub: None,
+        gap_limit: BITCOIN_GAP_LIMIT,
+        price_client: CoinGeckoClient::new(server.url.clone(), None),
+    });
+    let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+    portfolio_manager
+        .calculate_portfolio_history(&txs, &wallet_manager.wallets, &connectors)
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    let accounts = requests
+        .iter()
+        .find(|request| request.path == "/v2/accounts")
+        .unwrap();
+    assert_eq!(
+        accounts.header("CB-ACCESS-KEY"),
+        Some(&"test-key".to_string())
+    );
+    assert!(accounts.header("CB-ACCESS-SIGN").is_some());
+    assert!(accounts.header("CB-ACCESS-TIMESTAMP").is_some());
+}
@@ -0,0 +1,45 @@
+use chrono::{TimeZone, Utc};
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::CoinGeckoClient,
+    errors::ApiError,
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+fn coingecko_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture("/coins/bitcoin/history", "coingecko/history_bitcoin.json")
+            .with_query("date=10-03-2023"),
+        MockRoute::fixture("/search", "coingecko/search_jup.json").with_query("query=JUP"),
+        MockRoute::fixture(
+            "/coins/jupiter-exchange-solana/history",
+            "coingecko/history_jupiter.json",
+        ),
+        MockRoute::new("/search", r#"{"coins": []}"#),
+    ]
+}
+
+#[tokio::test]
+async fn coingecko_prices() {
+    let server = MockServer::start(coingecko_routes());
+    let client = CoinGeckoClient::new(server.url.clone(), Some("test-key".to_string()));
+    let time = Utc.with_ymd_and_hms(2023, 3, 10, 18, 30, 0).unwrap();
+
+    assert_eq!(
+        client.fetch_price("BTC", time).await.unwrap(),
+        dec!(18734.52)
+    );
+    // Not a known coin: searched by its symbol, the best ranked one is taken
+    assert_eq!(client.fetch_price("JUP", time).await.unwrap(), dec!(0.8123));
+    assert!(matches!(
+        client.fetch_price("UNKNOWN", time).await,
+        Err(ApiError::CouldNotFindPrice { .. })
+    ));
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].header("x-cg-demo-api-key"),
+        Some(&"test-key".to_string())
+    );
+}
@@ -0,0 +1,119 @@
+use std::fs;
+
+use chrono::{TimeZone, Utc};
+use hashbrown::HashMap;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        default_connectors, map_asset_pairs, CoinbaseClient, Connector, ConnectorRegistry,
+        DynConnector, KrakenConnector,
+    },
+    structs::{wallet_manager::WalletManager, Persistable, Platform},
+    tests::mock_server::{MockRoute, MockServer},
+    utils::save_data,
+};
+
+fn kraken_test_connector(name: &str) -> KrakenConnector {
+    let history_path = format!(".data_test/connector/{name}_history");
+    let mapped_path = format!(".data_test/connector/{name}_mapped_data");
+    let pairs_path = format!(".data_test/connector/{name}_pairs");
+    let _ = fs::remove_file(&history_path);
+    let _ = fs::remove_file(&mapped_path);
+    // The trades of the exports are all in euros, the pairs are not used
+    save_data(&pairs_path, &map_asset_pairs(HashMap::new())).unwrap();
+    KrakenConnector {
+        history_path,
+        mapped_path,
+        pairs_path,
+        csv_paths: Some((
+            "src/tests/fixtures/kraken/ledgers.csv".to_string(),
+            "src/tests/fixtures/kraken/trades.csv".to_string(),
+        )),
+    }
+}
+
+#[tokio::test]
+async fn kraken_connector_fetches_incrementally() {
+    let connector = kraken_test_connector("kraken_incremental");
+    assert!(Connector::is_configured(&connector));
+
+    let (ledger, trades, deposits, withdrawals) = connector.fetch_history().await.unwrap();
+    assert_eq!(ledger.len(), 7);
+    assert_eq!(trades.len(), 2);
+    assert_eq!(deposits.len(), 1);
+    assert_eq!(withdrawals.len(), 1);
+
+    // Nothing new since the previous fetch, the trades and movements are still given for the mapping
+    let (ledger, trades, _, withdrawals) = connector.fetch_history().await.unwrap();
+    assert!(ledger.is_empty());
+    assert_eq!(trades.len(), 2);
+    assert_eq!(withdrawals.len(), 1);
+}
+
+#[tokio::test]
+async fn kraken_connector_keeps_the_mapped_transactions() {
+    let connector = kraken_test_connector("kraken_mapped");
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+
+    let txs = connector
+        .fetch_transactions(&mut wallet_manager)
+        .await
+        .unwrap();
+    let ids: Vec<&String> = txs.iter().map(|tx| tx.get_id()).collect();
+    assert_eq!(ids, vec!["TJKLXF-PGMUI-4NTLXU", "TBZIP2-F6QOU-TMB6FY"]);
+
+    // The second fetch has no new ledger entry, the transactions mapped by the first one are still given
+    let txs = connector
+        .fetch_transactions(&mut wallet_manager)
+        .await
+        .unwrap();
+    let ids: Vec<&String> = txs.iter().map(|tx| tx.get_id()).collect();
+    assert_eq!(ids, vec!["TJKLXF-PGMUI-4NTLXU", "TBZIP2-F6QOU-TMB6FY"]);
+}
+
+#[test]
+fn connectors_by_platform() {
+    let connectors = default_connectors().unwrap();
+    let kraken = connectors.get(&Platform::Kraken).unwrap();
+    assert_eq!(kraken.platform(), Platform::Kraken);
+    let binance = connectors.get(&Platform::Binance).unwrap();
+    assert_eq!(binance.platform(), Platform::Binance);
+    assert_eq!(
+        connectors.get(&Platform::Blockchain).unwrap().platform(),
+        Platform::Blockchain
+    );
+    // The wallets of the imports on other platforms have no connector but are priced by the fallback
+    let bitstamp = Platform::Other("Bitstamp".to_string());
+    assert!(connectors.get(&bitstamp).is_none());
+    assert!(connectors.prices(&bitstamp));
+    assert!(connectors.prices(&Platform::Other("Lost".to_string())));
+}
+
+#[tokio::test]
+async fn registry_prices_other_platforms_with_the_fallback() {
+    let server = MockServer::start(vec![MockRoute::fixture(
+        "/v2/prices/BTC-EUR/spot",
+        "generic_csv/spot_btc_eur.json",
+    )]);
+    let bitstamp = Platform::Other("Bitstamp".to_string());
+    let time = Utc.with_ymd_and_hms(2023, 2, 10, 12, 0, 0).unwrap();
+
+    let mut connectors = ConnectorRegistry::default();
+    assert!(!connectors.prices(&bitstamp));
+    assert!(connectors.get_price(&bitstamp, time, "BTC").await.is_err());
+
+    connectors.set_fallback(CoinbaseClient::new(
+        server.url.clone(),
+        String::new(),
+        String::new(),
+    ));
+    let price = connectors.get_price(&bitstamp, time, "BTC").await.unwrap();
+    assert_eq!(price, dec!(24000));
+    // The fallback is only for the other platforms, a known platform needs its connector
+    assert!(!connectors.prices(&Platform::Kraken));
+    assert!(connectors
+        .get_price(&Platform::Kraken, time, "BTC")
+        .await
+        .is_err());
+}
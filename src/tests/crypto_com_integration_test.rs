@@ -0,0 +1,287 @@
+use chrono::{Duration, TimeZone, Utc};
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        create_crypto_com_txs, fetch_history_crypto_com, read_crypto_com_app_csv, CoinbaseClient,
+        CryptoComClient,
+    },
+    structs::{
+        wallet_manager::WalletManager, IncomeType, Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+fn crypto_com_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture("/exchange/v1/private/get-trades", "crypto_com/trades.json")
+            .with_body("\"method\":\"private/get-trades\""),
+        MockRoute::fixture(
+            "/exchange/v1/private/get-deposit-history",
+            "crypto_com/deposits.json",
+        ),
+        MockRoute::fixture(
+            "/exchange/v1/private/get-withdrawal-history",
+            "crypto_com/withdrawals.json",
+        ),
+        // Prices of the operations not given in euro
+        MockRoute::fixture("/v2/prices/BTC-EUR/spot", "crypto_com/spot_btc_eur.json"),
+        MockRoute::fixture("/v2/prices/ETH-EUR/spot", "crypto_com/spot_eth_eur.json"),
+        MockRoute::fixture("/v2/prices/CRO-EUR/spot", "crypto_com/spot_cro_eur.json"),
+    ]
+}
+
+#[tokio::test]
+async fn crypto_com_history_to_transactions() {
+    let server = MockServer::start(crypto_com_routes());
+    let client = CryptoComClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    );
+    let price_client = CoinbaseClient::new(server.url.clone(), String::new(), String::new());
+
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 1, 10, 0, 0, 0).unwrap();
+    let mut history = fetch_history_crypto_com(&client, start, end).await.unwrap();
+    history.app_rows =
+        read_crypto_com_app_csv("src/tests/fixtures/crypto_com/app_transactions.csv").unwrap();
+    assert_eq!(history.trades.len(), 2);
+    assert_eq!(history.deposits.len(), 3);
+    assert_eq!(history.withdrawals.len(), 2);
+    assert_eq!(history.app_rows.len(), 7);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_crypto_com_txs(&mut wallet_manager, &mut txs, &history, &price_client)
+        .await
+        .unwrap();
+
+    // The pending deposit, the failed withdrawal and the Earn deposit are not mapped
+    let ids: Vec<&str> = txs.iter().map(|tx| tx.get_id().as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "cryptocom-app-viban_deposit-EUR-1672563600",
+            "cryptocom-app-viban_purchase-EUR-1672563900",
+            "cryptocom-deposit-2220",
+            "cryptocom-deposit-2221",
+            "cryptocom-app-referral_card_cashback-CRO-1672660800",
+            "cryptocom-trade-5755600460443882761",
+            "cryptocom-app-crypto_earn_interest_paid-CRO-1672747200",
+            "cryptocom-trade-5755600460443882762",
+            "cryptocom-app-supercharger_reward_to_app_credited-CRO-1672833600",
+            "cryptocom-withdrawal-2560",
+            "cryptocom-app-crypto_viban_exchange-CRO-1673006400",
+        ]
+    );
+
+    let mut account_wallet = |currency: &str, account: &str, is_fiat: bool| {
+        wallet_manager.create_or_get_account_wallet_id(
+            currency,
+            &Platform::CryptoCom,
+            account,
+            is_fiat,
+        )
+    };
+    let app_eur = account_wallet("EUR", "app", true);
+    let app_cro = account_wallet("CRO", "app", false);
+    let exchange_btc = account_wallet("BTC", "exchange", false);
+    let exchange_eth = account_wallet("ETH", "exchange", false);
+    let exchange_cro = account_wallet("CRO", "exchange", false);
+    assert_ne!(app_cro, exchange_cro);
+    assert_eq!(
+        wallet_manager.wallets.get(&app_cro).unwrap().get().address,
+        None
+    );
+
+    assert!(
+        matches!(&txs[0], Transaction::Deposit { to, amount, .. } if to.id == app_eur && *amount == dec!(100))
+    );
+
+    // Purchase in the App, the price of CRO is given by the native amount in euro
+    match &txs[1] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, app_eur);
+            assert_eq!(from.pre_tx_balance, dec!(100));
+            assert_eq!(to.id, app_cro);
+            assert_eq!(to.price_eur, dec!(0.1));
+            assert_eq!(*sold_amount, dec!(100));
+            assert_eq!(*bought_amount, dec!(1000));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(100)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    // Card cashback
+    match &txs[4] {
+        Transaction::Transfer {
+            from,
+            to,
+            amount,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(from.id, app_cro);
+            assert_eq!(to.id, app_cro);
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(*amount, dec!(5));
+            assert_eq!(*income.get_subtype(), IncomeType::Income);
+            assert_eq!(income.get_value(), dec!(0.4));
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    // Exchange trade, the price of BTC is the traded one
+    match &txs[5] {
+        Transaction::Trade {
+            from,
+            to,
+            bought_amount,
+            trade_type,
+            exchange_pair,
+            ..
+        } => {
+            assert_eq!(from.id, exchange_btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.1));
+            assert_eq!(from.price_eur, dec!(20000));
+            assert_eq!(to.fee, Some(dec!(0.5)));
+            assert_eq!(*bought_amount, dec!(400));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+            assert_eq!(*exchange_pair, Some(("BTC".to_string(), "EUR".to_string())));
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    // Earn interest given in USD, priced with the Coinbase spot price
+    match &txs[6] {
+        Transaction::Transfer {
+            from,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(from.id, app_cro);
+            assert_eq!(from.pre_tx_balance, dec!(1005));
+            assert_eq!(from.price_eur, dec!(0.085));
+            assert_eq!(*income.get_subtype(), IncomeType::Interest);
+            assert_eq!(income.get_value(), dec!(0.17));
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match &txs[7] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, exchange_btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.08));
+            assert_eq!(from.price_eur, dec!(19000));
+            // Fee of 2 CRO paid with the discount, converted to BTC
+            assert_eq!(from.fee, Some(dec!(2) * dec!(0.085) / dec!(19000)));
+            assert_eq!(to.id, exchange_eth);
+            assert_eq!(to.price_eur, dec!(1500));
+            assert_eq!(*sold_amount, dec!(0.07));
+            assert_eq!(*bought_amount, dec!(1));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[8] {
+        Transaction::Transfer {
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+            assert_eq!(income.get_value(), dec!(0.3));
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match &txs[9] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, exchange_eth);
+            assert_eq!(from.pre_tx_balance, dec!(1));
+            assert_eq!(from.fee, Some(dec!(0.01)));
+            assert_eq!(*amount, dec!(0.5));
+            let wallet = wallet_manager.wallets.get(&to.id).unwrap().get();
+            assert_eq!(wallet.platform, Platform::Blockchain);
+            assert_eq!(
+                wallet.address,
+                Some("0x8ba1f109551bd432803012645ac136ddd64dba72".to_string())
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // CRO sold for euro in the App, the Earn deposit kept the CRO in the App wallet
+    match &txs[10] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, app_cro);
+            assert_eq!(from.pre_tx_balance, dec!(1010));
+            assert_eq!(from.price_eur, dec!(0.09));
+            assert_eq!(to.id, app_eur);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(*sold_amount, dec!(200));
+            assert_eq!(*bought_amount, dec!(18));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .filter(|request| request.path.starts_with("/exchange/v1/private/"))
+        .all(|request| request.method == "POST"
+            && request.body.contains("\"api_key\":\"test-key\"")
+            && request.body.contains("\"sig\":")));
+}
+
+#[tokio::test]
+async fn crypto_com_history_merged_with_a_new_fetch() {
+    let server = MockServer::start(crypto_com_routes());
+    let client = CryptoComClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+    );
+    let start = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 1, 10, 0, 0, 0).unwrap();
+
+    // The operations fetched again by the overlapping window are only kept once
+    let mut history = fetch_history_crypto_com(&client, start, end).await.unwrap();
+    let fetched = fetch_history_crypto_com(&client, end - Duration::days(1), end)
+        .await
+        .unwrap();
+    history.merge(fetched);
+    assert_eq!(history.trades.len(), 2);
+    assert_eq!(history.deposits.len(), 3);
+    assert_eq!(history.withdrawals.len(), 2);
+}
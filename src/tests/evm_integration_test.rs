@@ -0,0 +1,254 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        create_evm_txs, evm_amount, fetch_history_evm, CoinGeckoClient, EtherscanClient, EvmChain,
+    },
+    structs::{wallet_manager::WalletManager, Persistable, Platform, Transaction},
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+const ADDRESS: &str = "0x9f8c163cBA728e99993ABe7495F06c0A3c8Ac8b9";
+const WALLET_ADDRESS: &str = "ethereum:0x9f8c163cba728e99993abe7495f06c0a3c8ac8b9";
+const EXCHANGE: &str = "ethereum:0x28c6c06298d514db089934071355e5743bf21d60";
+const FRIEND: &str = "ethereum:0xab5801a7d398351b8be11c439e05c5b3259aec9b";
+const SPAM_TOKEN: &str = "0x1111111111111111111111111111111111111111";
+
+const DEPOSIT: &str = "0xc3b9fb78a452ce2fc90cff1608510235503e3b727683b71c7fefee54198bad63";
+const APPROVE: &str = "0x74e21680eac7385ca408cb01878465fd693b37e58eb0c0c32663a2d8f15d8136";
+const SWAP: &str = "0xda47c2f450a4f9d538d86d600d55149afd39d6672fdd1f30c68ad5be21cadad8";
+const SPAM: &str = "0x4e388ab32b10dc8dbc7e28144f552830adc74787c1e2c0824032078a79f227fb";
+const CLAIM: &str = "0xdd1b3c312cf7d816130354452e9629ce39355b0c534129dd26a08cd9a4502ede";
+const FAILED: &str = "0x5d28a90f4498a81461efbaf6f628a19d9778390bb5c81a393dd936181cc3d826";
+const SEND: &str = "0x27ce1d1bf4270020e1799f12e647f5cbabda2b9eafd7202c43012a539986916b";
+const ARBITRUM_DEPOSIT: &str =
+    "0x14ff769db0befc5ead2ffc85c1833809082ba1f291b20e82687918960b8476c1";
+
+fn etherscan_routes() -> Vec<MockRoute> {
+    let list = |chain_id: &str, action: &str, fixture: &str| {
+        MockRoute::fixture("/v2/api", &format!("evm/{fixture}.json"))
+            .with_query(&format!("chainid={chain_id}"))
+            .with_query(&format!("action={action}"))
+    };
+    vec![
+        list("1", "txlist", "ethereum_txlist"),
+        list("1", "txlistinternal", "ethereum_txlistinternal"),
+        list("1", "tokentx", "ethereum_tokentx"),
+        list("42161", "txlist", "arbitrum_txlist"),
+        list("42161", "txlistinternal", "no_transactions"),
+        list("42161", "tokentx", "no_transactions"),
+        MockRoute::fixture("/coins/ethereum/history", "evm/history_ethereum.json"),
+        MockRoute::fixture("/coins/usd-coin/history", "evm/history_usd_coin.json"),
+    ]
+}
+
+#[test]
+fn evm_amounts() {
+    assert_eq!(evm_amount("1500000000000000000", 18).unwrap(), dec!(1.5));
+    assert_eq!(evm_amount("42", 6).unwrap(), dec!(0.000042));
+    assert_eq!(evm_amount("0", 18).unwrap(), dec!(0));
+    assert_eq!(evm_amount("7", 0).unwrap(), dec!(7));
+    // Too many digits for a Decimal: the smallest ones are dropped
+    assert_eq!(
+        evm_amount("123456789012345678901234567890123456", 18).unwrap(),
+        dec!(123456789012345678.9012345678)
+    );
+    assert!(evm_amount("-1", 18).is_err());
+    assert!(EvmChain::parse("base:8453:ETH").is_ok());
+    assert!(EvmChain::parse("unknown").is_err());
+}
+
+#[tokio::test]
+async fn evm_history_to_transactions() {
+    let server = MockServer::start(etherscan_routes());
+    let client = EtherscanClient::new(format!("{}/v2/api", server.url), "test-key".to_string());
+    let price_client = CoinGeckoClient::new(server.url.clone(), None);
+
+    let ethereum = EvmChain::parse("ethereum").unwrap();
+    let history = fetch_history_evm(&client, &ethereum, ADDRESS)
+        .await
+        .unwrap();
+    assert_eq!(history.transactions.len(), 6);
+    assert_eq!(history.internal_transactions.len(), 1);
+    assert_eq!(history.token_transfers.len(), 3);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_evm_txs(&mut wallet_manager, &mut txs, &history, &price_client)
+        .await
+        .unwrap();
+
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            format!("ethereum-{DEPOSIT}-ETH"),
+            format!("ethereum-{APPROVE}-fee"),
+            format!("ethereum-{SWAP}"),
+            format!("ethereum-{SPAM}-{SPAM_TOKEN}"),
+            format!("ethereum-{CLAIM}-ETH"),
+            format!("ethereum-{FAILED}-fee"),
+            format!("ethereum-{SEND}-fee"),
+            format!("ethereum-{SEND}-USDC"),
+        ]
+    );
+
+    let mut own = |currency: &str| {
+        wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Blockchain,
+            &Some(WALLET_ADDRESS.to_string()),
+            false,
+        )
+    };
+    let eth = own("ETH");
+    let usdc = own("USDC");
+    let spam = own(SPAM_TOKEN);
+    let address_of = |wallet_manager: &WalletManager, id: &String| {
+        wallet_manager
+            .wallets
+            .get(id)
+            .unwrap()
+            .get()
+            .address
+            .clone()
+            .unwrap()
+    };
+
+    // Withdrawal from an exchange, which paid the gas
+    match &txs[0] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(to.id, eth);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.fee, None);
+            assert_eq!(to.price_eur, dec!(2000));
+            assert_eq!(*amount, dec!(1));
+            assert_eq!(address_of(&wallet_manager, &from.id), EXCHANGE);
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Approval: only the gas is paid
+    match &txs[1] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, eth);
+            assert_eq!(from.pre_tx_balance, dec!(1));
+            assert_eq!(from.fee, Some(dec!(0.00092)));
+            assert_eq!(to.fee, None);
+            assert_eq!(*amount, dec!(0));
+        }
+        _ => panic!("Expected a fee transfer"),
+    }
+
+    match &txs[2] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            ..
+        } => {
+            assert_eq!(from.id, eth);
+            assert_eq!(from.pre_tx_balance, dec!(0.99908));
+            assert_eq!(from.fee, Some(dec!(0.003)));
+            assert_eq!(to.id, usdc);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.price_eur, dec!(0.92));
+            assert_eq!(*sold_amount, dec!(0.5));
+            assert_eq!(*bought_amount, dec!(900));
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    // A token pretending to be USDT is kept apart, without price
+    match &txs[3] {
+        Transaction::Transfer { to, amount, .. } => {
+            assert_eq!(to.id, spam);
+            assert_eq!(to.price_eur, dec!(0));
+            assert_eq!(*amount, dec!(1000));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // ETH received through an internal transaction of the called contract
+    match &txs[4] {
+        Transaction::Transfer { to, amount, .. } => {
+            assert_eq!(to.id, eth);
+            assert_eq!(to.pre_tx_balance, dec!(0.49608));
+            assert_eq!(to.fee, Some(dec!(0.002)));
+            assert_eq!(*amount, dec!(0.2));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // The failed transaction moved nothing but its gas
+    match &txs[5] {
+        Transaction::Transfer { from, amount, .. } => {
+            assert_eq!(from.pre_tx_balance, dec!(0.69408));
+            assert_eq!(from.fee, Some(dec!(0.00042)));
+            assert_eq!(*amount, dec!(0));
+        }
+        _ => panic!("Expected a fee transfer"),
+    }
+
+    match (&txs[6], &txs[7]) {
+        (
+            Transaction::Transfer { from: gas, .. },
+            Transaction::Transfer {
+                from, to, amount, ..
+            },
+        ) => {
+            assert_eq!(gas.id, eth);
+            assert_eq!(gas.pre_tx_balance, dec!(0.69366));
+            assert_eq!(gas.fee, Some(dec!(0.001)));
+            assert_eq!(from.id, usdc);
+            assert_eq!(from.pre_tx_balance, dec!(900));
+            assert_eq!(from.fee, None);
+            assert_eq!(*amount, dec!(400));
+            assert_eq!(address_of(&wallet_manager, &to.id), FRIEND);
+        }
+        _ => panic!("Expected transfers"),
+    }
+
+    // The same address on another chain has its own wallets
+    let arbitrum = EvmChain::parse("arbitrum").unwrap();
+    let history = fetch_history_evm(&client, &arbitrum, ADDRESS)
+        .await
+        .unwrap();
+    let mut arbitrum_txs: Vec<Transaction> = Vec::new();
+    create_evm_txs(
+        &mut wallet_manager,
+        &mut arbitrum_txs,
+        &history,
+        &price_client,
+    )
+    .await
+    .unwrap();
+    assert_eq!(arbitrum_txs.len(), 1);
+    assert_eq!(
+        arbitrum_txs[0].get_id(),
+        &format!("arbitrum-{ARBITRUM_DEPOSIT}-ETH")
+    );
+    match &arbitrum_txs[0] {
+        Transaction::Transfer { to, amount, .. } => {
+            assert_ne!(to.id, eth);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(*amount, dec!(0.3));
+            assert_eq!(
+                address_of(&wallet_manager, &to.id),
+                "arbitrum:0x9f8c163cba728e99993abe7495f06c0a3c8ac8b9"
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    assert!(server
+        .requests()
+        .iter()
+        .filter(|request| request.path == "/v2/api")
+        .all(|request| request.query.contains("apikey=test-key")));
+}
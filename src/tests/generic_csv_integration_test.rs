@@ -0,0 +1,298 @@
+use chrono::{TimeZone, Utc};
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{CoinbaseClient, ConnectorRegistry, DynConnector},
+    parsing::{
+        create_generic_csv_txs, read_generic_csv, CsvMapping, CsvRowKind, GenericCsvConnector,
+    },
+    structs::{
+        portfolio_manager::PortfolioManager, wallet_manager::WalletManager, IncomeType,
+        Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+fn price_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture("/v2/prices/BTC-EUR/spot", "generic_csv/spot_btc_eur.json"),
+        MockRoute::fixture("/v2/prices/ETH-EUR/spot", "generic_csv/spot_eth_eur.json"),
+    ]
+}
+
+#[tokio::test]
+async fn generic_csv_bitstamp_toml_mapping() {
+    let server = MockServer::start(price_routes());
+    let price_client = CoinbaseClient::new(server.url.clone(), String::new(), String::new());
+
+    let mut mapping =
+        CsvMapping::from_file("src/tests/fixtures/generic_csv/bitstamp.toml").unwrap();
+    let csv_path = "src/tests/fixtures/generic_csv/bitstamp.csv";
+    let rows = read_generic_csv(csv_path, &mapping).unwrap();
+    // Without the transfer between sub accounts
+    assert_eq!(rows.len(), 6);
+    assert_eq!(rows[5].id, "bitstamp-1001");
+    assert_eq!(rows[5].amount, dec!(1000));
+    assert_eq!(rows[5].fee, None);
+    assert_eq!(rows[4].kind, CsvRowKind::Buy);
+    assert_eq!(rows[4].quote, Some((dec!(500), "EUR".to_string())));
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_generic_csv_txs(
+        &mut wallet_manager,
+        &mut txs,
+        &rows,
+        &mapping,
+        &price_client,
+    )
+    .await
+    .unwrap();
+
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "bitstamp-1001",
+            "bitstamp-1002",
+            "bitstamp-1003",
+            "bitstamp-1004",
+            "bitstamp-1005",
+            "bitstamp-1006",
+        ]
+    );
+
+    let bitstamp = Platform::Other("Bitstamp".to_string());
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &bitstamp, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &bitstamp, &None, false);
+
+    match &txs[1] {
+        Transaction::Trade {
+            from,
+            to,
+            exchange_pair,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(from.fee, Some(dec!(1.25)));
+            assert_eq!(to.id, btc);
+            assert_eq!(to.price_eur, dec!(25000));
+            assert_eq!(*exchange_pair, Some(("BTC".to_string(), "EUR".to_string())));
+            assert_eq!(*sold_amount, dec!(500));
+            assert_eq!(*bought_amount, dec!(0.02));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(500)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[2] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.02));
+            assert_eq!(to.pre_tx_balance, dec!(498.75));
+            assert_eq!(to.fee, Some(dec!(0.65)));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[3] {
+        Transaction::Transfer {
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(income.get_value(), dec!(80));
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match &txs[4] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.pre_tx_balance, dec!(0.01));
+            assert_eq!(from.fee, Some(dec!(0.0002)));
+            assert_eq!(*amount, dec!(0.005));
+            assert_eq!(
+                wallet_manager.wallets.get(&to.id).unwrap().get().platform,
+                Platform::Blockchain
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[5] {
+        Transaction::Withdrawal { from, amount, .. } => {
+            assert_eq!(from.id, eur);
+            assert_eq!(from.pre_tx_balance, dec!(758.1));
+            assert_eq!(from.fee, Some(dec!(0.9)));
+            assert_eq!(*amount, dec!(300));
+        }
+        _ => panic!("Expected a withdrawal"),
+    }
+
+    // A type missing from the mapping is not silently ignored
+    mapping.types.remove("Sub Account Transfer");
+    assert!(read_generic_csv(csv_path, &mapping).is_err());
+}
+
+#[tokio::test]
+async fn generic_csv_bison_json_mapping() {
+    let server = MockServer::start(price_routes());
+    let price_client = CoinbaseClient::new(server.url.clone(), String::new(), String::new());
+
+    let mapping = CsvMapping::from_file("src/tests/fixtures/generic_csv/bison.json").unwrap();
+    let rows = read_generic_csv("src/tests/fixtures/generic_csv/bison.csv", &mapping).unwrap();
+    assert_eq!(rows.len(), 6);
+    // The currencies are written after the amounts, the dates are in the time of Berlin
+    assert_eq!(rows[0].amount, dec!(2000));
+    assert_eq!(rows[0].currency, "EUR");
+    assert_eq!(
+        rows[0].time,
+        Utc.with_ymd_and_hms(2023, 3, 1, 9, 0, 0).unwrap()
+    );
+    assert_eq!(
+        rows[3].time,
+        Utc.with_ymd_and_hms(2023, 4, 3, 12, 0, 0).unwrap()
+    );
+    assert_eq!(rows[1].fee, None);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_generic_csv_txs(
+        &mut wallet_manager,
+        &mut txs,
+        &rows,
+        &mapping,
+        &price_client,
+    )
+    .await
+    .unwrap();
+    assert_eq!(txs.len(), 6);
+
+    let bison = Platform::Other("Bison".to_string());
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &bison, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &bison, &None, false);
+
+    match &txs[0] {
+        Transaction::Deposit { to, amount, .. } => {
+            assert_eq!(to.id, eur);
+            assert_eq!(*amount, dec!(2000));
+        }
+        _ => panic!("Expected a deposit"),
+    }
+
+    match &txs[2] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(to.price_eur, dec!(1600));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(800)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[3] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.05));
+            assert_eq!(from.price_eur, dec!(28000));
+            assert_eq!(to.pre_tx_balance, dec!(200));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[4] {
+        Transaction::Transfer { from, amount, .. } => {
+            assert_eq!(from.id, btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.03));
+            assert_eq!(from.price_eur, dec!(24000));
+            assert_eq!(*amount, dec!(0.01));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[5] {
+        Transaction::Transfer { from, to, .. } => {
+            assert_eq!(
+                wallet_manager.wallets.get(&from.id).unwrap().get().platform,
+                Platform::Blockchain
+            );
+            assert_eq!(to.pre_tx_balance, dec!(0.5));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+}
+
+#[tokio::test]
+async fn generic_csv_wallets_priced_by_the_fallback() {
+    let server = MockServer::start(price_routes());
+    let price_client = || CoinbaseClient::new(server.url.clone(), String::new(), String::new());
+    let connector = GenericCsvConnector {
+        imports: Some(
+            "src/tests/fixtures/generic_csv/bitstamp.toml=src/tests/fixtures/generic_csv/bitstamp_staking.csv"
+                .to_string(),
+        ),
+        price_client: price_client(),
+    };
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let txs = connector
+        .fetch_transactions(&mut wallet_manager)
+        .await
+        .unwrap();
+    assert_eq!(txs.len(), 4);
+
+    // Bitstamp has no connector, the staked ETH held at the sale is priced by the fallback of the registry
+    let mut connectors = ConnectorRegistry::default();
+    connectors.register(connector);
+    connectors.set_fallback(price_client());
+    let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+    portfolio_manager
+        .calculate_portfolio_history(&txs, &wallet_manager.wallets, &connectors)
+        .await
+        .unwrap();
+
+    let eth = wallet_manager.create_or_get_wallet_id(
+        "ETH",
+        &Platform::Other("Bitstamp".to_string()),
+        &None,
+        false,
+    );
+    let portfolio = portfolio_manager
+        .portfolio_history
+        .get("bitstamp-2004")
+        .unwrap();
+    let staked = portfolio.wallet_snaps.get(&eth).unwrap();
+    assert_eq!(staked.price_eur, Some(dec!(1600)));
+}
@@ -0,0 +1,314 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{CoinbaseClient, ConnectorRegistry, DynConnector},
+    parsing::{create_koinly_txs, read_koinly_csv, KoinlyConnector},
+    structs::{
+        portfolio_manager::PortfolioManager, wallet_manager::WalletManager, IncomeType,
+        Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+const KRAKEN: &str = "0A1B2C3D";
+const LEDGER: &str = "4E5F6A7B";
+const METAMASK: &str = "8C9D0E1F";
+
+#[tokio::test]
+async fn koinly_csv_to_transactions() {
+    let server = MockServer::start(vec![MockRoute::fixture(
+        "/v2/prices/UNI-EUR/spot",
+        "koinly/spot_uni_eur.json",
+    )]);
+    let price_client = CoinbaseClient::new(server.url.clone(), String::new(), String::new());
+
+    let rows = read_koinly_csv("src/tests/fixtures/koinly/transactions.csv").unwrap();
+    assert_eq!(rows.len(), 14);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_koinly_txs(&mut wallet_manager, &mut txs, &rows, &price_client)
+        .await
+        .unwrap();
+
+    // Sorted from the oldest, without the deleted row, the fee paid in a third currency being apart
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "koinly-7F3A0001",
+            "koinly-7F3A0002",
+            "koinly-7F3A0003",
+            "koinly-7F3A0004",
+            "koinly-7F3A0005",
+            "koinly-7F3A0006",
+            "koinly-7F3A0007",
+            "koinly-7F3A0008",
+            "koinly-7F3A0010",
+            "koinly-7F3A0011-fee",
+            "koinly-7F3A0011",
+            "koinly-7F3A0012",
+            "koinly-7F3A0013",
+            "koinly-7F3A0014",
+        ]
+    );
+
+    let mut wallet = |currency: &str, platform: Platform, koinly_id: &str| {
+        wallet_manager.create_or_get_wallet_id(
+            currency,
+            &platform,
+            &Some(koinly_id.to_string()),
+            currency == "EUR",
+        )
+    };
+    let kraken_eur = wallet("EUR", Platform::Kraken, KRAKEN);
+    let kraken_btc = wallet("BTC", Platform::Kraken, KRAKEN);
+    let kraken_dot = wallet("DOT", Platform::Kraken, KRAKEN);
+    let ledger_btc = wallet("BTC", Platform::Other("Ledger Nano S".to_string()), LEDGER);
+    let metamask_uni = wallet("UNI", Platform::Other("MetaMask".to_string()), METAMASK);
+    let wallet_of = |wallet_manager: &WalletManager, id: &String| {
+        let wallet = wallet_manager.wallets.get(id).unwrap().get();
+        (wallet.platform.clone(), wallet.address.clone())
+    };
+
+    match &txs[0] {
+        Transaction::Deposit { to, amount, .. } => {
+            assert_eq!(to.id, kraken_eur);
+            assert_eq!(*amount, dec!(1000));
+        }
+        _ => panic!("Expected a deposit"),
+    }
+
+    match &txs[1] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, kraken_eur);
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(from.fee, Some(dec!(2)));
+            assert_eq!(to.id, kraken_btc);
+            assert_eq!(to.price_eur, dec!(25000));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(500)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[2] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.pre_tx_balance, dec!(0.02));
+            assert_eq!(from.fee, None);
+            assert_eq!(to.id, kraken_dot);
+            assert_eq!(to.price_eur, dec!(5));
+            assert_eq!(to.fee, Some(dec!(0.1)));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    // The fee of the transfer is what didn't arrive
+    match &txs[3] {
+        Transaction::Transfer {
+            from,
+            to,
+            amount,
+            income,
+            ..
+        } => {
+            assert_eq!(from.id, kraken_btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.01));
+            assert_eq!(from.fee, Some(dec!(0.0001)));
+            assert_eq!(to.id, ledger_btc);
+            assert_eq!(to.price_eur, dec!(24500));
+            assert_eq!(*amount, dec!(0.0049));
+            assert!(income.is_none());
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Valued in USD by Koinly, so priced with Coinbase
+    match &txs[4] {
+        Transaction::Transfer {
+            to,
+            amount,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(to.id, metamask_uni);
+            assert_eq!(to.price_eur, dec!(5.5));
+            assert_eq!(*amount, dec!(10));
+            assert_eq!(income.get_value(), dec!(55));
+            assert_eq!(*income.get_subtype(), IncomeType::Airdrop);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match &txs[5] {
+        Transaction::Transfer {
+            from, to, income, ..
+        } => {
+            assert_eq!(
+                wallet_of(&wallet_manager, &from.id),
+                (Platform::Blockchain, Some("bc1qsender".to_string()))
+            );
+            assert_eq!(to.id, ledger_btc);
+            assert_eq!(to.pre_tx_balance, dec!(0.0049));
+            assert!(income.is_none());
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[6] {
+        Transaction::Transfer { from, to, .. } => {
+            assert_eq!(from.id, metamask_uni);
+            assert_eq!(from.pre_tx_balance, dec!(10));
+            assert_eq!(
+                wallet_of(&wallet_manager, &to.id).0,
+                Platform::Other("Lost".to_string())
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[7] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, kraken_dot);
+            assert_eq!(to.id, kraken_dot);
+            assert_eq!(from.pre_tx_balance, dec!(49.9));
+            assert_eq!(from.fee, Some(dec!(1)));
+            assert_eq!(*amount, dec!(0));
+        }
+        _ => panic!("Expected a fee"),
+    }
+
+    match &txs[8] {
+        Transaction::Transfer {
+            to,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(to.id, kraken_dot);
+            assert_eq!(to.pre_tx_balance, dec!(48.9));
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match (&txs[9], &txs[10]) {
+        (Transaction::Transfer { from: fee, .. }, Transaction::Trade { from, to, .. }) => {
+            assert_eq!(fee.id, kraken_eur);
+            assert_eq!(fee.pre_tx_balance, dec!(498));
+            assert_eq!(fee.fee, Some(dec!(0.5)));
+            assert_eq!(from.fee, None);
+            assert_eq!(to.fee, None);
+        }
+        _ => panic!("Expected a fee and a trade"),
+    }
+
+    match &txs[11] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, kraken_dot);
+            assert_eq!(from.pre_tx_balance, dec!(54.4));
+            assert_eq!(to.pre_tx_balance, dec!(497.5));
+            assert_eq!(to.fee, Some(dec!(0.5)));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[12] {
+        Transaction::Withdrawal { from, amount, .. } => {
+            assert_eq!(from.pre_tx_balance, dec!(607));
+            assert_eq!(from.fee, Some(dec!(1)));
+            assert_eq!(*amount, dec!(600));
+        }
+        _ => panic!("Expected a withdrawal"),
+    }
+
+    match &txs[13] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, ledger_btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.0059));
+            assert_eq!(from.fee, Some(dec!(0.0001)));
+            assert_eq!(*amount, dec!(0.002));
+            assert_eq!(
+                wallet_of(&wallet_manager, &to.id),
+                (Platform::Blockchain, Some("bc1qfriend".to_string()))
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+}
+
+#[tokio::test]
+async fn koinly_wallets_priced_by_the_fallback() {
+    let server = MockServer::start(vec![MockRoute::fixture(
+        "/v2/prices/BTC-EUR/spot",
+        "koinly/spot_btc_eur.json",
+    )]);
+    let price_client = || CoinbaseClient::new(server.url.clone(), String::new(), String::new());
+    let connector = KoinlyConnector {
+        csv_path: Some("src/tests/fixtures/koinly/other_wallets.csv".to_string()),
+        price_client: price_client(),
+    };
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let txs = connector
+        .fetch_transactions(&mut wallet_manager)
+        .await
+        .unwrap();
+    assert_eq!(txs.len(), 4);
+
+    // The wallets of the export have no connector of their platform, the ones still held at the sale are priced by
+    // the fallback of the registry
+    let mut connectors = ConnectorRegistry::default();
+    connectors.register(connector);
+    connectors.set_fallback(price_client());
+    let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+    portfolio_manager
+        .calculate_portfolio_history(&txs, &wallet_manager.wallets, &connectors)
+        .await
+        .unwrap();
+
+    let ledger_btc = wallet_manager.create_or_get_wallet_id(
+        "BTC",
+        &Platform::Other("Ledger Nano S".to_string()),
+        &Some(LEDGER.to_string()),
+        false,
+    );
+    let lost_btc = wallet_manager.create_or_get_wallet_id(
+        "BTC",
+        &Platform::Other("Lost".to_string()),
+        &None,
+        false,
+    );
+    let portfolio = portfolio_manager
+        .portfolio_history
+        .get("koinly-8B4C0004")
+        .unwrap();
+    let ledger = portfolio.wallet_snaps.get(&ledger_btc).unwrap();
+    assert_eq!(ledger.price_eur, Some(dec!(24000)));
+    let lost = portfolio.wallet_snaps.get(&lost_btc).unwrap();
+    assert_eq!(lost.price_eur, Some(dec!(24000)));
+}
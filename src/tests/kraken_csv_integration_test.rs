@@ -0,0 +1,107 @@
+use hashbrown::HashMap;
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{create_kraken_txs, EntryType},
+    parsing::read_kraken_csv,
+    structs::{wallet_manager::WalletManager, Persistable, TradeType, Transaction},
+    utils::f64_to_datetime_utc,
+};
+
+const BUY: &str = "TJKLXF-PGMUI-4NTLXU";
+const SELL: &str = "TBZIP2-F6QOU-TMB6FY";
+const WITHDRAWAL: &str = "FTPLOMm-Ts8nbGkIZhCojd5y4JQybq";
+
+#[tokio::test]
+async fn kraken_csv_to_history() {
+    let (ledger, trades, deposits, withdrawals) = read_kraken_csv(
+        "src/tests/fixtures/kraken/ledgers.csv",
+        "src/tests/fixtures/kraken/trades.csv",
+    )
+    .unwrap();
+
+    assert_eq!(ledger.len(), 7);
+    assert_eq!(ledger[0].asset, "ZEUR");
+    assert!(matches!(ledger[0].r#type, EntryType::Deposit));
+    assert_eq!(ledger[1].refid, BUY);
+    assert_eq!(ledger[2].refid, BUY);
+    assert_eq!(ledger[1].time, 1675332000.1234);
+    // Not known by the API structures, so ignored by the mapping
+    assert!(matches!(ledger[3].r#type, EntryType::None));
+
+    assert_eq!(trades.len(), 2);
+    let buy = trades.get(BUY).unwrap();
+    assert_eq!(buy.r#type, "buy");
+    assert_eq!(buy.price, dec!(25000));
+    assert_eq!(buy.fee, dec!(1.3));
+    assert_eq!(buy.trade_id, 56178432);
+    assert!(trades.get(SELL).unwrap().maker);
+
+    // Rebuilt from the ledger entries, as the exports don't have them
+    assert_eq!(deposits.len(), 1);
+    assert_eq!(withdrawals.get(WITHDRAWAL).unwrap().amount, dec!(0.005));
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_kraken_txs(
+        &mut wallet_manager,
+        &mut txs,
+        ledger,
+        trades,
+        deposits,
+        withdrawals,
+        HashMap::new(),
+    )
+    .await
+    .unwrap();
+
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(ids, vec![BUY, SELL]);
+    assert_eq!(
+        txs[0].get_tx_base().timestamp,
+        f64_to_datetime_utc(1675332000.1234).unwrap()
+    );
+
+    match &txs[0] {
+        Transaction::Trade {
+            from,
+            to,
+            exchange_pair,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(from.fee, Some(dec!(1.3)));
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(
+                *exchange_pair,
+                Some(("XXBT".to_string(), "ZEUR".to_string()))
+            );
+            assert_eq!(*sold_amount, dec!(500));
+            assert_eq!(*bought_amount, dec!(0.02));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(500)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[1] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.pre_tx_balance, dec!(0.02));
+            assert_eq!(to.pre_tx_balance, dec!(498.7));
+            assert_eq!(*trade_type, TradeType::CryptoToFiat);
+        }
+        _ => panic!("Expected a trade"),
+    }
+}
@@ -0,0 +1,249 @@
+use chrono::{Duration, TimeZone, Utc};
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{
+        create_kucoin_txs, fetch_history_kucoin, ConnectorRegistry, KuCoinClient, KuCoinConnector,
+    },
+    structs::{
+        portfolio_manager::PortfolioManager, wallet_manager::WalletManager, Persistable, Platform,
+        TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+fn kucoin_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture("/api/v1/fills", "kucoin/fills_page2.json")
+            .with_query("tradeType=TRADE")
+            .with_query("currentPage=2"),
+        MockRoute::fixture("/api/v1/fills", "kucoin/fills_page1.json")
+            .with_query("tradeType=TRADE"),
+        MockRoute::fixture("/api/v1/fills", "kucoin/fills_margin.json")
+            .with_query("tradeType=MARGIN_TRADE"),
+        MockRoute::fixture("/api/v1/deposits", "kucoin/deposits.json")
+            .with_query("startAt=1677628800000"),
+        MockRoute::fixture("/api/v1/deposits", "kucoin/empty_page.json"),
+        MockRoute::fixture("/api/v1/withdrawals", "kucoin/withdrawals.json")
+            .with_query("startAt=1677715200000"),
+        MockRoute::fixture("/api/v1/withdrawals", "kucoin/empty_page.json"),
+        MockRoute::fixture("/api/v1/accounts/ledgers", "kucoin/ledgers_day1.json")
+            .with_query("startAt=1677628800000"),
+        MockRoute::fixture("/api/v1/accounts/ledgers", "kucoin/ledgers_day2.json")
+            .with_query("startAt=1677715200000"),
+        MockRoute::fixture("/api/v1/market/candles", "kucoin/candles_btc_eur.json")
+            .with_query("symbol=BTC-EUR"),
+        MockRoute::fixture("/api/v1/market/candles", "kucoin/candles_eth_eur.json")
+            .with_query("symbol=ETH-EUR"),
+        MockRoute::fixture("/api/v1/market/candles", "kucoin/candles_usdt_eur.json")
+            .with_query("symbol=USDT-EUR"),
+        MockRoute::fixture("/api/v1/market/candles", "kucoin/candles_kcs_usdt.json")
+            .with_query("symbol=KCS-USDT"),
+    ]
+}
+
+#[tokio::test]
+async fn kucoin_history_to_transactions() {
+    let server = MockServer::start(kucoin_routes());
+    let client = KuCoinClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+        "test-passphrase".to_string(),
+    );
+    let start = Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 3, 3, 0, 0, 0).unwrap();
+
+    let history = fetch_history_kucoin(&client, start, end).await.unwrap();
+    assert_eq!(history.fills.len(), 3);
+    assert_eq!(history.deposits.len(), 1);
+    assert_eq!(history.withdrawals.len(), 1);
+    assert_eq!(history.ledgers.len(), 7); // One query per day for the ledgers
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_kucoin_txs(&mut wallet_manager, &mut txs, &history, &client)
+        .await
+        .unwrap();
+
+    let ids: Vec<&str> = txs.iter().map(|tx| tx.get_id().as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "kucoin-deposit-BTC-1677657600000",
+            "kucoin-transfer-l1",
+            "kucoin-fill-t1",
+            "kucoin-fill-t2",
+            "kucoin-transfer-l3",
+            "kucoin-fill-m1",
+            "kucoin-withdrawal-w1",
+        ]
+    );
+
+    let mut account_wallet = |currency: &str, account: &str| {
+        wallet_manager.create_or_get_account_wallet_id(currency, &Platform::KuCoin, account, false)
+    };
+    let btc_main = account_wallet("BTC", "main");
+    let btc_trade = account_wallet("BTC", "trade");
+    let btc_margin = account_wallet("BTC", "margin");
+    let usdt_trade = account_wallet("USDT", "trade");
+    let usdt_margin = account_wallet("USDT", "margin");
+    let kucoin_wallets = wallet_manager
+        .wallets
+        .values()
+        .filter(|wallet| wallet.get().platform == Platform::KuCoin)
+        .count();
+    assert_eq!(kucoin_wallets, 7); // BTC, USDT and ETH of the accounts used, KCS of the trade account
+                                   // The account is not the address of the wallet
+    let wallet = wallet_manager.wallets.get(&btc_trade).unwrap().get();
+    assert_eq!(wallet.address, None);
+    assert_eq!(wallet.info, Some("trade".to_string()));
+
+    // Internal transfer between the main and the trade accounts
+    match &txs[1] {
+        Transaction::Transfer {
+            from,
+            to,
+            amount,
+            income,
+            ..
+        } => {
+            assert_eq!(from.id, btc_main);
+            assert_eq!(from.pre_tx_balance, dec!(0.5));
+            assert_eq!(to.id, btc_trade);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(*amount, dec!(0.4));
+            assert!(income.is_none());
+        }
+        _ => panic!("Expected a transfer"),
+    }
+    assert!(!txs[1].is_taxable());
+
+    match &txs[2] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, btc_trade);
+            assert_eq!(from.pre_tx_balance, dec!(0.4));
+            assert_eq!(from.price_eur, dec!(21000));
+            assert_eq!(to.id, usdt_trade);
+            assert_eq!(to.fee, Some(dec!(2.2)));
+            assert_eq!(*sold_amount, dec!(0.1));
+            assert_eq!(*bought_amount, dec!(2200));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    // The KCS fee, whose price goes through USDT, is converted to a fee of the BTC sold
+    match &txs[3] {
+        Transaction::Trade { from, .. } => {
+            assert_eq!(from.id, btc_trade);
+            assert_eq!(from.price_eur, dec!(21000));
+            assert_eq!(from.fee, Some(dec!(0.001) * dec!(6.11) / dec!(21000)));
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[4] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, usdt_trade);
+            assert_eq!(from.pre_tx_balance, dec!(2197.8));
+            assert_eq!(to.id, usdt_margin);
+            assert_eq!(*amount, dec!(1000));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[5] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            ..
+        } => {
+            assert_eq!(from.id, usdt_margin);
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(from.fee, Some(dec!(0.23)));
+            assert_eq!(to.id, btc_margin);
+            assert_eq!(*sold_amount, dec!(230));
+            assert_eq!(*bought_amount, dec!(0.01));
+        }
+        _ => panic!("Expected a margin trade"),
+    }
+
+    match &txs[6] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, btc_main);
+            assert_eq!(from.pre_tx_balance, dec!(0.1));
+            assert_eq!(from.fee, Some(dec!(0.0005)));
+            assert_eq!(*amount, dec!(0.05));
+            let wallet = wallet_manager.wallets.get(&to.id).unwrap().get();
+            assert_eq!(wallet.platform, Platform::Blockchain);
+            assert_eq!(
+                wallet.address,
+                Some("bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string())
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    let mut connectors = ConnectorRegistry::default();
+    connectors.register(KuCoinConnector {
+        price_client: client,
+    });
+    let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+    portfolio_manager
+        .calculate_portfolio_history(&txs, &wallet_manager.wallets, &connectors)
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    let ledgers = requests
+        .iter()
+        .find(|request| request.path == "/api/v1/accounts/ledgers")
+        .unwrap();
+    assert_eq!(ledgers.header("KC-API-KEY"), Some(&"test-key".to_string()));
+    assert_eq!(ledgers.header("KC-API-KEY-VERSION"), Some(&"2".to_string()));
+    assert!(ledgers.header("KC-API-SIGN").is_some());
+    assert!(ledgers.header("KC-API-TIMESTAMP").is_some());
+    // The passphrase is not sent in clear
+    assert_ne!(
+        ledgers.header("KC-API-PASSPHRASE"),
+        Some(&"test-passphrase".to_string())
+    );
+}
+
+#[tokio::test]
+async fn kucoin_history_merged_with_a_new_fetch() {
+    let server = MockServer::start(kucoin_routes());
+    let client = KuCoinClient::new(
+        server.url.clone(),
+        "test-key".to_string(),
+        "test-secret".to_string(),
+        "test-passphrase".to_string(),
+    );
+    let start = Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap();
+    let end = Utc.with_ymd_and_hms(2023, 3, 3, 0, 0, 0).unwrap();
+
+    // The operations fetched again by the overlapping window are only kept once
+    let mut history = fetch_history_kucoin(&client, start, end).await.unwrap();
+    let fetched = fetch_history_kucoin(&client, end - Duration::days(1), end)
+        .await
+        .unwrap();
+    history.merge(fetched);
+    assert_eq!(history.fills.len(), 3);
+    assert_eq!(history.deposits.len(), 1);
+    assert_eq!(history.withdrawals.len(), 1);
+    assert_eq!(history.ledgers.len(), 7);
+}
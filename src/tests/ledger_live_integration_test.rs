@@ -0,0 +1,162 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::CoinbaseClient,
+    parsing::{create_ledger_live_txs, read_ledger_live_csv},
+    structs::{wallet_manager::WalletManager, IncomeType, Persistable, Platform, Transaction},
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+const BITCOIN_1: &str = "xpub6CUGRUonZSQ4TWtTMmzXdrXDtypWKiKrhko4egpiMZbpiaQL2jkwSB1icqYh2cfDfVxdx4df189oLKnC5fSwqPfgyP3hooxujYzAu3fDVmz";
+const BITCOIN_2: &str = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+const ETHEREUM_1: &str = "0x71C7656EC7ab88b098defB751B7401B5f6d8976F";
+
+#[tokio::test]
+async fn ledger_live_csv_to_transactions() {
+    let server = MockServer::start(vec![
+        MockRoute::fixture("/v2/prices/ETH-EUR/spot", "ledger_live/spot_eth_eur.json"),
+        MockRoute::fixture("/v2/prices/USDC-EUR/spot", "ledger_live/spot_usdc_eur.json"),
+        MockRoute::fixture("/v2/prices/ATOM-EUR/spot", "ledger_live/spot_atom_eur.json"),
+    ]);
+    let price_client = CoinbaseClient::new(server.url.clone(), String::new(), String::new());
+
+    let rows = read_ledger_live_csv("src/tests/fixtures/ledger_live/operations.csv").unwrap();
+    assert_eq!(rows.len(), 10);
+    assert_eq!(rows[3].fees, None);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_ledger_live_txs(&mut wallet_manager, &mut txs, &rows, &price_client)
+        .await
+        .unwrap();
+
+    // From the oldest, the move between the two Bitcoin accounts being a single transfer
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "ledger-live-5e0d3b91fa",
+            "ledger-live-a41c9d07e3",
+            "ledger-live-b6f1e2a8c4",
+            "ledger-live-0xd04a9c33",
+            "ledger-live-0x1c88e0b6",
+            "ledger-live-0x4e2a17d8-fee",
+            "ledger-live-0x4e2a17d8",
+            "ledger-live-0x9f3b05c1-fee",
+            "ledger-live-C7F1A3D2E4",
+        ]
+    );
+
+    let mut wallet = |currency: &str, xpub: &str| {
+        wallet_manager.create_or_get_wallet_id(
+            currency,
+            &Platform::Blockchain,
+            &Some(xpub.to_string()),
+            false,
+        )
+    };
+    let bitcoin_1 = wallet("BTC", BITCOIN_1);
+    let bitcoin_2 = wallet("BTC", BITCOIN_2);
+    let ethereum_1 = wallet("ETH", ETHEREUM_1);
+    let usdc = wallet("USDC", ETHEREUM_1);
+    let external_btc =
+        wallet_manager.create_or_get_wallet_id("BTC", &Platform::Blockchain, &None, false);
+
+    match &txs[0] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, external_btc);
+            assert_eq!(to.id, bitcoin_1);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.fee, None); // Paid by the sender
+            assert_eq!(to.price_eur, dec!(20000));
+            assert_eq!(*amount, dec!(0.05));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[1] {
+        Transaction::Transfer {
+            from,
+            to,
+            amount,
+            income,
+            ..
+        } => {
+            assert_eq!(from.id, bitcoin_1);
+            assert_eq!(from.pre_tx_balance, dec!(0.05));
+            assert_eq!(from.fee, Some(dec!(0.0001)));
+            assert_eq!(to.id, bitcoin_2);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(*amount, dec!(0.02));
+            assert!(income.is_none());
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[2] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, bitcoin_1);
+            assert_eq!(from.pre_tx_balance, dec!(0.0299));
+            assert_eq!(from.fee, Some(dec!(0.0002)));
+            assert_eq!(to.id, external_btc);
+            assert_eq!(*amount, dec!(0.01));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // The countervalue is in dollars, the price comes from Coinbase
+    match &txs[3] {
+        Transaction::Transfer { to, .. } => {
+            assert_eq!(to.id, ethereum_1);
+            assert_eq!(to.price_eur, dec!(1600));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // The fees of the token transfer are paid by the Ethereum account
+    match (&txs[5], &txs[6]) {
+        (
+            Transaction::Transfer {
+                from: fee_from,
+                amount: fee_amount,
+                ..
+            },
+            Transaction::Transfer { from, amount, .. },
+        ) => {
+            assert_eq!(fee_from.id, ethereum_1);
+            assert_eq!(fee_from.pre_tx_balance, dec!(1));
+            assert_eq!(fee_from.fee, Some(dec!(0.002)));
+            assert_eq!(*fee_amount, dec!(0));
+            assert_eq!(from.id, usdc);
+            assert_eq!(from.pre_tx_balance, dec!(150));
+            assert_eq!(from.fee, None);
+            assert_eq!(*amount, dec!(100));
+        }
+        _ => panic!("Expected transfers"),
+    }
+
+    // A failed operation only costs its fees
+    match &txs[7] {
+        Transaction::Transfer { from, amount, .. } => {
+            assert_eq!(from.pre_tx_balance, dec!(0.998));
+            assert_eq!(from.fee, Some(dec!(0.003)));
+            assert_eq!(*amount, dec!(0));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[8] {
+        Transaction::Transfer {
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(income.get_value(), dec!(5.5));
+            assert_eq!(*income.get_subtype(), IncomeType::Staking);
+        }
+        _ => panic!("Expected an income"),
+    }
+}
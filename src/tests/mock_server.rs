@@ -0,0 +1,170 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::utils::read_file;
+
+/* Local HTTP stand-in used to test the API connectors against recorded responses.
+
+Each route matches a path and optionally fragments of the query string (for instance "symbol=BTCEUR") or of the
+request body (for the APIs using POST).
+The first matching route is used, so more specific routes must be declared first.
+Every request received is recorded so the tests can check the headers (signature, api key...).
+*/
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&String> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockRoute {
+    pub path: String,
+    pub query: Vec<String>,
+    pub request_body: Vec<String>,
+    pub status: u16,
+    pub body: String,
+}
+
+impl MockRoute {
+    pub fn new(path: &str, body: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            query: Vec::new(),
+            request_body: Vec::new(),
+            status: 200,
+            body: body.to_string(),
+        }
+    }
+
+    /* Route answering with a recorded response from src/tests/fixtures */
+    pub fn fixture(path: &str, fixture: &str) -> Self {
+        let body = read_file(&format!("src/tests/fixtures/{fixture}"))
+            .unwrap_or_else(|_| panic!("Missing fixture {fixture}"));
+        Self::new(path, &body)
+    }
+
+    pub fn with_query(mut self, fragment: &str) -> Self {
+        self.query.push(fragment.to_string());
+        self
+    }
+
+    pub fn with_body(mut self, fragment: &str) -> Self {
+        self.request_body.push(fragment.to_string());
+        self
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    fn matches(&self, path: &str, query: &str, body: &str) -> bool {
+        self.path == path
+            && self
+                .query
+                .iter()
+                .all(|fragment| query.split('&').any(|param| param == fragment))
+            && self
+                .request_body
+                .iter()
+                .all(|fragment| body.contains(fragment.as_str()))
+    }
+}
+
+impl MockServer {
+    pub fn start(routes: Vec<MockRoute>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                handle_connection(stream, &routes, &recorded);
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    routes: &[MockRoute],
+    requests: &Arc<Mutex<Vec<MockRequest>>>,
+) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default().to_string();
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (target.clone(), String::new()),
+    };
+
+    let mut headers = Vec::new();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() || line.trim().is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.trim().split_once(':') {
+            if key.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let mut body = vec![0; content_length];
+    let _ = reader.read_exact(&mut body);
+
+    let body = String::from_utf8_lossy(&body).to_string();
+    let route = routes
+        .iter()
+        .find(|route| route.matches(&path, &query, &body));
+    requests.lock().unwrap().push(MockRequest {
+        method,
+        path,
+        query,
+        headers,
+        body,
+    });
+
+    let (status, body) = match route {
+        Some(route) => (route.status, route.body.as_str()),
+        None => (404, r#"{"error": "No route in the mock server"}"#),
+    };
+    let response = format!(
+        "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
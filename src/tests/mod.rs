@@ -1,2 +1,46 @@
 #[cfg(test)]
 pub mod simple_integration_test;
+#[cfg(test)]
+pub mod mock_server;
+#[cfg(test)]
+pub mod binance_integration_test;
+#[cfg(test)]
+pub mod coinbase_integration_test;
+#[cfg(test)]
+pub mod bitfinex_integration_test;
+#[cfg(test)]
+pub mod kucoin_integration_test;
+#[cfg(test)]
+pub mod bitpanda_integration_test;
+#[cfg(test)]
+pub mod crypto_com_integration_test;
+#[cfg(test)]
+pub mod solana_integration_test;
+#[cfg(test)]
+pub mod cardano_integration_test;
+#[cfg(test)]
+pub mod algorand_integration_test;
+#[cfg(test)]
+pub mod bitcoin_integration_test;
+#[cfg(test)]
+pub mod evm_integration_test;
+#[cfg(test)]
+pub mod koinly_integration_test;
+#[cfg(test)]
+pub mod kraken_csv_integration_test;
+#[cfg(test)]
+pub mod nexo_integration_test;
+#[cfg(test)]
+pub mod bitfinex_csv_integration_test;
+#[cfg(test)]
+pub mod binance_csv_integration_test;
+#[cfg(test)]
+pub mod ledger_live_integration_test;
+#[cfg(test)]
+pub mod generic_csv_integration_test;
+#[cfg(test)]
+pub mod standard_format_integration_test;
+#[cfg(test)]
+pub mod connector_integration_test;
+#[cfg(test)]
+pub mod coingecko_integration_test;
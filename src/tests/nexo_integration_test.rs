@@ -0,0 +1,184 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::CoinbaseClient,
+    parsing::{create_nexo_txs, read_nexo_csv},
+    structs::{
+        wallet_manager::WalletManager, IncomeType, Persistable, Platform, TradeType, Transaction,
+    },
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+#[tokio::test]
+async fn nexo_csv_to_transactions() {
+    let server = MockServer::start(vec![
+        MockRoute::fixture("/v2/prices/BTC-EUR/spot", "nexo/spot_btc_eur.json"),
+        MockRoute::fixture("/v2/prices/NEXO-EUR/spot", "nexo/spot_nexo_eur.json"),
+        MockRoute::fixture("/v2/prices/ETH-EUR/spot", "nexo/spot_eth_eur.json"),
+    ]);
+    let price_client = CoinbaseClient::new(server.url.clone(), String::new(), String::new());
+
+    let rows = read_nexo_csv("src/tests/fixtures/nexo/transactions.csv").unwrap();
+    assert_eq!(rows.len(), 11);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_nexo_txs(&mut wallet_manager, &mut txs, &rows, &price_client)
+        .await
+        .unwrap();
+
+    // From the oldest, without the rejected withdrawal
+    let ids: Vec<String> = txs.iter().map(|tx| tx.get_id().clone()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "nexo-NXTDEPOSITEUR1",
+            "nexo-NXTEXCHANGEBTC",
+            "nexo-NXTLOCKBTC0001",
+            "nexo-NXTINTEREST001",
+            "nexo-NXTFIXEDINT001",
+            "nexo-NXTCASHBACK001",
+            "nexo-NXTUNLOCKBTC01",
+            "nexo-NXTWITHDRAWBTC",
+            "nexo-NXTEXCHANGEETH",
+            "nexo-NXTWITHDRAWEUR",
+        ]
+    );
+
+    let nexo = Platform::Other("Nexo".to_string());
+    let mut wallet = |currency: &str, address: Option<String>| {
+        wallet_manager.create_or_get_wallet_id(currency, &nexo, &address, currency == "EUR")
+    };
+    let savings_eur = wallet("EUR", None);
+    let savings_btc = wallet("BTC", None);
+    let term_btc = wallet("BTC", Some("term".to_string()));
+
+    match &txs[0] {
+        Transaction::Deposit { to, amount, .. } => {
+            assert_eq!(to.id, savings_eur);
+            assert_eq!(*amount, dec!(1000));
+        }
+        _ => panic!("Expected a deposit"),
+    }
+
+    // EURX is the euro
+    match &txs[1] {
+        Transaction::Trade {
+            from,
+            to,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, savings_eur);
+            assert_eq!(from.pre_tx_balance, dec!(1000));
+            assert_eq!(to.id, savings_btc);
+            assert_eq!(to.price_eur, dec!(25000));
+            assert_eq!(
+                *trade_type,
+                TradeType::FiatToCrypto {
+                    local_cost_basis: dec!(500)
+                }
+            );
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[2] {
+        Transaction::Transfer {
+            from,
+            to,
+            amount,
+            income,
+            ..
+        } => {
+            assert_eq!(from.id, savings_btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.02));
+            assert_eq!(to.id, term_btc);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(*amount, dec!(0.01));
+            assert!(income.is_none());
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[3] {
+        Transaction::Transfer {
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(income.get_value(), dec!(1.2));
+            assert_eq!(*income.get_subtype(), IncomeType::Interest);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match &txs[4] {
+        Transaction::Transfer {
+            to,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(to.id, savings_btc);
+            assert_eq!(to.pre_tx_balance, dec!(0.01));
+            assert_eq!(income.get_value(), dec!(2));
+            assert_eq!(*income.get_subtype(), IncomeType::Interest);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match &txs[5] {
+        Transaction::Transfer {
+            to,
+            income: Some(income),
+            ..
+        } => {
+            assert_eq!(to.pre_tx_balance, dec!(1.5));
+            assert_eq!(*income.get_subtype(), IncomeType::Income);
+        }
+        _ => panic!("Expected an income"),
+    }
+
+    match &txs[6] {
+        Transaction::Transfer { from, to, .. } => {
+            assert_eq!(from.id, term_btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.01));
+            assert_eq!(to.id, savings_btc);
+            assert_eq!(to.pre_tx_balance, dec!(0.0101));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[7] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, savings_btc);
+            assert_eq!(from.pre_tx_balance, dec!(0.0201));
+            assert_eq!(*amount, dec!(0.005));
+            assert_eq!(
+                wallet_manager.wallets.get(&to.id).unwrap().get().platform,
+                Platform::Blockchain
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    match &txs[8] {
+        Transaction::Trade {
+            from, trade_type, ..
+        } => {
+            assert_eq!(from.pre_tx_balance, dec!(0.0151));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[9] {
+        Transaction::Withdrawal { from, amount, .. } => {
+            assert_eq!(from.id, savings_eur);
+            assert_eq!(from.pre_tx_balance, dec!(500));
+            assert_eq!(*amount, dec!(200));
+        }
+        _ => panic!("Expected a withdrawal"),
+    }
+}
@@ -1,15 +1,16 @@
-use chrono::Utc;
+use chrono::{TimeZone, Utc};
 use rust_decimal_macros::dec;
 
 use crate::{
-    functions::calculate_tax_gains,
+    api::ConnectorRegistry,
+    functions::{calculate_form_2042c, calculate_tax_gains, generate_form_2086},
     structs::{
-        portfolio_manager::PortfolioManager, wallet_manager::WalletManager, GlobalCostBasisManager, Owner, Persistable, Platform, TradeType, Transaction, TransactionBase, Wallet, WalletBase, WalletSnapshot
+        portfolio_manager::PortfolioManager, wallet_manager::WalletManager, GlobalCostBasisManager, Owner, Persistable, Platform, TradeType, Transaction, TransactionBase, TransactionManager, Wallet, WalletBase, WalletSnapshot
     },
 };
 
-#[test]
-fn simple_two_trades() {
+#[tokio::test]
+async fn simple_two_trades() {
     let tx0 = Transaction::Trade {
         tx: TransactionBase {
             id: "test0".to_string(),
@@ -33,6 +34,7 @@ fn simple_two_trades() {
         trade_type: TradeType::FiatToCrypto {
             local_cost_basis: dec!(1000),
         },
+        soulte: None,
     };
 
     let tx1 = Transaction::Trade {
@@ -56,6 +58,7 @@ fn simple_two_trades() {
         sold_amount: dec!(1.125),
         bought_amount: dec!(450),
         trade_type: TradeType::CryptoToFiat,
+        soulte: None,
     };
 
     let tx2 = Transaction::Trade {
@@ -79,6 +82,7 @@ fn simple_two_trades() {
         sold_amount: dec!(1.875),
         bought_amount: dec!(1300),
         trade_type: TradeType::CryptoToFiat,
+        soulte: None,
     };
 
     let transactions = vec![tx0, tx1, tx2];
@@ -116,7 +120,8 @@ fn simple_two_trades() {
         PortfolioManager::new_non_persistent().unwrap();
 
     portfolio_manager
-        .calculate_portfolio_history(&transactions, &wallet_manager.wallets)
+        .calculate_portfolio_history(&transactions, &wallet_manager.wallets, &ConnectorRegistry::default())
+        .await
         .unwrap();
 
     let tx_id_1 = transactions[1].get_id();
@@ -139,3 +144,114 @@ fn simple_two_trades() {
 
     let _ = portfolio_manager.delete();
 }
+
+#[tokio::test]
+async fn exempt_year_still_consumes_cost_basis() {
+    let btc_snapshot = |pre_tx_balance, price_eur| WalletSnapshot {
+        id: "btc".to_string(),
+        pre_tx_balance,
+        fee: None,
+        price_eur,
+    };
+    let eur_snapshot = |pre_tx_balance| WalletSnapshot {
+        id: "eur".to_string(),
+        pre_tx_balance,
+        fee: None,
+        price_eur: dec!(1),
+    };
+
+    let transactions = vec![
+        Transaction::Trade {
+            tx: TransactionBase {
+                id: "buy".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap(),
+            },
+            from: eur_snapshot(dec!(1000)),
+            to: btc_snapshot(dec!(0), dec!(0)),
+            exchange_pair: None,
+            sold_amount: dec!(1000),
+            bought_amount: dec!(3),
+            trade_type: TradeType::FiatToCrypto {
+                local_cost_basis: dec!(1000),
+            },
+            soulte: None,
+        },
+        Transaction::Trade {
+            tx: TransactionBase {
+                id: "small_sell".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2022, 6, 1, 0, 0, 0).unwrap(),
+            },
+            from: btc_snapshot(dec!(3), dec!(400)),
+            to: eur_snapshot(dec!(0)),
+            exchange_pair: None,
+            sold_amount: dec!(0.5),
+            bought_amount: dec!(200),
+            trade_type: TradeType::CryptoToFiat,
+            soulte: None,
+        },
+        Transaction::Trade {
+            tx: TransactionBase {
+                id: "sell".to_string(),
+                timestamp: Utc.with_ymd_and_hms(2023, 6, 1, 0, 0, 0).unwrap(),
+            },
+            from: btc_snapshot(dec!(2.5), dec!(400)),
+            to: eur_snapshot(dec!(200)),
+            exchange_pair: None,
+            sold_amount: dec!(2.5),
+            bought_amount: dec!(1000),
+            trade_type: TradeType::CryptoToFiat,
+            soulte: None,
+        },
+    ];
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    wallet_manager.wallets.insert(
+        "btc".to_string(),
+        Wallet::Crypto(WalletBase {
+            id: "btc".to_string(),
+            currency: "BTC".to_string(),
+            platform: Platform::Binance,
+            address: None,
+            owner: Owner::User,
+            balance: dec!(0),
+            info: None,
+        }),
+    );
+    wallet_manager.wallets.insert(
+        "eur".to_string(),
+        Wallet::Fiat(WalletBase {
+            id: "eur".to_string(),
+            currency: "EUR".to_string(),
+            platform: Platform::Binance,
+            address: None,
+            owner: Owner::User,
+            balance: dec!(0),
+            info: None,
+        }),
+    );
+
+    let mut transactions_manager = TransactionManager::new_non_persistent().unwrap();
+    transactions_manager.extend(transactions);
+    let mut portfolio_manager = PortfolioManager::new_non_persistent().unwrap();
+    portfolio_manager
+        .calculate_portfolio_history(transactions_manager.get(), &wallet_manager.wallets, &ConnectorRegistry::default())
+        .await
+        .unwrap();
+    let mut cost_basis_manager = GlobalCostBasisManager::new_non_persistent().unwrap();
+    cost_basis_manager.calculate_full_cost_basis(transactions_manager.get(), &portfolio_manager.portfolio_history);
+
+    let form_2022 = generate_form_2086(2022, &transactions_manager, &portfolio_manager, &cost_basis_manager).unwrap();
+    assert!(form_2022.is_exempt);
+    assert_eq!(form_2022.total_sell_price, dec!(200));
+    let form_2042c_2022 = calculate_form_2042c(&form_2022, dec!(0.30));
+    assert_eq!(form_2042c_2022.box_3an, dec!(0));
+    assert_eq!(form_2042c_2022.pfu.total, dec!(0));
+
+    // The exempted cession of 2022 consumed 1000 * 200 / 1200 of the acquisition price
+    let form_2023 = generate_form_2086(2023, &transactions_manager, &portfolio_manager, &cost_basis_manager).unwrap();
+    assert!(!form_2023.is_exempt);
+    let cession = &form_2023.cessions[0];
+    assert_eq!(cession.total_acquisition_price, dec!(1000));
+    assert_eq!(cession.previous_fractions.round_dp(2), dec!(166.67));
+    assert_eq!(cession.gains.round_dp(2), dec!(166.67));
+}
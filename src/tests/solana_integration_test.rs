@@ -0,0 +1,160 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    api::{create_solana_txs, fetch_history_solana, CoinGeckoClient, SolanaRpcClient},
+    errors::ApiError,
+    structs::{wallet_manager::WalletManager, Persistable, Platform, TradeType, Transaction},
+    tests::mock_server::{MockRoute, MockServer},
+};
+
+const ADDRESS: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+
+fn solana_routes() -> Vec<MockRoute> {
+    vec![
+        MockRoute::fixture("/", "solana/signatures.json")
+            .with_body("\"method\":\"getSignaturesForAddress\""),
+        MockRoute::fixture("/", "solana/transaction_deposit.json")
+            .with_body("5tGvKcWq1dEpoSitXr9uJfA2sN8bYhLm3QeZ7wPpD4kR"),
+        MockRoute::fixture("/", "solana/transaction_swap.json")
+            .with_body("3KpSwAp7vNcE2hJdLq9RtYx5MbWu8ZfGk4aPo6iTs1Xe"),
+        MockRoute::fixture("/", "solana/transaction_transfer.json")
+            .with_body("4nQTrAnSfEr8mKpLw2cYhJ6vRbXz9uDgF3aNo5eVt7Sk"),
+        MockRoute::fixture("/", "solana/transaction_failed.json")
+            .with_body("2fXFaiLeD3wRz7qNpKcJm9hTy4vBgL8uXeA6sDo5iZ1W"),
+        MockRoute::fixture("/coins/solana/history", "solana/history_solana.json"),
+        MockRoute::fixture("/coins/usd-coin/history", "solana/history_usd_coin.json"),
+    ]
+}
+
+#[tokio::test]
+async fn solana_history_to_transactions() {
+    let server = MockServer::start(solana_routes());
+    let client = SolanaRpcClient::new(server.url.clone());
+    let price_client = CoinGeckoClient::new(server.url.clone(), None);
+
+    let history = fetch_history_solana(&client, ADDRESS).await.unwrap();
+    assert_eq!(history.transactions.len(), 4);
+
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut txs: Vec<Transaction> = Vec::new();
+    create_solana_txs(&mut wallet_manager, &mut txs, &history, &price_client)
+        .await
+        .unwrap();
+
+    let ids: Vec<&str> = txs.iter().map(|tx| tx.get_id().as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "solana-5tGvKcWq1dEpoSitXr9uJfA2sN8bYhLm3QeZ7wPpD4kR-SOL",
+            "solana-3KpSwAp7vNcE2hJdLq9RtYx5MbWu8ZfGk4aPo6iTs1Xe",
+            "solana-4nQTrAnSfEr8mKpLw2cYhJ6vRbXz9uDgF3aNo5eVt7Sk-fee",
+            "solana-4nQTrAnSfEr8mKpLw2cYhJ6vRbXz9uDgF3aNo5eVt7Sk-USDC",
+            "solana-2fXFaiLeD3wRz7qNpKcJm9hTy4vBgL8uXeA6sDo5iZ1W-fee",
+        ]
+    );
+
+    let address = Some(ADDRESS.to_string());
+    let sol =
+        wallet_manager.create_or_get_wallet_id("SOL", &Platform::Blockchain, &address, false);
+    let usdc =
+        wallet_manager.create_or_get_wallet_id("USDC", &Platform::Blockchain, &address, false);
+    assert!(wallet_manager.wallets.get(&sol).unwrap().is_crypto());
+
+    // Deposit from the sender, who paid the fee
+    match &txs[0] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            let sender = wallet_manager.wallets.get(&from.id).unwrap().get();
+            assert_eq!(
+                sender.address,
+                Some("9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string())
+            );
+            assert_eq!(to.id, sol);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.fee, None);
+            assert_eq!(to.price_eur, dec!(10));
+            assert_eq!(*amount, dec!(2));
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // Swap of SOL for USDC, the fee paid by the address is taken from the SOL wallet
+    match &txs[1] {
+        Transaction::Trade {
+            from,
+            to,
+            sold_amount,
+            bought_amount,
+            trade_type,
+            ..
+        } => {
+            assert_eq!(from.id, sol);
+            assert_eq!(from.pre_tx_balance, dec!(2));
+            assert_eq!(from.fee, Some(dec!(0.000005)));
+            assert_eq!(to.id, usdc);
+            assert_eq!(to.pre_tx_balance, dec!(0));
+            assert_eq!(to.price_eur, dec!(0.94));
+            assert_eq!(*sold_amount, dec!(1));
+            assert_eq!(*bought_amount, dec!(20));
+            assert_eq!(*trade_type, TradeType::CryptoToCrypto);
+        }
+        _ => panic!("Expected a trade"),
+    }
+
+    match &txs[2] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, sol);
+            assert_eq!(from.pre_tx_balance, dec!(0.999995));
+            assert_eq!(from.fee, Some(dec!(0.000005)));
+            assert_eq!(to.fee, None);
+            assert_eq!(*amount, dec!(0));
+        }
+        _ => panic!("Expected a fee transfer"),
+    }
+
+    match &txs[3] {
+        Transaction::Transfer {
+            from, to, amount, ..
+        } => {
+            assert_eq!(from.id, usdc);
+            assert_eq!(from.pre_tx_balance, dec!(20));
+            assert_eq!(from.fee, None);
+            assert_eq!(*amount, dec!(5));
+            let recipient = wallet_manager.wallets.get(&to.id).unwrap().get();
+            assert_eq!(recipient.platform, Platform::Blockchain);
+            assert_eq!(
+                recipient.address,
+                Some("Gh9ZwEmdLJ8DscKNTkTqPbNwLNNBjuSzaG9Vp2KGtKJr".to_string())
+            );
+        }
+        _ => panic!("Expected a transfer"),
+    }
+
+    // The failed transaction only costs its fee
+    assert!(
+        matches!(&txs[4], Transaction::Transfer { from, amount, .. } if from.id == sol && from.pre_tx_balance == dec!(0.99999) && *amount == dec!(0))
+    );
+
+    let requests = server.requests();
+    assert!(requests
+        .iter()
+        .filter(|request| request.path == "/")
+        .all(|request| request.method == "POST" && request.body.contains("\"jsonrpc\":\"2.0\"")));
+}
+
+#[tokio::test]
+async fn solana_rpc_error() {
+    let server = MockServer::start(vec![MockRoute::new(
+        "/",
+        r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32005,"message":"Node is behind by 42 slots"}}"#,
+    )]);
+    let client = SolanaRpcClient::new(server.url.clone());
+
+    match fetch_history_solana(&client, ADDRESS).await {
+        Err(ApiError::ApiCallError(message)) => assert!(message.contains("Node is behind")),
+        _ => panic!("Expected an api call error"),
+    }
+}
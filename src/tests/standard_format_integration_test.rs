@@ -0,0 +1,216 @@
+use chrono::{TimeZone, Utc};
+use rust_decimal_macros::dec;
+
+use crate::{
+    parsing::{
+        export_standard_csv, export_standard_json, import_standard_csv, import_standard_json,
+    },
+    structs::{
+        wallet_manager::WalletManager, Income, IncomeType, Owner, Persistable, Platform,
+        TradeType, Transaction, TransactionBase, TransactionManager, WalletSnapshot,
+    },
+};
+
+fn snapshot(
+    id: &str,
+    pre_tx_balance: rust_decimal::Decimal,
+    fee: Option<rust_decimal::Decimal>,
+) -> WalletSnapshot {
+    WalletSnapshot {
+        id: id.to_string(),
+        pre_tx_balance,
+        price_eur: dec!(25000.50),
+        fee,
+    }
+}
+
+fn tx_base(id: &str, day: u32) -> TransactionBase {
+    TransactionBase {
+        id: id.to_string(),
+        timestamp: Utc.with_ymd_and_hms(2024, 1, day, 12, 30, 15).unwrap(),
+    }
+}
+
+/* Data with every kind of transaction, trade and income, and wallets of every platform kind */
+fn sample_data() -> (WalletManager, TransactionManager) {
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let eur = wallet_manager.create_or_get_wallet_id("EUR", &Platform::Kraken, &None, true);
+    let btc = wallet_manager.create_or_get_wallet_id("BTC", &Platform::Kraken, &None, false);
+    let eth = wallet_manager.create_or_get_wallet_id("ETH", &Platform::KuCoin, &None, false);
+    let bitstamp = Platform::Other("Bitstamp".to_string());
+    let other_btc = wallet_manager.create_or_get_wallet_id("BTC", &bitstamp, &None, false);
+    let address = Some("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".to_string());
+    let cold_btc =
+        wallet_manager.create_or_get_wallet_id("BTC", &Platform::Blockchain, &address, false);
+    let external_btc =
+        wallet_manager.create_or_get_wallet_id("BTC", &Platform::Blockchain, &None, false);
+    let external = wallet_manager
+        .wallets
+        .get_mut(&external_btc)
+        .unwrap()
+        .get_mut();
+    external.owner = Owner::Other;
+    external.info = Some("Payment, \"shop\"".to_string());
+    wallet_manager
+        .wallets
+        .get_mut(&btc)
+        .unwrap()
+        .get_mut()
+        .balance = dec!(0.01900000);
+
+    let mut transaction_manager = TransactionManager::new_non_persistent().unwrap();
+    transaction_manager.extend(vec![
+        Transaction::Deposit {
+            tx: tx_base("deposit", 1),
+            to: snapshot(&eur, dec!(0), None),
+            amount: dec!(1000),
+        },
+        Transaction::Trade {
+            tx: tx_base("buy", 2),
+            from: snapshot(&eur, dec!(1000), Some(dec!(1.5))),
+            to: snapshot(&btc, dec!(0), None),
+            exchange_pair: Some(("BTC".to_string(), "EUR".to_string())),
+            sold_amount: dec!(500),
+            bought_amount: dec!(0.02),
+            trade_type: TradeType::FiatToCrypto {
+                local_cost_basis: dec!(501.5),
+            },
+            soulte: None,
+        },
+        Transaction::Trade {
+            tx: tx_base("swap", 3),
+            from: snapshot(&btc, dec!(0.02), None),
+            to: snapshot(&eth, dec!(0), Some(dec!(0.0001))),
+            exchange_pair: None,
+            sold_amount: dec!(0.001),
+            bought_amount: dec!(0.015),
+            trade_type: TradeType::CryptoToCrypto,
+            soulte: Some(dec!(-12.5)),
+        },
+        Transaction::Trade {
+            tx: tx_base("sell", 4),
+            from: snapshot(&other_btc, dec!(1), None),
+            to: snapshot(&eur, dec!(498.5), None),
+            exchange_pair: Some(("BTC".to_string(), "EUR".to_string())),
+            sold_amount: dec!(0.1),
+            bought_amount: dec!(2500),
+            trade_type: TradeType::CryptoToFiat,
+            soulte: None,
+        },
+        Transaction::Transfer {
+            tx: tx_base("staking", 5),
+            from: snapshot(&eth, dec!(0.015), None),
+            to: snapshot(&eth, dec!(0.015), None),
+            amount: dec!(0.0001),
+            income: Some(Income::new(dec!(0.2), IncomeType::Staking)),
+        },
+        Transaction::Transfer {
+            tx: tx_base("referral", 6),
+            from: snapshot(&btc, dec!(0.019), None),
+            to: snapshot(&btc, dec!(0.019), None),
+            amount: dec!(0.00001),
+            income: Some(Income::new(
+                dec!(0.25),
+                IncomeType::Other("Referral, bonus".to_string()),
+            )),
+        },
+        Transaction::Transfer {
+            tx: tx_base("to-cold", 7),
+            from: snapshot(&btc, dec!(0.01901), Some(dec!(0.0002))),
+            to: snapshot(&cold_btc, dec!(0), None),
+            amount: dec!(0.01),
+            income: None,
+        },
+        Transaction::Transfer {
+            tx: tx_base("payment", 8),
+            from: snapshot(&cold_btc, dec!(0.01), Some(dec!(0.00001))),
+            to: snapshot(&external_btc, dec!(0), None),
+            amount: dec!(0.005),
+            income: None,
+        },
+        Transaction::Withdrawal {
+            tx: tx_base("withdrawal", 9),
+            from: snapshot(&eur, dec!(2998.5), Some(dec!(0.9))),
+            amount: dec!(300),
+        },
+    ]);
+    (wallet_manager, transaction_manager)
+}
+
+fn assert_same_data(
+    wallet_manager: &WalletManager,
+    transaction_manager: &TransactionManager,
+    imported_wallet_manager: &WalletManager,
+    imported_transaction_manager: &TransactionManager,
+) {
+    assert_eq!(
+        imported_transaction_manager.get(),
+        transaction_manager.get()
+    );
+    assert_eq!(imported_wallet_manager.wallets, wallet_manager.wallets);
+    assert_eq!(
+        imported_wallet_manager.wallet_ids.ids,
+        wallet_manager.wallet_ids.ids
+    );
+}
+
+#[test]
+fn standard_csv_round_trip() {
+    let (wallet_manager, transaction_manager) = sample_data();
+    let transactions_path = ".data_test/standard_format/transactions.csv";
+    let wallets_path = ".data_test/standard_format/wallets.csv";
+    export_standard_csv(
+        &transaction_manager,
+        &wallet_manager,
+        transactions_path,
+        wallets_path,
+    )
+    .unwrap();
+
+    let mut imported_wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut imported_transaction_manager = TransactionManager::new_non_persistent().unwrap();
+    let txs = import_standard_csv(
+        &mut imported_wallet_manager,
+        transactions_path,
+        wallets_path,
+    )
+    .unwrap();
+    imported_transaction_manager.extend(txs);
+
+    assert_same_data(
+        &wallet_manager,
+        &transaction_manager,
+        &imported_wallet_manager,
+        &imported_transaction_manager,
+    );
+}
+
+#[test]
+fn standard_json_round_trip() {
+    let (wallet_manager, transaction_manager) = sample_data();
+    let path = ".data_test/standard_format/data.json";
+    export_standard_json(&transaction_manager, &wallet_manager, path).unwrap();
+
+    let mut imported_wallet_manager = WalletManager::new_non_persistent().unwrap();
+    let mut imported_transaction_manager = TransactionManager::new_non_persistent().unwrap();
+    let txs = import_standard_json(&mut imported_wallet_manager, path).unwrap();
+    imported_transaction_manager.extend(txs);
+
+    assert_same_data(
+        &wallet_manager,
+        &transaction_manager,
+        &imported_wallet_manager,
+        &imported_transaction_manager,
+    );
+
+    // A transaction missing a field of its kind is not imported
+    let document = std::fs::read_to_string(path).unwrap().replacen(
+        "\"sold_amount\": \"500\"",
+        "\"sold_amount\": null",
+        1,
+    );
+    let broken_path = ".data_test/standard_format/broken.json";
+    std::fs::write(broken_path, document).unwrap();
+    let mut wallet_manager = WalletManager::new_non_persistent().unwrap();
+    assert!(import_standard_json(&mut wallet_manager, broken_path).is_err());
+}
@@ -4,6 +4,12 @@ use std::{
     path::Path,
 };
 
+use rmp_serde::Serializer;
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::IoError;
+
 pub fn file_exists(file_name: &str) -> bool {
     File::open(file_name).is_ok()
 }
@@ -23,3 +29,72 @@ pub fn create_directories_if_needed(file_path: &str) {
         }
     }
 }
+
+/* Data saved with save_data, None when it hasn't been saved yet */
+pub fn read_saved_data<T: DeserializeOwned>(file_path: &str) -> Result<Option<T>, IoError> {
+    if !file_exists(file_path) {
+        return Ok(None);
+    }
+    let file = File::open(file_path).map_err(|e| IoError::new(e.to_string()))?;
+    rmp_serde::from_read(file)
+        .map(Some)
+        .map_err(|e| IoError::new(e.to_string()))
+}
+
+/* Save the data (raw history, mapped transactions...) of a connector so it doesn't have to be fetched again */
+pub fn save_data<T: Serialize>(file_path: &str, data: &T) -> Result<(), IoError> {
+    create_directories_if_needed(file_path);
+    let file = File::create(file_path).map_err(|e| IoError::new(e.to_string()))?;
+    let mut writer = Serializer::new(file);
+    data.serialize(&mut writer)
+        .map_err(|e| IoError::new(e.to_string()))
+}
+
+/* Data saved with save_mapped_data, None when it hasn't been saved yet or was mapped from other inputs */
+pub fn read_mapped_data<I: Serialize, T: DeserializeOwned>(
+    file_path: &str,
+    inputs: &I,
+) -> Result<Option<T>, IoError> {
+    let Some((digest, data)) = read_saved_data::<(String, T)>(file_path)? else {
+        return Ok(None);
+    };
+    Ok((digest == data_digest(inputs)?).then_some(data))
+}
+
+/* Save the data mapped from the inputs (the transactions of a raw history) with the digest of the inputs, so that
+it is mapped again as soon as the inputs change */
+pub fn save_mapped_data<I: Serialize, T: Serialize>(
+    file_path: &str,
+    inputs: &I,
+    data: &T,
+) -> Result<(), IoError> {
+    save_data(file_path, &(data_digest(inputs)?, data))
+}
+
+fn data_digest<I: Serialize>(inputs: &I) -> Result<String, IoError> {
+    let bytes = rmp_serde::to_vec(inputs).map_err(|e| IoError::new(e.to_string()))?;
+    Ok(hex::encode(Sha256::digest(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mapped_data() {
+        let file_path = ".data_test/files/mapped_data";
+        let _ = fs::remove_file(file_path);
+        assert_eq!(
+            read_mapped_data::<_, Vec<String>>(file_path, &[1, 2]).unwrap(),
+            None
+        );
+
+        save_mapped_data(file_path, &[1, 2], &vec!["tx-1".to_string()]).unwrap();
+        let data: Option<Vec<String>> = read_mapped_data(file_path, &[1, 2]).unwrap();
+        assert_eq!(data, Some(vec!["tx-1".to_string()]));
+
+        // Mapped from other inputs
+        let data: Option<Vec<String>> = read_mapped_data(file_path, &[1, 2, 3]).unwrap();
+        assert_eq!(data, None);
+    }
+}
@@ -0,0 +1,35 @@
+use std::hash::Hash;
+
+use hashbrown::HashMap;
+
+/* Add the fetched items to the saved ones. An item already saved (same key) is replaced, as its status may have
+changed since (a pending deposit credited...) */
+pub fn merge_by_key<T, K: Eq + Hash>(saved: &mut Vec<T>, fetched: Vec<T>, key: impl Fn(&T) -> K) {
+    let mut positions: HashMap<K, usize> = saved
+        .iter()
+        .enumerate()
+        .map(|(position, item)| (key(item), position))
+        .collect();
+    for item in fetched {
+        match positions.get(&key(&item)) {
+            Some(&position) => saved[position] = item,
+            None => {
+                positions.insert(key(&item), saved.len());
+                saved.push(item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_by_key() {
+        let mut saved = vec![(1, "pending"), (2, "done")];
+        merge_by_key(&mut saved, vec![(3, "done"), (1, "done")], |item| item.0);
+
+        assert_eq!(saved, vec![(1, "done"), (2, "done"), (3, "done")]);
+    }
+}
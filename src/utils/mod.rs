@@ -6,3 +6,6 @@ pub use files::*;
 
 pub mod time;
 pub use time::*;
+
+pub mod merge;
+pub use merge::*;
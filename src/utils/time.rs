@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 
 pub fn f64_to_datetime_utc(timestamp: f64) -> Option<DateTime<Utc>> {
     // Convert the f64 timestamp to seconds and nanoseconds
@@ -8,3 +8,42 @@ pub fn f64_to_datetime_utc(timestamp: f64) -> Option<DateTime<Utc>> {
     // Create a NaiveDateTime from seconds and nanoseconds
     return DateTime::from_timestamp(seconds, nanoseconds);
 }
+
+/* Split [start, end] in consecutive windows as most of the history endpoints limit the time range of a query */
+pub fn time_windows(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    max_duration: Duration,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut windows = Vec::new();
+    let mut window_start = start;
+    while window_start < end {
+        let window_end = (window_start + max_duration).min(end);
+        windows.push((window_start, window_end));
+        window_start = window_end;
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_windows() {
+        let end = Utc::now();
+        let start = end - Duration::days(200);
+        let windows = time_windows(start, end, Duration::days(90));
+
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0].0, start);
+        assert_eq!(windows[2].1, end);
+
+        let end = start + Duration::hours(60);
+        let windows = time_windows(start, end, Duration::days(1));
+
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[1].0, start + Duration::days(1));
+        assert_eq!(windows[2].1, end);
+    }
+}